@@ -46,4 +46,160 @@ pub enum QstakingErrors {
     // 6010
     #[msg("The NFT is not staked")]
     NotStaked,
-} 
+    
+    // 6011
+    #[msg("The supplied Character traits are not proven by the published traits root")]
+    InvalidTraitsProof,
+    
+    // 6012
+    #[msg("The whitelist entry is disabled")]
+    WhitelistDisabled,
+    
+    // 6013
+    #[msg("The whitelist entry has expired")]
+    WhitelistExpired,
+    
+    // 6014
+    #[msg("The maximum number of Characters for the whitelist entry has been reached")]
+    WhitelistQuotaReached,
+    
+    // 6015
+    #[msg("The NFT is on the denylist")]
+    Denylisted,
+    
+    // 6016
+    #[msg("The requested custody mode is not valid for this operation")]
+    InvalidCustodyMode,
+    
+    // 6017
+    #[msg("The reward token account, mint or token program is not valid")]
+    InvalidRewardTokenAccount,
+    
+    // 6018
+    #[msg("The reward mint uses a token extension that is not supported")]
+    UnsupportedMintExtension,
+    
+    // 6019
+    #[msg("The account already uses the current layout version")]
+    AccountAlreadyMigrated,
+    
+    // 6020
+    #[msg("The NFT mint still has a non-zero supply")]
+    MintSupplyNotZero,
+    
+    // 6021
+    #[msg("The emission schedule parameters are not valid")]
+    InvalidEmissionSchedule,
+    
+    // 6022
+    #[msg("The emission cap cannot be lower than the amount already emitted")]
+    EmissionCapBelowEmitted,
+    
+    // 6023
+    #[msg("There are no vested Reward Tokens to release")]
+    NothingToClaim,
+    
+    // 6024
+    #[msg("Invalid vesting period or early exit penalty")]
+    InvalidVestingParameters,
+    
+    // 6025
+    #[msg("Invalid or disabled lock tier")]
+    InvalidLockTier,
+    
+    // 6026
+    #[msg("The lock-up of the NFT has not expired yet")]
+    LockNotExpired,
+    
+    // 6027
+    #[msg("Invalid or unregistered boost item")]
+    InvalidBoost,
+    
+    // 6028
+    #[msg("The Character already has an active boost")]
+    BoostActive,
+    
+    // 6029
+    #[msg("The Character has no expired boost")]
+    BoostNotExpired,
+    
+    // 6030
+    #[msg("Invalid, disabled or unmatched gear collection")]
+    InvalidGearCollection,
+    
+    // 6031
+    #[msg("All gear slots of the Character are in use")]
+    GearSlotsFull,
+    
+    // 6032
+    #[msg("Programmable NFTs cannot be equipped as gear")]
+    ProgrammableGear,
+    
+    // 6033
+    #[msg("The Character still has gear attached")]
+    GearEquipped,
+    
+    // 6034
+    #[msg("Invalid guild account or parameters")]
+    InvalidGuild,
+    
+    // 6035
+    #[msg("The Character is a member of a guild")]
+    GuildMember,
+    
+    // 6036
+    #[msg("The Character is not a member of the guild")]
+    NotGuildMember,
+    
+    // 6037
+    #[msg("Invalid referrer account or parameters")]
+    InvalidReferrer,
+    
+    // 6038
+    #[msg("Invalid season")]
+    InvalidSeason,
+    
+    // 6039
+    #[msg("Invalid batch of NFT accounts")]
+    InvalidBatch,
+    
+    // 6040
+    #[msg("Invalid operator account or parameters")]
+    InvalidOperator,
+    
+    // 6041
+    #[msg("Invalid upgrade attribute or parameters")]
+    InvalidUpgrade,
+    
+    // 6042
+    #[msg("The attribute is at its maximum upgrade level")]
+    UpgradeMaxLevel,
+    
+    // 6043
+    #[msg("The respec cooldown has not expired")]
+    RespecCooldown,
+    
+    // 6044
+    #[msg("Too many different boost end timestamps are active in the Mine")]
+    BoostScheduleFull,
+    
+    // 6045
+    #[msg("The Character has earned referral rewards")]
+    ReferralRewardsEarned,
+    
+    // 6046
+    #[msg("The Character has no withheld rewards")]
+    NoWithheldRewards,
+    
+    // 6047
+    #[msg("The Character has withheld rewards")]
+    WithheldRewards,
+    
+    // 6048
+    #[msg("The Character traits are invalid")]
+    InvalidTraits,
+    
+    // 6049
+    #[msg("The Character is within the referral window of its referrer")]
+    InReferralWindow,
+}
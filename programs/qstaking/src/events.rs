@@ -0,0 +1,31 @@
+use anchor_lang::prelude::*;
+
+
+// Lifetime totals of a Character, emitted before the Character account is closed
+#[event]
+pub struct CharacterArchived {
+
+    // Mint of the NFT
+    pub mint: Pubkey,
+    
+    // Level of the Character
+    pub level: u8,
+    
+    // Rarity tier of the NFT
+    pub tier: u8,
+    
+    // Comulative experience points
+    pub experience: u64,
+    
+    // Comulative amount of mined Reward Tokens
+    pub mining_rewards: u128,
+    
+    // Comulative amount of looted Reward Tokens
+    pub looting_rewards: u128,
+    
+    // Comulative amount of bounty Reward Tokens
+    pub bounty_rewards: u128,
+    
+    // Timestamp when the Character account was closed
+    pub timestamp: i64,
+}
@@ -0,0 +1,715 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, TokenAccount, Token};
+use solana_program::program::invoke_signed;
+use mpl_token_metadata as metaplex;
+
+use crate::metadata;
+use crate::token_interface;
+use crate::states::*;
+use crate::errors::QstakingErrors;
+use crate::instructions::mine_staking::{self, settle_mine, stake_guild_member, settle_guild_bonus, unstake_guild_member, pay_referral, mint_rewards, vest_rewards};
+use crate::instructions::loot_staking::{settle_loot, transfer_rewards};
+
+
+// Number of remaining accounts passed for each NFT of a batch:
+// [character, nft_account, nft_mint, nft_edition, nft_metadata, denylist]
+pub const BATCH_NFT_ACCOUNTS: usize = 6;
+
+// Number of remaining accounts passed for each NFT unstaked from the Mine in a batch,
+// followed by the vesting account of the user for the NFT
+pub const BATCH_MINE_UNSTAKE_ACCOUNTS: usize = BATCH_NFT_ACCOUNTS + 1;
+
+
+
+/*
+ *  Stake-delegate a batch of NFTs in the Mine.
+ *  The accounts of each NFT are passed as remaining accounts, followed by the Guild accounts of the guild members.
+ */
+
+
+#[derive(Accounts)]
+pub struct StakeMineBatch<'info> {
+
+    // Mine account in which to stake the NFTs
+    #[account(
+        mut,
+        seeds = [b"mine".as_ref(),],
+        bump,
+    )]
+    pub mine: Box<Account<'info, Mine>>,
+    
+    // Whitelist account to be used for the whitelist proofs
+    pub whitelist: Box<Account<'info, Whitelist>>,
+    
+    // User account that holds the NFTs
+    #[account(mut)]
+    pub user: Signer<'info>,
+    
+    // Metaplex Token Metadata program
+    /// CHECKED: This is not dangerous because we don't read or write from this account
+    #[account(address = metaplex::id())]
+    pub token_metadata_program: UncheckedAccount<'info>,
+    
+    // Token program
+    pub token_program: Program<'info, Token>,
+}
+
+
+pub fn stake_mine<'info>(ctx: Context<'_, '_, '_, 'info, StakeMineBatch<'info>>, proofs: Vec<Option<WhitelistProof>>, lock_tier: u8) -> Result<()> {
+
+    let clock = Clock::get()?;
+    let mine = &mut ctx.accounts.mine;
+    let whitelist = &ctx.accounts.whitelist;
+    
+    require!(
+        !mine.locked,
+        QstakingErrors::StakingPoolLocked
+    );
+    
+    require!(
+        mine.custody == 0,
+        QstakingErrors::InvalidCustodyMode
+    );
+    
+    let (nft_accounts, extra_accounts) = split_batch(ctx.remaining_accounts, proofs.len(), BATCH_NFT_ACCOUNTS)?;
+    mine.update_accrued_rewards(clock.unix_timestamp)?;
+    
+    for (accounts, proof) in nft_accounts.chunks(BATCH_NFT_ACCOUNTS).zip(proofs.iter()) {
+        let mut nft = BatchNft::load(ctx.program_id, accounts, &ctx.accounts.user.key())?;
+        let character = &mut nft.character;
+        
+        require!(
+            character.staked == 0,
+            QstakingErrors::AlreadyStaked
+        );
+        
+        whitelist.verify(ctx.program_id, &whitelist.key(), nft.nft_mint.key, &nft.nft_metadata, &nft.denylist, proof)?;
+        let (staked_weight, lock_duration) = mine.lock_tier(lock_tier, character.mining_points)?;
+        stake_guild_member(accounts_from(extra_accounts, &character.guild), ctx.program_id, mine, character, clock.unix_timestamp)?;
+        mine.add_character(clock.unix_timestamp, staked_weight)?;
+        character.stake_mine(clock.unix_timestamp, mine.season, mine.accrued_rewards, 0, &nft.nft_account.key(), &Pubkey::default())?;
+        character.lock(clock.unix_timestamp, lock_tier, lock_duration, staked_weight)?;
+        character.exit(ctx.program_id)?;
+        
+        freeze_nft(
+            &nft,
+            ctx.accounts.token_metadata_program.to_account_info(),
+            ctx.accounts.token_program.to_account_info(),
+            mine.to_account_info(),
+            ctx.accounts.user.to_account_info(),
+            &[b"mine".as_ref(), &[mine.bump]],
+        )?;
+    }
+    
+    Ok(())
+}
+
+
+
+/*
+ *  Unstake-delegate a batch of NFTs from the Mine.
+ *  The accounts of each NFT are passed as remaining accounts, followed by the Guild and guild treasury of the guild members,
+ *  the referral accounts of the Characters within their referral window and the Leaderboard.
+ */
+
+
+#[derive(Accounts)]
+pub struct UnstakeMineBatch<'info> {
+
+    // Mine staking pool account
+    #[account(
+        mut,
+        seeds = [b"mine".as_ref(),],
+        bump,
+    )]
+    pub mine: Box<Account<'info, Mine>>,
+    
+    // Loot staking pool account
+    #[account(
+        mut,
+        seeds = [b"loot".as_ref(),],
+        bump,
+    )]
+    pub loot: Box<Account<'info, Loot>>,
+    
+    // Token account with loot rewards
+    ///CHECKED: the account is unpacked and its mint and owner checked with the reward token program
+    #[account(
+        mut,
+        seeds = [b"proceeds".as_ref(), loot.key().as_ref()],
+        bump,
+    )]
+    pub loot_proceeds: UncheckedAccount<'info>,
+    
+    // Token account of the Mine holding the vesting Reward Tokens
+    ///CHECKED: the address is derived from the Mine and the account is validated by the reward token program
+    #[account(
+        mut,
+        seeds = [b"escrow".as_ref(), mine.key().as_ref()],
+        bump,
+    )]
+    pub vesting_escrow: UncheckedAccount<'info>,
+    
+    // Associated token account for the reward tokens
+    ///CHECKED: the address is derived from the user, the reward mint and the reward token program
+    #[account(mut)]
+    pub reward_ata: UncheckedAccount<'info>,
+    
+    // Address of the reward mint, owned by the Token or the Token-2022 program
+    ///CHECKED: the address is checked against the Mine, the mint authority by the reward token program
+    #[account(
+        mut,
+        constraint = mine.mint == reward_mint.key(),
+    )]
+    pub reward_mint: UncheckedAccount<'info>,
+    
+    // User account that holds the NFTs
+    #[account(mut)]
+    pub user: Signer<'info>,
+    
+    // Metaplex Token Metadata program
+    /// CHECKED: This is not dangerous because we don't read or write from this account
+    #[account(address = metaplex::id())]
+    pub token_metadata_program: UncheckedAccount<'info>,
+    
+    // Token program
+    pub token_program: Program<'info, Token>,
+    
+    // Token program of the reward mint
+    ///CHECKED: the address is checked against the owner of the reward mint
+    #[account(
+        constraint = reward_token_program.key() == *reward_mint.owner @ QstakingErrors::InvalidRewardTokenAccount,
+    )]
+    pub reward_token_program: UncheckedAccount<'info>,
+    
+    // System program
+    pub system_program: Program<'info, System>,
+}
+
+
+pub fn unstake_mine<'info>(ctx: Context<'_, '_, '_, 'info, UnstakeMineBatch<'info>>, count: u8) -> Result<()> {
+
+    let clock = Clock::get()?;
+    let mine = &mut ctx.accounts.mine;
+    let loot = &mut ctx.accounts.loot;
+    let user = ctx.accounts.user.key();
+    
+    token_interface::assert_reward_accounts(
+        &ctx.accounts.reward_token_program.key(),
+        &ctx.accounts.reward_mint.to_account_info(),
+        &ctx.accounts.reward_ata.to_account_info(),
+        &user,
+        &ctx.accounts.loot_proceeds.to_account_info(),
+        &loot.key(),
+    )?;
+    
+    let (nft_accounts, extra_accounts) = split_batch(ctx.remaining_accounts, usize::from(count), BATCH_MINE_UNSTAKE_ACCOUNTS)?;
+    mine.update_accrued_rewards(clock.unix_timestamp)?;
+    
+    let mut claimed_reward: u64 = 0;
+    let mut vested_reward: u64 = 0;
+    let mut looted_reward: u64 = 0;
+    
+    for accounts in nft_accounts.chunks(BATCH_MINE_UNSTAKE_ACCOUNTS) {
+        let mut nft = BatchNft::load(ctx.program_id, accounts, &user)?;
+        let character = &mut nft.character;
+        
+        require_keys_eq!(
+            nft.nft_account.key(),
+            character.nft_account,
+            QstakingErrors::NotHolder
+        );
+        
+        require!(
+            character.staked == 1,
+            QstakingErrors::NotStaked
+        );
+        
+        require!(
+            character.custody == 0,
+            QstakingErrors::InvalidCustodyMode
+        );
+        
+        let denylisted = Denylist::is_denylisted(ctx.program_id, &nft.denylist);
+        settle_guild_bonus(accounts_from(extra_accounts, &character.guild), ctx.program_id, mine, character, clock.unix_timestamp)?;
+        let (accrued_reward, nft_looted_reward) = settle_mine(mine, loot, character, denylisted, clock.unix_timestamp)?;
+        let mined_reward = accrued_reward;
+        Leaderboard::record(ctx.program_id, extra_accounts, nft.nft_mint.key, character)?;
+        
+        let (accrued_reward, _) = unstake_guild_member(
+            accounts_from(extra_accounts, &character.guild),
+            character,
+            ctx.accounts.reward_token_program.to_account_info(),
+            ctx.accounts.reward_mint.to_account_info(),
+            mine.to_account_info(),
+            mine.bump,
+            accrued_reward,
+        )?;
+        
+        // The referral accounts of a referred Character start at its referrer Character
+        let referral_accounts = if character.referrer == Pubkey::default() {
+            &[]
+        } else {
+            let (referrer_character, _) = Pubkey::find_program_address(&[b"character".as_ref(), character.referrer.as_ref()], ctx.program_id);
+            accounts_from(extra_accounts, &referrer_character)
+        };
+        
+        pay_referral(
+            referral_accounts,
+            ctx.program_id,
+            mine,
+            character,
+            ctx.accounts.reward_token_program.to_account_info(),
+            ctx.accounts.reward_mint.to_account_info(),
+            clock.unix_timestamp,
+            mined_reward,
+        )?;
+        
+        // Mined rewards go to the vesting escrow when the Mine vests them
+        if mine.vesting_period > 0 && accrued_reward > 0 {
+            let (vesting, vesting_bump) = Pubkey::find_program_address(&[b"vesting".as_ref(), nft.nft_mint.key.as_ref(), user.as_ref()], ctx.program_id);
+            require_keys_eq!(
+                accounts[BATCH_NFT_ACCOUNTS].key(),
+                vesting,
+                QstakingErrors::InvalidBatch
+            );
+            
+            vest_rewards(
+                accounts[BATCH_NFT_ACCOUNTS].clone(),
+                ctx.accounts.user.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+                &user,
+                nft.nft_mint.key,
+                vesting_bump,
+                clock.unix_timestamp,
+                mine.vesting_period,
+                accrued_reward,
+            )?;
+            vested_reward = vested_reward.checked_add(accrued_reward).ok_or(QstakingErrors::InvalidComputation).unwrap();
+        } else {
+            claimed_reward = claimed_reward.checked_add(accrued_reward).ok_or(QstakingErrors::InvalidComputation).unwrap();
+        }
+        looted_reward = looted_reward.checked_add(nft_looted_reward).ok_or(QstakingErrors::InvalidComputation).unwrap();
+        character.exit(ctx.program_id)?;
+        
+        thaw_nft(
+            &nft,
+            ctx.accounts.token_metadata_program.to_account_info(),
+            ctx.accounts.token_program.to_account_info(),
+            mine.to_account_info(),
+            ctx.accounts.user.to_account_info(),
+            &[b"mine".as_ref(), &[mine.bump]],
+        )?;
+    }
+    
+    mint_rewards(
+        ctx.accounts.reward_token_program.to_account_info(),
+        ctx.accounts.reward_mint.to_account_info(),
+        ctx.accounts.reward_ata.to_account_info(),
+        ctx.accounts.loot_proceeds.to_account_info(),
+        mine.to_account_info(),
+        mine.bump,
+        claimed_reward,
+        looted_reward,
+    )?;
+    
+    // Mint the vested rewards of the batch to the vesting escrow
+    if vested_reward > 0 {
+        token_interface::mint_to(
+            ctx.accounts.reward_token_program.to_account_info(),
+            ctx.accounts.reward_mint.to_account_info(),
+            ctx.accounts.vesting_escrow.to_account_info(),
+            mine.to_account_info(),
+            vested_reward,
+            &[&[b"mine".as_ref(), &[mine.bump]]],
+        )?;
+    }
+    
+    Ok(())
+}
+
+
+
+/*
+ *  Stake-delegate a batch of NFTs in the Loot.
+ *  The accounts of each NFT are passed as remaining accounts.
+ */
+
+
+#[derive(Accounts)]
+pub struct StakeLootBatch<'info> {
+
+    // Loot account in which to stake the NFTs
+    #[account(
+        mut,
+        seeds = [b"loot".as_ref(),],
+        bump,
+    )]
+    pub loot: Box<Account<'info, Loot>>,
+    
+    // Whitelist account to be used for the whitelist proofs
+    pub whitelist: Box<Account<'info, Whitelist>>,
+    
+    // User account that holds the NFTs
+    #[account(mut)]
+    pub user: Signer<'info>,
+    
+    // Metaplex Token Metadata program
+    /// CHECKED: This is not dangerous because we don't read or write from this account
+    #[account(address = metaplex::id())]
+    pub token_metadata_program: UncheckedAccount<'info>,
+    
+    // Token program
+    pub token_program: Program<'info, Token>,
+}
+
+
+pub fn stake_loot<'info>(ctx: Context<'_, '_, '_, 'info, StakeLootBatch<'info>>, proofs: Vec<Option<WhitelistProof>>) -> Result<()> {
+
+    let clock = Clock::get()?;
+    let loot = &mut ctx.accounts.loot;
+    let whitelist = &ctx.accounts.whitelist;
+    
+    require!(
+        !loot.locked,
+        QstakingErrors::StakingPoolLocked
+    );
+    
+    require!(
+        loot.custody == 0,
+        QstakingErrors::InvalidCustodyMode
+    );
+    
+    let (nft_accounts, _) = split_batch(ctx.remaining_accounts, proofs.len(), BATCH_NFT_ACCOUNTS)?;
+    loot.update_accrued_rewards(clock.unix_timestamp)?;
+    
+    for (accounts, proof) in nft_accounts.chunks(BATCH_NFT_ACCOUNTS).zip(proofs.iter()) {
+        let mut nft = BatchNft::load(ctx.program_id, accounts, &ctx.accounts.user.key())?;
+        let character = &mut nft.character;
+        
+        require!(
+            character.staked == 0,
+            QstakingErrors::AlreadyStaked
+        );
+        
+        whitelist.verify(ctx.program_id, &whitelist.key(), nft.nft_mint.key, &nft.nft_metadata, &nft.denylist, proof)?;
+        loot.add_character(clock.unix_timestamp, character.looting_points)?;
+        character.stake_loot(clock.unix_timestamp, loot.season, loot.accrued_rewards, 0, &nft.nft_account.key(), &Pubkey::default())?;
+        character.exit(ctx.program_id)?;
+        
+        freeze_nft(
+            &nft,
+            ctx.accounts.token_metadata_program.to_account_info(),
+            ctx.accounts.token_program.to_account_info(),
+            loot.to_account_info(),
+            ctx.accounts.user.to_account_info(),
+            &[b"loot".as_ref(), &[loot.bump]],
+        )?;
+    }
+    
+    Ok(())
+}
+
+
+
+/*
+ *  Unstake-delegate a batch of NFTs from the Loot.
+ *  The accounts of each NFT are passed as remaining accounts, followed by the Leaderboard.
+ */
+
+
+#[derive(Accounts)]
+pub struct UnstakeLootBatch<'info> {
+
+    // Loot staking pool account
+    #[account(
+        mut,
+        seeds = [b"loot".as_ref(),],
+        bump,
+    )]
+    pub loot: Box<Account<'info, Loot>>,
+    
+    // Token account with loot rewards
+    ///CHECKED: the account is unpacked and its mint and owner checked with the reward token program
+    #[account(
+        mut,
+        seeds = [b"proceeds".as_ref(), loot.key().as_ref()],
+        bump,
+    )]
+    pub loot_proceeds: UncheckedAccount<'info>,
+    
+    // Associated token account for the reward tokens
+    ///CHECKED: the address is derived from the user, the reward mint and the reward token program
+    #[account(mut)]
+    pub reward_ata: UncheckedAccount<'info>,
+    
+    // Address of the reward mint, owned by the Token or the Token-2022 program
+    ///CHECKED: the reward token program checks the mint of the loot proceeds account
+    pub reward_mint: UncheckedAccount<'info>,
+    
+    // User account that holds the NFTs
+    #[account(mut)]
+    pub user: Signer<'info>,
+    
+    // Metaplex Token Metadata program
+    /// CHECKED: This is not dangerous because we don't read or write from this account
+    #[account(address = metaplex::id())]
+    pub token_metadata_program: UncheckedAccount<'info>,
+    
+    // Token program
+    pub token_program: Program<'info, Token>,
+    
+    // Token program of the reward mint
+    ///CHECKED: the address is checked against the owner of the reward mint
+    #[account(
+        constraint = reward_token_program.key() == *reward_mint.owner @ QstakingErrors::InvalidRewardTokenAccount,
+    )]
+    pub reward_token_program: UncheckedAccount<'info>,
+}
+
+
+pub fn unstake_loot<'info>(ctx: Context<'_, '_, '_, 'info, UnstakeLootBatch<'info>>, count: u8) -> Result<()> {
+
+    let clock = Clock::get()?;
+    let loot = &mut ctx.accounts.loot;
+    
+    token_interface::assert_reward_accounts(
+        &ctx.accounts.reward_token_program.key(),
+        &ctx.accounts.reward_mint.to_account_info(),
+        &ctx.accounts.reward_ata.to_account_info(),
+        &ctx.accounts.user.key(),
+        &ctx.accounts.loot_proceeds.to_account_info(),
+        &loot.key(),
+    )?;
+    
+    let (nft_accounts, extra_accounts) = split_batch(ctx.remaining_accounts, usize::from(count), BATCH_NFT_ACCOUNTS)?;
+    loot.update_accrued_rewards(clock.unix_timestamp)?;
+    
+    let mut claimed_reward: u64 = 0;
+    
+    for accounts in nft_accounts.chunks(BATCH_NFT_ACCOUNTS) {
+        let mut nft = BatchNft::load(ctx.program_id, accounts, &ctx.accounts.user.key())?;
+        let character = &mut nft.character;
+        
+        require_keys_eq!(
+            nft.nft_account.key(),
+            character.nft_account,
+            QstakingErrors::NotHolder
+        );
+        
+        require!(
+            character.staked == 2,
+            QstakingErrors::NotStaked
+        );
+        
+        require!(
+            character.custody == 0,
+            QstakingErrors::InvalidCustodyMode
+        );
+        
+        let denylisted = Denylist::is_denylisted(ctx.program_id, &nft.denylist);
+        let accrued_reward = settle_loot(loot, character, denylisted, clock.unix_timestamp)?;
+        Leaderboard::record(ctx.program_id, extra_accounts, nft.nft_mint.key, character)?;
+        claimed_reward = claimed_reward.checked_add(accrued_reward).ok_or(QstakingErrors::InvalidComputation).unwrap();
+        character.exit(ctx.program_id)?;
+        
+        thaw_nft(
+            &nft,
+            ctx.accounts.token_metadata_program.to_account_info(),
+            ctx.accounts.token_program.to_account_info(),
+            loot.to_account_info(),
+            ctx.accounts.user.to_account_info(),
+            &[b"loot".as_ref(), &[loot.bump]],
+        )?;
+    }
+    
+    transfer_rewards(
+        ctx.accounts.reward_token_program.to_account_info(),
+        ctx.accounts.loot_proceeds.to_account_info(),
+        ctx.accounts.reward_mint.to_account_info(),
+        ctx.accounts.reward_ata.to_account_info(),
+        loot.to_account_info(),
+        loot.bump,
+        claimed_reward,
+    )?;
+    
+    Ok(())
+}
+
+
+
+/*
+ *  Utility functions
+ */
+
+
+// Accounts of one NFT of a batch
+pub struct BatchNft<'info> {
+
+    // Character account of the NFT
+    pub character: Account<'info, Character>,
+    
+    // Token account of the user holding the NFT
+    pub nft_account: Account<'info, TokenAccount>,
+    
+    // Mint account of the NFT
+    pub nft_mint: AccountInfo<'info>,
+    
+    // Token (Master) Edition account
+    pub nft_edition: AccountInfo<'info>,
+    
+    // Metadata account of the NFT
+    pub nft_metadata: AccountInfo<'info>,
+    
+    // Denylist account of the NFT, only initialized if the mint is denylisted
+    pub denylist: AccountInfo<'info>,
+}
+
+
+impl<'info> BatchNft<'info> {
+
+    // Loads and checks the accounts of one NFT of a batch held by the user.
+    // Programmable NFTs need their token record and authorization rules, so they are staked one at a time
+    pub fn load(program_id: &Pubkey, accounts: &[AccountInfo<'info>], user: &Pubkey) -> Result<Self> {
+    
+        let nft_mint = accounts[2].key();
+        mine_staking::assert_edition_account(&nft_mint, &accounts[3])?;
+        metadata::assert_metadata_account(&nft_mint, &accounts[4])?;
+        
+        require!(
+            !metadata::is_programmable(&accounts[4])?,
+            QstakingErrors::InvalidBatch
+        );
+        
+        let (character, _) = Pubkey::find_program_address(&[b"character".as_ref(), nft_mint.as_ref()], program_id);
+        let (denylist, _) = Pubkey::find_program_address(&[b"denylist".as_ref(), nft_mint.as_ref()], program_id);
+        require!(
+            accounts[0].key() == character && accounts[5].key() == denylist,
+            QstakingErrors::InvalidBatch
+        );
+        
+        let nft_account: Account<TokenAccount> = Account::try_from(&accounts[1])?;
+        require!(
+            nft_account.mint == nft_mint && nft_account.owner == *user && nft_account.amount == 1,
+            QstakingErrors::NotHolder
+        );
+        
+        Ok(Self {
+            character: Account::try_from(&accounts[0])?,
+            nft_account,
+            nft_mint: accounts[2].clone(),
+            nft_edition: accounts[3].clone(),
+            nft_metadata: accounts[4].clone(),
+            denylist: accounts[5].clone(),
+        })
+    }
+}
+
+
+// Splits the remaining accounts into the accounts of the NFTs of a batch and the extra accounts passed after them
+pub fn split_batch<'a, 'info>(remaining_accounts: &'a [AccountInfo<'info>], count: usize, nft_accounts: usize) -> Result<(&'a [AccountInfo<'info>], &'a [AccountInfo<'info>])> {
+
+    let batch_accounts = count.checked_mul(nft_accounts).ok_or(QstakingErrors::InvalidComputation).unwrap();
+    require!(
+        count > 0 && remaining_accounts.len() >= batch_accounts,
+        QstakingErrors::InvalidBatch
+    );
+    
+    Ok(remaining_accounts.split_at(batch_accounts))
+}
+
+
+// Returns the extra accounts starting at the given address, empty when the address is not passed
+pub fn accounts_from<'a, 'info>(extra_accounts: &'a [AccountInfo<'info>], key: &Pubkey) -> &'a [AccountInfo<'info>] {
+
+    match extra_accounts.iter().position(|account| account.key == key) {
+        Some(index) => &extra_accounts[index..],
+        None => &[],
+    }
+}
+
+
+// Adds the staking pool as delegate to the user's NFT token account and freezes it
+pub fn freeze_nft<'info>(
+    nft: &BatchNft<'info>,
+    token_metadata_program: AccountInfo<'info>,
+    token_program: AccountInfo<'info>,
+    pool: AccountInfo<'info>,
+    user: AccountInfo<'info>,
+    pool_seeds: &[&[u8]],
+) -> Result<()> {
+
+    token::approve(
+        CpiContext::new(
+            token_program,
+            token::Approve {
+                to: nft.nft_account.to_account_info(),
+                delegate: pool.clone(),
+                authority: user,
+            },
+        ),
+        1,
+    )?;
+    
+    invoke_signed(
+        &metaplex::instruction::freeze_delegated_account(
+            token_metadata_program.key(),
+            pool.key(),
+            nft.nft_account.key(),
+            nft.nft_edition.key(),
+            nft.nft_mint.key(),
+        ),
+        &[
+            token_metadata_program,
+            pool,
+            nft.nft_account.to_account_info(),
+            nft.nft_edition.clone(),
+            nft.nft_mint.clone(),
+        ],
+        &[pool_seeds],
+    )?;
+    
+    Ok(())
+}
+
+
+// Thaws the user's NFT token account and removes the staking pool as its delegate
+pub fn thaw_nft<'info>(
+    nft: &BatchNft<'info>,
+    token_metadata_program: AccountInfo<'info>,
+    token_program: AccountInfo<'info>,
+    pool: AccountInfo<'info>,
+    user: AccountInfo<'info>,
+    pool_seeds: &[&[u8]],
+) -> Result<()> {
+
+    invoke_signed(
+        &metaplex::instruction::thaw_delegated_account(
+            token_metadata_program.key(),
+            pool.key(),
+            nft.nft_account.key(),
+            nft.nft_edition.key(),
+            nft.nft_mint.key(),
+        ),
+        &[
+            token_metadata_program,
+            pool,
+            nft.nft_account.to_account_info(),
+            nft.nft_edition.clone(),
+            nft.nft_mint.clone(),
+        ],
+        &[pool_seeds],
+    )?;
+    
+    token::revoke(
+        CpiContext::new(
+            token_program,
+            token::Revoke {
+                source: nft.nft_account.to_account_info(),
+                authority: user,
+            },
+        )
+    )?;
+    
+    Ok(())
+}
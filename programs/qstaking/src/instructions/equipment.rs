@@ -0,0 +1,266 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, Mint, TokenAccount, Token};
+use solana_program::program::invoke_signed;
+use mpl_token_metadata as metaplex;
+
+use crate::states::*;
+use crate::errors::QstakingErrors;
+use crate::instructions::mine_staking::assert_edition_account;
+
+
+
+/*
+ *  Equip a gear NFT on a Character.
+ *  The gear NFT is frozen in the user's wallet with the Character account as delegate, so it stays locked
+ *  while the Character is staked and until it is detached again.
+ */
+
+
+#[derive(Accounts)]
+pub struct EquipGear<'info> {
+
+    // GearCollection account of the gear NFT's creator
+    #[account(
+        seeds = [b"gear".as_ref(), gear_collection.creator.as_ref()],
+        bump = gear_collection.bump,
+    )]
+    pub gear_collection: Box<Account<'info, GearCollection>>,
+    
+    // Character account of the NFT
+    #[account(
+        mut,
+        seeds = [b"character".as_ref(), nft_mint.key().as_ref(),],
+        bump,
+    )]
+    pub character: Box<Account<'info, Character>>,
+    
+    // Token account holding the Character NFT
+    #[account(
+        token::mint = nft_mint,
+        token::authority = user,
+    )]
+    pub nft_account: Box<Account<'info, TokenAccount>>,
+    
+    // Mint account of the Character NFT
+    pub nft_mint: Box<Account<'info, Mint>>,
+    
+    // Equipment account of the gear NFT
+    #[account(
+        init,
+        payer = user,
+        seeds = [b"equipment".as_ref(), gear_mint.key().as_ref()],
+        bump,
+        space = 8 + Equipment::LEN,
+    )]
+    pub equipment: Box<Account<'info, Equipment>>,
+    
+    // Token account holding the gear NFT
+    #[account(
+        mut,
+        token::mint = gear_mint,
+        token::authority = user,
+    )]
+    pub gear_account: Box<Account<'info, TokenAccount>>,
+    
+    // Token (Master) Edition account of the gear NFT
+    ///CHECKED: custom logic checks for the validity of this account
+    pub gear_edition: UncheckedAccount<'info>,
+    
+    // Metadata account of the gear NFT
+    ///CHECKED: custom logic checks for the validity of this account
+    pub gear_metadata: UncheckedAccount<'info>,
+    
+    // Mint account of the gear NFT
+    pub gear_mint: Box<Account<'info, Mint>>,
+    
+    // User account that holds the Character and the gear NFT
+    #[account(mut)]
+    pub user: Signer<'info>,
+    
+    // Metaplex Token Metadata program
+    /// CHECKED: This is not dangerous because we don't read or write from this account
+    #[account(address = metaplex::id())]
+    pub token_metadata_program: UncheckedAccount<'info>,
+    
+    // Token program
+    pub token_program: Program<'info, Token>,
+    
+    // System program
+    pub system_program: Program<'info, System>,
+}
+
+
+pub fn equip(ctx: Context<EquipGear>) -> Result<()> {
+
+    assert_edition_account(&ctx.accounts.gear_mint.key(), &ctx.accounts.gear_edition.to_account_info())?;
+    
+    let character = &mut ctx.accounts.character;
+    let gear_collection = &ctx.accounts.gear_collection;
+    
+    require!(
+        ctx.accounts.nft_account.amount == 1 && ctx.accounts.gear_account.amount == 1,
+        QstakingErrors::NotHolder
+    );
+    
+    require!(
+        character.staked == 0,
+        QstakingErrors::AlreadyStaked
+    );
+    
+    gear_collection.verify(&ctx.accounts.gear_mint.key(), &ctx.accounts.gear_metadata.to_account_info())?;
+    
+    let bonus = gear_collection.bonus_for(character)?;
+    character.equip(&ctx.accounts.gear_mint.key(), gear_collection.stat, bonus)?;
+    
+    let equipment = &mut ctx.accounts.equipment;
+    equipment.initialize(
+        *ctx.bumps.get("equipment").unwrap(),
+        &ctx.accounts.nft_mint.key(),
+        &ctx.accounts.gear_mint.key(),
+        &ctx.accounts.gear_account.key(),
+        &ctx.accounts.user.key(),
+        gear_collection.stat,
+        bonus,
+    )?;
+    
+    // Add the Character account as delegate to user's gear token account
+    token::approve(
+        CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            token::Approve {
+                to: ctx.accounts.gear_account.to_account_info(),
+                delegate: character.to_account_info(),
+                authority: ctx.accounts.user.to_account_info(),
+            },
+        ),
+        1,
+    )?;
+    
+    // Freeze the user's gear token account
+    invoke_signed(
+        &metaplex::instruction::freeze_delegated_account(
+            ctx.accounts.token_metadata_program.key(),
+            character.key(),
+            ctx.accounts.gear_account.key(),
+            ctx.accounts.gear_edition.key(),
+            ctx.accounts.gear_mint.key(),
+        ),
+        &[
+            ctx.accounts.token_metadata_program.to_account_info(),
+            character.to_account_info(),
+            ctx.accounts.gear_account.to_account_info(),
+            ctx.accounts.gear_edition.to_account_info(),
+            ctx.accounts.gear_mint.to_account_info(),
+        ],
+        &[&[b"character".as_ref(), ctx.accounts.nft_mint.key().as_ref(), &[character.bump]]],
+    )?;
+    
+    Ok(())
+}
+
+
+
+/*
+ *  Detach a gear NFT from an unstaked Character and thaw it in the owner's wallet
+ */
+
+
+#[derive(Accounts)]
+pub struct UnequipGear<'info> {
+
+    // Character account the gear is attached to
+    #[account(
+        mut,
+        seeds = [b"character".as_ref(), nft_mint.key().as_ref(),],
+        bump,
+    )]
+    pub character: Box<Account<'info, Character>>,
+    
+    // Mint account of the Character NFT
+    pub nft_mint: Box<Account<'info, Mint>>,
+    
+    // Equipment account of the gear NFT
+    #[account(
+        mut,
+        seeds = [b"equipment".as_ref(), gear_mint.key().as_ref()],
+        bump = equipment.bump,
+        close = user,
+        constraint = equipment.character_mint == nft_mint.key() @ QstakingErrors::InvalidGearCollection,
+        constraint = equipment.owner == user.key() @ QstakingErrors::NotHolder,
+    )]
+    pub equipment: Box<Account<'info, Equipment>>,
+    
+    // Token account holding the frozen gear NFT
+    #[account(
+        mut,
+        constraint = gear_account.key() == equipment.gear_account @ QstakingErrors::NotHolder,
+    )]
+    pub gear_account: Box<Account<'info, TokenAccount>>,
+    
+    // Token (Master) Edition account of the gear NFT
+    ///CHECKED: custom logic checks for the validity of this account
+    pub gear_edition: UncheckedAccount<'info>,
+    
+    // Mint account of the gear NFT
+    pub gear_mint: Box<Account<'info, Mint>>,
+    
+    // Owner of the gear NFT
+    #[account(mut)]
+    pub user: Signer<'info>,
+    
+    // Metaplex Token Metadata program
+    /// CHECKED: This is not dangerous because we don't read or write from this account
+    #[account(address = metaplex::id())]
+    pub token_metadata_program: UncheckedAccount<'info>,
+    
+    // Token program
+    pub token_program: Program<'info, Token>,
+}
+
+
+pub fn unequip(ctx: Context<UnequipGear>) -> Result<()> {
+
+    assert_edition_account(&ctx.accounts.gear_mint.key(), &ctx.accounts.gear_edition.to_account_info())?;
+    
+    let character = &mut ctx.accounts.character;
+    let equipment = &ctx.accounts.equipment;
+    
+    require!(
+        character.staked == 0,
+        QstakingErrors::AlreadyStaked
+    );
+    
+    character.unequip(&ctx.accounts.gear_mint.key(), equipment.stat, equipment.bonus)?;
+    
+    // Thaw the user's gear token account
+    invoke_signed(
+        &metaplex::instruction::thaw_delegated_account(
+            ctx.accounts.token_metadata_program.key(),
+            character.key(),
+            ctx.accounts.gear_account.key(),
+            ctx.accounts.gear_edition.key(),
+            ctx.accounts.gear_mint.key(),
+        ),
+        &[
+            ctx.accounts.token_metadata_program.to_account_info(),
+            character.to_account_info(),
+            ctx.accounts.gear_account.to_account_info(),
+            ctx.accounts.gear_edition.to_account_info(),
+            ctx.accounts.gear_mint.to_account_info(),
+        ],
+        &[&[b"character".as_ref(), ctx.accounts.nft_mint.key().as_ref(), &[character.bump]]],
+    )?;
+    
+    // Remove the Character account as delegate from user's gear token account
+    token::revoke(
+        CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            token::Revoke {
+                source: ctx.accounts.gear_account.to_account_info(),
+                authority: ctx.accounts.user.to_account_info(),
+            },
+        )
+    )?;
+    
+    Ok(())
+}
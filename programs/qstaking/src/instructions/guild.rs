@@ -0,0 +1,372 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{Mint, TokenAccount};
+
+use crate::token_interface;
+use crate::states::*;
+use crate::errors::QstakingErrors;
+
+
+
+/*
+ *  Create a Guild led by the signer together with its treasury token account
+ */
+
+
+#[derive(Accounts)]
+pub struct CreateGuild<'info> {
+
+    // Mine staking pool account
+    #[account(
+        seeds = [b"mine".as_ref(),],
+        bump,
+    )]
+    pub mine: Box<Account<'info, Mine>>,
+    
+    // Guild account
+    #[account(
+        init,
+        payer = leader,
+        seeds = [b"guild".as_ref(), leader.key().as_ref()],
+        bump,
+        space = 8 + Guild::LEN,
+    )]
+    pub guild: Box<Account<'info, Guild>>,
+    
+    // Token account of the Guild receiving the cut of the members' mined rewards
+    ///CHECKED: the address is derived from the Guild and the account is created by the reward token program
+    #[account(
+        mut,
+        seeds = [b"treasury".as_ref(), guild.key().as_ref()],
+        bump,
+    )]
+    pub guild_treasury: UncheckedAccount<'info>,
+    
+    // Address of the reward mint, owned by the Token or the Token-2022 program
+    ///CHECKED: the address is checked against the Mine
+    #[account(
+        constraint = mine.mint == reward_mint.key(),
+    )]
+    pub reward_mint: UncheckedAccount<'info>,
+    
+    // Leader of the Guild
+    #[account(mut)]
+    pub leader: Signer<'info>,
+    
+    // Token program of the reward mint
+    ///CHECKED: the address is checked against the owner of the reward mint
+    #[account(
+        constraint = reward_token_program.key() == *reward_mint.owner @ QstakingErrors::InvalidRewardTokenAccount,
+    )]
+    pub reward_token_program: UncheckedAccount<'info>,
+    
+    // System program
+    pub system_program: Program<'info, System>,
+}
+
+
+pub fn create(ctx: Context<CreateGuild>, cut_bps: u16) -> Result<()> {
+
+    let guild = &mut ctx.accounts.guild;
+    let guild_key = guild.key();
+    let treasury_bump = *ctx.bumps.get("guild_treasury").unwrap();
+    
+    guild.initialize(
+        *ctx.bumps.get("guild").unwrap(),
+        &ctx.accounts.leader.key(),
+        &ctx.accounts.guild_treasury.key(),
+        cut_bps,
+    )?;
+    
+    token_interface::create_token_account(
+        ctx.accounts.reward_token_program.to_account_info(),
+        ctx.accounts.guild_treasury.to_account_info(),
+        ctx.accounts.reward_mint.to_account_info(),
+        &guild_key,
+        ctx.accounts.leader.to_account_info(),
+        ctx.accounts.system_program.to_account_info(),
+        &[&[b"treasury".as_ref(), guild_key.as_ref(), &[treasury_bump]]],
+    )?;
+    
+    Ok(())
+}
+
+
+
+/*
+ *  Set the guild cut of the members' mined rewards.
+ *  Staked members keep the cut they were staked with until they unstake.
+ */
+
+
+#[derive(Accounts)]
+pub struct SetGuildCut<'info> {
+
+    // Guild account
+    #[account(
+        mut,
+        seeds = [b"guild".as_ref(), leader.key().as_ref()],
+        bump = guild.bump,
+        constraint = guild.leader == leader.key(),
+    )]
+    pub guild: Box<Account<'info, Guild>>,
+    
+    // Leader of the Guild
+    pub leader: Signer<'info>,
+}
+
+
+pub fn set_cut(ctx: Context<SetGuildCut>, cut_bps: u16) -> Result<()> {
+
+    let guild = &mut ctx.accounts.guild;
+    guild.set_cut(cut_bps)?;
+    
+    Ok(())
+}
+
+
+
+/*
+ *  Join a Guild with an unstaked Character
+ */
+
+
+#[derive(Accounts)]
+pub struct JoinGuild<'info> {
+
+    // Guild account
+    #[account(
+        mut,
+        seeds = [b"guild".as_ref(), guild.leader.as_ref()],
+        bump = guild.bump,
+    )]
+    pub guild: Box<Account<'info, Guild>>,
+    
+    // Character account of the NFT
+    #[account(
+        mut,
+        seeds = [b"character".as_ref(), nft_mint.key().as_ref(),],
+        bump,
+    )]
+    pub character: Box<Account<'info, Character>>,
+    
+    // Token account holding the Character NFT
+    #[account(
+        token::mint = nft_mint,
+        token::authority = user,
+    )]
+    pub nft_account: Box<Account<'info, TokenAccount>>,
+    
+    // Mint account of the Character NFT
+    pub nft_mint: Box<Account<'info, Mint>>,
+    
+    // User account that holds the Character NFT
+    pub user: Signer<'info>,
+}
+
+
+pub fn join(ctx: Context<JoinGuild>) -> Result<()> {
+
+    let guild = &mut ctx.accounts.guild;
+    let character = &mut ctx.accounts.character;
+    
+    require!(
+        ctx.accounts.nft_account.amount == 1,
+        QstakingErrors::NotHolder
+    );
+    
+    require!(
+        character.staked == 0,
+        QstakingErrors::AlreadyStaked
+    );
+    
+    require!(
+        character.guild == Pubkey::default(),
+        QstakingErrors::GuildMember
+    );
+    
+    guild.add_member()?;
+    character.guild = guild.key();
+    
+    Ok(())
+}
+
+
+
+/*
+ *  Leave the Guild with an unstaked Character
+ */
+
+
+#[derive(Accounts)]
+pub struct LeaveGuild<'info> {
+
+    // Guild account of the Character
+    #[account(
+        mut,
+        seeds = [b"guild".as_ref(), guild.leader.as_ref()],
+        bump = guild.bump,
+    )]
+    pub guild: Box<Account<'info, Guild>>,
+    
+    // Character account of the NFT
+    #[account(
+        mut,
+        seeds = [b"character".as_ref(), nft_mint.key().as_ref(),],
+        bump,
+        constraint = character.guild == guild.key() @ QstakingErrors::NotGuildMember,
+    )]
+    pub character: Box<Account<'info, Character>>,
+    
+    // Token account holding the Character NFT
+    #[account(
+        token::mint = nft_mint,
+        token::authority = user,
+    )]
+    pub nft_account: Box<Account<'info, TokenAccount>>,
+    
+    // Mint account of the Character NFT
+    pub nft_mint: Box<Account<'info, Mint>>,
+    
+    // User account that holds the Character NFT
+    pub user: Signer<'info>,
+}
+
+
+pub fn leave(ctx: Context<LeaveGuild>) -> Result<()> {
+
+    let guild = &mut ctx.accounts.guild;
+    let character = &mut ctx.accounts.character;
+    
+    require!(
+        ctx.accounts.nft_account.amount == 1,
+        QstakingErrors::NotHolder
+    );
+    
+    require!(
+        character.staked == 0,
+        QstakingErrors::AlreadyStaked
+    );
+    
+    guild.remove_member()?;
+    character.guild = Pubkey::default();
+    
+    Ok(())
+}
+
+
+
+/*
+ *  Remove an unstaked Character from the Guild by the leader
+ */
+
+
+#[derive(Accounts)]
+pub struct KickGuildMember<'info> {
+
+    // Guild account
+    #[account(
+        mut,
+        seeds = [b"guild".as_ref(), leader.key().as_ref()],
+        bump = guild.bump,
+        constraint = guild.leader == leader.key(),
+    )]
+    pub guild: Box<Account<'info, Guild>>,
+    
+    // Character account of the NFT
+    #[account(
+        mut,
+        seeds = [b"character".as_ref(), nft_mint.key().as_ref(),],
+        bump,
+        constraint = character.guild == guild.key() @ QstakingErrors::NotGuildMember,
+    )]
+    pub character: Box<Account<'info, Character>>,
+    
+    // Mint account of the Character NFT
+    pub nft_mint: Box<Account<'info, Mint>>,
+    
+    // Leader of the Guild
+    pub leader: Signer<'info>,
+}
+
+
+pub fn kick(ctx: Context<KickGuildMember>) -> Result<()> {
+
+    let guild = &mut ctx.accounts.guild;
+    let character = &mut ctx.accounts.character;
+    
+    require!(
+        character.staked == 0,
+        QstakingErrors::AlreadyStaked
+    );
+    
+    guild.remove_member()?;
+    character.guild = Pubkey::default();
+    
+    Ok(())
+}
+
+
+
+/*
+ *  Withdraw Reward Tokens from the guild treasury by the leader
+ */
+
+
+#[derive(Accounts)]
+pub struct WithdrawGuildTreasury<'info> {
+
+    // Guild account
+    #[account(
+        seeds = [b"guild".as_ref(), leader.key().as_ref()],
+        bump = guild.bump,
+        constraint = guild.leader == leader.key(),
+    )]
+    pub guild: Box<Account<'info, Guild>>,
+    
+    // Token account of the Guild holding the treasury
+    ///CHECKED: the address is checked against the Guild
+    #[account(
+        mut,
+        constraint = guild_treasury.key() == guild.treasury @ QstakingErrors::InvalidGuild,
+    )]
+    pub guild_treasury: UncheckedAccount<'info>,
+    
+    // Token account receiving the Reward Tokens
+    ///CHECKED: the reward token program checks the mint of this account
+    #[account(mut)]
+    pub destination: UncheckedAccount<'info>,
+    
+    // Address of the reward mint, owned by the Token or the Token-2022 program
+    ///CHECKED: the reward token program checks the mint of the treasury
+    pub reward_mint: UncheckedAccount<'info>,
+    
+    // Leader of the Guild
+    pub leader: Signer<'info>,
+    
+    // Token program of the reward mint
+    ///CHECKED: the address is checked against the owner of the reward mint
+    #[account(
+        constraint = reward_token_program.key() == *reward_mint.owner @ QstakingErrors::InvalidRewardTokenAccount,
+    )]
+    pub reward_token_program: UncheckedAccount<'info>,
+}
+
+
+pub fn withdraw(ctx: Context<WithdrawGuildTreasury>, amount: u64) -> Result<()> {
+
+    let guild = &ctx.accounts.guild;
+    let decimals = token_interface::unpack_mint(&ctx.accounts.reward_mint.to_account_info(), &ctx.accounts.reward_token_program.key())?.decimals;
+    
+    token_interface::transfer_checked(
+        ctx.accounts.reward_token_program.to_account_info(),
+        ctx.accounts.guild_treasury.to_account_info(),
+        ctx.accounts.reward_mint.to_account_info(),
+        ctx.accounts.destination.to_account_info(),
+        guild.to_account_info(),
+        amount,
+        decimals,
+        &[&[b"guild".as_ref(), guild.leader.as_ref(), &[guild.bump]]],
+    )?;
+    
+    Ok(())
+}
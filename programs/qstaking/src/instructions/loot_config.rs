@@ -12,7 +12,7 @@ use crate::errors::QstakingErrors;
 
 #[derive(Accounts)]
 pub struct SetLootManager<'info> {
-    
+
     // AdminSettings account
     #[account(
         seeds = [b"admin".as_ref()],
@@ -48,7 +48,7 @@ pub fn set_manager(ctx: Context<SetLootManager>, manager: Pubkey) -> Result<()>
     
     let loot = &mut ctx.accounts.loot;
     loot.manager = manager;
-        
+    
     Ok(())
 }
 
@@ -57,7 +57,7 @@ pub fn set_manager(ctx: Context<SetLootManager>, manager: Pubkey) -> Result<()>
 /*
  *  Set the Loot pool lock.
  */
- 
+
 
 #[derive(Accounts)]
 pub struct SetLootLock<'info> {
@@ -80,9 +80,50 @@ pub struct SetLootLock<'info> {
 
 
 pub fn set_locked(ctx: Context<SetLootLock>, locked: bool) -> Result<()> {
-    
+
     let loot = &mut ctx.accounts.loot;
     loot.locked = locked;
+    loot.season_locked = false;
+    
+    Ok(())
+}
+
+
+
+/*
+ *  Set the Loot pool custody mode.
+ */
+
+
+#[derive(Accounts)]
+pub struct SetLootCustody<'info> {
+
+    // Address of the Loot staking pool
+    #[account(
+        mut,
+        seeds = [b"loot".as_ref(),],
+        bump,
+    )]
+    pub loot: Account<'info, Loot>,
+    
+    // Manager of the Loot staking pool
+    #[account(
+        mut,
+        constraint = loot.manager == manager.key()
+    )]
+    pub manager: Signer<'info>,
+}
+
+
+pub fn set_custody(ctx: Context<SetLootCustody>, custody: u8) -> Result<()> {
+
+    require!(
+        custody < 2,
+        QstakingErrors::InvalidCustodyMode
+    );
+    
+    let loot = &mut ctx.accounts.loot;
+    loot.custody = custody;
     
     Ok(())
 }
@@ -93,7 +134,7 @@ pub fn set_locked(ctx: Context<SetLootLock>, locked: bool) -> Result<()> {
  *  Set the Loot pool parameters
  */
 
- 
+
 #[derive(Accounts)]
 pub struct SetLootParameters<'info> {
 
@@ -116,7 +157,7 @@ pub struct SetLootParameters<'info> {
 
 
 pub fn set_parameters(ctx: Context<SetLootParameters>, duration: u64, price: u64, cooldown: u64) -> Result<()> {
-    
+
     require!(
         duration > 0,
         QstakingErrors::InvalidLootDuration
@@ -139,8 +180,8 @@ pub fn set_parameters(ctx: Context<SetLootParameters>, duration: u64, price: u64
 /*
  *  Update Loot staking pool's accrued rewards
  */
- 
- 
+
+
 #[derive(Accounts)]
 pub struct UpdateLoot<'info> {
 
@@ -1,10 +1,14 @@
 use anchor_lang::prelude::*;
 use anchor_spl::token::{self, Mint, TokenAccount, Token};
 use anchor_spl::associated_token::AssociatedToken;
-use solana_program::program::invoke_signed;
+use solana_program::program::{invoke, invoke_signed};
+use solana_program::sysvar;
+use solana_program::program_option::COption;
 use mpl_token_metadata as metaplex;
 //use mpl_token_metadata::state::Metadata;
 
+use crate::metadata;
+use crate::token_interface;
 use crate::states::*;
 use crate::errors::QstakingErrors; 
 
@@ -38,13 +42,21 @@ pub struct StakeLoot<'info> {
     #[account(mut)]
     pub whitelist: Account<'info, Whitelist>,
     
-    // Associated token account of the NFT
+    // Denylist account of the NFT, only initialized if the mint is denylisted
+    ///CHECKED: the address is derived from the NFT mint and only the existence of the account is inspected
+    #[account(
+        seeds = [b"denylist".as_ref(), nft_mint.key().as_ref()],
+        bump,
+    )]
+    pub denylist: UncheckedAccount<'info>,
+    
+    // Token account holding the NFT
     #[account(
         mut,
-        associated_token::mint = nft_mint,
-        associated_token::authority = user,
+        token::mint = nft_mint,
+        token::authority = user,
     )]
-    pub nft_ata: Account<'info, TokenAccount>,
+    pub nft_account: Account<'info, TokenAccount>,
     
     // Token (Master) Edition account
     ///CHECKED: custom logic checks for the validity of this account
@@ -52,6 +64,7 @@ pub struct StakeLoot<'info> {
     
     // Metadata account of the NFT
     ///CHECKED: custom logic checks for the validity of this account
+    #[account(mut)]
     pub nft_metadata: UncheckedAccount<'info>,
     
     // Mint account of the NFT
@@ -61,8 +74,27 @@ pub struct StakeLoot<'info> {
     #[account(mut)]
     pub user: Signer<'info>,
     
+    // Token record account of the NFT, only used by programmable NFTs
+    ///CHECKED: validated by the Token Metadata program
+    #[account(mut)]
+    pub nft_token_record: UncheckedAccount<'info>,
+    
+    // Authorization rules account of the NFT, only used by programmable NFTs
+    ///CHECKED: validated by the Token Metadata program
+    pub authorization_rules: UncheckedAccount<'info>,
+    
+    // Metaplex Token Authorization Rules program, only used by programmable NFTs
+    ///CHECKED: validated by the Token Metadata program
+    pub authorization_rules_program: UncheckedAccount<'info>,
+    
+    // Instructions sysvar
+    ///CHECKED: the address is checked against the instructions sysvar
+    #[account(address = sysvar::instructions::ID)]
+    pub sysvar_instructions: UncheckedAccount<'info>,
+    
     // Metaplex Token Metadata program
     /// CHECKED: This is not dangerous because we don't read or write from this account
+    #[account(address = metaplex::id())]
     pub token_metadata_program: UncheckedAccount<'info>,
     
     // Token program
@@ -70,13 +102,13 @@ pub struct StakeLoot<'info> {
     
     // Associated token program
     pub associated_token_program: Program<'info, AssociatedToken>,
-
+    
     // System program
     pub system_program: Program<'info, System>,
 }
 
 
-pub fn stake(ctx: Context<StakeLoot>) -> Result<()> {
+pub fn stake(ctx: Context<StakeLoot>, proof: Option<WhitelistProof>) -> Result<()> {
 
     assert_edition_account(&ctx.accounts.nft_mint.key(), &ctx.accounts.nft_edition.to_account_info())?;
     
@@ -91,7 +123,12 @@ pub fn stake(ctx: Context<StakeLoot>) -> Result<()> {
     );
     
     require!(
-        ctx.accounts.nft_ata.amount == 1,
+        loot.custody == 0,
+        QstakingErrors::InvalidCustodyMode
+    );
+    
+    require!(
+        ctx.accounts.nft_account.amount == 1,
         QstakingErrors::NotHolder
     );
     
@@ -101,44 +138,102 @@ pub fn stake(ctx: Context<StakeLoot>) -> Result<()> {
     );
     
     
-    whitelist.verify(&ctx.program_id, &whitelist.key(), &ctx.accounts.nft_mint.key(), &ctx.accounts.nft_metadata.to_account_info())?;
-    loot.add_character(clock.unix_timestamp, character.mining_points)?;
-    character.stake_loot(clock.unix_timestamp, loot.accrued_rewards)?;
+    whitelist.verify(&ctx.program_id, &whitelist.key(), &ctx.accounts.nft_mint.key(), &ctx.accounts.nft_metadata.to_account_info(), &ctx.accounts.denylist.to_account_info(), &proof)?;
+    loot.add_character(clock.unix_timestamp, character.looting_points)?;
+    character.stake_loot(clock.unix_timestamp, loot.season, loot.accrued_rewards, 0, &ctx.accounts.nft_account.key(), &Pubkey::default())?;
     
-    // Add the Loot account as delegate to user's NFT ATA
-    token::approve(
-        CpiContext::new(
-            ctx.accounts.token_program.to_account_info(),
-            token::Approve {
-                to: ctx.accounts.nft_ata.to_account_info(),
-                delegate: loot.to_account_info(),
-                authority: ctx.accounts.user.to_account_info(),
-            },
-        ),
-        1,
-    )?;
+    if metadata::is_programmable(&ctx.accounts.nft_metadata.to_account_info())? {
     
-    // Freeze the user's NFT ATA
-    invoke_signed(
-        &metaplex::instruction::freeze_delegated_account(
-            ctx.accounts.token_metadata_program.key(),
-            loot.key(),
-            ctx.accounts.nft_ata.key(),
-            ctx.accounts.nft_edition.key(),
-            ctx.accounts.nft_mint.key(),
-        ),
-        &[
+        let pnft_account_infos = [
             ctx.accounts.token_metadata_program.to_account_info(),
             loot.to_account_info(),
-            ctx.accounts.nft_ata.to_account_info(),
+            ctx.accounts.nft_metadata.to_account_info(),
             ctx.accounts.nft_edition.to_account_info(),
+            ctx.accounts.nft_token_record.to_account_info(),
             ctx.accounts.nft_mint.to_account_info(),
-        ],
-        &[&[b"loot".as_ref(), &[loot.bump]]],
-    )?;
+            ctx.accounts.nft_account.to_account_info(),
+            ctx.accounts.user.to_account_info(),
+            ctx.accounts.system_program.to_account_info(),
+            ctx.accounts.sysvar_instructions.to_account_info(),
+            ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.authorization_rules_program.to_account_info(),
+            ctx.accounts.authorization_rules.to_account_info(),
+        ];
+        
+        // Add the Loot account as staking delegate of user's pNFT
+        invoke(
+            &metadata::delegate_staking(
+                ctx.accounts.token_metadata_program.key(),
+                loot.key(),
+                ctx.accounts.nft_metadata.key(),
+                ctx.accounts.nft_edition.key(),
+                ctx.accounts.nft_token_record.key(),
+                ctx.accounts.nft_mint.key(),
+                ctx.accounts.nft_account.key(),
+                ctx.accounts.user.key(),
+                ctx.accounts.user.key(),
+                ctx.accounts.authorization_rules_program.key(),
+                ctx.accounts.authorization_rules.key(),
+            ),
+            &pnft_account_infos,
+        )?;
+        
+        // Lock the user's pNFT
+        invoke_signed(
+            &metadata::lock(
+                ctx.accounts.token_metadata_program.key(),
+                loot.key(),
+                ctx.accounts.user.key(),
+                ctx.accounts.nft_account.key(),
+                ctx.accounts.nft_mint.key(),
+                ctx.accounts.nft_metadata.key(),
+                ctx.accounts.nft_edition.key(),
+                ctx.accounts.nft_token_record.key(),
+                ctx.accounts.user.key(),
+                ctx.accounts.authorization_rules_program.key(),
+                ctx.accounts.authorization_rules.key(),
+            ),
+            &pnft_account_infos,
+            &[&[b"loot".as_ref(), &[loot.bump]]],
+        )?;
+    
+    } else {
+    
+        // Add the Loot account as delegate to user's NFT token account
+        token::approve(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                token::Approve {
+                    to: ctx.accounts.nft_account.to_account_info(),
+                    delegate: loot.to_account_info(),
+                    authority: ctx.accounts.user.to_account_info(),
+                },
+            ),
+            1,
+        )?;
+        
+        // Freeze the user's NFT token account
+        invoke_signed(
+            &metaplex::instruction::freeze_delegated_account(
+                ctx.accounts.token_metadata_program.key(),
+                loot.key(),
+                ctx.accounts.nft_account.key(),
+                ctx.accounts.nft_edition.key(),
+                ctx.accounts.nft_mint.key(),
+            ),
+            &[
+                ctx.accounts.token_metadata_program.to_account_info(),
+                loot.to_account_info(),
+                ctx.accounts.nft_account.to_account_info(),
+                ctx.accounts.nft_edition.to_account_info(),
+                ctx.accounts.nft_mint.to_account_info(),
+            ],
+            &[&[b"loot".as_ref(), &[loot.bump]]],
+        )?;
+    }
     
     Ok(())
- 
+
 } 
 
 
@@ -150,7 +245,7 @@ pub fn stake(ctx: Context<StakeLoot>) -> Result<()> {
 
 #[derive(Accounts)]
 pub struct UnstakeLoot<'info> {
-    
+
     // Loot staking pool account
     #[account(
         mut,
@@ -168,51 +263,92 @@ pub struct UnstakeLoot<'info> {
     pub character: Box<Account<'info, Character>>,
     
     // Token account with loot rewards
+    ///CHECKED: the account is unpacked and its mint and owner checked with the reward token program
     #[account(
         mut,
         seeds = [b"proceeds".as_ref(), loot.key().as_ref()],
         bump,
-        token::authority = loot,
     )]
-    pub loot_proceeds: Box<Account<'info, TokenAccount>>,
+    pub loot_proceeds: UncheckedAccount<'info>,
+    
+    // Denylist account of the NFT, only initialized if the mint is denylisted
+    ///CHECKED: the address is derived from the NFT mint and only the existence of the account is inspected
+    #[account(
+        seeds = [b"denylist".as_ref(), nft_mint.key().as_ref()],
+        bump,
+    )]
+    pub denylist: UncheckedAccount<'info>,
     
-    // Associated token account of the NFT
+    // Token account holding the NFT
     #[account(
         mut,
-        associated_token::mint = nft_mint,
-        associated_token::authority = user,
+        token::mint = nft_mint,
+        token::authority = user,
+        constraint = nft_account.key() == character.nft_account @ QstakingErrors::NotHolder,
     )]
-    pub nft_ata: Box<Account<'info, TokenAccount>>,
+    pub nft_account: Box<Account<'info, TokenAccount>>,
     
     // Token (Master) Edition account
     ///CHECKED: custom logic checks for the validity of this account
     pub nft_edition: UncheckedAccount<'info>,
     
+    // Metadata account of the NFT
+    ///CHECKED: custom logic checks for the validity of this account
+    #[account(mut)]
+    pub nft_metadata: UncheckedAccount<'info>,
+    
     // Mint account of the NFT
     pub nft_mint: Box<Account<'info, Mint>>,
     
     // User's associated token account for the reward tokens
-    #[account(
-        mut,
-        associated_token::mint = loot_proceeds.mint,
-        associated_token::authority = user,
-    )]
-    pub reward_ata: Box<Account<'info, TokenAccount>>,
+    ///CHECKED: the address is derived from the user, the reward mint and the reward token program
+    #[account(mut)]
+    pub reward_ata: UncheckedAccount<'info>,
+    
+    // Address of the reward mint, owned by the Token or the Token-2022 program
+    ///CHECKED: the mint is checked against the mint of the loot proceeds account
+    pub reward_mint: UncheckedAccount<'info>,
     
     // User account that holds the NFT
     #[account(mut)]
     pub user: Signer<'info>,
     
+    // Token record account of the NFT, only used by programmable NFTs
+    ///CHECKED: validated by the Token Metadata program
+    #[account(mut)]
+    pub nft_token_record: UncheckedAccount<'info>,
+    
+    // Authorization rules account of the NFT, only used by programmable NFTs
+    ///CHECKED: validated by the Token Metadata program
+    pub authorization_rules: UncheckedAccount<'info>,
+    
+    // Metaplex Token Authorization Rules program, only used by programmable NFTs
+    ///CHECKED: validated by the Token Metadata program
+    pub authorization_rules_program: UncheckedAccount<'info>,
+    
+    // Instructions sysvar
+    ///CHECKED: the address is checked against the instructions sysvar
+    #[account(address = sysvar::instructions::ID)]
+    pub sysvar_instructions: UncheckedAccount<'info>,
+    
     // Metaplex Token Metadata program
     /// CHECKED: This is not dangerous because we don't read or write from this account
+    #[account(address = metaplex::id())]
     pub token_metadata_program: UncheckedAccount<'info>,
     
     // Token program
     pub token_program: Program<'info, Token>,
     
+    // Token program of the reward mint
+    ///CHECKED: the address is checked against the owner of the reward mint
+    #[account(
+        constraint = reward_token_program.key() == *reward_mint.owner @ QstakingErrors::InvalidRewardTokenAccount,
+    )]
+    pub reward_token_program: UncheckedAccount<'info>,
+    
     // Associated token program
     pub associated_token_program: Program<'info, AssociatedToken>,
-
+    
     // System program
     pub system_program: Program<'info, System>,
 }
@@ -221,13 +357,14 @@ pub struct UnstakeLoot<'info> {
 pub fn unstake(ctx: Context<UnstakeLoot>) -> Result<()> {
 
     assert_edition_account(&ctx.accounts.nft_mint.key(), &ctx.accounts.nft_edition.to_account_info())?;
+    metadata::assert_metadata_account(&ctx.accounts.nft_mint.key(), &ctx.accounts.nft_metadata.to_account_info())?;
     
     let clock = Clock::get()?;
     let loot = &mut ctx.accounts.loot;
     let character = &mut ctx.accounts.character;
     
     require!(
-        ctx.accounts.nft_ata.amount == 1,
+        ctx.accounts.nft_account.amount == 1,
         QstakingErrors::NotHolder
     );
     
@@ -236,54 +373,636 @@ pub fn unstake(ctx: Context<UnstakeLoot>) -> Result<()> {
         QstakingErrors::NotStaked
     );
     
-    loot.update_accrued_rewards(clock.unix_timestamp)?;
-    let accrued_reward = character.unstake_loot(clock.unix_timestamp, loot.cooldown, loot.accrued_rewards)?;
-    loot.remove_character(character.looting_points, accrued_reward)?;
-    
-    // Thaw the user's NFT ATA
-    invoke_signed(
-        &metaplex::instruction::thaw_delegated_account(
-            ctx.accounts.token_metadata_program.key(),
-            loot.key(),
-            ctx.accounts.nft_ata.key(),
-            ctx.accounts.nft_edition.key(),
-            ctx.accounts.nft_mint.key(),
-        ),
-        &[
+    require!(
+        character.custody == 0,
+        QstakingErrors::InvalidCustodyMode
+    );
+    
+    token_interface::assert_reward_accounts(
+        &ctx.accounts.reward_token_program.key(),
+        &ctx.accounts.reward_mint.to_account_info(),
+        &ctx.accounts.reward_ata.to_account_info(),
+        &ctx.accounts.user.key(),
+        &ctx.accounts.loot_proceeds.to_account_info(),
+        &loot.key(),
+    )?;
+    
+    let denylisted = Denylist::is_denylisted(ctx.program_id, &ctx.accounts.denylist.to_account_info());
+    let accrued_reward = settle_loot(loot, character, denylisted, clock.unix_timestamp)?;
+    Leaderboard::record(ctx.program_id, ctx.remaining_accounts, &ctx.accounts.nft_mint.key(), character)?;
+    
+    if metadata::is_programmable(&ctx.accounts.nft_metadata.to_account_info())? {
+    
+        let pnft_account_infos = [
             ctx.accounts.token_metadata_program.to_account_info(),
             loot.to_account_info(),
-            ctx.accounts.nft_ata.to_account_info(),
+            ctx.accounts.nft_metadata.to_account_info(),
             ctx.accounts.nft_edition.to_account_info(),
+            ctx.accounts.nft_token_record.to_account_info(),
             ctx.accounts.nft_mint.to_account_info(),
-        ],
-        &[&[b"loot".as_ref(), &[loot.bump]]],
+            ctx.accounts.nft_account.to_account_info(),
+            ctx.accounts.user.to_account_info(),
+            ctx.accounts.system_program.to_account_info(),
+            ctx.accounts.sysvar_instructions.to_account_info(),
+            ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.authorization_rules_program.to_account_info(),
+            ctx.accounts.authorization_rules.to_account_info(),
+        ];
+        
+        // Unlock the user's pNFT
+        invoke_signed(
+            &metadata::unlock(
+                ctx.accounts.token_metadata_program.key(),
+                loot.key(),
+                ctx.accounts.user.key(),
+                ctx.accounts.nft_account.key(),
+                ctx.accounts.nft_mint.key(),
+                ctx.accounts.nft_metadata.key(),
+                ctx.accounts.nft_edition.key(),
+                ctx.accounts.nft_token_record.key(),
+                ctx.accounts.user.key(),
+                ctx.accounts.authorization_rules_program.key(),
+                ctx.accounts.authorization_rules.key(),
+            ),
+            &pnft_account_infos,
+            &[&[b"loot".as_ref(), &[loot.bump]]],
+        )?;
+        
+        // Remove the Loot account as staking delegate of user's pNFT
+        invoke(
+            &metadata::revoke_staking(
+                ctx.accounts.token_metadata_program.key(),
+                loot.key(),
+                ctx.accounts.nft_metadata.key(),
+                ctx.accounts.nft_edition.key(),
+                ctx.accounts.nft_token_record.key(),
+                ctx.accounts.nft_mint.key(),
+                ctx.accounts.nft_account.key(),
+                ctx.accounts.user.key(),
+                ctx.accounts.user.key(),
+                ctx.accounts.authorization_rules_program.key(),
+                ctx.accounts.authorization_rules.key(),
+            ),
+            &pnft_account_infos,
+        )?;
+    
+    } else {
+    
+        // Thaw the user's NFT token account
+        invoke_signed(
+            &metaplex::instruction::thaw_delegated_account(
+                ctx.accounts.token_metadata_program.key(),
+                loot.key(),
+                ctx.accounts.nft_account.key(),
+                ctx.accounts.nft_edition.key(),
+                ctx.accounts.nft_mint.key(),
+            ),
+            &[
+                ctx.accounts.token_metadata_program.to_account_info(),
+                loot.to_account_info(),
+                ctx.accounts.nft_account.to_account_info(),
+                ctx.accounts.nft_edition.to_account_info(),
+                ctx.accounts.nft_mint.to_account_info(),
+            ],
+            &[&[b"loot".as_ref(), &[loot.bump]]],
+        )?;
+        
+        // Remove the Loot account as delegate from user's NFT token account
+        token::revoke(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                token::Revoke {
+                    source: ctx.accounts.nft_account.to_account_info(),
+                    authority: ctx.accounts.user.to_account_info(),
+                },
+            )
+        )?;
+    }
+    
+    
+    transfer_rewards(
+        ctx.accounts.reward_token_program.to_account_info(),
+        ctx.accounts.loot_proceeds.to_account_info(),
+        ctx.accounts.reward_mint.to_account_info(),
+        ctx.accounts.reward_ata.to_account_info(),
+        loot.to_account_info(),
+        loot.bump,
+        accrued_reward,
     )?;
     
-    // Remove the Loot account as delegate from user's NFT ATA
-    token::revoke(
+    Ok(())
+}
+
+
+
+/*
+ *  Force-unstake a denylisted NFT from the Loot, its rewards are withheld on the Character
+ */
+
+
+#[derive(Accounts)]
+pub struct ForceUnstakeLoot<'info> {
+
+    // AdminSettings account
+    #[account(
+        seeds = [b"admin".as_ref()],
+        bump,
+    )]
+    pub admin_settings: Box<Account<'info, AdminSettings>>,
+    
+    // Loot staking pool account
+    #[account(
+        mut,
+        seeds = [b"loot".as_ref(),],
+        bump,
+    )]
+    pub loot: Box<Account<'info, Loot>>,
+    
+    // Character account of the NFT
+    #[account(
+        mut,
+        seeds = [b"character".as_ref(), nft_mint.key().as_ref(),],
+        bump,
+    )]
+    pub character: Box<Account<'info, Character>>,
+    
+    // Denylist account of the NFT
+    #[account(
+        seeds = [b"denylist".as_ref(), nft_mint.key().as_ref()],
+        bump,
+    )]
+    pub denylist: Box<Account<'info, Denylist>>,
+    
+    // Token account of the NFT frozen with the Loot as delegate
+    #[account(
+        mut,
+        token::mint = nft_mint,
+        constraint = nft_account.delegate == COption::Some(loot.key()),
+        constraint = nft_account.key() == character.nft_account,
+    )]
+    pub nft_account: Box<Account<'info, TokenAccount>>,
+    
+    // Token (Master) Edition account
+    ///CHECKED: custom logic checks for the validity of this account
+    pub nft_edition: UncheckedAccount<'info>,
+    
+    // Metadata account of the NFT
+    ///CHECKED: custom logic checks for the validity of this account
+    #[account(mut)]
+    pub nft_metadata: UncheckedAccount<'info>,
+    
+    // Mint account of the NFT
+    pub nft_mint: Box<Account<'info, Mint>>,
+    
+    // Holder of the NFT token account
+    ///CHECKED: the address is checked against the owner of the NFT token account
+    #[account(
+        constraint = nft_account.owner == nft_owner.key(),
+    )]
+    pub nft_owner: UncheckedAccount<'info>,
+    
+    // Staking program admin defined in AdminSettings
+    #[account(
+        mut,
+        constraint = admin_settings.admin_key == authority.key(),
+    )]
+    pub authority: Signer<'info>,
+    
+    // Token record account of the NFT, only used by programmable NFTs
+    ///CHECKED: validated by the Token Metadata program
+    #[account(mut)]
+    pub nft_token_record: UncheckedAccount<'info>,
+    
+    // Authorization rules account of the NFT, only used by programmable NFTs
+    ///CHECKED: validated by the Token Metadata program
+    pub authorization_rules: UncheckedAccount<'info>,
+    
+    // Metaplex Token Authorization Rules program, only used by programmable NFTs
+    ///CHECKED: validated by the Token Metadata program
+    pub authorization_rules_program: UncheckedAccount<'info>,
+    
+    // Instructions sysvar
+    ///CHECKED: the address is checked against the instructions sysvar
+    #[account(address = sysvar::instructions::ID)]
+    pub sysvar_instructions: UncheckedAccount<'info>,
+    
+    // Metaplex Token Metadata program
+    /// CHECKED: This is not dangerous because we don't read or write from this account
+    #[account(address = metaplex::id())]
+    pub token_metadata_program: UncheckedAccount<'info>,
+    
+    // Token program
+    pub token_program: Program<'info, Token>,
+    
+    // System program
+    pub system_program: Program<'info, System>,
+}
+
+
+pub fn force_unstake(ctx: Context<ForceUnstakeLoot>) -> Result<()> {
+
+    assert_edition_account(&ctx.accounts.nft_mint.key(), &ctx.accounts.nft_edition.to_account_info())?;
+    metadata::assert_metadata_account(&ctx.accounts.nft_mint.key(), &ctx.accounts.nft_metadata.to_account_info())?;
+    
+    let clock = Clock::get()?;
+    let loot = &mut ctx.accounts.loot;
+    let character = &mut ctx.accounts.character;
+    
+    require!(
+        character.staked == 2,
+        QstakingErrors::NotStaked
+    );
+    
+    require!(
+        character.custody == 0,
+        QstakingErrors::InvalidCustodyMode
+    );
+    
+    settle_loot(loot, character, true, clock.unix_timestamp)?;
+    
+    if metadata::is_programmable(&ctx.accounts.nft_metadata.to_account_info())? {
+    
+        let pnft_account_infos = [
+            ctx.accounts.token_metadata_program.to_account_info(),
+            loot.to_account_info(),
+            ctx.accounts.nft_metadata.to_account_info(),
+            ctx.accounts.nft_edition.to_account_info(),
+            ctx.accounts.nft_token_record.to_account_info(),
+            ctx.accounts.nft_mint.to_account_info(),
+            ctx.accounts.nft_account.to_account_info(),
+            ctx.accounts.nft_owner.to_account_info(),
+            ctx.accounts.authority.to_account_info(),
+            ctx.accounts.system_program.to_account_info(),
+            ctx.accounts.sysvar_instructions.to_account_info(),
+            ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.authorization_rules_program.to_account_info(),
+            ctx.accounts.authorization_rules.to_account_info(),
+        ];
+        
+        // Unlock the holder's pNFT
+        invoke_signed(
+            &metadata::unlock(
+                ctx.accounts.token_metadata_program.key(),
+                loot.key(),
+                ctx.accounts.nft_owner.key(),
+                ctx.accounts.nft_account.key(),
+                ctx.accounts.nft_mint.key(),
+                ctx.accounts.nft_metadata.key(),
+                ctx.accounts.nft_edition.key(),
+                ctx.accounts.nft_token_record.key(),
+                ctx.accounts.authority.key(),
+                ctx.accounts.authorization_rules_program.key(),
+                ctx.accounts.authorization_rules.key(),
+            ),
+            &pnft_account_infos,
+            &[&[b"loot".as_ref(), &[loot.bump]]],
+        )?;
+        
+        // Remove the Loot account as staking delegate of the holder's pNFT
+        invoke_signed(
+            &metadata::revoke_staking(
+                ctx.accounts.token_metadata_program.key(),
+                loot.key(),
+                ctx.accounts.nft_metadata.key(),
+                ctx.accounts.nft_edition.key(),
+                ctx.accounts.nft_token_record.key(),
+                ctx.accounts.nft_mint.key(),
+                ctx.accounts.nft_account.key(),
+                loot.key(),
+                ctx.accounts.authority.key(),
+                ctx.accounts.authorization_rules_program.key(),
+                ctx.accounts.authorization_rules.key(),
+            ),
+            &pnft_account_infos,
+            &[&[b"loot".as_ref(), &[loot.bump]]],
+        )?;
+    
+    } else {
+    
+        // Only the holder can revoke a delegate of an SPL token account, so the Loot stays delegate of the thawed
+        // account until the holder revokes it, transfers the NFT or stakes it again
+        
+        // Thaw the holder's NFT token account
+        invoke_signed(
+            &metaplex::instruction::thaw_delegated_account(
+                ctx.accounts.token_metadata_program.key(),
+                loot.key(),
+                ctx.accounts.nft_account.key(),
+                ctx.accounts.nft_edition.key(),
+                ctx.accounts.nft_mint.key(),
+            ),
+            &[
+                ctx.accounts.token_metadata_program.to_account_info(),
+                loot.to_account_info(),
+                ctx.accounts.nft_account.to_account_info(),
+                ctx.accounts.nft_edition.to_account_info(),
+                ctx.accounts.nft_mint.to_account_info(),
+            ],
+            &[&[b"loot".as_ref(), &[loot.bump]]],
+        )?;
+    }
+    
+    Ok(())
+}
+
+
+
+/*
+ *  Stake an NFT by escrowing it into a Loot vault
+ */
+
+
+#[derive(Accounts)]
+pub struct StakeLootVault<'info> {
+
+    // Loot account in which to stake the NFT
+    #[account(
+        mut,
+        seeds = [b"loot".as_ref(),],
+        bump,
+    )]
+    pub loot: Box<Account<'info, Loot>>,
+    
+    // Character account of the NFT
+    #[account(
+        mut,
+        seeds = [b"character".as_ref(), nft_mint.key().as_ref(),],
+        bump,
+    )]
+    pub character: Box<Account<'info, Character>>,
+    
+    // Whitelist account to be used for whitelist proof
+    #[account(mut)]
+    pub whitelist: Box<Account<'info, Whitelist>>,
+    
+    // Denylist account of the NFT, only initialized if the mint is denylisted
+    ///CHECKED: the address is derived from the NFT mint and only the existence of the account is inspected
+    #[account(
+        seeds = [b"denylist".as_ref(), nft_mint.key().as_ref()],
+        bump,
+    )]
+    pub denylist: UncheckedAccount<'info>,
+    
+    // Token account holding the NFT
+    #[account(
+        mut,
+        token::mint = nft_mint,
+        token::authority = user,
+    )]
+    pub nft_account: Box<Account<'info, TokenAccount>>,
+    
+    // Vault token account of the Loot holding the NFT while staked
+    #[account(
+        init,
+        payer = user,
+        seeds = [b"vault".as_ref(), nft_mint.key().as_ref()],
+        bump,
+        token::mint = nft_mint,
+        token::authority = loot,
+    )]
+    pub nft_vault: Box<Account<'info, TokenAccount>>,
+    
+    // Metadata account of the NFT
+    ///CHECKED: custom logic checks for the validity of this account
+    pub nft_metadata: UncheckedAccount<'info>,
+    
+    // Mint account of the NFT
+    pub nft_mint: Box<Account<'info, Mint>>,
+    
+    // User account that holds the NFT
+    #[account(mut)]
+    pub user: Signer<'info>,
+    
+    // Token program
+    pub token_program: Program<'info, Token>,
+    
+    // Associated token program
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    
+    // System program
+    pub system_program: Program<'info, System>,
+    
+    // Rent program
+    pub rent: Sysvar<'info, Rent>,
+}
+
+
+pub fn stake_vault(ctx: Context<StakeLootVault>, proof: Option<WhitelistProof>) -> Result<()> {
+
+    let clock = Clock::get()?;
+    let loot = &mut ctx.accounts.loot;
+    let character = &mut ctx.accounts.character;
+    let whitelist = &mut ctx.accounts.whitelist;
+    
+    require!(
+        !loot.locked,
+        QstakingErrors::StakingPoolLocked
+    );
+    
+    require!(
+        loot.custody == 1,
+        QstakingErrors::InvalidCustodyMode
+    );
+    
+    require!(
+        ctx.accounts.nft_account.amount == 1,
+        QstakingErrors::NotHolder
+    );
+    
+    require!(
+        character.staked == 0,
+        QstakingErrors::AlreadyStaked
+    );
+    
+    // Programmable NFTs can only be moved through Token Metadata and are staked in the user's wallet
+    require!(
+        !metadata::is_programmable(&ctx.accounts.nft_metadata.to_account_info())?,
+        QstakingErrors::InvalidCustodyMode
+    );
+    
+    
+    whitelist.verify(&ctx.program_id, &whitelist.key(), &ctx.accounts.nft_mint.key(), &ctx.accounts.nft_metadata.to_account_info(), &ctx.accounts.denylist.to_account_info(), &proof)?;
+    loot.add_character(clock.unix_timestamp, character.looting_points)?;
+    character.stake_loot(clock.unix_timestamp, loot.season, loot.accrued_rewards, 1, &ctx.accounts.nft_vault.key(), &ctx.accounts.user.key())?;
+    
+    // Escrow the NFT into the Loot vault
+    token::transfer(
         CpiContext::new(
             ctx.accounts.token_program.to_account_info(),
-            token::Revoke {
-                source: ctx.accounts.nft_ata.to_account_info(),
+            token::Transfer {
+                from: ctx.accounts.nft_account.to_account_info(),
+                to: ctx.accounts.nft_vault.to_account_info(),
                 authority: ctx.accounts.user.to_account_info(),
             },
-        )
+        ),
+        1,
+    )?;
+    
+    Ok(())
+}
+
+
+
+/*
+ *  Unstake an NFT escrowed in a Loot vault
+ */
+
+
+#[derive(Accounts)]
+pub struct UnstakeLootVault<'info> {
+
+    // Loot staking pool account
+    #[account(
+        mut,
+        seeds = [b"loot".as_ref(),],
+        bump,
+    )]
+    pub loot: Box<Account<'info, Loot>>,
+    
+    // Character account of the NFT
+    #[account(
+        mut,
+        seeds = [b"character".as_ref(), nft_mint.key().as_ref(),],
+        bump,
+        constraint = character.vault_owner == user.key() @ QstakingErrors::NotHolder,
+    )]
+    pub character: Box<Account<'info, Character>>,
+    
+    // Denylist account of the NFT, only initialized if the mint is denylisted
+    ///CHECKED: the address is derived from the NFT mint and only the existence of the account is inspected
+    #[account(
+        seeds = [b"denylist".as_ref(), nft_mint.key().as_ref()],
+        bump,
+    )]
+    pub denylist: UncheckedAccount<'info>,
+    
+    // Token account with loot rewards
+    ///CHECKED: the account is unpacked and its mint and owner checked with the reward token program
+    #[account(
+        mut,
+        seeds = [b"proceeds".as_ref(), loot.key().as_ref()],
+        bump,
+    )]
+    pub loot_proceeds: UncheckedAccount<'info>,
+    
+    // Token account holding the NFT
+    #[account(
+        mut,
+        token::mint = nft_mint,
+        token::authority = user,
+    )]
+    pub nft_account: Box<Account<'info, TokenAccount>>,
+    
+    // Vault token account of the Loot holding the NFT
+    #[account(
+        mut,
+        seeds = [b"vault".as_ref(), nft_mint.key().as_ref()],
+        bump,
+        token::mint = nft_mint,
+        token::authority = loot,
+    )]
+    pub nft_vault: Box<Account<'info, TokenAccount>>,
+    
+    // Mint account of the NFT
+    pub nft_mint: Box<Account<'info, Mint>>,
+    
+    // User's associated token account for the reward tokens
+    ///CHECKED: the address is derived from the user, the reward mint and the reward token program
+    #[account(mut)]
+    pub reward_ata: UncheckedAccount<'info>,
+    
+    // Address of the reward mint, owned by the Token or the Token-2022 program
+    ///CHECKED: the mint is checked against the mint of the loot proceeds account
+    pub reward_mint: UncheckedAccount<'info>,
+    
+    // User account that staked the NFT
+    #[account(mut)]
+    pub user: Signer<'info>,
+    
+    // Token program
+    pub token_program: Program<'info, Token>,
+    
+    // Token program of the reward mint
+    ///CHECKED: the address is checked against the owner of the reward mint
+    #[account(
+        constraint = reward_token_program.key() == *reward_mint.owner @ QstakingErrors::InvalidRewardTokenAccount,
+    )]
+    pub reward_token_program: UncheckedAccount<'info>,
+    
+    // Associated token program
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    
+    // System program
+    pub system_program: Program<'info, System>,
+}
+
+
+pub fn unstake_vault(ctx: Context<UnstakeLootVault>) -> Result<()> {
+
+    let clock = Clock::get()?;
+    let loot = &mut ctx.accounts.loot;
+    let character = &mut ctx.accounts.character;
+    
+    require!(
+        character.staked == 2,
+        QstakingErrors::NotStaked
+    );
+    
+    require!(
+        character.custody == 1,
+        QstakingErrors::InvalidCustodyMode
+    );
+    
+    
+    token_interface::assert_reward_accounts(
+        &ctx.accounts.reward_token_program.key(),
+        &ctx.accounts.reward_mint.to_account_info(),
+        &ctx.accounts.reward_ata.to_account_info(),
+        &ctx.accounts.user.key(),
+        &ctx.accounts.loot_proceeds.to_account_info(),
+        &loot.key(),
     )?;
     
-    // Mint the reward tokens to user's ATA
+    let denylisted = Denylist::is_denylisted(ctx.program_id, &ctx.accounts.denylist.to_account_info());
+    let accrued_reward = settle_loot(loot, character, denylisted, clock.unix_timestamp)?;
+    Leaderboard::record(ctx.program_id, ctx.remaining_accounts, &ctx.accounts.nft_mint.key(), character)?;
+    
+    // Return the NFT from the Loot vault to user's NFT ATA
     token::transfer(
         CpiContext::new_with_signer(
             ctx.accounts.token_program.to_account_info(),
             token::Transfer {
-                from: ctx.accounts.loot_proceeds.to_account_info(),
-                to: ctx.accounts.reward_ata.to_account_info(),
+                from: ctx.accounts.nft_vault.to_account_info(),
+                to: ctx.accounts.nft_account.to_account_info(),
                 authority: loot.to_account_info(),
             },
             &[&[b"loot".as_ref(), &[loot.bump]]],
         ),
-        accrued_reward,
+        1,
     )?;
     
+    // Close the Loot vault and refund its rent to the user
+    token::close_account(
+        CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            token::CloseAccount {
+                account: ctx.accounts.nft_vault.to_account_info(),
+                destination: ctx.accounts.user.to_account_info(),
+                authority: loot.to_account_info(),
+            },
+            &[&[b"loot".as_ref(), &[loot.bump]]],
+        ),
+    )?;
+    
+    transfer_rewards(
+        ctx.accounts.reward_token_program.to_account_info(),
+        ctx.accounts.loot_proceeds.to_account_info(),
+        ctx.accounts.reward_mint.to_account_info(),
+        ctx.accounts.reward_ata.to_account_info(),
+        loot.to_account_info(),
+        loot.bump,
+        accrued_reward,
+    )?;
     
     Ok(())
 }
@@ -295,6 +1014,60 @@ pub fn unstake(ctx: Context<UnstakeLoot>) -> Result<()> {
  */
 
 
+pub fn settle_loot(loot: &mut Loot, character: &mut Character, denylisted: bool, timestamp: i64) -> Result<u64> {
+
+    loot.update_accrued_rewards(timestamp)?;
+    let staked_seconds = character.staked_seconds(timestamp)?;
+    
+    let accrued_reward = if denylisted {
+        character.unstake_frozen_loot(loot.accrued_rewards)?
+    } else {
+        character.unstake_loot(timestamp, loot.cooldown, loot.accrued_rewards)?
+    };
+    loot.remove_character(character.looting_points, accrued_reward, staked_seconds)?;
+    
+    // Rewards of denylisted NFTs are withheld on the Character and stay reserved out of the Loot fund
+    // until the admin releases or confiscates them
+    if denylisted {
+        character.withhold_rewards(0, accrued_reward)?;
+        return Ok(0);
+    }
+    
+    Ok(accrued_reward)
+}
+
+
+#[allow(clippy::too_many_arguments)]
+pub fn transfer_rewards<'info>(
+    token_program: AccountInfo<'info>,
+    loot_proceeds: AccountInfo<'info>,
+    reward_mint: AccountInfo<'info>,
+    reward_ata: AccountInfo<'info>,
+    loot: AccountInfo<'info>,
+    loot_bump: u8,
+    accrued_reward: u64,
+) -> Result<()> {
+
+    let decimals = token_interface::unpack_mint(&reward_mint, token_program.key)?.decimals;
+    
+    // Transfer the looted reward tokens from the loot proceeds account to user's ATA
+    // For reward mints with a transfer fee the fee is withheld from the amount received by the user
+    token_interface::transfer_checked(
+        token_program,
+        loot_proceeds,
+        reward_mint,
+        reward_ata,
+        loot,
+        accrued_reward,
+        decimals,
+        &[&[b"loot".as_ref(), &[loot_bump]]],
+    )?;
+    
+    Ok(())
+}
+
+
+
 pub fn assert_edition_account(mint: &Pubkey, mint_edition: &AccountInfo) -> Result<()> {
     let metadata_program = metaplex::id();
     
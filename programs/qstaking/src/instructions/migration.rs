@@ -0,0 +1,330 @@
+use anchor_lang::prelude::*;
+use anchor_lang::system_program;
+use anchor_spl::token::{Mint, TokenAccount};
+
+use crate::program::Qstaking;
+use crate::states::*;
+use crate::errors::QstakingErrors;
+
+
+
+/*
+ *  Migrate the AdminSettings account to the current layout version
+ *  Accounts created before versioning were sized with std::mem::size_of and have no version byte, they are
+ *  reallocated to the serialized size of the current layout and read as version 0. The AdminSettings account
+ *  has to be migrated first, as the other migrations are authorized by the admin stored in it.
+ */
+
+
+#[derive(Accounts)]
+pub struct MigrateAdmin<'info> {
+
+    // AdminSettings account
+    ///CHECKED: the discriminator is checked when the account is deserialized after reallocation
+    #[account(
+        mut,
+        seeds = [b"admin".as_ref()],
+        bump,
+    )]
+    pub admin_settings: UncheckedAccount<'info>,
+    
+    // Qstaking program
+    #[account(
+        constraint = program.programdata_address()? == Some(program_data.key())
+    )]
+    pub program: Program<'info, Qstaking>,
+    
+    // Qstaking program data
+    #[account(
+        constraint = program_data.upgrade_authority_address == Some(authority.key())
+    )]
+    pub program_data: Account<'info, ProgramData>,
+    
+    // Authority for migrating the AdminSettings account -> upgrade authority of the Qstaking program
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    // System program
+    pub system_program: Program<'info, System>,
+}
+
+
+pub fn migrate_admin(ctx: Context<MigrateAdmin>) -> Result<()> {
+
+    migrate_account(
+        &ctx.accounts.admin_settings.to_account_info(),
+        &ctx.accounts.authority.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+        8 + AdminSettings::LEN,
+        prefix_layout,
+        AdminSettings::migrate,
+    )
+}
+
+
+
+/*
+ *  Migrate the Mine account to the current layout version
+ */
+
+
+#[derive(Accounts)]
+pub struct MigrateMine<'info> {
+
+    // AdminSettings account
+    #[account(
+        seeds = [b"admin".as_ref()],
+        bump,
+    )]
+    pub admin_settings: Account<'info, AdminSettings>,
+    
+    // Mine account
+    ///CHECKED: the discriminator is checked when the account is deserialized after reallocation
+    #[account(
+        mut,
+        seeds = [b"mine".as_ref()],
+        bump,
+    )]
+    pub mine: UncheckedAccount<'info>,
+    
+    // Staking program admin defined in AdminSettings
+    #[account(
+        mut,
+        constraint = admin_settings.admin_key == authority.key(),
+    )]
+    pub authority: Signer<'info>,
+    
+    // System program
+    pub system_program: Program<'info, System>,
+}
+
+
+pub fn migrate_mine(ctx: Context<MigrateMine>) -> Result<()> {
+
+    migrate_account(
+        &ctx.accounts.mine.to_account_info(),
+        &ctx.accounts.authority.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+        8 + Mine::LEN,
+        Mine::read_legacy,
+        Mine::migrate,
+    )
+}
+
+
+
+/*
+ *  Migrate the Loot account to the current layout version
+ */
+
+
+#[derive(Accounts)]
+pub struct MigrateLoot<'info> {
+
+    // AdminSettings account
+    #[account(
+        seeds = [b"admin".as_ref()],
+        bump,
+    )]
+    pub admin_settings: Account<'info, AdminSettings>,
+    
+    // Loot account
+    ///CHECKED: the discriminator is checked when the account is deserialized after reallocation
+    #[account(
+        mut,
+        seeds = [b"loot".as_ref()],
+        bump,
+    )]
+    pub loot: UncheckedAccount<'info>,
+    
+    // Staking program admin defined in AdminSettings
+    #[account(
+        mut,
+        constraint = admin_settings.admin_key == authority.key(),
+    )]
+    pub authority: Signer<'info>,
+    
+    // System program
+    pub system_program: Program<'info, System>,
+}
+
+
+pub fn migrate_loot(ctx: Context<MigrateLoot>) -> Result<()> {
+
+    migrate_account(
+        &ctx.accounts.loot.to_account_info(),
+        &ctx.accounts.authority.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+        8 + Loot::LEN,
+        Loot::read_legacy,
+        Loot::migrate,
+    )
+}
+
+
+
+/*
+ *  Migrate a Whitelist account to the current layout version
+ */
+
+
+#[derive(Accounts)]
+#[instruction(reference_account: Pubkey)]
+pub struct MigrateWhitelist<'info> {
+
+    // AdminSettings account
+    #[account(
+        seeds = [b"admin".as_ref()],
+        bump,
+    )]
+    pub admin_settings: Account<'info, AdminSettings>,
+    
+    // Whitelist account of the reference account
+    ///CHECKED: the discriminator is checked when the account is deserialized after reallocation
+    #[account(
+        mut,
+        seeds = [b"whitelist".as_ref(), reference_account.as_ref()],
+        bump,
+    )]
+    pub whitelist: UncheckedAccount<'info>,
+    
+    // Staking program admin defined in AdminSettings
+    #[account(
+        mut,
+        constraint = admin_settings.admin_key == authority.key(),
+    )]
+    pub authority: Signer<'info>,
+    
+    // System program
+    pub system_program: Program<'info, System>,
+}
+
+
+pub fn migrate_whitelist(ctx: Context<MigrateWhitelist>, _reference_account: Pubkey) -> Result<()> {
+
+    migrate_account(
+        &ctx.accounts.whitelist.to_account_info(),
+        &ctx.accounts.authority.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+        8 + Whitelist::LEN,
+        prefix_layout,
+        Whitelist::migrate,
+    )
+}
+
+
+
+/*
+ *  Migrate a Character account to the current layout version
+ *  Migrating a Character only changes its layout, so anyone paying for the additional rent can do it.
+ *  A Character staked before versioning passes the token account holding its NFT as remaining account.
+ */
+
+
+#[derive(Accounts)]
+pub struct MigrateCharacter<'info> {
+
+    // Character account of the NFT
+    ///CHECKED: the discriminator is checked when the account is deserialized after reallocation
+    #[account(
+        mut,
+        seeds = [b"character".as_ref(), nft_mint.key().as_ref()],
+        bump,
+    )]
+    pub character: UncheckedAccount<'info>,
+    
+    // Mint account of the NFT
+    pub nft_mint: Account<'info, Mint>,
+    
+    // Account paying for the additional rent of the Character account
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    
+    // System program
+    pub system_program: Program<'info, System>,
+}
+
+
+pub fn migrate_character(ctx: Context<MigrateCharacter>) -> Result<()> {
+
+    let nft_mint = ctx.accounts.nft_mint.key();
+    let nft_account = ctx.remaining_accounts.first();
+    
+    migrate_account(
+        &ctx.accounts.character.to_account_info(),
+        &ctx.accounts.payer.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+        8 + Character::LEN,
+        Character::read_legacy,
+        |character: &mut Character| {
+            // Legacy Characters were staked without recording the token account that holds the NFT
+            if character.staked != 0 && character.nft_account == Pubkey::default() {
+                let nft_account = nft_account.ok_or(QstakingErrors::NotHolder)?;
+                let token_account = Account::<TokenAccount>::try_from(nft_account)?;
+                require!(
+                    token_account.mint == nft_mint && token_account.amount == 1,
+                    QstakingErrors::NotHolder
+                );
+                character.nft_account = nft_account.key();
+            }
+            
+            character.migrate()
+        },
+    )
+}
+
+
+
+/*
+ *  Utility functions
+ */
+
+
+pub fn migrate_account<'info, T: AccountSerialize + AccountDeserialize>(
+    account: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    space: usize,
+    read_legacy: fn(&[u8]) -> Result<Option<T>>,
+    migrate: impl FnOnce(&mut T) -> Result<()>,
+) -> Result<()> {
+
+    // Accounts created before versioning are converted from their legacy layout before the reallocation
+    let legacy = read_legacy(&account.try_borrow_data()?)?;
+    
+    // Top up the rent of the account, any excess of a larger legacy layout is left in the account
+    let rent_exempt_balance = Rent::get()?.minimum_balance(space);
+    let balance = account.lamports();
+    
+    if rent_exempt_balance > balance {
+        system_program::transfer(
+            CpiContext::new(
+                system_program.clone(),
+                system_program::Transfer {
+                    from: payer.clone(),
+                    to: account.clone(),
+                },
+            ),
+            rent_exempt_balance - balance,
+        )?;
+    }
+    
+    account.realloc(space, true)?;
+    
+    // Versioned layouts only add fields after the version, which read as zero from the reserved and reallocated space
+    let mut data = account.try_borrow_mut_data()?;
+    let mut state = match legacy {
+        Some(state) => state,
+        None => T::try_deserialize(&mut &data[..])?,
+    };
+    migrate(&mut state)?;
+    state.try_serialize(&mut &mut data[..])?;
+    
+    Ok(())
+}
+
+
+// Legacy layout of accounts that only gained fields at their end, which read as zero with the current layout
+pub fn prefix_layout<T>(_data: &[u8]) -> Result<Option<T>> {
+    Ok(None)
+}
@@ -1,18 +1,19 @@
 use anchor_lang::prelude::*;
 
 use crate::states::*;
-// use crate::errors::QstakingErrors;
+use crate::token_interface;
+use crate::errors::QstakingErrors;
 
 
 /*
  *  Initialize a new Mine.
  *  There is a hard limit of 1 Mine per Qstaking smart contract. The init() function can only be called 
  *  by the owner of the smart contract instance.
- 
+
 
 #[derive(Accounts)]
 pub struct InitMine<'info> {
-    
+
     // AdminSettings account
     #[account(
         seeds = [b"admin".as_ref()],
@@ -59,7 +60,7 @@ pub fn init(ctx: Context<InitMine>, manager: Pubkey) -> Result<()> {
     
     let mine = &mut ctx.accounts.mine;
     mine.initialize(*ctx.bumps.get("mine").unwrap(), &manager, &ctx.accounts.mint.key())?;
-        
+    
     Ok(())
 }
  */
@@ -73,7 +74,7 @@ pub fn init(ctx: Context<InitMine>, manager: Pubkey) -> Result<()> {
 
 #[derive(Accounts)]
 pub struct SetMineManager<'info> {
-    
+
     // AdminSettings account
     #[account(
         seeds = [b"admin".as_ref()],
@@ -109,7 +110,50 @@ pub fn set_manager(ctx: Context<SetMineManager>, manager: Pubkey) -> Result<()>
     
     let mine = &mut ctx.accounts.mine;
     mine.manager = manager;
-        
+    
+    Ok(())
+}
+
+
+
+/*
+ *  Set the mine's emission cap
+ */
+
+
+#[derive(Accounts)]
+pub struct SetMineEmissionCap<'info> {
+
+    // AdminSettings account
+    #[account(
+        seeds = [b"admin".as_ref()],
+        bump,
+    )]
+    pub admin_settings: Account<'info, AdminSettings>,
+    
+    // Address of the Mine
+    #[account(
+        mut,
+        seeds = [b"mine".as_ref(),],
+        bump,
+    )]
+    pub mine: Account<'info, Mine>,
+    
+    // Staking program admin defined in AdminSettings
+    #[account(
+        mut,
+        constraint = admin_settings.admin_key == authority.key(),
+    )]
+    pub authority: Signer<'info>,
+}
+
+
+pub fn set_emission_cap(ctx: Context<SetMineEmissionCap>, emission_cap: u64) -> Result<()> {
+
+    let clock = Clock::get()?;
+    let mine = &mut ctx.accounts.mine;
+    mine.set_emission_cap(clock.unix_timestamp, emission_cap)?;
+    
     Ok(())
 }
 
@@ -118,7 +162,7 @@ pub fn set_manager(ctx: Context<SetMineManager>, manager: Pubkey) -> Result<()>
 /*
  *  Set the mine lock.
  */
- 
+
 
 #[derive(Accounts)]
 pub struct SetMineLock<'info> {
@@ -141,9 +185,50 @@ pub struct SetMineLock<'info> {
 
 
 pub fn set_locked(ctx: Context<SetMineLock>, locked: bool) -> Result<()> {
-    
+
     let mine = &mut ctx.accounts.mine;
     mine.locked = locked;
+    mine.season_locked = false;
+    
+    Ok(())
+}
+
+
+
+/*
+ *  Set the mine custody mode.
+ */
+
+
+#[derive(Accounts)]
+pub struct SetMineCustody<'info> {
+
+    // Address of the Mine
+    #[account(
+        mut,
+        seeds = [b"mine".as_ref(),],
+        bump,
+    )]
+    pub mine: Account<'info, Mine>,
+    
+    // Manager of the Mine
+    #[account(
+        mut,
+        constraint = mine.manager == manager.key()
+    )]
+    pub manager: Signer<'info>,
+}
+
+
+pub fn set_custody(ctx: Context<SetMineCustody>, custody: u8) -> Result<()> {
+
+    require!(
+        custody < 2,
+        QstakingErrors::InvalidCustodyMode
+    );
+    
+    let mine = &mut ctx.accounts.mine;
+    mine.custody = custody;
     
     Ok(())
 }
@@ -154,7 +239,7 @@ pub fn set_locked(ctx: Context<SetMineLock>, locked: bool) -> Result<()> {
  *  Set the mine.
  */
 
- 
+
 #[derive(Accounts)]
 pub struct SetMineParameters<'info> {
 
@@ -177,12 +262,11 @@ pub struct SetMineParameters<'info> {
 
 
 pub fn set_parameters(ctx: Context<SetMineParameters>, rate: u64, price: u64, cooldown: u64) -> Result<()> {
-    
+
     let clock = Clock::get()?;
     let mine = &mut ctx.accounts.mine;
     
-    mine.update_accrued_rewards(clock.unix_timestamp)?;
-    mine.rate = rate;
+    mine.set_rate(clock.unix_timestamp, rate)?;
     mine.price = price;
     mine.cooldown = cooldown;
     
@@ -190,11 +274,341 @@ pub fn set_parameters(ctx: Context<SetMineParameters>, rate: u64, price: u64, co
 }
 
 
+/*
+ *  Set the mine's emission schedule.
+ */
+
+
+#[derive(Accounts)]
+pub struct SetMineSchedule<'info> {
+
+    // Address of the Mine
+    #[account(
+        mut,
+        seeds = [b"mine".as_ref(),],
+        bump,
+    )]
+    pub mine: Account<'info, Mine>,
+    
+    // Manager of the Mine
+    #[account(
+        mut,
+        constraint = mine.manager == manager.key()
+    )]
+    pub manager: Signer<'info>,
+}
+
+
+
+pub fn set_schedule(ctx: Context<SetMineSchedule>, schedule: u8, step_timestamps: [i64; 8], decay_interval: u64, decay_bps: u16, floor_rate: u64) -> Result<()> {
+
+    let clock = Clock::get()?;
+    let mine = &mut ctx.accounts.mine;
+    
+    mine.set_schedule(clock.unix_timestamp, schedule, step_timestamps, decay_interval, decay_bps, floor_rate)?;
+    
+    Ok(())
+}
+
+
+/*
+ *  Set the mine's vesting of mined rewards.
+ *  The escrow account holding the vesting Reward Tokens is created the first time vesting is configured.
+ */
+
+
+#[derive(Accounts)]
+pub struct SetMineVesting<'info> {
+
+    // Address of the Mine
+    #[account(
+        mut,
+        seeds = [b"mine".as_ref(),],
+        bump,
+    )]
+    pub mine: Account<'info, Mine>,
+    
+    // Token account of the Mine holding the vesting Reward Tokens
+    ///CHECKED: the address is derived from the Mine and the account is created by the reward token program
+    #[account(
+        mut,
+        seeds = [b"escrow".as_ref(), mine.key().as_ref()],
+        bump,
+    )]
+    pub vesting_escrow: UncheckedAccount<'info>,
+    
+    // Address of the reward mint, owned by the Token or the Token-2022 program
+    ///CHECKED: the address is checked against the Mine
+    #[account(
+        constraint = mine.mint == reward_mint.key(),
+    )]
+    pub reward_mint: UncheckedAccount<'info>,
+    
+    // Manager of the Mine
+    #[account(
+        mut,
+        constraint = mine.manager == manager.key()
+    )]
+    pub manager: Signer<'info>,
+    
+    // Token program of the reward mint
+    ///CHECKED: the address is checked against the owner of the reward mint
+    #[account(
+        constraint = reward_token_program.key() == *reward_mint.owner @ QstakingErrors::InvalidRewardTokenAccount,
+    )]
+    pub reward_token_program: UncheckedAccount<'info>,
+    
+    // System program
+    pub system_program: Program<'info, System>,
+}
+
+
+
+pub fn set_vesting(ctx: Context<SetMineVesting>, vesting_period: u64, vesting_penalty_bps: u16) -> Result<()> {
+
+    let mine = &mut ctx.accounts.mine;
+    mine.set_vesting(vesting_period, vesting_penalty_bps)?;
+    
+    if ctx.accounts.vesting_escrow.data_is_empty() {
+        let mine_key = mine.key();
+        let escrow_bump = *ctx.bumps.get("vesting_escrow").unwrap();
+        token_interface::create_token_account(
+            ctx.accounts.reward_token_program.to_account_info(),
+            ctx.accounts.vesting_escrow.to_account_info(),
+            ctx.accounts.reward_mint.to_account_info(),
+            &mine_key,
+            ctx.accounts.manager.to_account_info(),
+            ctx.accounts.system_program.to_account_info(),
+            &[&[b"escrow".as_ref(), mine_key.as_ref(), &[escrow_bump]]],
+        )?;
+    }
+    
+    Ok(())
+}
+
+
+/*
+ *  Set the mine's lock tiers.
+ */
+
+
+#[derive(Accounts)]
+pub struct SetMineLockTiers<'info> {
+
+    // Address of the Mine
+    #[account(
+        mut,
+        seeds = [b"mine".as_ref(),],
+        bump,
+    )]
+    pub mine: Account<'info, Mine>,
+    
+    // Manager of the Mine
+    #[account(
+        mut,
+        constraint = mine.manager == manager.key()
+    )]
+    pub manager: Signer<'info>,
+}
+
+
+
+pub fn set_lock_tiers(ctx: Context<SetMineLockTiers>, lock_durations: [u64; 3], lock_multipliers_bps: [u16; 3]) -> Result<()> {
+
+    let mine = &mut ctx.accounts.mine;
+    mine.set_lock_tiers(lock_durations, lock_multipliers_bps)?;
+    
+    Ok(())
+}
+
+
+/*
+ *  Initialize the BoostRegistry holding the boost items of the Mine
+ */
+
+
+#[derive(Accounts)]
+pub struct InitBoostRegistry<'info> {
+
+    // Address of the Mine
+    #[account(
+        seeds = [b"mine".as_ref(),],
+        bump,
+    )]
+    pub mine: Account<'info, Mine>,
+    
+    // BoostRegistry account
+    #[account(
+        init,
+        seeds = [b"boosts".as_ref()],
+        bump,
+        payer = manager,
+        space = 8 + BoostRegistry::LEN,
+    )]
+    pub boost_registry: Account<'info, BoostRegistry>,
+    
+    // Manager of the Mine
+    #[account(
+        mut,
+        constraint = mine.manager == manager.key()
+    )]
+    pub manager: Signer<'info>,
+    
+    // System program
+    pub system_program: Program<'info, System>,
+}
+
+
+
+pub fn init_boost_registry(ctx: Context<InitBoostRegistry>) -> Result<()> {
+
+    let boost_registry = &mut ctx.accounts.boost_registry;
+    boost_registry.initialize(*ctx.bumps.get("boost_registry").unwrap())?;
+    
+    Ok(())
+}
+
+
+
+/*
+ *  Define or clear a boost item of the Mine.
+ *  Changing a boost item only affects boosts applied afterwards.
+ */
+
+
+#[derive(Accounts)]
+pub struct SetMineBoost<'info> {
+
+    // Address of the Mine
+    #[account(
+        seeds = [b"mine".as_ref(),],
+        bump,
+    )]
+    pub mine: Account<'info, Mine>,
+    
+    // BoostRegistry account
+    #[account(
+        mut,
+        seeds = [b"boosts".as_ref()],
+        bump,
+    )]
+    pub boost_registry: Account<'info, BoostRegistry>,
+    
+    // Manager of the Mine
+    #[account(
+        mut,
+        constraint = mine.manager == manager.key()
+    )]
+    pub manager: Signer<'info>,
+}
+
+
+
+pub fn set_boost(ctx: Context<SetMineBoost>, slot: u8, mint: Pubkey, multiplier_bps: u16, duration: u64) -> Result<()> {
+
+    let boost_registry = &mut ctx.accounts.boost_registry;
+    boost_registry.set_boost(slot, &mint, multiplier_bps, duration)?;
+    
+    Ok(())
+}
+
+
+/*
+ *  Initialize the UpgradeShop holding the cost curves of the Character upgrades
+ */
+
+
+#[derive(Accounts)]
+pub struct InitUpgradeShop<'info> {
+
+    // Address of the Mine
+    #[account(
+        seeds = [b"mine".as_ref(),],
+        bump,
+    )]
+    pub mine: Account<'info, Mine>,
+    
+    // UpgradeShop account
+    #[account(
+        init,
+        seeds = [b"upgrades".as_ref()],
+        bump,
+        payer = manager,
+        space = 8 + UpgradeShop::LEN,
+    )]
+    pub upgrade_shop: Account<'info, UpgradeShop>,
+    
+    // Manager of the Mine
+    #[account(
+        mut,
+        constraint = mine.manager == manager.key()
+    )]
+    pub manager: Signer<'info>,
+    
+    // System program
+    pub system_program: Program<'info, System>,
+}
+
+
+
+pub fn init_upgrade_shop(ctx: Context<InitUpgradeShop>) -> Result<()> {
+
+    let upgrade_shop = &mut ctx.accounts.upgrade_shop;
+    upgrade_shop.initialize(*ctx.bumps.get("upgrade_shop").unwrap())?;
+    
+    Ok(())
+}
+
+
+
+/*
+ *  Define the cost curve of a Character attribute upgrade.
+ *  Levels already bought are kept when the curve changes.
+ */
+
+
+#[derive(Accounts)]
+pub struct SetMineUpgrade<'info> {
+
+    // Address of the Mine
+    #[account(
+        seeds = [b"mine".as_ref(),],
+        bump,
+    )]
+    pub mine: Account<'info, Mine>,
+    
+    // UpgradeShop account
+    #[account(
+        mut,
+        seeds = [b"upgrades".as_ref()],
+        bump = upgrade_shop.bump,
+    )]
+    pub upgrade_shop: Account<'info, UpgradeShop>,
+    
+    // Manager of the Mine
+    #[account(
+        mut,
+        constraint = mine.manager == manager.key()
+    )]
+    pub manager: Signer<'info>,
+}
+
+
+
+pub fn set_upgrade(ctx: Context<SetMineUpgrade>, attribute: u8, base_cost: u64, growth_bps: u16, points: u64, max_level: u8) -> Result<()> {
+
+    let upgrade_shop = &mut ctx.accounts.upgrade_shop;
+    upgrade_shop.set_upgrade(attribute, base_cost, growth_bps, points, max_level)?;
+    
+    Ok(())
+}
+
+
 /*
  *  Update mine's accrued rewards
  */
- 
- 
+
+
 #[derive(Accounts)]
 pub struct UpdateMine<'info> {
 
@@ -221,3 +635,112 @@ pub fn update(ctx: Context<UpdateMine>) -> Result<()> {
 
 
 
+
+
+/*
+ *  Set the reward bonus of guild members.
+ *  The bonus of a Guild is accrued up to each stake or unstake of one of its members, so a change applies to
+ *  every Guild from its last stake or unstake.
+ */
+
+
+#[derive(Accounts)]
+pub struct SetMineGuildBonus<'info> {
+
+    // Address of the Mine
+    #[account(
+        mut,
+        seeds = [b"mine".as_ref(),],
+        bump,
+    )]
+    pub mine: Account<'info, Mine>,
+    
+    // Manager of the Mine
+    #[account(
+        mut,
+        constraint = mine.manager == manager.key()
+    )]
+    pub manager: Signer<'info>,
+}
+
+
+
+pub fn set_guild_bonus(ctx: Context<SetMineGuildBonus>, guild_bonus_bps: u16, guild_bonus_cap_bps: u16) -> Result<()> {
+
+    let mine = &mut ctx.accounts.mine;
+    mine.set_guild_bonus(guild_bonus_bps, guild_bonus_cap_bps)?;
+    
+    Ok(())
+}
+
+
+
+/*
+ *  Set the referral rewards of referred Characters.
+ */
+
+
+#[derive(Accounts)]
+pub struct SetMineReferral<'info> {
+
+    // Address of the Mine
+    #[account(
+        mut,
+        seeds = [b"mine".as_ref(),],
+        bump,
+    )]
+    pub mine: Account<'info, Mine>,
+    
+    // Manager of the Mine
+    #[account(
+        mut,
+        constraint = mine.manager == manager.key()
+    )]
+    pub manager: Signer<'info>,
+}
+
+
+
+pub fn set_referral(ctx: Context<SetMineReferral>, referral_bps: u16, referral_window: u64, referral_cap: u64) -> Result<()> {
+
+    let mine = &mut ctx.accounts.mine;
+    mine.set_referral(referral_bps, referral_window, referral_cap)?;
+    
+    Ok(())
+}
+
+
+
+/*
+ *  Set the fee and the cooldown of Character respecs
+ */
+
+
+#[derive(Accounts)]
+pub struct SetMineRespec<'info> {
+
+    // Address of the Mine
+    #[account(
+        mut,
+        seeds = [b"mine".as_ref(),],
+        bump,
+    )]
+    pub mine: Account<'info, Mine>,
+    
+    // Manager of the Mine
+    #[account(
+        mut,
+        constraint = mine.manager == manager.key()
+    )]
+    pub manager: Signer<'info>,
+}
+
+
+
+pub fn set_respec(ctx: Context<SetMineRespec>, respec_fee: u64, respec_cooldown: u64) -> Result<()> {
+
+    let mine = &mut ctx.accounts.mine;
+    mine.set_respec(respec_fee, respec_cooldown)?;
+    
+    Ok(())
+}
@@ -1,10 +1,15 @@
 use anchor_lang::prelude::*;
 use anchor_spl::token::{self, Mint, TokenAccount, Token};
 use anchor_spl::associated_token::AssociatedToken;
-use solana_program::program::invoke_signed;
+use solana_program::program::{invoke, invoke_signed};
+use solana_program::sysvar;
+use solana_program::system_instruction;
+use solana_program::program_option::COption;
 use mpl_token_metadata as metaplex;
 //use mpl_token_metadata::state::Metadata;
 
+use crate::metadata;
+use crate::token_interface;
 use crate::states::*;
 use crate::errors::QstakingErrors; 
 
@@ -16,7 +21,7 @@ use crate::errors::QstakingErrors;
 
 #[derive(Accounts)]
 pub struct CreateMiner<'info> {
-    
+
     // Miner account of the NFT
     #[account(
         init,
@@ -52,13 +57,13 @@ pub struct CreateMiner<'info> {
     
     // Associated token program
     pub associated_token_program: Program<'info, AssociatedToken>,
-
+    
     // System program
     pub system_program: Program<'info, System>,
     
     // Rent program
     pub rent: Sysvar<'info, Rent>,
-    
+
 }
 
 
@@ -79,15 +84,15 @@ pub fn create(ctx: Context<CreateMiner>) -> Result<()> {
 }
  */
 
- 
 
-/*
- *  Stake an NFT
 
+/*
+ *  Stake-delegate an NFT
+ */
 
 
 #[derive(Accounts)]
-pub struct StakeMiner<'info> {
+pub struct StakeMine<'info> {
 
     // Mine account in which to stake the NFT
     #[account(
@@ -95,41 +100,43 @@ pub struct StakeMiner<'info> {
         seeds = [b"mine".as_ref(),],
         bump,
     )]
-    mine: Account<'info, Mine>,
+    pub mine: Account<'info, Mine>,
     
-    // Miner account of the NFT
+    // Character account of the NFT
     #[account(
         mut,
-        seeds = [b"miner".as_ref(), nft_mint.key().as_ref(),],
+        seeds = [b"character".as_ref(), nft_mint.key().as_ref(),],
         bump,
     )]
-    pub miner: Account<'info, Miner>,
+    pub character: Account<'info, Character>,
     
     // Whitelist account to be used for whitelist proof
     #[account(mut)]
-    whitelist: Account<'info, Whitelist>,
+    pub whitelist: Account<'info, Whitelist>,
     
-    // Associated token account of the NFT
+    // Denylist account of the NFT, only initialized if the mint is denylisted
+    ///CHECKED: the address is derived from the NFT mint and only the existence of the account is inspected
     #[account(
-        mut,
-        associated_token::mint = nft_mint,
-        associated_token::authority = user,
+        seeds = [b"denylist".as_ref(), nft_mint.key().as_ref()],
+        bump,
     )]
-    pub nft_source_ata: Account<'info, TokenAccount>,
+    pub denylist: UncheckedAccount<'info>,
     
-    // Token account in the staking pool
+    // Token account holding the NFT
     #[account(
-        init,
-        payer = user,
-        seeds = [nft_mint.key().as_ref(),],
-        bump,
+        mut,
         token::mint = nft_mint,
-        token::authority = mine,
+        token::authority = user,
     )]
-    pub nft_destination_ata: Account<'info, TokenAccount>,
+    pub nft_account: Account<'info, TokenAccount>,
+    
+    // Token (Master) Edition account
+    ///CHECKED: custom logic checks for the validity of this account
+    pub nft_edition: UncheckedAccount<'info>,
     
     // Metadata account of the NFT
     ///CHECKED: custom logic checks for the validity of this account
+    #[account(mut)]
     pub nft_metadata: UncheckedAccount<'info>,
     
     // Mint account of the NFT
@@ -139,25 +146,47 @@ pub struct StakeMiner<'info> {
     #[account(mut)]
     pub user: Signer<'info>,
     
+    // Token record account of the NFT, only used by programmable NFTs
+    ///CHECKED: validated by the Token Metadata program
+    #[account(mut)]
+    pub nft_token_record: UncheckedAccount<'info>,
+    
+    // Authorization rules account of the NFT, only used by programmable NFTs
+    ///CHECKED: validated by the Token Metadata program
+    pub authorization_rules: UncheckedAccount<'info>,
+    
+    // Metaplex Token Authorization Rules program, only used by programmable NFTs
+    ///CHECKED: validated by the Token Metadata program
+    pub authorization_rules_program: UncheckedAccount<'info>,
+    
+    // Instructions sysvar
+    ///CHECKED: the address is checked against the instructions sysvar
+    #[account(address = sysvar::instructions::ID)]
+    pub sysvar_instructions: UncheckedAccount<'info>,
+    
+    // Metaplex Token Metadata program
+    /// CHECKED: This is not dangerous because we don't read or write from this account
+    #[account(address = metaplex::id())]
+    pub token_metadata_program: UncheckedAccount<'info>,
+    
     // Token program
     pub token_program: Program<'info, Token>,
     
     // Associated token program
     pub associated_token_program: Program<'info, AssociatedToken>,
-
+    
     // System program
     pub system_program: Program<'info, System>,
-    
-    // Rent program
-    pub rent: Sysvar<'info, Rent>,
-    
 }
 
 
-pub fn stake(ctx: Context<StakeMiner>) -> Result<()> {
+pub fn stake<'info>(ctx: Context<'_, '_, '_, 'info, StakeMine<'info>>, proof: Option<WhitelistProof>, lock_tier: u8) -> Result<()> {
 
+    assert_edition_account(&ctx.accounts.nft_mint.key(), &ctx.accounts.nft_edition.to_account_info())?;
+    
+    let clock = Clock::get()?;
     let mine = &mut ctx.accounts.mine;
-    let miner = &mut ctx.accounts.miner;
+    let character = &mut ctx.accounts.character;
     let whitelist = &mut ctx.accounts.whitelist;
     
     require!(
@@ -166,171 +195,516 @@ pub fn stake(ctx: Context<StakeMiner>) -> Result<()> {
     );
     
     require!(
-        ctx.accounts.nft_source_ata.amount == 1,
+        mine.custody == 0,
+        QstakingErrors::InvalidCustodyMode
+    );
+    
+    require!(
+        ctx.accounts.nft_account.amount == 1,
         QstakingErrors::NotHolder
     );
     
-    whitelist.verify(&ctx.program_id, &whitelist.key(), &ctx.accounts.nft_mint.key(), &ctx.accounts.nft_metadata.to_account_info())?;
-    mine.add_miner(miner.points)?;
-    miner.stake(mine.accrued_rewards)?;
+    require!(
+        character.staked == 0,
+        QstakingErrors::AlreadyStaked
+    );
     
-    token::transfer(
-        CpiContext::new(
+    
+    whitelist.verify(&ctx.program_id, &whitelist.key(), &ctx.accounts.nft_mint.key(), &ctx.accounts.nft_metadata.to_account_info(), &ctx.accounts.denylist.to_account_info(), &proof)?;
+    let (staked_weight, lock_duration) = mine.lock_tier(lock_tier, character.mining_points)?;
+    let guild_accounts = stake_guild_member(ctx.remaining_accounts, ctx.program_id, mine, character, clock.unix_timestamp)?;
+    mine.add_character(clock.unix_timestamp, staked_weight)?;
+    character.stake_mine(clock.unix_timestamp, mine.season, mine.accrued_rewards, 0, &ctx.accounts.nft_account.key(), &Pubkey::default())?;
+    character.lock(clock.unix_timestamp, lock_tier, lock_duration, staked_weight)?;
+    
+    // A boost item is burned on staking when the BoostRegistry, the boost mint and the user's boost token account are passed
+    let boost_accounts = &ctx.remaining_accounts[guild_accounts..];
+    if !boost_accounts.is_empty() {
+        require!(
+            boost_accounts.len() == 3,
+            QstakingErrors::InvalidBoost
+        );
+        
+        let boost_registry: Account<BoostRegistry> = Account::try_from(&boost_accounts[0])?;
+        let boost_mint: Account<Mint> = Account::try_from(&boost_accounts[1])?;
+        let boost_account: Account<TokenAccount> = Account::try_from(&boost_accounts[2])?;
+        
+        require_keys_eq!(
+            boost_account.owner,
+            ctx.accounts.user.key(),
+            QstakingErrors::InvalidBoost
+        );
+        
+        apply_boost_item(
+            mine,
+            character,
+            &boost_registry,
+            &boost_mint,
+            &boost_account,
+            ctx.accounts.user.to_account_info(),
             ctx.accounts.token_program.to_account_info(),
-            token::Transfer {
-                from: ctx.accounts.nft_source_ata.to_account_info(),
-                to: ctx.accounts.nft_destination_ata.to_account_info(),
-                authority: ctx.accounts.user.to_account_info(),
-            },
-        ),
-        1,
-    )?;
+            clock.unix_timestamp,
+        )?;
+    }
+    
+    if metadata::is_programmable(&ctx.accounts.nft_metadata.to_account_info())? {
+    
+        let pnft_account_infos = [
+            ctx.accounts.token_metadata_program.to_account_info(),
+            mine.to_account_info(),
+            ctx.accounts.nft_metadata.to_account_info(),
+            ctx.accounts.nft_edition.to_account_info(),
+            ctx.accounts.nft_token_record.to_account_info(),
+            ctx.accounts.nft_mint.to_account_info(),
+            ctx.accounts.nft_account.to_account_info(),
+            ctx.accounts.user.to_account_info(),
+            ctx.accounts.system_program.to_account_info(),
+            ctx.accounts.sysvar_instructions.to_account_info(),
+            ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.authorization_rules_program.to_account_info(),
+            ctx.accounts.authorization_rules.to_account_info(),
+        ];
+        
+        // Add the Mine account as staking delegate of user's pNFT
+        invoke(
+            &metadata::delegate_staking(
+                ctx.accounts.token_metadata_program.key(),
+                mine.key(),
+                ctx.accounts.nft_metadata.key(),
+                ctx.accounts.nft_edition.key(),
+                ctx.accounts.nft_token_record.key(),
+                ctx.accounts.nft_mint.key(),
+                ctx.accounts.nft_account.key(),
+                ctx.accounts.user.key(),
+                ctx.accounts.user.key(),
+                ctx.accounts.authorization_rules_program.key(),
+                ctx.accounts.authorization_rules.key(),
+            ),
+            &pnft_account_infos,
+        )?;
+        
+        // Lock the user's pNFT
+        invoke_signed(
+            &metadata::lock(
+                ctx.accounts.token_metadata_program.key(),
+                mine.key(),
+                ctx.accounts.user.key(),
+                ctx.accounts.nft_account.key(),
+                ctx.accounts.nft_mint.key(),
+                ctx.accounts.nft_metadata.key(),
+                ctx.accounts.nft_edition.key(),
+                ctx.accounts.nft_token_record.key(),
+                ctx.accounts.user.key(),
+                ctx.accounts.authorization_rules_program.key(),
+                ctx.accounts.authorization_rules.key(),
+            ),
+            &pnft_account_infos,
+            &[&[b"mine".as_ref(), &[mine.bump]]],
+        )?;
+    
+    } else {
+    
+        // Add the Mine account as delegate to user's NFT token account
+        token::approve(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                token::Approve {
+                    to: ctx.accounts.nft_account.to_account_info(),
+                    delegate: mine.to_account_info(),
+                    authority: ctx.accounts.user.to_account_info(),
+                },
+            ),
+            1,
+        )?;
+        
+        // Freeze the user's NFT token account
+        invoke_signed(
+            &metaplex::instruction::freeze_delegated_account(
+                ctx.accounts.token_metadata_program.key(),
+                mine.key(),
+                ctx.accounts.nft_account.key(),
+                ctx.accounts.nft_edition.key(),
+                ctx.accounts.nft_mint.key(),
+            ),
+            &[
+                ctx.accounts.token_metadata_program.to_account_info(),
+                mine.to_account_info(),
+                ctx.accounts.nft_account.to_account_info(),
+                ctx.accounts.nft_edition.to_account_info(),
+                ctx.accounts.nft_mint.to_account_info(),
+            ],
+            &[&[b"mine".as_ref(), &[mine.bump]]],
+        )?;
+    }
     
     Ok(())
- 
-}
- */
+
+} 
+
 
 
 /*
- *  Unstake an NFT
+ *  Unstake-delegate an NFT
+ */
 
 
 #[derive(Accounts)]
-pub struct UnstakeMiner<'info> {
+pub struct UnstakeMine<'info> {
 
-    // Mine account from which to unstake the NFT
+    // Mine staking pool account
     #[account(
         mut,
         seeds = [b"mine".as_ref(),],
         bump,
     )]
-    mine: Box<Account<'info, Mine>>,
+    pub mine: Box<Account<'info, Mine>>,
     
-    // Miner account of the NFT
+    // Loot staking pool account
     #[account(
         mut,
-        seeds = [b"miner".as_ref(), nft_mint.key().as_ref(),],
+        seeds = [b"loot".as_ref(),],
         bump,
-        constraint = miner.owner == user.key(),
     )]
-    pub miner: Box<Account<'info, Miner>>,
+    pub loot: Box<Account<'info, Loot>>,
     
-    // Associated token account of the NFT
+    // Miner account of the NFT
     #[account(
         mut,
-        associated_token::mint = nft_mint,
-        associated_token::authority = user,
+        seeds = [b"character".as_ref(), nft_mint.key().as_ref(),],
+        bump,
     )]
-    pub nft_destination_ata: Box<Account<'info, TokenAccount>>,
+    pub character: Box<Account<'info, Character>>,
     
-    // Token account in the staking pool
+    // Token account with loot rewards
+    ///CHECKED: the account is unpacked and its mint and owner checked with the reward token program
     #[account(
         mut,
-        seeds = [nft_mint.key().as_ref(),],
+        seeds = [b"proceeds".as_ref(), loot.key().as_ref()],
+        bump,
+    )]
+    pub loot_proceeds: UncheckedAccount<'info>,
+    
+    // Denylist account of the NFT, only initialized if the mint is denylisted
+    ///CHECKED: the address is derived from the NFT mint and only the existence of the account is inspected
+    #[account(
+        seeds = [b"denylist".as_ref(), nft_mint.key().as_ref()],
         bump,
+    )]
+    pub denylist: UncheckedAccount<'info>,
+    
+    // Token account holding the NFT
+    #[account(
+        mut,
         token::mint = nft_mint,
-        token::authority = mine,
-        //close = user,
+        token::authority = user,
+        constraint = nft_account.key() == character.nft_account @ QstakingErrors::NotHolder,
     )]
-    pub nft_source_ata: Box<Account<'info, TokenAccount>>,
+    pub nft_account: Box<Account<'info, TokenAccount>>,
+    
+    // Token (Master) Edition account
+    ///CHECKED: custom logic checks for the validity of this account
+    pub nft_edition: UncheckedAccount<'info>,
+    
+    // Metadata account of the NFT
+    ///CHECKED: custom logic checks for the validity of this account
+    #[account(mut)]
+    pub nft_metadata: UncheckedAccount<'info>,
     
     // Mint account of the NFT
     pub nft_mint: Box<Account<'info, Mint>>,
     
-    // Associated token account for the reward tokens
+    // Vesting account of the user for the NFT, only created once mined rewards vest
+    ///CHECKED: the address is derived from the NFT mint and the user, the account is created and deserialized by custom logic
     #[account(
         mut,
-        associated_token::mint = reward_mint,
-        associated_token::authority = user,
+        seeds = [b"vesting".as_ref(), nft_mint.key().as_ref(), user.key().as_ref()],
+        bump,
+    )]
+    pub vesting: UncheckedAccount<'info>,
+    
+    // Token account of the Mine holding the vesting Reward Tokens
+    ///CHECKED: the address is derived from the Mine and the account is validated by the reward token program
+    #[account(
+        mut,
+        seeds = [b"escrow".as_ref(), mine.key().as_ref()],
+        bump,
     )]
-    pub reward_ata: Box<Account<'info, TokenAccount>>,
+    pub vesting_escrow: UncheckedAccount<'info>,
+    
+    // Associated token account for the reward tokens
+    ///CHECKED: the address is derived from the user, the reward mint and the reward token program
+    #[account(mut)]
+    pub reward_ata: UncheckedAccount<'info>,
     
-    // Address of the reward mint
+    // Address of the reward mint, owned by the Token or the Token-2022 program
+    ///CHECKED: the address is checked against the Mine, the mint authority by the reward token program
     #[account(
         mut,
-        mint::authority = mine.key()
+        constraint = mine.mint == reward_mint.key(),
     )]
-    pub reward_mint: Box<Account<'info, Mint>>,
+    pub reward_mint: UncheckedAccount<'info>,
     
     // User account that holds the NFT
     #[account(mut)]
     pub user: Signer<'info>,
     
+    // Token record account of the NFT, only used by programmable NFTs
+    ///CHECKED: validated by the Token Metadata program
+    #[account(mut)]
+    pub nft_token_record: UncheckedAccount<'info>,
+    
+    // Authorization rules account of the NFT, only used by programmable NFTs
+    ///CHECKED: validated by the Token Metadata program
+    pub authorization_rules: UncheckedAccount<'info>,
+    
+    // Metaplex Token Authorization Rules program, only used by programmable NFTs
+    ///CHECKED: validated by the Token Metadata program
+    pub authorization_rules_program: UncheckedAccount<'info>,
+    
+    // Instructions sysvar
+    ///CHECKED: the address is checked against the instructions sysvar
+    #[account(address = sysvar::instructions::ID)]
+    pub sysvar_instructions: UncheckedAccount<'info>,
+    
+    // Metaplex Token Metadata program
+    /// CHECKED: This is not dangerous because we don't read or write from this account
+    #[account(address = metaplex::id())]
+    pub token_metadata_program: UncheckedAccount<'info>,
+    
     // Token program
     pub token_program: Program<'info, Token>,
     
+    // Token program of the reward mint
+    ///CHECKED: the address is checked against the owner of the reward mint
+    #[account(
+        constraint = reward_token_program.key() == *reward_mint.owner @ QstakingErrors::InvalidRewardTokenAccount,
+    )]
+    pub reward_token_program: UncheckedAccount<'info>,
+    
     // Associated token program
     pub associated_token_program: Program<'info, AssociatedToken>,
-
+    
     // System program
     pub system_program: Program<'info, System>,
-    
-    // Rent program
-    pub rent: Sysvar<'info, Rent>,
 }
 
 
-pub fn unstake(ctx: Context<UnstakeMiner>) -> Result<()> {
+pub fn unstake<'info>(ctx: Context<'_, '_, '_, 'info, UnstakeMine<'info>>) -> Result<()> {
+
+    assert_edition_account(&ctx.accounts.nft_mint.key(), &ctx.accounts.nft_edition.to_account_info())?;
+    metadata::assert_metadata_account(&ctx.accounts.nft_mint.key(), &ctx.accounts.nft_metadata.to_account_info())?;
+    
+    let clock = Clock::get()?;
+    let mine = &mut ctx.accounts.mine;
+    let loot = &mut ctx.accounts.loot;
+    let character = &mut ctx.accounts.character;
+    
+    require!(
+        ctx.accounts.nft_account.amount == 1,
+        QstakingErrors::NotHolder
+    );
     
     require!(
-        ctx.accounts.nft_source_ata.amount == 1,
+        character.staked == 1,
         QstakingErrors::NotStaked
     );
     
-    let mine = &mut ctx.accounts.mine;
-    let miner = &mut ctx.accounts.miner;
-    mine.remove_miner(miner.points)?;
-    let accrued_reward = miner.unstake(mine.accrued_rewards)?;
+    require!(
+        character.custody == 0,
+        QstakingErrors::InvalidCustodyMode
+    );
     
-    token::transfer(
-        CpiContext::new_with_signer(
-            ctx.accounts.token_program.to_account_info(),
-            token::Transfer {
-                from: ctx.accounts.nft_source_ata.to_account_info(),
-                to: ctx.accounts.nft_destination_ata.to_account_info(),
-                authority: mine.to_account_info(),
-            },
-            &[&[b"mine".as_ref(), &[mine.bump]]],
-        ),
-        1,
+    
+    token_interface::assert_reward_accounts(
+        &ctx.accounts.reward_token_program.key(),
+        &ctx.accounts.reward_mint.to_account_info(),
+        &ctx.accounts.reward_ata.to_account_info(),
+        &ctx.accounts.user.key(),
+        &ctx.accounts.loot_proceeds.to_account_info(),
+        &loot.key(),
     )?;
     
-    token::mint_to(
-        CpiContext::new_with_signer(
+    let denylisted = Denylist::is_denylisted(ctx.program_id, &ctx.accounts.denylist.to_account_info());
+    settle_guild_bonus(ctx.remaining_accounts, ctx.program_id, mine, character, clock.unix_timestamp)?;
+    let (accrued_reward, looted_reward) = settle_mine(mine, loot, character, denylisted, clock.unix_timestamp)?;
+    let mined_reward = accrued_reward;
+    Leaderboard::record(ctx.program_id, ctx.remaining_accounts, &ctx.accounts.nft_mint.key(), character)?;
+    
+    if metadata::is_programmable(&ctx.accounts.nft_metadata.to_account_info())? {
+    
+        let pnft_account_infos = [
+            ctx.accounts.token_metadata_program.to_account_info(),
+            mine.to_account_info(),
+            ctx.accounts.nft_metadata.to_account_info(),
+            ctx.accounts.nft_edition.to_account_info(),
+            ctx.accounts.nft_token_record.to_account_info(),
+            ctx.accounts.nft_mint.to_account_info(),
+            ctx.accounts.nft_account.to_account_info(),
+            ctx.accounts.user.to_account_info(),
+            ctx.accounts.system_program.to_account_info(),
+            ctx.accounts.sysvar_instructions.to_account_info(),
             ctx.accounts.token_program.to_account_info(),
-            token::MintTo {
-                mint: ctx.accounts.reward_mint.to_account_info(),
-                to: ctx.accounts.reward_ata.to_account_info(),
-                authority: mine.to_account_info(),
-            },
+            ctx.accounts.authorization_rules_program.to_account_info(),
+            ctx.accounts.authorization_rules.to_account_info(),
+        ];
+        
+        // Unlock the user's pNFT
+        invoke_signed(
+            &metadata::unlock(
+                ctx.accounts.token_metadata_program.key(),
+                mine.key(),
+                ctx.accounts.user.key(),
+                ctx.accounts.nft_account.key(),
+                ctx.accounts.nft_mint.key(),
+                ctx.accounts.nft_metadata.key(),
+                ctx.accounts.nft_edition.key(),
+                ctx.accounts.nft_token_record.key(),
+                ctx.accounts.user.key(),
+                ctx.accounts.authorization_rules_program.key(),
+                ctx.accounts.authorization_rules.key(),
+            ),
+            &pnft_account_infos,
             &[&[b"mine".as_ref(), &[mine.bump]]],
-        ),
+        )?;
+        
+        // Remove the Mine account as staking delegate of user's pNFT
+        invoke(
+            &metadata::revoke_staking(
+                ctx.accounts.token_metadata_program.key(),
+                mine.key(),
+                ctx.accounts.nft_metadata.key(),
+                ctx.accounts.nft_edition.key(),
+                ctx.accounts.nft_token_record.key(),
+                ctx.accounts.nft_mint.key(),
+                ctx.accounts.nft_account.key(),
+                ctx.accounts.user.key(),
+                ctx.accounts.user.key(),
+                ctx.accounts.authorization_rules_program.key(),
+                ctx.accounts.authorization_rules.key(),
+            ),
+            &pnft_account_infos,
+        )?;
+    
+    } else {
+    
+        // Thaw the user's NFT token account
+        invoke_signed(
+            &metaplex::instruction::thaw_delegated_account(
+                ctx.accounts.token_metadata_program.key(),
+                mine.key(),
+                ctx.accounts.nft_account.key(),
+                ctx.accounts.nft_edition.key(),
+                ctx.accounts.nft_mint.key(),
+            ),
+            &[
+                ctx.accounts.token_metadata_program.to_account_info(),
+                mine.to_account_info(),
+                ctx.accounts.nft_account.to_account_info(),
+                ctx.accounts.nft_edition.to_account_info(),
+                ctx.accounts.nft_mint.to_account_info(),
+            ],
+            &[&[b"mine".as_ref(), &[mine.bump]]],
+        )?;
+        
+        // Remove the Mine account as delegate from user's NFT token account
+        token::revoke(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                token::Revoke {
+                    source: ctx.accounts.nft_account.to_account_info(),
+                    authority: ctx.accounts.user.to_account_info(),
+                },
+            )
+        )?;
+    }
+    
+    
+    let (accrued_reward, guild_accounts) = unstake_guild_member(
+        ctx.remaining_accounts,
+        character,
+        ctx.accounts.reward_token_program.to_account_info(),
+        ctx.accounts.reward_mint.to_account_info(),
+        mine.to_account_info(),
+        mine.bump,
+        accrued_reward,
+    )?;
+    
+    pay_referral(
+        &ctx.remaining_accounts[guild_accounts..],
+        ctx.program_id,
+        mine,
+        character,
+        ctx.accounts.reward_token_program.to_account_info(),
+        ctx.accounts.reward_mint.to_account_info(),
+        clock.unix_timestamp,
+        mined_reward,
+    )?;
+    
+    // Mined rewards go to the vesting escrow when the Mine vests them
+    let reward_destination = if mine.vesting_period > 0 && accrued_reward > 0 {
+        vest_rewards(
+            ctx.accounts.vesting.to_account_info(),
+            ctx.accounts.user.to_account_info(),
+            ctx.accounts.system_program.to_account_info(),
+            &ctx.accounts.user.key(),
+            &ctx.accounts.nft_mint.key(),
+            *ctx.bumps.get("vesting").unwrap(),
+            clock.unix_timestamp,
+            mine.vesting_period,
+            accrued_reward,
+        )?;
+        ctx.accounts.vesting_escrow.to_account_info()
+    } else {
+        ctx.accounts.reward_ata.to_account_info()
+    };
+    
+    mint_rewards(
+        ctx.accounts.reward_token_program.to_account_info(),
+        ctx.accounts.reward_mint.to_account_info(),
+        reward_destination,
+        ctx.accounts.loot_proceeds.to_account_info(),
+        mine.to_account_info(),
+        mine.bump,
         accrued_reward,
+        looted_reward,
     )?;
     
     Ok(())
- 
+
 }
- */
 
 
 
 /*
- *  Stake-delegate an NFT
+ *  Force-unstake a denylisted NFT from the Mine, its mined rewards are withheld on the Character
  */
 
 
 #[derive(Accounts)]
-pub struct StakeMine<'info> {
+pub struct ForceUnstakeMine<'info> {
 
-    // Mine account in which to stake the NFT
+    // AdminSettings account
+    #[account(
+        seeds = [b"admin".as_ref()],
+        bump,
+    )]
+    pub admin_settings: Box<Account<'info, AdminSettings>>,
+    
+    // Mine staking pool account
     #[account(
         mut,
         seeds = [b"mine".as_ref(),],
         bump,
     )]
-    pub mine: Account<'info, Mine>,
+    pub mine: Box<Account<'info, Mine>>,
+    
+    // Loot staking pool account
+    #[account(
+        mut,
+        seeds = [b"loot".as_ref(),],
+        bump,
+    )]
+    pub loot: Box<Account<'info, Loot>>,
     
     // Character account of the NFT
     #[account(
@@ -338,19 +712,40 @@ pub struct StakeMine<'info> {
         seeds = [b"character".as_ref(), nft_mint.key().as_ref(),],
         bump,
     )]
-    pub character: Account<'info, Character>,
+    pub character: Box<Account<'info, Character>>,
     
-    // Whitelist account to be used for whitelist proof
-    #[account(mut)]
-    pub whitelist: Account<'info, Whitelist>,
+    // Denylist account of the NFT
+    #[account(
+        seeds = [b"denylist".as_ref(), nft_mint.key().as_ref()],
+        bump,
+    )]
+    pub denylist: Box<Account<'info, Denylist>>,
     
-    // Associated token account of the NFT
+    // Token account with loot rewards
+    ///CHECKED: the account is unpacked and its mint and owner checked with the reward token program
     #[account(
         mut,
-        associated_token::mint = nft_mint,
-        associated_token::authority = user,
+        seeds = [b"proceeds".as_ref(), loot.key().as_ref()],
+        bump,
     )]
-    pub nft_ata: Account<'info, TokenAccount>,
+    pub loot_proceeds: UncheckedAccount<'info>,
+    
+    // Address of the reward mint, owned by the Token or the Token-2022 program
+    ///CHECKED: the address is checked against the Mine, the mint authority by the reward token program
+    #[account(
+        mut,
+        constraint = mine.mint == reward_mint.key(),
+    )]
+    pub reward_mint: UncheckedAccount<'info>,
+    
+    // Token account of the NFT frozen with the Mine as delegate
+    #[account(
+        mut,
+        token::mint = nft_mint,
+        constraint = nft_account.delegate == COption::Some(mine.key()),
+        constraint = nft_account.key() == character.nft_account,
+    )]
+    pub nft_account: Box<Account<'info, TokenAccount>>,
     
     // Token (Master) Edition account
     ///CHECKED: custom logic checks for the validity of this account
@@ -358,34 +753,275 @@ pub struct StakeMine<'info> {
     
     // Metadata account of the NFT
     ///CHECKED: custom logic checks for the validity of this account
+    #[account(mut)]
     pub nft_metadata: UncheckedAccount<'info>,
     
     // Mint account of the NFT
-    pub nft_mint: Account<'info, Mint>,
+    pub nft_mint: Box<Account<'info, Mint>>,
     
-    // User account that holds the NFT
+    // Holder of the NFT token account
+    ///CHECKED: the address is checked against the owner of the NFT token account
+    #[account(
+        constraint = nft_account.owner == nft_owner.key(),
+    )]
+    pub nft_owner: UncheckedAccount<'info>,
+    
+    // Staking program admin defined in AdminSettings
+    #[account(
+        mut,
+        constraint = admin_settings.admin_key == authority.key(),
+    )]
+    pub authority: Signer<'info>,
+    
+    // Token record account of the NFT, only used by programmable NFTs
+    ///CHECKED: validated by the Token Metadata program
     #[account(mut)]
-    pub user: Signer<'info>,
+    pub nft_token_record: UncheckedAccount<'info>,
+    
+    // Authorization rules account of the NFT, only used by programmable NFTs
+    ///CHECKED: validated by the Token Metadata program
+    pub authorization_rules: UncheckedAccount<'info>,
+    
+    // Metaplex Token Authorization Rules program, only used by programmable NFTs
+    ///CHECKED: validated by the Token Metadata program
+    pub authorization_rules_program: UncheckedAccount<'info>,
+    
+    // Instructions sysvar
+    ///CHECKED: the address is checked against the instructions sysvar
+    #[account(address = sysvar::instructions::ID)]
+    pub sysvar_instructions: UncheckedAccount<'info>,
     
     // Metaplex Token Metadata program
     /// CHECKED: This is not dangerous because we don't read or write from this account
+    #[account(address = metaplex::id())]
     pub token_metadata_program: UncheckedAccount<'info>,
     
     // Token program
     pub token_program: Program<'info, Token>,
     
-    // Associated token program
-    pub associated_token_program: Program<'info, AssociatedToken>,
-
+    // Token program of the reward mint
+    ///CHECKED: the address is checked against the owner of the reward mint
+    #[account(
+        constraint = reward_token_program.key() == *reward_mint.owner @ QstakingErrors::InvalidRewardTokenAccount,
+    )]
+    pub reward_token_program: UncheckedAccount<'info>,
+    
     // System program
     pub system_program: Program<'info, System>,
 }
 
 
-pub fn stake(ctx: Context<StakeMine>) -> Result<()> {
+pub fn force_unstake<'info>(ctx: Context<'_, '_, '_, 'info, ForceUnstakeMine<'info>>) -> Result<()> {
 
     assert_edition_account(&ctx.accounts.nft_mint.key(), &ctx.accounts.nft_edition.to_account_info())?;
+    metadata::assert_metadata_account(&ctx.accounts.nft_mint.key(), &ctx.accounts.nft_metadata.to_account_info())?;
+    
+    let clock = Clock::get()?;
+    let mine = &mut ctx.accounts.mine;
+    let loot = &mut ctx.accounts.loot;
+    let character = &mut ctx.accounts.character;
+    
+    require!(
+        character.staked == 1,
+        QstakingErrors::NotStaked
+    );
+    
+    require!(
+        character.custody == 0,
+        QstakingErrors::InvalidCustodyMode
+    );
+    
+    settle_guild_bonus(ctx.remaining_accounts, ctx.program_id, mine, character, clock.unix_timestamp)?;
+    
+    token_interface::unpack_mint(&ctx.accounts.reward_mint.to_account_info(), &ctx.accounts.reward_token_program.key())?;
+    token_interface::assert_token_account(
+        &ctx.accounts.loot_proceeds.to_account_info(),
+        &loot.key(),
+        &ctx.accounts.reward_mint.key(),
+        &ctx.accounts.reward_token_program.key(),
+    )?;
+    
+    let (_, looted_reward) = settle_mine_rewards(mine, loot, character, true, clock.unix_timestamp)?;
+    
+    // Mint the looted share of the withheld rewards to loot proceeds account
+    token_interface::mint_to(
+        ctx.accounts.reward_token_program.to_account_info(),
+        ctx.accounts.reward_mint.to_account_info(),
+        ctx.accounts.loot_proceeds.to_account_info(),
+        mine.to_account_info(),
+        looted_reward,
+        &[&[b"mine".as_ref(), &[mine.bump]]],
+    )?;
+    
+    if metadata::is_programmable(&ctx.accounts.nft_metadata.to_account_info())? {
+    
+        let pnft_account_infos = [
+            ctx.accounts.token_metadata_program.to_account_info(),
+            mine.to_account_info(),
+            ctx.accounts.nft_metadata.to_account_info(),
+            ctx.accounts.nft_edition.to_account_info(),
+            ctx.accounts.nft_token_record.to_account_info(),
+            ctx.accounts.nft_mint.to_account_info(),
+            ctx.accounts.nft_account.to_account_info(),
+            ctx.accounts.nft_owner.to_account_info(),
+            ctx.accounts.authority.to_account_info(),
+            ctx.accounts.system_program.to_account_info(),
+            ctx.accounts.sysvar_instructions.to_account_info(),
+            ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.authorization_rules_program.to_account_info(),
+            ctx.accounts.authorization_rules.to_account_info(),
+        ];
+        
+        // Unlock the holder's pNFT
+        invoke_signed(
+            &metadata::unlock(
+                ctx.accounts.token_metadata_program.key(),
+                mine.key(),
+                ctx.accounts.nft_owner.key(),
+                ctx.accounts.nft_account.key(),
+                ctx.accounts.nft_mint.key(),
+                ctx.accounts.nft_metadata.key(),
+                ctx.accounts.nft_edition.key(),
+                ctx.accounts.nft_token_record.key(),
+                ctx.accounts.authority.key(),
+                ctx.accounts.authorization_rules_program.key(),
+                ctx.accounts.authorization_rules.key(),
+            ),
+            &pnft_account_infos,
+            &[&[b"mine".as_ref(), &[mine.bump]]],
+        )?;
+        
+        // Remove the Mine account as staking delegate of the holder's pNFT
+        invoke_signed(
+            &metadata::revoke_staking(
+                ctx.accounts.token_metadata_program.key(),
+                mine.key(),
+                ctx.accounts.nft_metadata.key(),
+                ctx.accounts.nft_edition.key(),
+                ctx.accounts.nft_token_record.key(),
+                ctx.accounts.nft_mint.key(),
+                ctx.accounts.nft_account.key(),
+                mine.key(),
+                ctx.accounts.authority.key(),
+                ctx.accounts.authorization_rules_program.key(),
+                ctx.accounts.authorization_rules.key(),
+            ),
+            &pnft_account_infos,
+            &[&[b"mine".as_ref(), &[mine.bump]]],
+        )?;
+    
+    } else {
+    
+        // Only the holder can revoke a delegate of an SPL token account, so the Mine stays delegate of the thawed
+        // account until the holder revokes it, transfers the NFT or stakes it again
+        
+        // Thaw the holder's NFT token account
+        invoke_signed(
+            &metaplex::instruction::thaw_delegated_account(
+                ctx.accounts.token_metadata_program.key(),
+                mine.key(),
+                ctx.accounts.nft_account.key(),
+                ctx.accounts.nft_edition.key(),
+                ctx.accounts.nft_mint.key(),
+            ),
+            &[
+                ctx.accounts.token_metadata_program.to_account_info(),
+                mine.to_account_info(),
+                ctx.accounts.nft_account.to_account_info(),
+                ctx.accounts.nft_edition.to_account_info(),
+                ctx.accounts.nft_mint.to_account_info(),
+            ],
+            &[&[b"mine".as_ref(), &[mine.bump]]],
+        )?;
+    }
+    
+    Ok(())
+}
+
+
+
+/*
+ *  Stake an NFT by escrowing it into a Mine vault
+ */
+
+
+#[derive(Accounts)]
+pub struct StakeMineVault<'info> {
+
+    // Mine account in which to stake the NFT
+    #[account(
+        mut,
+        seeds = [b"mine".as_ref(),],
+        bump,
+    )]
+    pub mine: Box<Account<'info, Mine>>,
+    
+    // Character account of the NFT
+    #[account(
+        mut,
+        seeds = [b"character".as_ref(), nft_mint.key().as_ref(),],
+        bump,
+    )]
+    pub character: Box<Account<'info, Character>>,
     
+    // Whitelist account to be used for whitelist proof
+    #[account(mut)]
+    pub whitelist: Box<Account<'info, Whitelist>>,
+    
+    // Denylist account of the NFT, only initialized if the mint is denylisted
+    ///CHECKED: the address is derived from the NFT mint and only the existence of the account is inspected
+    #[account(
+        seeds = [b"denylist".as_ref(), nft_mint.key().as_ref()],
+        bump,
+    )]
+    pub denylist: UncheckedAccount<'info>,
+    
+    // Token account holding the NFT
+    #[account(
+        mut,
+        token::mint = nft_mint,
+        token::authority = user,
+    )]
+    pub nft_account: Box<Account<'info, TokenAccount>>,
+    
+    // Vault token account of the Mine holding the NFT while staked
+    #[account(
+        init,
+        payer = user,
+        seeds = [b"vault".as_ref(), nft_mint.key().as_ref()],
+        bump,
+        token::mint = nft_mint,
+        token::authority = mine,
+    )]
+    pub nft_vault: Box<Account<'info, TokenAccount>>,
+    
+    // Metadata account of the NFT
+    ///CHECKED: custom logic checks for the validity of this account
+    pub nft_metadata: UncheckedAccount<'info>,
+    
+    // Mint account of the NFT
+    pub nft_mint: Box<Account<'info, Mint>>,
+    
+    // User account that holds the NFT
+    #[account(mut)]
+    pub user: Signer<'info>,
+    
+    // Token program
+    pub token_program: Program<'info, Token>,
+    
+    // Associated token program
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    
+    // System program
+    pub system_program: Program<'info, System>,
+    
+    // Rent program
+    pub rent: Sysvar<'info, Rent>,
+}
+
+
+pub fn stake_vault<'info>(ctx: Context<'_, '_, '_, 'info, StakeMineVault<'info>>, proof: Option<WhitelistProof>, lock_tier: u8) -> Result<()> {
+
     let clock = Clock::get()?;
     let mine = &mut ctx.accounts.mine;
     let character = &mut ctx.accounts.character;
@@ -397,7 +1033,12 @@ pub fn stake(ctx: Context<StakeMine>) -> Result<()> {
     );
     
     require!(
-        ctx.accounts.nft_ata.amount == 1,
+        mine.custody == 1,
+        QstakingErrors::InvalidCustodyMode
+    );
+    
+    require!(
+        ctx.accounts.nft_account.amount == 1,
         QstakingErrors::NotHolder
     );
     
@@ -406,56 +1047,45 @@ pub fn stake(ctx: Context<StakeMine>) -> Result<()> {
         QstakingErrors::AlreadyStaked
     );
     
+    // Programmable NFTs can only be moved through Token Metadata and are staked in the user's wallet
+    require!(
+        !metadata::is_programmable(&ctx.accounts.nft_metadata.to_account_info())?,
+        QstakingErrors::InvalidCustodyMode
+    );
+    
     
-    whitelist.verify(&ctx.program_id, &whitelist.key(), &ctx.accounts.nft_mint.key(), &ctx.accounts.nft_metadata.to_account_info())?;
-    mine.add_character(clock.unix_timestamp, character.mining_points)?;
-    character.stake_mine(clock.unix_timestamp, mine.accrued_rewards)?;
+    whitelist.verify(&ctx.program_id, &whitelist.key(), &ctx.accounts.nft_mint.key(), &ctx.accounts.nft_metadata.to_account_info(), &ctx.accounts.denylist.to_account_info(), &proof)?;
+    let (staked_weight, lock_duration) = mine.lock_tier(lock_tier, character.mining_points)?;
+    stake_guild_member(ctx.remaining_accounts, ctx.program_id, mine, character, clock.unix_timestamp)?;
+    mine.add_character(clock.unix_timestamp, staked_weight)?;
+    character.stake_mine(clock.unix_timestamp, mine.season, mine.accrued_rewards, 1, &ctx.accounts.nft_vault.key(), &ctx.accounts.user.key())?;
+    character.lock(clock.unix_timestamp, lock_tier, lock_duration, staked_weight)?;
     
-    // Add the Mine account as delegate to user's NFT ATA
-    token::approve(
+    // Escrow the NFT into the Mine vault
+    token::transfer(
         CpiContext::new(
             ctx.accounts.token_program.to_account_info(),
-            token::Approve {
-                to: ctx.accounts.nft_ata.to_account_info(),
-                delegate: mine.to_account_info(),
+            token::Transfer {
+                from: ctx.accounts.nft_account.to_account_info(),
+                to: ctx.accounts.nft_vault.to_account_info(),
                 authority: ctx.accounts.user.to_account_info(),
             },
         ),
         1,
     )?;
     
-    // Freeze the user's NFT ATA
-    invoke_signed(
-        &metaplex::instruction::freeze_delegated_account(
-            ctx.accounts.token_metadata_program.key(),
-            mine.key(),
-            ctx.accounts.nft_ata.key(),
-            ctx.accounts.nft_edition.key(),
-            ctx.accounts.nft_mint.key(),
-        ),
-        &[
-            ctx.accounts.token_metadata_program.to_account_info(),
-            mine.to_account_info(),
-            ctx.accounts.nft_ata.to_account_info(),
-            ctx.accounts.nft_edition.to_account_info(),
-            ctx.accounts.nft_mint.to_account_info(),
-        ],
-        &[&[b"mine".as_ref(), &[mine.bump]]],
-    )?;
-    
     Ok(())
- 
-} 
+}
 
 
 
 /*
- *  Unstake-delegate an NFT
+ *  Unstake an NFT escrowed in a Mine vault
  */
 
 
 #[derive(Accounts)]
-pub struct UnstakeMine<'info> {
+pub struct UnstakeMineVault<'info> {
 
     // Mine staking pool account
     #[account(
@@ -473,179 +1103,1028 @@ pub struct UnstakeMine<'info> {
     )]
     pub loot: Box<Account<'info, Loot>>,
     
-    // Miner account of the NFT
+    // Character account of the NFT
     #[account(
         mut,
         seeds = [b"character".as_ref(), nft_mint.key().as_ref(),],
         bump,
+        constraint = character.vault_owner == user.key() @ QstakingErrors::NotHolder,
     )]
     pub character: Box<Account<'info, Character>>,
     
+    // Denylist account of the NFT, only initialized if the mint is denylisted
+    ///CHECKED: the address is derived from the NFT mint and only the existence of the account is inspected
+    #[account(
+        seeds = [b"denylist".as_ref(), nft_mint.key().as_ref()],
+        bump,
+    )]
+    pub denylist: UncheckedAccount<'info>,
+    
     // Token account with loot rewards
+    ///CHECKED: the account is unpacked and its mint and owner checked with the reward token program
     #[account(
         mut,
         seeds = [b"proceeds".as_ref(), loot.key().as_ref()],
         bump,
-        token::mint = reward_mint,
-        token::authority = loot,
     )]
-    pub loot_proceeds: Box<Account<'info, TokenAccount>>,
+    pub loot_proceeds: UncheckedAccount<'info>,
     
-    // Associated token account of the NFT
+    // Token account holding the NFT
     #[account(
         mut,
-        associated_token::mint = nft_mint,
-        associated_token::authority = user,
+        token::mint = nft_mint,
+        token::authority = user,
     )]
-    pub nft_ata: Box<Account<'info, TokenAccount>>,
+    pub nft_account: Box<Account<'info, TokenAccount>>,
     
-    // Token (Master) Edition account
-    ///CHECKED: custom logic checks for the validity of this account
-    pub nft_edition: UncheckedAccount<'info>,
+    // Vault token account of the Mine holding the NFT
+    #[account(
+        mut,
+        seeds = [b"vault".as_ref(), nft_mint.key().as_ref()],
+        bump,
+        token::mint = nft_mint,
+        token::authority = mine,
+    )]
+    pub nft_vault: Box<Account<'info, TokenAccount>>,
     
     // Mint account of the NFT
     pub nft_mint: Box<Account<'info, Mint>>,
     
-    // Associated token account for the reward tokens
+    // Vesting account of the user for the NFT, only created once mined rewards vest
+    ///CHECKED: the address is derived from the NFT mint and the user, the account is created and deserialized by custom logic
     #[account(
         mut,
-        associated_token::mint = reward_mint,
-        associated_token::authority = user,
+        seeds = [b"vesting".as_ref(), nft_mint.key().as_ref(), user.key().as_ref()],
+        bump,
+    )]
+    pub vesting: UncheckedAccount<'info>,
+    
+    // Token account of the Mine holding the vesting Reward Tokens
+    ///CHECKED: the address is derived from the Mine and the account is validated by the reward token program
+    #[account(
+        mut,
+        seeds = [b"escrow".as_ref(), mine.key().as_ref()],
+        bump,
     )]
-    pub reward_ata: Box<Account<'info, TokenAccount>>,
+    pub vesting_escrow: UncheckedAccount<'info>,
+    
+    // Associated token account for the reward tokens
+    ///CHECKED: the address is derived from the user, the reward mint and the reward token program
+    #[account(mut)]
+    pub reward_ata: UncheckedAccount<'info>,
     
-    // Address of the reward mint
+    // Address of the reward mint, owned by the Token or the Token-2022 program
+    ///CHECKED: the address is checked against the Mine, the mint authority by the reward token program
     #[account(
         mut,
-        mint::authority = mine.key(),
         constraint = mine.mint == reward_mint.key(),
     )]
-    pub reward_mint: Box<Account<'info, Mint>>,
+    pub reward_mint: UncheckedAccount<'info>,
     
-    // User account that holds the NFT
+    // User account that staked the NFT
     #[account(mut)]
     pub user: Signer<'info>,
     
-    // Metaplex Token Metadata program
-    /// CHECKED: This is not dangerous because we don't read or write from this account
-    pub token_metadata_program: UncheckedAccount<'info>,
-    
     // Token program
     pub token_program: Program<'info, Token>,
     
+    // Token program of the reward mint
+    ///CHECKED: the address is checked against the owner of the reward mint
+    #[account(
+        constraint = reward_token_program.key() == *reward_mint.owner @ QstakingErrors::InvalidRewardTokenAccount,
+    )]
+    pub reward_token_program: UncheckedAccount<'info>,
+    
     // Associated token program
     pub associated_token_program: Program<'info, AssociatedToken>,
-
+    
     // System program
     pub system_program: Program<'info, System>,
 }
 
 
-pub fn unstake(ctx: Context<UnstakeMine>) -> Result<()> {
+pub fn unstake_vault<'info>(ctx: Context<'_, '_, '_, 'info, UnstakeMineVault<'info>>) -> Result<()> {
 
-    assert_edition_account(&ctx.accounts.nft_mint.key(), &ctx.accounts.nft_edition.to_account_info())?;
-    
     let clock = Clock::get()?;
     let mine = &mut ctx.accounts.mine;
     let loot = &mut ctx.accounts.loot;
     let character = &mut ctx.accounts.character;
     
-    require!(
-        ctx.accounts.nft_ata.amount == 1,
-        QstakingErrors::NotHolder
-    );
-    
     require!(
         character.staked == 1,
         QstakingErrors::NotStaked
     );
     
+    require!(
+        character.custody == 1,
+        QstakingErrors::InvalidCustodyMode
+    );
     
-    let mine_pool_strength = u64::try_from(mine.staked_characters).unwrap().checked_add(20).ok_or(QstakingErrors::InvalidComputation).unwrap();
-    let loot_pool_strength = u64::try_from(loot.staked_characters).unwrap().checked_add(5).ok_or(QstakingErrors::InvalidComputation).unwrap();
-    
-    mine.remove_character(clock.unix_timestamp, character.mining_points)?;
-    let (accrued_reward, looted_reward) = character.unstake_mine(
-        clock.unix_timestamp,
-        mine.cooldown,
-        mine_pool_strength,
-        loot_pool_strength,
-        mine.accrued_rewards
-    )?;
-    loot.add_funds(clock.unix_timestamp, looted_reward)?;
-    
-    // Thaw the user's NFT ATA
-    invoke_signed(
-        &metaplex::instruction::thaw_delegated_account(
-            ctx.accounts.token_metadata_program.key(),
-            mine.key(),
-            ctx.accounts.nft_ata.key(),
-            ctx.accounts.nft_edition.key(),
-            ctx.accounts.nft_mint.key(),
-        ),
-        &[
-            ctx.accounts.token_metadata_program.to_account_info(),
-            mine.to_account_info(),
-            ctx.accounts.nft_ata.to_account_info(),
-            ctx.accounts.nft_edition.to_account_info(),
-            ctx.accounts.nft_mint.to_account_info(),
-        ],
-        &[&[b"mine".as_ref(), &[mine.bump]]],
-    )?;
     
-    // Remove the Mine account as delegate from user's NFT ATA
-    token::revoke(
-        CpiContext::new(
-            ctx.accounts.token_program.to_account_info(),
-            token::Revoke {
-                source: ctx.accounts.nft_ata.to_account_info(),
-                authority: ctx.accounts.user.to_account_info(),
-            },
-        )
+    token_interface::assert_reward_accounts(
+        &ctx.accounts.reward_token_program.key(),
+        &ctx.accounts.reward_mint.to_account_info(),
+        &ctx.accounts.reward_ata.to_account_info(),
+        &ctx.accounts.user.key(),
+        &ctx.accounts.loot_proceeds.to_account_info(),
+        &loot.key(),
     )?;
     
+    let denylisted = Denylist::is_denylisted(ctx.program_id, &ctx.accounts.denylist.to_account_info());
+    settle_guild_bonus(ctx.remaining_accounts, ctx.program_id, mine, character, clock.unix_timestamp)?;
+    let (accrued_reward, looted_reward) = settle_mine(mine, loot, character, denylisted, clock.unix_timestamp)?;
+    let mined_reward = accrued_reward;
+    Leaderboard::record(ctx.program_id, ctx.remaining_accounts, &ctx.accounts.nft_mint.key(), character)?;
     
-    // Mint the reward tokens to user's ATA
-    token::mint_to(
+    // Return the NFT from the Mine vault to user's NFT ATA
+    token::transfer(
         CpiContext::new_with_signer(
             ctx.accounts.token_program.to_account_info(),
-            token::MintTo {
-                mint: ctx.accounts.reward_mint.to_account_info(),
-                to: ctx.accounts.reward_ata.to_account_info(),
+            token::Transfer {
+                from: ctx.accounts.nft_vault.to_account_info(),
+                to: ctx.accounts.nft_account.to_account_info(),
                 authority: mine.to_account_info(),
             },
             &[&[b"mine".as_ref(), &[mine.bump]]],
         ),
-        accrued_reward,
+        1,
     )?;
     
-    
-    // Mint the looted reward tokens to loot proceeds account
-    token::mint_to(
+    // Close the Mine vault and refund its rent to the user
+    token::close_account(
         CpiContext::new_with_signer(
             ctx.accounts.token_program.to_account_info(),
-            token::MintTo {
-                mint: ctx.accounts.reward_mint.to_account_info(),
-                to: ctx.accounts.loot_proceeds.to_account_info(),
+            token::CloseAccount {
+                account: ctx.accounts.nft_vault.to_account_info(),
+                destination: ctx.accounts.user.to_account_info(),
                 authority: mine.to_account_info(),
             },
             &[&[b"mine".as_ref(), &[mine.bump]]],
         ),
-        looted_reward,
     )?;
     
+    let (accrued_reward, guild_accounts) = unstake_guild_member(
+        ctx.remaining_accounts,
+        character,
+        ctx.accounts.reward_token_program.to_account_info(),
+        ctx.accounts.reward_mint.to_account_info(),
+        mine.to_account_info(),
+        mine.bump,
+        accrued_reward,
+    )?;
+    
+    pay_referral(
+        &ctx.remaining_accounts[guild_accounts..],
+        ctx.program_id,
+        mine,
+        character,
+        ctx.accounts.reward_token_program.to_account_info(),
+        ctx.accounts.reward_mint.to_account_info(),
+        clock.unix_timestamp,
+        mined_reward,
+    )?;
+    
+    // Mined rewards go to the vesting escrow when the Mine vests them
+    let reward_destination = if mine.vesting_period > 0 && accrued_reward > 0 {
+        vest_rewards(
+            ctx.accounts.vesting.to_account_info(),
+            ctx.accounts.user.to_account_info(),
+            ctx.accounts.system_program.to_account_info(),
+            &ctx.accounts.user.key(),
+            &ctx.accounts.nft_mint.key(),
+            *ctx.bumps.get("vesting").unwrap(),
+            clock.unix_timestamp,
+            mine.vesting_period,
+            accrued_reward,
+        )?;
+        ctx.accounts.vesting_escrow.to_account_info()
+    } else {
+        ctx.accounts.reward_ata.to_account_info()
+    };
+    
+    mint_rewards(
+        ctx.accounts.reward_token_program.to_account_info(),
+        ctx.accounts.reward_mint.to_account_info(),
+        reward_destination,
+        ctx.accounts.loot_proceeds.to_account_info(),
+        mine.to_account_info(),
+        mine.bump,
+        accrued_reward,
+        looted_reward,
+    )?;
     
     Ok(())
- 
 }
 
 
 
 /*
- *  Utility functions
+ *  Claim the vested mined rewards of an NFT
  */
 
 
+#[derive(Accounts)]
+pub struct ClaimVested<'info> {
+
+    // Mine staking pool account
+    #[account(
+        seeds = [b"mine".as_ref(),],
+        bump,
+    )]
+    pub mine: Box<Account<'info, Mine>>,
+    
+    // Vesting account of the user for the NFT
+    #[account(
+        mut,
+        seeds = [b"vesting".as_ref(), vesting.nft_mint.as_ref(), user.key().as_ref()],
+        bump = vesting.bump,
+    )]
+    pub vesting: Box<Account<'info, Vesting>>,
+    
+    // Token account of the Mine holding the vesting Reward Tokens
+    ///CHECKED: the address is derived from the Mine and the account is validated by the reward token program
+    #[account(
+        mut,
+        seeds = [b"escrow".as_ref(), mine.key().as_ref()],
+        bump,
+    )]
+    pub vesting_escrow: UncheckedAccount<'info>,
+    
+    // Associated token account for the reward tokens
+    ///CHECKED: the address is derived from the user, the reward mint and the reward token program
+    #[account(mut)]
+    pub reward_ata: UncheckedAccount<'info>,
+    
+    // Address of the reward mint, owned by the Token or the Token-2022 program
+    ///CHECKED: the address is checked against the Mine
+    #[account(
+        constraint = mine.mint == reward_mint.key(),
+    )]
+    pub reward_mint: UncheckedAccount<'info>,
+    
+    // User the vesting Reward Tokens are released to
+    pub user: Signer<'info>,
+    
+    // Token program of the reward mint
+    ///CHECKED: the address is checked against the owner of the reward mint
+    #[account(
+        constraint = reward_token_program.key() == *reward_mint.owner @ QstakingErrors::InvalidRewardTokenAccount,
+    )]
+    pub reward_token_program: UncheckedAccount<'info>,
+}
+
+
+pub fn claim_vested(ctx: Context<ClaimVested>) -> Result<()> {
+
+    let clock = Clock::get()?;
+    let mine = &ctx.accounts.mine;
+    let vesting = &mut ctx.accounts.vesting;
+    
+    token_interface::assert_associated_token_account(
+        &ctx.accounts.reward_ata.to_account_info(),
+        &ctx.accounts.user.key(),
+        &ctx.accounts.reward_mint.key(),
+        &ctx.accounts.reward_token_program.key(),
+    )?;
+    
+    let claimed_amount = vesting.claim(clock.unix_timestamp)?;
+    
+    release_vested(
+        ctx.accounts.reward_token_program.to_account_info(),
+        ctx.accounts.vesting_escrow.to_account_info(),
+        ctx.accounts.reward_mint.to_account_info(),
+        ctx.accounts.reward_ata.to_account_info(),
+        mine.to_account_info(),
+        mine.bump,
+        claimed_amount,
+    )?;
+    
+    Ok(())
+}
+
+
+
+/*
+ *  Release all vesting mined rewards of an NFT early, a share of the unvested rewards is forfeited to the Loot
+ */
+
+
+#[derive(Accounts)]
+pub struct ExitVesting<'info> {
+
+    // Mine staking pool account
+    #[account(
+        seeds = [b"mine".as_ref(),],
+        bump,
+    )]
+    pub mine: Box<Account<'info, Mine>>,
+    
+    // Loot staking pool account
+    #[account(
+        mut,
+        seeds = [b"loot".as_ref(),],
+        bump,
+    )]
+    pub loot: Box<Account<'info, Loot>>,
+    
+    // Vesting account of the user for the NFT
+    #[account(
+        mut,
+        seeds = [b"vesting".as_ref(), vesting.nft_mint.as_ref(), user.key().as_ref()],
+        bump = vesting.bump,
+    )]
+    pub vesting: Box<Account<'info, Vesting>>,
+    
+    // Token account of the Mine holding the vesting Reward Tokens
+    ///CHECKED: the address is derived from the Mine and the account is validated by the reward token program
+    #[account(
+        mut,
+        seeds = [b"escrow".as_ref(), mine.key().as_ref()],
+        bump,
+    )]
+    pub vesting_escrow: UncheckedAccount<'info>,
+    
+    // Token account with loot rewards
+    ///CHECKED: the account is unpacked and its mint and owner checked with the reward token program
+    #[account(
+        mut,
+        seeds = [b"proceeds".as_ref(), loot.key().as_ref()],
+        bump,
+    )]
+    pub loot_proceeds: UncheckedAccount<'info>,
+    
+    // Associated token account for the reward tokens
+    ///CHECKED: the address is derived from the user, the reward mint and the reward token program
+    #[account(mut)]
+    pub reward_ata: UncheckedAccount<'info>,
+    
+    // Address of the reward mint, owned by the Token or the Token-2022 program
+    ///CHECKED: the address is checked against the Mine
+    #[account(
+        constraint = mine.mint == reward_mint.key(),
+    )]
+    pub reward_mint: UncheckedAccount<'info>,
+    
+    // User the vesting Reward Tokens are released to
+    pub user: Signer<'info>,
+    
+    // Token program of the reward mint
+    ///CHECKED: the address is checked against the owner of the reward mint
+    #[account(
+        constraint = reward_token_program.key() == *reward_mint.owner @ QstakingErrors::InvalidRewardTokenAccount,
+    )]
+    pub reward_token_program: UncheckedAccount<'info>,
+}
+
+
+pub fn exit_vesting(ctx: Context<ExitVesting>) -> Result<()> {
+
+    let clock = Clock::get()?;
+    let mine = &ctx.accounts.mine;
+    let loot = &mut ctx.accounts.loot;
+    let vesting = &mut ctx.accounts.vesting;
+    
+    token_interface::assert_reward_accounts(
+        &ctx.accounts.reward_token_program.key(),
+        &ctx.accounts.reward_mint.to_account_info(),
+        &ctx.accounts.reward_ata.to_account_info(),
+        &ctx.accounts.user.key(),
+        &ctx.accounts.loot_proceeds.to_account_info(),
+        &loot.key(),
+    )?;
+    
+    let (released_amount, forfeited_amount) = vesting.exit_early(clock.unix_timestamp, mine.vesting_penalty_bps)?;
+    
+    release_vested(
+        ctx.accounts.reward_token_program.to_account_info(),
+        ctx.accounts.vesting_escrow.to_account_info(),
+        ctx.accounts.reward_mint.to_account_info(),
+        ctx.accounts.reward_ata.to_account_info(),
+        mine.to_account_info(),
+        mine.bump,
+        released_amount,
+    )?;
+    
+    if forfeited_amount > 0 {
+        release_vested(
+            ctx.accounts.reward_token_program.to_account_info(),
+            ctx.accounts.vesting_escrow.to_account_info(),
+            ctx.accounts.reward_mint.to_account_info(),
+            ctx.accounts.loot_proceeds.to_account_info(),
+            mine.to_account_info(),
+            mine.bump,
+            forfeited_amount,
+        )?;
+        
+        // The Loot is only funded with what the proceeds account receives after a transfer fee
+        let fee = token_interface::transfer_fee(&ctx.accounts.reward_mint.to_account_info(), forfeited_amount, clock.epoch)?;
+        loot.add_funds(
+            clock.unix_timestamp,
+            forfeited_amount.checked_sub(fee).ok_or(QstakingErrors::InvalidComputation).unwrap()
+        )?;
+    }
+    
+    Ok(())
+}
+
+
+
+/*
+ *  Burn a boost item to raise the stake weight of an NFT staked in the Mine
+ */
+
+
+#[derive(Accounts)]
+pub struct ApplyBoost<'info> {
+
+    // Mine staking pool account
+    #[account(
+        mut,
+        seeds = [b"mine".as_ref(),],
+        bump,
+    )]
+    pub mine: Box<Account<'info, Mine>>,
+    
+    // BoostRegistry account with the boost items of the Mine
+    #[account(
+        seeds = [b"boosts".as_ref()],
+        bump,
+    )]
+    pub boost_registry: Box<Account<'info, BoostRegistry>>,
+    
+    // Character account of the NFT
+    #[account(
+        mut,
+        seeds = [b"character".as_ref(), nft_mint.key().as_ref(),],
+        bump,
+    )]
+    pub character: Box<Account<'info, Character>>,
+    
+    // Token account holding the staked NFT
+    #[account(
+        constraint = nft_account.key() == character.nft_account @ QstakingErrors::NotHolder,
+    )]
+    pub nft_account: Box<Account<'info, TokenAccount>>,
+    
+    // Mint account of the NFT
+    pub nft_mint: Box<Account<'info, Mint>>,
+    
+    // Mint of the boost item
+    #[account(mut)]
+    pub boost_mint: Box<Account<'info, Mint>>,
+    
+    // Token account of the user holding the boost item
+    #[account(
+        mut,
+        token::mint = boost_mint,
+        token::authority = user,
+    )]
+    pub boost_account: Box<Account<'info, TokenAccount>>,
+    
+    // User account that staked the NFT
+    pub user: Signer<'info>,
+    
+    // Token program
+    pub token_program: Program<'info, Token>,
+}
+
+
+pub fn apply_boost(ctx: Context<ApplyBoost>) -> Result<()> {
+
+    let clock = Clock::get()?;
+    let mine = &mut ctx.accounts.mine;
+    let character = &mut ctx.accounts.character;
+    
+    require!(
+        character.staked == 1,
+        QstakingErrors::NotStaked
+    );
+    
+    // Escrowed NFTs are held by the Mine vault on behalf of the user who staked them
+    let holder = if character.custody == 1 {
+        character.vault_owner
+    } else {
+        ctx.accounts.nft_account.owner
+    };
+    require_keys_eq!(
+        holder,
+        ctx.accounts.user.key(),
+        QstakingErrors::NotHolder
+    );
+    
+    // An expired boost is ended before the new one is applied
+    if character.boost_end_timestamp != 0 && character.boost_end_timestamp <= clock.unix_timestamp {
+        end_boost(mine, character, clock.unix_timestamp)?;
+    }
+    
+    apply_boost_item(
+        mine,
+        character,
+        &ctx.accounts.boost_registry,
+        &ctx.accounts.boost_mint,
+        &ctx.accounts.boost_account,
+        ctx.accounts.user.to_account_info(),
+        ctx.accounts.token_program.to_account_info(),
+        clock.unix_timestamp,
+    )?;
+    
+    Ok(())
+}
+
+
+
+/*
+ *  Settle the rewards of an expired boost into the Character and free its Mine entry, can be called by anyone
+ */
+
+
+#[derive(Accounts)]
+pub struct ExpireBoost<'info> {
+
+    // Mine staking pool account
+    #[account(
+        mut,
+        seeds = [b"mine".as_ref(),],
+        bump,
+    )]
+    pub mine: Box<Account<'info, Mine>>,
+    
+    // Character account of the NFT
+    #[account(
+        mut,
+        seeds = [b"character".as_ref(), nft_mint.key().as_ref(),],
+        bump,
+    )]
+    pub character: Box<Account<'info, Character>>,
+    
+    // Mint account of the NFT
+    pub nft_mint: Box<Account<'info, Mint>>,
+}
+
+
+pub fn expire_boost(ctx: Context<ExpireBoost>) -> Result<()> {
+
+    let clock = Clock::get()?;
+    let mine = &mut ctx.accounts.mine;
+    let character = &mut ctx.accounts.character;
+    
+    require!(
+        character.staked == 1
+            && character.boost_end_timestamp != 0
+            && character.boost_end_timestamp <= clock.unix_timestamp,
+        QstakingErrors::BoostNotExpired
+    );
+    
+    end_boost(mine, character, clock.unix_timestamp)?;
+    
+    Ok(())
+}
+
+
+
+/*
+ *  Utility functions
+ */
+
+
+// Burns one boost item of the user and adds the boosted stake weight to the Mine until the boost ends
+#[allow(clippy::too_many_arguments)]
+pub fn apply_boost_item<'info>(
+    mine: &mut Mine,
+    character: &mut Character,
+    boost_registry: &BoostRegistry,
+    boost_mint: &Account<'info, Mint>,
+    boost_account: &Account<'info, TokenAccount>,
+    user: AccountInfo<'info>,
+    token_program: AccountInfo<'info>,
+    timestamp: i64,
+) -> Result<()> {
+
+    let boost = boost_registry.find(&boost_mint.key())?;
+    
+    require!(
+        character.boost_end_timestamp == 0,
+        QstakingErrors::BoostActive
+    );
+    
+    let boost_weight = u128::from(character.staked_weight).checked_mul(
+        u128::from(boost.multiplier_bps - 10000)
+    ).ok_or(QstakingErrors::InvalidComputation).unwrap() / 10000;
+    let boost_weight = u64::try_from(boost_weight).unwrap();
+    
+    require!(
+        boost_weight > 0,
+        QstakingErrors::InvalidBoost
+    );
+    
+    let end_timestamp = timestamp.checked_add(i64::try_from(boost.duration).unwrap()).ok_or(QstakingErrors::InvalidComputation).unwrap();
+    mine.add_boost(timestamp, end_timestamp, boost_weight)?;
+    character.boost(timestamp, mine.accrued_rewards, boost_weight, boost.duration)?;
+    
+    // Burn one whole boost item from the user's token account
+    token::burn(
+        CpiContext::new(
+            token_program,
+            token::Burn {
+                mint: boost_mint.to_account_info(),
+                from: boost_account.to_account_info(),
+                authority: user,
+            },
+        ),
+        10u64.checked_pow(u32::from(boost_mint.decimals)).ok_or(QstakingErrors::InvalidComputation).unwrap(),
+    )?;
+    
+    Ok(())
+}
+
+
+// Loads the Guild of a guild member, passed as the first remaining account
+pub fn load_guild<'info>(remaining_accounts: &[AccountInfo<'info>], character: &Character) -> Result<Option<Account<'info, Guild>>> {
+
+    if character.guild == Pubkey::default() {
+        return Ok(None);
+    }
+    
+    require!(
+        !remaining_accounts.is_empty(),
+        QstakingErrors::InvalidGuild
+    );
+    
+    let guild: Account<Guild> = Account::try_from(&remaining_accounts[0])?;
+    require_keys_eq!(
+        guild.key(),
+        character.guild,
+        QstakingErrors::InvalidGuild
+    );
+    
+    Ok(Some(guild))
+}
+
+
+// Adds a guild member to the staked members of its Guild and pegs its guild bonus, returns the number of remaining accounts used
+pub fn stake_guild_member(remaining_accounts: &[AccountInfo], program_id: &Pubkey, mine: &mut Mine, character: &mut Character, timestamp: i64) -> Result<usize> {
+
+    let mut guild = match load_guild(remaining_accounts, character)? {
+        Some(guild) => guild,
+        None => return Ok(0),
+    };
+    
+    // The bonus changes with the number of staked members, so it is accrued up to now before the member joins them
+    mine.update_accrued_rewards(timestamp)?;
+    let bonus_bps = mine.guild_bonus_bps(guild.staked_members)?;
+    guild.accrue_bonus(mine.accrued_rewards, bonus_bps)?;
+    guild.add_staked_member()?;
+    guild.exit(program_id)?;
+    character.guild_cut_bps = guild.cut_bps;
+    character.guild_bonus_peg = guild.bonus_accrued;
+    
+    Ok(1)
+}
+
+
+// Removes a guild member from the staked members of its Guild and adds the guild bonus accrued during its stake to its rewards
+pub fn settle_guild_bonus(remaining_accounts: &[AccountInfo], program_id: &Pubkey, mine: &mut Mine, character: &mut Character, timestamp: i64) -> Result<()> {
+
+    let mut guild = match load_guild(remaining_accounts, character)? {
+        Some(guild) => guild,
+        None => return Ok(()),
+    };
+    
+    mine.update_accrued_rewards(timestamp)?;
+    let bonus_bps = mine.guild_bonus_bps(guild.staked_members)?;
+    guild.accrue_bonus(mine.accrued_rewards, bonus_bps)?;
+    guild.remove_staked_member()?;
+    guild.exit(program_id)?;
+    character.add_guild_bonus(guild.bonus_accrued)?;
+    
+    Ok(())
+}
+
+
+// Mints the guild cut of the mined rewards of a guild member settled with settle_guild_bonus to the guild treasury,
+// passed as the second remaining account. Returns the mined rewards left to the member
+// together with the number of remaining accounts used
+#[allow(clippy::too_many_arguments)]
+pub fn unstake_guild_member<'info>(
+    remaining_accounts: &[AccountInfo<'info>],
+    character: &Character,
+    reward_token_program: AccountInfo<'info>,
+    reward_mint: AccountInfo<'info>,
+    mine: AccountInfo<'info>,
+    mine_bump: u8,
+    accrued_reward: u64,
+) -> Result<(u64, usize)> {
+
+    let guild = match load_guild(remaining_accounts, character)? {
+        Some(guild) => guild,
+        None => return Ok((accrued_reward, 0)),
+    };
+    
+    require!(
+        remaining_accounts.len() > 1 && remaining_accounts[1].key() == guild.treasury,
+        QstakingErrors::InvalidGuild
+    );
+    
+    let guild_cut = u128::from(accrued_reward).checked_mul(u128::from(character.guild_cut_bps)).ok_or(QstakingErrors::InvalidComputation).unwrap() / 10000;
+    let guild_cut = u64::try_from(guild_cut).unwrap();
+    
+    // Mint the guild cut to the guild treasury
+    token_interface::mint_to(
+        reward_token_program,
+        reward_mint,
+        remaining_accounts[1].clone(),
+        mine,
+        guild_cut,
+        &[&[b"mine".as_ref(), &[mine_bump]]],
+    )?;
+    
+    Ok((accrued_reward.checked_sub(guild_cut).ok_or(QstakingErrors::InvalidComputation).unwrap(), 2))
+}
+
+
+// Mints the referral reward of a referred Character unstaked within the referral window to the holder of the referrer NFT.
+// The referrer Character, the token account holding the referrer NFT and the holder's reward token account are passed as remaining accounts
+#[allow(clippy::too_many_arguments)]
+pub fn pay_referral<'info>(
+    referral_accounts: &[AccountInfo<'info>],
+    program_id: &Pubkey,
+    mine: &mut Account<'info, Mine>,
+    character: &Character,
+    reward_token_program: AccountInfo<'info>,
+    reward_mint: AccountInfo<'info>,
+    timestamp: i64,
+    accrued_reward: u64,
+) -> Result<()> {
+
+    if accrued_reward == 0 || !character.in_referral_window(timestamp, mine.referral_window)? {
+        return Ok(());
+    }
+    
+    require!(
+        !referral_accounts.is_empty(),
+        QstakingErrors::InvalidReferrer
+    );
+    
+    let (referrer_character, _) = Pubkey::find_program_address(&[b"character".as_ref(), character.referrer.as_ref()], program_id);
+    require_keys_eq!(
+        referral_accounts[0].key(),
+        referrer_character,
+        QstakingErrors::InvalidReferrer
+    );
+    
+    // Referrers whose Character account was closed earn no referral rewards
+    if referral_accounts[0].data_is_empty() {
+        return Ok(());
+    }
+    
+    require!(
+        referral_accounts.len() >= 2,
+        QstakingErrors::InvalidReferrer
+    );
+    
+    // Referrers whose NFT was burned have no holder to pay, which is proven by passing the referrer mint without supply
+    if referral_accounts[1].key() == character.referrer {
+        let referrer_mint: Account<Mint> = Account::try_from(&referral_accounts[1])?;
+        require!(
+            referrer_mint.supply == 0,
+            QstakingErrors::InvalidReferrer
+        );
+        return Ok(());
+    }
+    
+    require!(
+        referral_accounts.len() >= 3,
+        QstakingErrors::InvalidReferrer
+    );
+    
+    let mut referrer: Account<Character> = Account::try_from(&referral_accounts[0])?;
+    
+    // NFTs escrowed in the Mine or the Loot vault are held on behalf of the vault owner
+    let holder = if referrer.custody == 1 {
+        require_keys_eq!(
+            referral_accounts[1].key(),
+            referrer.nft_account,
+            QstakingErrors::InvalidReferrer
+        );
+        referrer.vault_owner
+    } else {
+        let nft_account: Account<TokenAccount> = Account::try_from(&referral_accounts[1])?;
+        require!(
+            nft_account.mint == character.referrer && nft_account.amount == 1,
+            QstakingErrors::InvalidReferrer
+        );
+        nft_account.owner
+    };
+    
+    token_interface::assert_token_account(&referral_accounts[2], &holder, &reward_mint.key(), &reward_token_program.key())?;
+    
+    let referral_reward = mine.referral_reward(accrued_reward, referrer.referral_earned)?;
+    referrer.add_referral_rewards(referral_reward)?;
+    referrer.exit(program_id)?;
+    mine.record_rewards(referral_reward, 0, 0)?;
+    
+    // Mint the referral reward to the referrer's reward token account
+    token_interface::mint_to(
+        reward_token_program,
+        reward_mint,
+        referral_accounts[2].clone(),
+        mine.to_account_info(),
+        referral_reward,
+        &[&[b"mine".as_ref(), &[mine.bump]]],
+    )?;
+    
+    Ok(())
+}
+
+
+pub fn end_boost(mine: &mut Mine, character: &mut Character, timestamp: i64) -> Result<()> {
+
+    if character.boost_end_timestamp == 0 {
+        return Ok(());
+    }
+    
+    let accrued_rewards = mine.end_boost(timestamp, character.boost_end_timestamp, character.boost_weight, character.boost_peg)?;
+    character.end_boost(accrued_rewards)?;
+    
+    Ok(())
+}
+
+
+pub fn settle_mine(mine: &mut Mine, loot: &mut Loot, character: &mut Character, denylisted: bool, timestamp: i64) -> Result<(u64, u64)> {
+
+    require!(
+        timestamp >= character.lock_end_timestamp,
+        QstakingErrors::LockNotExpired
+    );
+    
+    settle_mine_rewards(mine, loot, character, denylisted, timestamp)
+}
+
+
+// Removes the Character from the Mine and returns its mined and looted rewards, regardless of its lock
+pub fn settle_mine_rewards(mine: &mut Mine, loot: &mut Loot, character: &mut Character, denylisted: bool, timestamp: i64) -> Result<(u64, u64)> {
+
+    let mine_pool_strength = u64::try_from(mine.staked_characters).unwrap().checked_add(20).ok_or(QstakingErrors::InvalidComputation).unwrap();
+    let loot_pool_strength = u64::try_from(loot.staked_characters).unwrap().checked_add(5).ok_or(QstakingErrors::InvalidComputation).unwrap();
+    
+    end_boost(mine, character, timestamp)?;
+    mine.remove_character(timestamp, character.staked_weight, character.staked_peg, character.staked_seconds(timestamp)?)?;
+    
+    let (accrued_reward, looted_reward, clamped_reward) = if denylisted {
+        character.unstake_frozen_mine(mine_pool_strength, loot_pool_strength, mine.accrued_rewards)?
+    } else {
+        character.unstake_mine(
+            timestamp,
+            mine.cooldown,
+            mine_pool_strength,
+            loot_pool_strength,
+            mine.accrued_rewards
+        )?
+    };
+    let (accrued_reward, looted_reward) = mine.cap_rewards(accrued_reward, looted_reward)?;
+    mine.record_rewards(accrued_reward, looted_reward, clamped_reward)?;
+    loot.add_funds(timestamp, looted_reward)?;
+    
+    // Mined rewards of denylisted NFTs are withheld on the Character until the admin releases or confiscates them,
+    // the looted share still goes to the Loot
+    if denylisted {
+        character.withhold_rewards(accrued_reward, 0)?;
+        return Ok((0, looted_reward));
+    }
+    
+    Ok((accrued_reward, looted_reward))
+}
+
+
+#[allow(clippy::too_many_arguments)]
+pub fn mint_rewards<'info>(
+    token_program: AccountInfo<'info>,
+    reward_mint: AccountInfo<'info>,
+    reward_ata: AccountInfo<'info>,
+    loot_proceeds: AccountInfo<'info>,
+    mine: AccountInfo<'info>,
+    mine_bump: u8,
+    accrued_reward: u64,
+    looted_reward: u64,
+) -> Result<()> {
+
+    // Mint the reward tokens to user's ATA
+    token_interface::mint_to(
+        token_program.clone(),
+        reward_mint.clone(),
+        reward_ata,
+        mine.clone(),
+        accrued_reward,
+        &[&[b"mine".as_ref(), &[mine_bump]]],
+    )?;
+    
+    // Mint the looted reward tokens to loot proceeds account
+    token_interface::mint_to(
+        token_program,
+        reward_mint,
+        loot_proceeds,
+        mine,
+        looted_reward,
+        &[&[b"mine".as_ref(), &[mine_bump]]],
+    )?;
+    
+    Ok(())
+}
+
+
+
+// Creates the Vesting account on the first vested unstake and adds the mined rewards to its schedule
+#[allow(clippy::too_many_arguments)]
+pub fn vest_rewards<'info>(
+    vesting: AccountInfo<'info>,
+    payer: AccountInfo<'info>,
+    system_program: AccountInfo<'info>,
+    holder: &Pubkey,
+    nft_mint: &Pubkey,
+    vesting_bump: u8,
+    timestamp: i64,
+    vesting_period: u64,
+    amount: u64,
+) -> Result<()> {
+
+    let created = vesting.data_is_empty();
+    if created {
+        let space = 8 + Vesting::LEN;
+        let rent_exempt_balance = Rent::get()?.minimum_balance(space);
+        let balance = vesting.lamports();
+        
+        // The address may already hold lamports, so the account is funded, allocated and assigned separately
+        if rent_exempt_balance > balance {
+            invoke(
+                &system_instruction::transfer(payer.key, vesting.key, rent_exempt_balance - balance),
+                &[payer, vesting.clone(), system_program.clone()],
+            )?;
+        }
+        
+        let vesting_seeds: &[&[u8]] = &[b"vesting".as_ref(), nft_mint.as_ref(), holder.as_ref(), &[vesting_bump]];
+        invoke_signed(
+            &system_instruction::allocate(vesting.key, space as u64),
+            &[vesting.clone(), system_program.clone()],
+            &[vesting_seeds],
+        )?;
+        invoke_signed(
+            &system_instruction::assign(vesting.key, &crate::ID),
+            &[vesting.clone(), system_program],
+            &[vesting_seeds],
+        )?;
+    }
+    
+    require_keys_eq!(
+        *vesting.owner,
+        crate::ID,
+        ErrorCode::AccountOwnedByWrongProgram
+    );
+    
+    let mut data = vesting.try_borrow_mut_data()?;
+    let mut state = if created {
+        let mut state = Vesting::try_deserialize_unchecked(&mut &data[..])?;
+        state.initialize(vesting_bump, holder, nft_mint)?;
+        state
+    } else {
+        Vesting::try_deserialize(&mut &data[..])?
+    };
+    
+    state.add_rewards(timestamp, vesting_period, amount)?;
+    state.try_serialize(&mut &mut data[..])?;
+    
+    Ok(())
+}
+
+
+pub fn release_vested<'info>(
+    token_program: AccountInfo<'info>,
+    vesting_escrow: AccountInfo<'info>,
+    reward_mint: AccountInfo<'info>,
+    destination: AccountInfo<'info>,
+    mine: AccountInfo<'info>,
+    mine_bump: u8,
+    amount: u64,
+) -> Result<()> {
+
+    let decimals = token_interface::unpack_mint(&reward_mint, token_program.key)?.decimals;
+    
+    // Transfer the released reward tokens from the vesting escrow account
+    token_interface::transfer_checked(
+        token_program,
+        vesting_escrow,
+        reward_mint,
+        destination,
+        mine,
+        amount,
+        decimals,
+        &[&[b"mine".as_ref(), &[mine_bump]]],
+    )?;
+    
+    Ok(())
+}
+
+
+
 pub fn assert_edition_account(mint: &Pubkey, mint_edition: &AccountInfo) -> Result<()> {
     let metadata_program = metaplex::id();
     
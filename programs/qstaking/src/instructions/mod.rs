@@ -3,9 +3,21 @@ pub mod mine_config;
 pub mod loot_config;
 pub mod mine_staking;
 pub mod loot_staking;
+pub mod migration;
+pub mod equipment;
+pub mod guild;
+pub mod batch_staking;
+pub mod operator;
+pub mod upgrade;
 
 pub use program_config::*;
 pub use mine_config::*;
 pub use loot_config::*;
 pub use mine_staking::*;
 pub use loot_staking::*;
+pub use migration::*;
+pub use equipment::*;
+pub use guild::*;
+pub use batch_staking::*;
+pub use operator::*;
+pub use upgrade::*;
@@ -0,0 +1,677 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, Mint, TokenAccount, Token};
+use solana_program::program::invoke_signed;
+use solana_program::program_option::COption;
+use mpl_token_metadata as metaplex;
+
+use crate::metadata;
+use crate::token_interface;
+use crate::states::*;
+use crate::errors::QstakingErrors;
+use crate::instructions::mine_staking::{self, settle_mine, stake_guild_member, settle_guild_bonus, unstake_guild_member, pay_referral, mint_rewards, vest_rewards, release_vested};
+
+
+
+/*
+ *  Grant an operator key the right to stake, unstake and claim a Character in the Mine on behalf of the holder.
+ *  The holder caps the lock tier the operator may stake with, as a lock keeps the NFT frozen until it expires.
+ *  The Mine is approved as delegate of the NFT token account so the operator can freeze it without the holder.
+ *  The approval is granted here, while the holder signs, and stays in place while the NFT is unstaked until the holder
+ *  calls revoke_operator, transfers the NFT or unstakes it with unstake_mine. Only the Mine can use it, to freeze the
+ *  NFT on an operator stake in frozen custody mode, but wallets show the NFT as delegated for that whole time.
+ */
+
+
+#[derive(Accounts)]
+pub struct CreateOperator<'info> {
+
+    // Mine staking pool account
+    #[account(
+        seeds = [b"mine".as_ref(),],
+        bump,
+    )]
+    pub mine: Box<Account<'info, Mine>>,
+    
+    // Operator account of the holder for the NFT
+    #[account(
+        init,
+        payer = holder,
+        seeds = [b"operator".as_ref(), nft_mint.key().as_ref(), holder.key().as_ref()],
+        bump,
+        space = 8 + Operator::LEN,
+    )]
+    pub operator: Box<Account<'info, Operator>>,
+    
+    // Character account of the NFT
+    #[account(
+        seeds = [b"character".as_ref(), nft_mint.key().as_ref(),],
+        bump,
+    )]
+    pub character: Box<Account<'info, Character>>,
+    
+    // Token account holding the NFT
+    #[account(
+        mut,
+        token::mint = nft_mint,
+        token::authority = holder,
+    )]
+    pub nft_account: Box<Account<'info, TokenAccount>>,
+    
+    // Mint account of the NFT
+    pub nft_mint: Box<Account<'info, Mint>>,
+    
+    // Key allowed to act on behalf of the holder
+    ///CHECKED: any key can be granted the delegation by the holder
+    pub authority: UncheckedAccount<'info>,
+    
+    // User account that holds the NFT
+    #[account(mut)]
+    pub holder: Signer<'info>,
+    
+    // Token program
+    pub token_program: Program<'info, Token>,
+    
+    // System program
+    pub system_program: Program<'info, System>,
+}
+
+
+pub fn create(ctx: Context<CreateOperator>, max_lock_tier: u8) -> Result<()> {
+
+    let operator = &mut ctx.accounts.operator;
+    
+    require!(
+        ctx.accounts.nft_account.amount == 1,
+        QstakingErrors::NotHolder
+    );
+    
+    require!(
+        ctx.accounts.character.staked == 0,
+        QstakingErrors::AlreadyStaked
+    );
+    
+    operator.initialize(
+        *ctx.bumps.get("operator").unwrap(),
+        &ctx.accounts.holder.key(),
+        &ctx.accounts.nft_mint.key(),
+        &ctx.accounts.authority.key(),
+        max_lock_tier,
+    )?;
+    
+    // Add the Mine account as delegate to the holder's NFT token account
+    token::approve(
+        CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            token::Approve {
+                to: ctx.accounts.nft_account.to_account_info(),
+                delegate: ctx.accounts.mine.to_account_info(),
+                authority: ctx.accounts.holder.to_account_info(),
+            },
+        ),
+        1,
+    )?;
+    
+    Ok(())
+}
+
+
+
+/*
+ *  Revoke the delegation of the operator key.
+ *  The Mine stays delegate of a staked NFT until it is unstaked.
+ */
+
+
+#[derive(Accounts)]
+pub struct RevokeOperator<'info> {
+
+    // Mine staking pool account
+    #[account(
+        seeds = [b"mine".as_ref(),],
+        bump,
+    )]
+    pub mine: Box<Account<'info, Mine>>,
+    
+    // Operator account of the holder for the NFT
+    #[account(
+        mut,
+        close = holder,
+        seeds = [b"operator".as_ref(), operator.nft_mint.as_ref(), holder.key().as_ref()],
+        bump = operator.bump,
+    )]
+    pub operator: Box<Account<'info, Operator>>,
+    
+    // Token account of the holder for the NFT
+    #[account(
+        mut,
+        token::mint = operator.nft_mint,
+        token::authority = holder,
+    )]
+    pub nft_account: Box<Account<'info, TokenAccount>>,
+    
+    // User account that granted the delegation
+    #[account(mut)]
+    pub holder: Signer<'info>,
+    
+    // Token program
+    pub token_program: Program<'info, Token>,
+}
+
+
+pub fn revoke(ctx: Context<RevokeOperator>) -> Result<()> {
+
+    let nft_account = &ctx.accounts.nft_account;
+    
+    // Remove the Mine account as delegate from the holder's NFT token account unless the NFT is staked
+    if !nft_account.is_frozen() && nft_account.delegate == COption::Some(ctx.accounts.mine.key()) {
+        token::revoke(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                token::Revoke {
+                    source: nft_account.to_account_info(),
+                    authority: ctx.accounts.holder.to_account_info(),
+                },
+            )
+        )?;
+    }
+    
+    Ok(())
+}
+
+
+
+/*
+ *  Stake-delegate an NFT in the Mine by the operator of the holder.
+ *  A guild member passes its Guild as remaining account.
+ */
+
+
+#[derive(Accounts)]
+pub struct StakeMineOperator<'info> {
+
+    // Mine account in which to stake the NFT
+    #[account(
+        mut,
+        seeds = [b"mine".as_ref(),],
+        bump,
+    )]
+    pub mine: Box<Account<'info, Mine>>,
+    
+    // Operator account of the holder for the NFT
+    #[account(
+        seeds = [b"operator".as_ref(), nft_mint.key().as_ref(), holder.key().as_ref()],
+        bump = operator.bump,
+        constraint = operator.authority == authority.key() @ QstakingErrors::InvalidOperator,
+    )]
+    pub operator: Box<Account<'info, Operator>>,
+    
+    // Character account of the NFT
+    #[account(
+        mut,
+        seeds = [b"character".as_ref(), nft_mint.key().as_ref(),],
+        bump,
+    )]
+    pub character: Box<Account<'info, Character>>,
+    
+    // Whitelist account to be used for whitelist proof
+    pub whitelist: Box<Account<'info, Whitelist>>,
+    
+    // Denylist account of the NFT, only initialized if the mint is denylisted
+    ///CHECKED: the address is derived from the NFT mint and only the existence of the account is inspected
+    #[account(
+        seeds = [b"denylist".as_ref(), nft_mint.key().as_ref()],
+        bump,
+    )]
+    pub denylist: UncheckedAccount<'info>,
+    
+    // Token account holding the NFT with the Mine as delegate
+    #[account(
+        mut,
+        token::mint = nft_mint,
+        token::authority = holder,
+        constraint = nft_account.delegate == COption::Some(mine.key()) && nft_account.delegated_amount == 1 @ QstakingErrors::InvalidOperator,
+    )]
+    pub nft_account: Box<Account<'info, TokenAccount>>,
+    
+    // Token (Master) Edition account
+    ///CHECKED: custom logic checks for the validity of this account
+    pub nft_edition: UncheckedAccount<'info>,
+    
+    // Metadata account of the NFT
+    ///CHECKED: custom logic checks for the validity of this account
+    pub nft_metadata: UncheckedAccount<'info>,
+    
+    // Mint account of the NFT
+    pub nft_mint: Box<Account<'info, Mint>>,
+    
+    // User account that holds the NFT
+    ///CHECKED: the address is a seed of the Operator account
+    pub holder: UncheckedAccount<'info>,
+    
+    // Operator key of the holder
+    pub authority: Signer<'info>,
+    
+    // Metaplex Token Metadata program
+    /// CHECKED: This is not dangerous because we don't read or write from this account
+    #[account(address = metaplex::id())]
+    pub token_metadata_program: UncheckedAccount<'info>,
+}
+
+
+pub fn stake_mine<'info>(ctx: Context<'_, '_, '_, 'info, StakeMineOperator<'info>>, proof: Option<WhitelistProof>, lock_tier: u8) -> Result<()> {
+
+    mine_staking::assert_edition_account(&ctx.accounts.nft_mint.key(), &ctx.accounts.nft_edition.to_account_info())?;
+    
+    let clock = Clock::get()?;
+    let mine = &mut ctx.accounts.mine;
+    let character = &mut ctx.accounts.character;
+    let whitelist = &ctx.accounts.whitelist;
+    
+    require!(
+        !mine.locked,
+        QstakingErrors::StakingPoolLocked
+    );
+    
+    require!(
+        mine.custody == 0,
+        QstakingErrors::InvalidCustodyMode
+    );
+    
+    require!(
+        ctx.accounts.nft_account.amount == 1,
+        QstakingErrors::NotHolder
+    );
+    
+    require!(
+        character.staked == 0,
+        QstakingErrors::AlreadyStaked
+    );
+    
+    require!(
+        lock_tier <= ctx.accounts.operator.max_lock_tier,
+        QstakingErrors::InvalidLockTier
+    );
+    
+    // Programmable NFTs are delegated by the holder through the Token Metadata program
+    require!(
+        !metadata::is_programmable(&ctx.accounts.nft_metadata.to_account_info())?,
+        QstakingErrors::InvalidOperator
+    );
+    
+    
+    whitelist.verify(ctx.program_id, &whitelist.key(), &ctx.accounts.nft_mint.key(), &ctx.accounts.nft_metadata.to_account_info(), &ctx.accounts.denylist.to_account_info(), &proof)?;
+    let (staked_weight, lock_duration) = mine.lock_tier(lock_tier, character.mining_points)?;
+    stake_guild_member(ctx.remaining_accounts, ctx.program_id, mine, character, clock.unix_timestamp)?;
+    mine.add_character(clock.unix_timestamp, staked_weight)?;
+    character.stake_mine(clock.unix_timestamp, mine.season, mine.accrued_rewards, 0, &ctx.accounts.nft_account.key(), &Pubkey::default())?;
+    character.lock(clock.unix_timestamp, lock_tier, lock_duration, staked_weight)?;
+    
+    // Freeze the holder's NFT token account
+    invoke_signed(
+        &metaplex::instruction::freeze_delegated_account(
+            ctx.accounts.token_metadata_program.key(),
+            mine.key(),
+            ctx.accounts.nft_account.key(),
+            ctx.accounts.nft_edition.key(),
+            ctx.accounts.nft_mint.key(),
+        ),
+        &[
+            ctx.accounts.token_metadata_program.to_account_info(),
+            mine.to_account_info(),
+            ctx.accounts.nft_account.to_account_info(),
+            ctx.accounts.nft_edition.to_account_info(),
+            ctx.accounts.nft_mint.to_account_info(),
+        ],
+        &[&[b"mine".as_ref(), &[mine.bump]]],
+    )?;
+    
+    Ok(())
+}
+
+
+
+/*
+ *  Unstake-delegate an NFT from the Mine by the operator of the holder, the rewards are paid to the holder.
+ *  The Mine stays delegate of the NFT token account so the operator can stake it again.
+ */
+
+
+#[derive(Accounts)]
+pub struct UnstakeMineOperator<'info> {
+
+    // Mine staking pool account
+    #[account(
+        mut,
+        seeds = [b"mine".as_ref(),],
+        bump,
+    )]
+    pub mine: Box<Account<'info, Mine>>,
+    
+    // Loot staking pool account
+    #[account(
+        mut,
+        seeds = [b"loot".as_ref(),],
+        bump,
+    )]
+    pub loot: Box<Account<'info, Loot>>,
+    
+    // Operator account of the holder for the NFT
+    #[account(
+        seeds = [b"operator".as_ref(), nft_mint.key().as_ref(), holder.key().as_ref()],
+        bump = operator.bump,
+        constraint = operator.authority == authority.key() @ QstakingErrors::InvalidOperator,
+    )]
+    pub operator: Box<Account<'info, Operator>>,
+    
+    // Miner account of the NFT
+    #[account(
+        mut,
+        seeds = [b"character".as_ref(), nft_mint.key().as_ref(),],
+        bump,
+    )]
+    pub character: Box<Account<'info, Character>>,
+    
+    // Token account with loot rewards
+    ///CHECKED: the account is unpacked and its mint and owner checked with the reward token program
+    #[account(
+        mut,
+        seeds = [b"proceeds".as_ref(), loot.key().as_ref()],
+        bump,
+    )]
+    pub loot_proceeds: UncheckedAccount<'info>,
+    
+    // Denylist account of the NFT, only initialized if the mint is denylisted
+    ///CHECKED: the address is derived from the NFT mint and only the existence of the account is inspected
+    #[account(
+        seeds = [b"denylist".as_ref(), nft_mint.key().as_ref()],
+        bump,
+    )]
+    pub denylist: UncheckedAccount<'info>,
+    
+    // Token account holding the NFT
+    #[account(
+        mut,
+        token::mint = nft_mint,
+        token::authority = holder,
+        constraint = nft_account.key() == character.nft_account @ QstakingErrors::NotHolder,
+    )]
+    pub nft_account: Box<Account<'info, TokenAccount>>,
+    
+    // Token (Master) Edition account
+    ///CHECKED: custom logic checks for the validity of this account
+    pub nft_edition: UncheckedAccount<'info>,
+    
+    // Metadata account of the NFT
+    ///CHECKED: custom logic checks for the validity of this account
+    pub nft_metadata: UncheckedAccount<'info>,
+    
+    // Mint account of the NFT
+    pub nft_mint: Box<Account<'info, Mint>>,
+    
+    // Vesting account of the holder for the NFT, only created once mined rewards vest
+    ///CHECKED: the address is derived from the NFT mint and the holder, the account is created and deserialized by custom logic
+    #[account(
+        mut,
+        seeds = [b"vesting".as_ref(), nft_mint.key().as_ref(), holder.key().as_ref()],
+        bump,
+    )]
+    pub vesting: UncheckedAccount<'info>,
+    
+    // Token account of the Mine holding the vesting Reward Tokens
+    ///CHECKED: the address is derived from the Mine and the account is validated by the reward token program
+    #[account(
+        mut,
+        seeds = [b"escrow".as_ref(), mine.key().as_ref()],
+        bump,
+    )]
+    pub vesting_escrow: UncheckedAccount<'info>,
+    
+    // Associated token account of the holder for the reward tokens
+    ///CHECKED: the address is derived from the holder, the reward mint and the reward token program
+    #[account(mut)]
+    pub reward_ata: UncheckedAccount<'info>,
+    
+    // Address of the reward mint, owned by the Token or the Token-2022 program
+    ///CHECKED: the address is checked against the Mine, the mint authority by the reward token program
+    #[account(
+        mut,
+        constraint = mine.mint == reward_mint.key(),
+    )]
+    pub reward_mint: UncheckedAccount<'info>,
+    
+    // User account that holds the NFT
+    ///CHECKED: the address is a seed of the Operator account
+    pub holder: UncheckedAccount<'info>,
+    
+    // Operator key of the holder, pays for the Vesting account
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    
+    // Metaplex Token Metadata program
+    /// CHECKED: This is not dangerous because we don't read or write from this account
+    #[account(address = metaplex::id())]
+    pub token_metadata_program: UncheckedAccount<'info>,
+    
+    // Token program of the reward mint
+    ///CHECKED: the address is checked against the owner of the reward mint
+    #[account(
+        constraint = reward_token_program.key() == *reward_mint.owner @ QstakingErrors::InvalidRewardTokenAccount,
+    )]
+    pub reward_token_program: UncheckedAccount<'info>,
+    
+    // System program
+    pub system_program: Program<'info, System>,
+}
+
+
+pub fn unstake_mine<'info>(ctx: Context<'_, '_, '_, 'info, UnstakeMineOperator<'info>>) -> Result<()> {
+
+    mine_staking::assert_edition_account(&ctx.accounts.nft_mint.key(), &ctx.accounts.nft_edition.to_account_info())?;
+    metadata::assert_metadata_account(&ctx.accounts.nft_mint.key(), &ctx.accounts.nft_metadata.to_account_info())?;
+    
+    let clock = Clock::get()?;
+    let mine = &mut ctx.accounts.mine;
+    let loot = &mut ctx.accounts.loot;
+    let character = &mut ctx.accounts.character;
+    
+    require!(
+        ctx.accounts.nft_account.amount == 1,
+        QstakingErrors::NotHolder
+    );
+    
+    require!(
+        character.staked == 1,
+        QstakingErrors::NotStaked
+    );
+    
+    require!(
+        character.custody == 0,
+        QstakingErrors::InvalidCustodyMode
+    );
+    
+    
+    token_interface::assert_reward_accounts(
+        &ctx.accounts.reward_token_program.key(),
+        &ctx.accounts.reward_mint.to_account_info(),
+        &ctx.accounts.reward_ata.to_account_info(),
+        &ctx.accounts.holder.key(),
+        &ctx.accounts.loot_proceeds.to_account_info(),
+        &loot.key(),
+    )?;
+    
+    let denylisted = Denylist::is_denylisted(ctx.program_id, &ctx.accounts.denylist.to_account_info());
+    settle_guild_bonus(ctx.remaining_accounts, ctx.program_id, mine, character, clock.unix_timestamp)?;
+    let (accrued_reward, looted_reward) = settle_mine(mine, loot, character, denylisted, clock.unix_timestamp)?;
+    let mined_reward = accrued_reward;
+    Leaderboard::record(ctx.program_id, ctx.remaining_accounts, &ctx.accounts.nft_mint.key(), character)?;
+    
+    // Thaw the holder's NFT token account
+    invoke_signed(
+        &metaplex::instruction::thaw_delegated_account(
+            ctx.accounts.token_metadata_program.key(),
+            mine.key(),
+            ctx.accounts.nft_account.key(),
+            ctx.accounts.nft_edition.key(),
+            ctx.accounts.nft_mint.key(),
+        ),
+        &[
+            ctx.accounts.token_metadata_program.to_account_info(),
+            mine.to_account_info(),
+            ctx.accounts.nft_account.to_account_info(),
+            ctx.accounts.nft_edition.to_account_info(),
+            ctx.accounts.nft_mint.to_account_info(),
+        ],
+        &[&[b"mine".as_ref(), &[mine.bump]]],
+    )?;
+    
+    
+    let (accrued_reward, guild_accounts) = unstake_guild_member(
+        ctx.remaining_accounts,
+        character,
+        ctx.accounts.reward_token_program.to_account_info(),
+        ctx.accounts.reward_mint.to_account_info(),
+        mine.to_account_info(),
+        mine.bump,
+        accrued_reward,
+    )?;
+    
+    pay_referral(
+        &ctx.remaining_accounts[guild_accounts..],
+        ctx.program_id,
+        mine,
+        character,
+        ctx.accounts.reward_token_program.to_account_info(),
+        ctx.accounts.reward_mint.to_account_info(),
+        clock.unix_timestamp,
+        mined_reward,
+    )?;
+    
+    // Mined rewards go to the vesting escrow when the Mine vests them
+    let reward_destination = if mine.vesting_period > 0 && accrued_reward > 0 {
+        vest_rewards(
+            ctx.accounts.vesting.to_account_info(),
+            ctx.accounts.authority.to_account_info(),
+            ctx.accounts.system_program.to_account_info(),
+            &ctx.accounts.holder.key(),
+            &ctx.accounts.nft_mint.key(),
+            *ctx.bumps.get("vesting").unwrap(),
+            clock.unix_timestamp,
+            mine.vesting_period,
+            accrued_reward,
+        )?;
+        ctx.accounts.vesting_escrow.to_account_info()
+    } else {
+        ctx.accounts.reward_ata.to_account_info()
+    };
+    
+    mint_rewards(
+        ctx.accounts.reward_token_program.to_account_info(),
+        ctx.accounts.reward_mint.to_account_info(),
+        reward_destination,
+        ctx.accounts.loot_proceeds.to_account_info(),
+        mine.to_account_info(),
+        mine.bump,
+        accrued_reward,
+        looted_reward,
+    )?;
+    
+    Ok(())
+}
+
+
+
+/*
+ *  Claim the vested mined rewards of an NFT by the operator of the holder, the rewards are paid to the holder
+ */
+
+
+#[derive(Accounts)]
+pub struct ClaimVestedOperator<'info> {
+
+    // Mine staking pool account
+    #[account(
+        seeds = [b"mine".as_ref(),],
+        bump,
+    )]
+    pub mine: Box<Account<'info, Mine>>,
+    
+    // Operator account of the holder for the NFT
+    #[account(
+        seeds = [b"operator".as_ref(), vesting.nft_mint.as_ref(), holder.key().as_ref()],
+        bump = operator.bump,
+        constraint = operator.authority == authority.key() @ QstakingErrors::InvalidOperator,
+    )]
+    pub operator: Box<Account<'info, Operator>>,
+    
+    // Vesting account of the holder for the NFT
+    #[account(
+        mut,
+        seeds = [b"vesting".as_ref(), vesting.nft_mint.as_ref(), holder.key().as_ref()],
+        bump = vesting.bump,
+    )]
+    pub vesting: Box<Account<'info, Vesting>>,
+    
+    // Token account of the Mine holding the vesting Reward Tokens
+    ///CHECKED: the address is derived from the Mine and the account is validated by the reward token program
+    #[account(
+        mut,
+        seeds = [b"escrow".as_ref(), mine.key().as_ref()],
+        bump,
+    )]
+    pub vesting_escrow: UncheckedAccount<'info>,
+    
+    // Associated token account of the holder for the reward tokens
+    ///CHECKED: the address is derived from the holder, the reward mint and the reward token program
+    #[account(mut)]
+    pub reward_ata: UncheckedAccount<'info>,
+    
+    // Address of the reward mint, owned by the Token or the Token-2022 program
+    ///CHECKED: the address is checked against the Mine
+    #[account(
+        constraint = mine.mint == reward_mint.key(),
+    )]
+    pub reward_mint: UncheckedAccount<'info>,
+    
+    // User the vesting Reward Tokens are released to
+    ///CHECKED: the address is a seed of the Operator account
+    pub holder: UncheckedAccount<'info>,
+    
+    // Operator key of the holder
+    pub authority: Signer<'info>,
+    
+    // Token program of the reward mint
+    ///CHECKED: the address is checked against the owner of the reward mint
+    #[account(
+        constraint = reward_token_program.key() == *reward_mint.owner @ QstakingErrors::InvalidRewardTokenAccount,
+    )]
+    pub reward_token_program: UncheckedAccount<'info>,
+}
+
+
+pub fn claim_vested(ctx: Context<ClaimVestedOperator>) -> Result<()> {
+
+    let clock = Clock::get()?;
+    let mine = &ctx.accounts.mine;
+    let vesting = &mut ctx.accounts.vesting;
+    
+    token_interface::assert_associated_token_account(
+        &ctx.accounts.reward_ata.to_account_info(),
+        &ctx.accounts.holder.key(),
+        &ctx.accounts.reward_mint.key(),
+        &ctx.accounts.reward_token_program.key(),
+    )?;
+    
+    let claimed_amount = vesting.claim(clock.unix_timestamp)?;
+    
+    release_vested(
+        ctx.accounts.reward_token_program.to_account_info(),
+        ctx.accounts.vesting_escrow.to_account_info(),
+        ctx.accounts.reward_mint.to_account_info(),
+        ctx.accounts.reward_ata.to_account_info(),
+        mine.to_account_info(),
+        mine.bump,
+        claimed_amount,
+    )?;
+    
+    Ok(())
+}
@@ -1,10 +1,14 @@
 use anchor_lang::prelude::*;
-use anchor_spl::token::{Mint, TokenAccount, Token};
+use anchor_lang::solana_program::program_option::COption;
+use anchor_spl::token::{Mint, TokenAccount};
 use anchor_spl::associated_token::AssociatedToken;
 
 use crate::program::Qstaking;
 use crate::errors::QstakingErrors;
 use crate::states::*;
+use crate::token_interface;
+use crate::instructions::mine_staking::mint_rewards;
+use crate::instructions::loot_staking::transfer_rewards;
 
 
 
@@ -22,7 +26,7 @@ pub struct InitAdmin<'info> {
         seeds = [b"admin".as_ref()], 
         bump, 
         payer = authority,
-        space = 8 + std::mem::size_of::<AdminSettings>(),
+        space = 8 + AdminSettings::LEN,
     )]
     pub admin_settings: Account<'info, AdminSettings>,
     
@@ -52,6 +56,7 @@ pub fn init_admin(ctx: Context<InitAdmin>, admin_key: Pubkey) -> Result<()> {
     let admin_settings = &mut ctx.accounts.admin_settings;
     admin_settings.bump = *ctx.bumps.get("admin_settings").unwrap();
     admin_settings.admin_key = admin_key;
+    admin_settings.version = AdminSettings::VERSION;
     
     Ok(())
 }
@@ -102,6 +107,248 @@ pub fn set_admin(ctx: Context<SetAdmin>, admin_key: Pubkey) -> Result<()> {
 
 
 
+/*
+ *  Add a mint to the denylist
+ */
+
+
+#[derive(Accounts)]
+#[instruction(mint: Pubkey)]
+pub struct AddDenylist<'info> {
+
+    // AdminSettings account
+    #[account(
+        seeds = [b"admin".as_ref()],
+        bump,
+    )]
+    pub admin_settings: Account<'info, AdminSettings>,
+    
+    // Denylist account
+    #[account(
+        init,
+        payer = authority,
+        seeds = [b"denylist".as_ref(), mint.as_ref()],
+        bump,
+        space = 8 + Denylist::LEN,
+    )]
+    pub denylist: Account<'info, Denylist>,
+    
+    // Staking program admin defined in AdminSettings
+    #[account(
+        mut,
+        constraint = admin_settings.admin_key == authority.key(),
+    )]
+    pub authority: Signer<'info>,
+    
+    // System program
+    pub system_program: Program<'info, System>,
+}
+
+
+pub fn add_denylist(ctx: Context<AddDenylist>, mint: Pubkey) -> Result<()> {
+
+    let clock = Clock::get()?;
+    let denylist = &mut ctx.accounts.denylist;
+    denylist.initialize(*ctx.bumps.get("denylist").unwrap(), &mint, clock.unix_timestamp)?;
+    
+    Ok(())
+}
+
+
+
+/*
+ *  Remove a mint from the denylist
+ */
+
+
+#[derive(Accounts)]
+#[instruction(mint: Pubkey)]
+pub struct RemoveDenylist<'info> {
+
+    // AdminSettings account
+    #[account(
+        seeds = [b"admin".as_ref()],
+        bump,
+    )]
+    pub admin_settings: Account<'info, AdminSettings>,
+    
+    // Denylist account
+    #[account(
+        mut,
+        seeds = [b"denylist".as_ref(), mint.as_ref()],
+        bump,
+        close = authority,
+    )]
+    pub denylist: Account<'info, Denylist>,
+    
+    // Staking program admin defined in AdminSettings
+    #[account(
+        mut,
+        constraint = admin_settings.admin_key == authority.key(),
+    )]
+    pub authority: Signer<'info>,
+    
+    // System program
+    pub system_program: Program<'info, System>,
+}
+
+
+pub fn remove_denylist(_ctx: Context<RemoveDenylist>, _mint: Pubkey) -> Result<()> {
+
+    Ok(())
+}
+
+
+
+/*
+ *  Release the withheld rewards of a denylisted Character to the NFT holder or confiscate them
+ */
+
+
+#[derive(Accounts)]
+pub struct SettleWithheldRewards<'info> {
+
+    // AdminSettings account
+    #[account(
+        seeds = [b"admin".as_ref()],
+        bump,
+    )]
+    pub admin_settings: Box<Account<'info, AdminSettings>>,
+    
+    // Mine staking pool account
+    #[account(
+        mut,
+        seeds = [b"mine".as_ref(),],
+        bump,
+    )]
+    pub mine: Box<Account<'info, Mine>>,
+    
+    // Loot staking pool account
+    #[account(
+        mut,
+        seeds = [b"loot".as_ref(),],
+        bump,
+    )]
+    pub loot: Box<Account<'info, Loot>>,
+    
+    // Character account of the NFT
+    #[account(
+        mut,
+        seeds = [b"character".as_ref(), nft_mint.key().as_ref(),],
+        bump,
+    )]
+    pub character: Box<Account<'info, Character>>,
+    
+    // Mint account of the NFT
+    pub nft_mint: Box<Account<'info, Mint>>,
+    
+    // Token account holding the NFT
+    #[account(
+        token::mint = nft_mint,
+        constraint = nft_account.amount == 1 @ QstakingErrors::NotHolder,
+    )]
+    pub nft_account: Box<Account<'info, TokenAccount>>,
+    
+    // Holder of the NFT token account
+    ///CHECKED: the address is checked against the owner of the NFT token account
+    #[account(
+        constraint = nft_account.owner == nft_owner.key(),
+    )]
+    pub nft_owner: UncheckedAccount<'info>,
+    
+    // Associated token account of the holder for the reward mint
+    ///CHECKED: the account is unpacked and its mint and owner checked with the reward token program
+    #[account(mut)]
+    pub reward_ata: UncheckedAccount<'info>,
+    
+    // Token account with loot rewards
+    ///CHECKED: the account is unpacked and its mint and owner checked with the reward token program
+    #[account(
+        mut,
+        seeds = [b"proceeds".as_ref(), loot.key().as_ref()],
+        bump,
+    )]
+    pub loot_proceeds: UncheckedAccount<'info>,
+    
+    // Address of the reward mint, owned by the Token or the Token-2022 program
+    ///CHECKED: the address is checked against the Mine, the mint authority by the reward token program
+    #[account(
+        mut,
+        constraint = mine.mint == reward_mint.key(),
+    )]
+    pub reward_mint: UncheckedAccount<'info>,
+    
+    // Staking program admin defined in AdminSettings
+    #[account(
+        constraint = admin_settings.admin_key == authority.key(),
+    )]
+    pub authority: Signer<'info>,
+    
+    // Token program of the reward mint
+    ///CHECKED: the address is checked against the owner of the reward mint
+    #[account(
+        constraint = reward_token_program.key() == *reward_mint.owner @ QstakingErrors::InvalidRewardTokenAccount,
+    )]
+    pub reward_token_program: UncheckedAccount<'info>,
+}
+
+
+pub fn settle_withheld_rewards(ctx: Context<SettleWithheldRewards>, release: bool) -> Result<()> {
+
+    let clock = Clock::get()?;
+    let mine = &mut ctx.accounts.mine;
+    let loot = &mut ctx.accounts.loot;
+    let character = &mut ctx.accounts.character;
+    
+    let (mining_rewards, looting_rewards) = character.take_withheld_rewards()?;
+    require!(
+        mining_rewards > 0 || looting_rewards > 0,
+        QstakingErrors::NoWithheldRewards
+    );
+    
+    // Confiscated mined rewards are never minted and confiscated looted rewards return to the Loot fund
+    if !release {
+        mine.forfeit_rewards(mining_rewards)?;
+        loot.restore_funds(clock.unix_timestamp, looting_rewards)?;
+        return Ok(());
+    }
+    
+    token_interface::assert_reward_accounts(
+        &ctx.accounts.reward_token_program.key(),
+        &ctx.accounts.reward_mint.to_account_info(),
+        &ctx.accounts.reward_ata.to_account_info(),
+        &ctx.accounts.nft_owner.key(),
+        &ctx.accounts.loot_proceeds.to_account_info(),
+        &loot.key(),
+    )?;
+    
+    // Released rewards are paid straight to the holder, without guild cut, referral reward or vesting
+    mint_rewards(
+        ctx.accounts.reward_token_program.to_account_info(),
+        ctx.accounts.reward_mint.to_account_info(),
+        ctx.accounts.reward_ata.to_account_info(),
+        ctx.accounts.loot_proceeds.to_account_info(),
+        mine.to_account_info(),
+        mine.bump,
+        mining_rewards,
+        0,
+    )?;
+    
+    transfer_rewards(
+        ctx.accounts.reward_token_program.to_account_info(),
+        ctx.accounts.loot_proceeds.to_account_info(),
+        ctx.accounts.reward_mint.to_account_info(),
+        ctx.accounts.reward_ata.to_account_info(),
+        loot.to_account_info(),
+        loot.bump,
+        looting_rewards,
+    )?;
+    
+    Ok(())
+}
+
+
+
 /*
  *  Add reference account to whitelist
  */
@@ -110,7 +357,7 @@ pub fn set_admin(ctx: Context<SetAdmin>, admin_key: Pubkey) -> Result<()> {
 #[derive(Accounts)]
 #[instruction(_reference_account: Pubkey)]
 pub struct AddWhitelist<'info> {
-    
+
     // AdminSettings account
     #[account(
         seeds = [b"admin".as_ref()],
@@ -124,7 +371,7 @@ pub struct AddWhitelist<'info> {
         payer = authority,
         seeds = [b"whitelist".as_ref(), _reference_account.as_ref()],
         bump,
-        space = 8 + std::mem::size_of::<Whitelist>(),
+        space = 8 + Whitelist::LEN,
     )]
     pub whitelist: Account<'info, Whitelist>,
     
@@ -141,7 +388,7 @@ pub struct AddWhitelist<'info> {
 
 
 pub fn add_whitelist(ctx: Context<AddWhitelist>, _reference_account: Pubkey, whitelist_type: u8) -> Result<()> {
-    
+
     //TODO Add sanity checks for the reference_account
     
     require!(
@@ -149,9 +396,449 @@ pub fn add_whitelist(ctx: Context<AddWhitelist>, _reference_account: Pubkey, whi
         QstakingErrors::InvalidWhitelistType
     );
     
-    let whitelist = &mut ctx.accounts.whitelist;
-    whitelist.bump = *ctx.bumps.get("whitelist").unwrap();
-    whitelist.whitelist_type = whitelist_type;
+    let whitelist = &mut ctx.accounts.whitelist;
+    whitelist.initialize(*ctx.bumps.get("whitelist").unwrap(), whitelist_type, [0u8; 32])?;
+    
+    Ok(())
+}
+
+
+
+/*
+ *  Add a Merkle root whitelist of allowed mints under the reference account
+ */
+
+
+#[derive(Accounts)]
+#[instruction(_reference_account: Pubkey)]
+pub struct AddMerkleWhitelist<'info> {
+
+    // AdminSettings account
+    #[account(
+        seeds = [b"admin".as_ref()],
+        bump,
+    )]
+    pub admin_settings: Account<'info, AdminSettings>,
+    
+    // Whitelist account
+    #[account(
+        init,
+        payer = authority,
+        seeds = [b"whitelist".as_ref(), _reference_account.as_ref()],
+        bump,
+        space = 8 + Whitelist::LEN,
+    )]
+    pub whitelist: Account<'info, Whitelist>,
+    
+    // Staking program admin defined in AdminSettings
+    #[account(
+        mut,
+        constraint = admin_settings.admin_key == authority.key(),
+    )]
+    pub authority: Signer<'info>,
+    
+    // System program
+    pub system_program: Program<'info, System>,
+}
+
+
+pub fn add_merkle_whitelist(ctx: Context<AddMerkleWhitelist>, _reference_account: Pubkey, root: [u8; 32]) -> Result<()> {
+
+    let whitelist = &mut ctx.accounts.whitelist;
+    whitelist.initialize(*ctx.bumps.get("whitelist").unwrap(), 2, root)?;
+    
+    Ok(())
+}
+
+
+
+/*
+ *  Rotate the Merkle root of a Merkle whitelist
+ */
+
+
+#[derive(Accounts)]
+#[instruction(_reference_account: Pubkey)]
+pub struct SetWhitelistRoot<'info> {
+
+    // AdminSettings account
+    #[account(
+        seeds = [b"admin".as_ref()],
+        bump,
+    )]
+    pub admin_settings: Account<'info, AdminSettings>,
+    
+    // Whitelist account
+    #[account(
+        mut,
+        seeds = [b"whitelist".as_ref(), _reference_account.as_ref()],
+        bump,
+    )]
+    pub whitelist: Account<'info, Whitelist>,
+    
+    // Staking program admin defined in AdminSettings
+    #[account(
+        constraint = admin_settings.admin_key == authority.key(),
+    )]
+    pub authority: Signer<'info>,
+}
+
+
+pub fn set_whitelist_root(ctx: Context<SetWhitelistRoot>, _reference_account: Pubkey, root: [u8; 32]) -> Result<()> {
+
+    let whitelist = &mut ctx.accounts.whitelist;
+    
+    require!(
+        whitelist.whitelist_type == 2,
+        QstakingErrors::InvalidWhitelistType
+    );
+    
+    whitelist.root = root;
+    
+    Ok(())
+}
+
+
+
+/*
+ *  Enable or disable a whitelist entry without closing it
+ */
+
+
+#[derive(Accounts)]
+#[instruction(_reference_account: Pubkey)]
+pub struct SetWhitelistEnabled<'info> {
+
+    // AdminSettings account
+    #[account(
+        seeds = [b"admin".as_ref()],
+        bump,
+    )]
+    pub admin_settings: Account<'info, AdminSettings>,
+    
+    // Whitelist account
+    #[account(
+        mut,
+        seeds = [b"whitelist".as_ref(), _reference_account.as_ref()],
+        bump,
+    )]
+    pub whitelist: Account<'info, Whitelist>,
+    
+    // Staking program admin defined in AdminSettings
+    #[account(
+        constraint = admin_settings.admin_key == authority.key(),
+    )]
+    pub authority: Signer<'info>,
+}
+
+
+pub fn set_whitelist_enabled(ctx: Context<SetWhitelistEnabled>, _reference_account: Pubkey, enabled: bool) -> Result<()> {
+
+    let whitelist = &mut ctx.accounts.whitelist;
+    whitelist.enabled = enabled;
+    
+    Ok(())
+}
+
+
+
+/*
+ *  Set the expiry and Character quota of a whitelist entry
+ */
+
+
+#[derive(Accounts)]
+#[instruction(_reference_account: Pubkey)]
+pub struct SetWhitelistLimits<'info> {
+
+    // AdminSettings account
+    #[account(
+        seeds = [b"admin".as_ref()],
+        bump,
+    )]
+    pub admin_settings: Account<'info, AdminSettings>,
+    
+    // Whitelist account
+    #[account(
+        mut,
+        seeds = [b"whitelist".as_ref(), _reference_account.as_ref()],
+        bump,
+    )]
+    pub whitelist: Account<'info, Whitelist>,
+    
+    // Staking program admin defined in AdminSettings
+    #[account(
+        constraint = admin_settings.admin_key == authority.key(),
+    )]
+    pub authority: Signer<'info>,
+}
+
+
+pub fn set_whitelist_limits(ctx: Context<SetWhitelistLimits>, _reference_account: Pubkey, expiry_timestamp: i64, max_characters: u32) -> Result<()> {
+
+    let whitelist = &mut ctx.accounts.whitelist;
+    whitelist.expiry_timestamp = expiry_timestamp;
+    whitelist.max_characters = max_characters;
+    
+    Ok(())
+}
+
+
+
+/*
+ *  Remove reference account from whitelist
+ */
+
+
+#[derive(Accounts)]
+#[instruction(_reference_account: Pubkey)]
+pub struct RemoveWhitelist<'info> {
+
+    // AdminSettings account
+    #[account(
+        seeds = [b"admin".as_ref()],
+        bump,
+    )]
+    pub admin_settings: Account<'info, AdminSettings>,
+    
+    // Whitelist account
+    #[account(
+        mut,
+        seeds = [b"whitelist".as_ref(), _reference_account.as_ref()],
+        bump,
+        close = authority,
+    )]
+    pub whitelist: Account<'info, Whitelist>,
+    
+    // Staking program admin defined in AdminSettings
+    #[account(
+        mut,
+        constraint = admin_settings.admin_key == authority.key(),
+    )]
+    pub authority: Signer<'info>,
+    
+    // System program
+    pub system_program: Program<'info, System>,
+}
+
+
+pub fn remove_whitelist(_ctx: Context<RemoveWhitelist>, _reference_account: Pubkey) -> Result<()> {
+
+    Ok(())
+}
+
+
+
+/*
+ *  Add a gear collection whose NFTs can be equipped on Characters
+ */
+
+
+#[derive(Accounts)]
+#[instruction(creator: Pubkey)]
+pub struct AddGearCollection<'info> {
+
+    // AdminSettings account
+    #[account(
+        seeds = [b"admin".as_ref()],
+        bump,
+    )]
+    pub admin_settings: Account<'info, AdminSettings>,
+    
+    // GearCollection account of the creator
+    #[account(
+        init,
+        payer = authority,
+        seeds = [b"gear".as_ref(), creator.as_ref()],
+        bump,
+        space = 8 + GearCollection::LEN,
+    )]
+    pub gear_collection: Account<'info, GearCollection>,
+    
+    // Staking program admin defined in AdminSettings
+    #[account(
+        mut,
+        constraint = admin_settings.admin_key == authority.key(),
+    )]
+    pub authority: Signer<'info>,
+    
+    // System program
+    pub system_program: Program<'info, System>,
+}
+
+
+pub fn add_gear_collection(ctx: Context<AddGearCollection>, creator: Pubkey, stat: u8, bonus_kind: u8, bonus: u64) -> Result<()> {
+
+    let gear_collection = &mut ctx.accounts.gear_collection;
+    gear_collection.initialize(*ctx.bumps.get("gear_collection").unwrap(), &creator, stat, bonus_kind, bonus)?;
+    
+    Ok(())
+}
+
+
+
+/*
+ *  Enable or disable equipping gear of a gear collection, attached gear is not affected
+ */
+
+
+#[derive(Accounts)]
+#[instruction(creator: Pubkey)]
+pub struct SetGearCollectionEnabled<'info> {
+
+    // AdminSettings account
+    #[account(
+        seeds = [b"admin".as_ref()],
+        bump,
+    )]
+    pub admin_settings: Account<'info, AdminSettings>,
+    
+    // GearCollection account of the creator
+    #[account(
+        mut,
+        seeds = [b"gear".as_ref(), creator.as_ref()],
+        bump,
+    )]
+    pub gear_collection: Account<'info, GearCollection>,
+    
+    // Staking program admin defined in AdminSettings
+    #[account(
+        constraint = admin_settings.admin_key == authority.key(),
+    )]
+    pub authority: Signer<'info>,
+}
+
+
+pub fn set_gear_collection_enabled(ctx: Context<SetGearCollectionEnabled>, _creator: Pubkey, enabled: bool) -> Result<()> {
+
+    let gear_collection = &mut ctx.accounts.gear_collection;
+    gear_collection.enabled = enabled;
+    
+    Ok(())
+}
+
+
+
+/*
+ *  Initialize the Leaderboard ranking the top Characters
+ */
+
+
+#[derive(Accounts)]
+pub struct InitLeaderboard<'info> {
+
+    // AdminSettings account
+    #[account(
+        seeds = [b"admin".as_ref()],
+        bump,
+    )]
+    pub admin_settings: Account<'info, AdminSettings>,
+    
+    // Leaderboard account
+    #[account(
+        init,
+        payer = authority,
+        seeds = [b"leaderboard".as_ref()],
+        bump,
+        space = 8 + Leaderboard::LEN,
+    )]
+    pub leaderboard: AccountLoader<'info, Leaderboard>,
+    
+    // Staking program admin defined in AdminSettings
+    #[account(
+        mut,
+        constraint = admin_settings.admin_key == authority.key(),
+    )]
+    pub authority: Signer<'info>,
+    
+    // System program
+    pub system_program: Program<'info, System>,
+}
+
+
+pub fn init_leaderboard(ctx: Context<InitLeaderboard>) -> Result<()> {
+
+    let mut leaderboard = ctx.accounts.leaderboard.load_init()?;
+    leaderboard.initialize(*ctx.bumps.get("leaderboard").unwrap())?;
+    
+    Ok(())
+}
+
+
+
+/*
+ *  Start the next season of the game, clear the Leaderboard and reopen the staking pools locked by the end of the
+ *  previous season
+ */
+
+
+#[derive(Accounts)]
+#[instruction(season_id: u64)]
+pub struct StartSeason<'info> {
+
+    // AdminSettings account
+    #[account(
+        seeds = [b"admin".as_ref()],
+        bump,
+    )]
+    pub admin_settings: Account<'info, AdminSettings>,
+    
+    // Mine staking pool account
+    #[account(
+        mut,
+        seeds = [b"mine".as_ref()],
+        bump,
+    )]
+    pub mine: Box<Account<'info, Mine>>,
+    
+    // Loot staking pool account
+    #[account(
+        mut,
+        seeds = [b"loot".as_ref()],
+        bump,
+    )]
+    pub loot: Box<Account<'info, Loot>>,
+    
+    // Leaderboard account
+    #[account(
+        mut,
+        seeds = [b"leaderboard".as_ref()],
+        bump,
+    )]
+    pub leaderboard: AccountLoader<'info, Leaderboard>,
+    
+    // Season account
+    #[account(
+        init,
+        payer = authority,
+        seeds = [b"season".as_ref(), season_id.to_le_bytes().as_ref()],
+        bump,
+        space = 8 + Season::LEN,
+    )]
+    pub season: Box<Account<'info, Season>>,
+    
+    // Staking program admin defined in AdminSettings
+    #[account(
+        mut,
+        constraint = admin_settings.admin_key == authority.key(),
+    )]
+    pub authority: Signer<'info>,
+    
+    // System program
+    pub system_program: Program<'info, System>,
+}
+
+
+pub fn start_season(ctx: Context<StartSeason>, season_id: u64) -> Result<()> {
+
+    let clock = Clock::get()?;
+    
+    ctx.accounts.mine.start_season(season_id)?;
+    ctx.accounts.loot.start_season(season_id)?;
+    ctx.accounts.leaderboard.load_mut()?.start_season(season_id)?;
+    
+    let season = &mut ctx.accounts.season;
+    season.initialize(*ctx.bumps.get("season").unwrap(), season_id, clock.unix_timestamp)?;
     
     Ok(())
 }
@@ -159,14 +846,14 @@ pub fn add_whitelist(ctx: Context<AddWhitelist>, _reference_account: Pubkey, whi
 
 
 /*
- *  Remove reference account from whitelist
+ *  End the current season, snapshot the pool totals and freeze staking.
+ *  Staked Characters can still be unstaked to settle their rewards of the season.
  */
 
 
 #[derive(Accounts)]
-#[instruction(_reference_account: Pubkey)]
-pub struct RemoveWhitelist<'info> {
-    
+pub struct EndSeason<'info> {
+
     // AdminSettings account
     #[account(
         seeds = [b"admin".as_ref()],
@@ -174,29 +861,50 @@ pub struct RemoveWhitelist<'info> {
     )]
     pub admin_settings: Account<'info, AdminSettings>,
     
-    // Whitelist account
+    // Mine staking pool account
     #[account(
         mut,
-        seeds = [b"whitelist".as_ref(), _reference_account.as_ref()],
+        seeds = [b"mine".as_ref()],
         bump,
-        close = authority,
     )]
-    pub whitelist: Account<'info, Whitelist>,
+    pub mine: Box<Account<'info, Mine>>,
     
-    // Staking program admin defined in AdminSettings
+    // Loot staking pool account
+    #[account(
+        mut,
+        seeds = [b"loot".as_ref()],
+        bump,
+    )]
+    pub loot: Box<Account<'info, Loot>>,
+    
+    // Season account of the current season
     #[account(
         mut,
+        seeds = [b"season".as_ref(), mine.season.to_le_bytes().as_ref()],
+        bump = season.bump,
+    )]
+    pub season: Box<Account<'info, Season>>,
+    
+    // Staking program admin defined in AdminSettings
+    #[account(
         constraint = admin_settings.admin_key == authority.key(),
     )]
     pub authority: Signer<'info>,
-    
-    // System program
-    pub system_program: Program<'info, System>,
 }
 
 
-pub fn remove_whitelist(_ctx: Context<RemoveWhitelist>, _reference_account: Pubkey) -> Result<()> {
+pub fn end_season(ctx: Context<EndSeason>) -> Result<()> {
 
+    let clock = Clock::get()?;
+    let mine = &mut ctx.accounts.mine;
+    let loot = &mut ctx.accounts.loot;
+    
+    mine.end_season()?;
+    loot.end_season()?;
+    
+    let season = &mut ctx.accounts.season;
+    season.end(clock.unix_timestamp, mine, loot)?;
+    
     Ok(())
 }
 
@@ -207,11 +915,11 @@ pub fn remove_whitelist(_ctx: Context<RemoveWhitelist>, _reference_account: Pubk
  *  There is a hard limit of 1 Mine and 1 Loot pool per Qstaking smart contract. The init() function 
  *  can only be called by the the smart contract administrator.
  */
- 
+
 
 #[derive(Accounts)]
 pub struct InitPools<'info> {
-    
+
     // AdminSettings account
     #[account(
         seeds = [b"admin".as_ref()],
@@ -225,7 +933,7 @@ pub struct InitPools<'info> {
         payer = authority,
         seeds = [b"mine".as_ref(),],
         bump,
-        space = 8 + std::mem::size_of::<Mine>()
+        space = 8 + Mine::LEN
     )]
     pub mine: Account<'info, Mine>,
     
@@ -235,26 +943,22 @@ pub struct InitPools<'info> {
         payer = authority,
         seeds = [b"loot".as_ref(),],
         bump,
-        space = 8 + std::mem::size_of::<Loot>()
+        space = 8 + Loot::LEN
     )]
     pub loot: Account<'info, Loot>,
     
-    // Token account with loot rewards
+    // Token account with loot rewards, created for the token program of the reward mint
+    ///CHECKED: the account is created and initialized by the instruction
     #[account(
-        init,
-        payer = authority,
+        mut,
         seeds = [b"proceeds".as_ref(), loot.key().as_ref()],
         bump,
-        token::mint = mint,
-        token::authority = loot,
     )]
-    pub loot_proceeds: Account<'info, TokenAccount>,
+    pub loot_proceeds: UncheckedAccount<'info>,
     
-    // Address of the reward mint
-    #[account(
-        mint::authority = mine.key()
-    )]
-    pub mint: Account<'info, Mint>,
+    // Address of the reward mint, owned by the Token or the Token-2022 program
+    ///CHECKED: the mint is unpacked and its mint authority checked against the Mine
+    pub mint: UncheckedAccount<'info>,
     
     // Authority for creating the Mine -> upgrade authority of the Qstaking program
     #[account(
@@ -263,8 +967,12 @@ pub struct InitPools<'info> {
     )]
     pub authority: Signer<'info>,
     
-    // Token program
-    pub token_program: Program<'info, Token>,
+    // Token program of the reward mint
+    ///CHECKED: the address is checked against the Token and Token-2022 programs
+    #[account(
+        constraint = token_interface::is_token_program(&token_program.key()) @ QstakingErrors::InvalidRewardTokenAccount,
+    )]
+    pub token_program: UncheckedAccount<'info>,
     
     // System program
     pub system_program: Program<'info, System>,
@@ -288,12 +996,122 @@ pub fn init_pools(ctx: Context<InitPools>, mine_manager: Pubkey, loot_manager: P
     );
     */
     
+    let mint = token_interface::unpack_mint(&ctx.accounts.mint.to_account_info(), &ctx.accounts.token_program.key())?;
+    require!(
+        mint.mint_authority == COption::Some(ctx.accounts.mine.key()),
+        QstakingErrors::InvalidRewardTokenAccount
+    );
+    token_interface::assert_supported_mint(&ctx.accounts.mint.to_account_info())?;
+    
     let mine = &mut ctx.accounts.mine;
     mine.initialize(*ctx.bumps.get("mine").unwrap(), &mine_manager, &ctx.accounts.mint.key())?;
     
     let loot = &mut ctx.accounts.loot;
     loot.initialize(*ctx.bumps.get("loot").unwrap(), &loot_manager)?;
-        
+    
+    // Create the loot proceeds account owned by the Loot
+    let loot_key = loot.key();
+    let proceeds_bump = *ctx.bumps.get("loot_proceeds").unwrap();
+    token_interface::create_token_account(
+        ctx.accounts.token_program.to_account_info(),
+        ctx.accounts.loot_proceeds.to_account_info(),
+        ctx.accounts.mint.to_account_info(),
+        &loot_key,
+        ctx.accounts.authority.to_account_info(),
+        ctx.accounts.system_program.to_account_info(),
+        &[&[b"proceeds".as_ref(), loot_key.as_ref(), &[proceeds_bump]]],
+    )?;
+    
+    Ok(())
+}
+
+
+
+/*
+ *  Initialize the TraitsRegistry holding the Merkle root of per-mint Character traits
+ */
+
+
+#[derive(Accounts)]
+pub struct InitTraitsRegistry<'info> {
+
+    // AdminSettings account
+    #[account(
+        seeds = [b"admin".as_ref()],
+        bump,
+    )]
+    pub admin_settings: Account<'info, AdminSettings>,
+    
+    // TraitsRegistry account
+    #[account(
+        init,
+        payer = authority,
+        seeds = [b"traits".as_ref()],
+        bump,
+        space = 8 + TraitsRegistry::LEN,
+    )]
+    pub traits_registry: Account<'info, TraitsRegistry>,
+    
+    // Staking program admin defined in AdminSettings
+    #[account(
+        mut,
+        constraint = admin_settings.admin_key == authority.key(),
+    )]
+    pub authority: Signer<'info>,
+    
+    // System program
+    pub system_program: Program<'info, System>,
+}
+
+
+pub fn init_traits_registry(ctx: Context<InitTraitsRegistry>, root: [u8; 32]) -> Result<()> {
+
+    let traits_registry = &mut ctx.accounts.traits_registry;
+    traits_registry.bump = *ctx.bumps.get("traits_registry").unwrap();
+    traits_registry.root = root;
+    traits_registry.version = TraitsRegistry::VERSION;
+    
+    Ok(())
+}
+
+
+
+/*
+ *  Publish a new Merkle root of per-mint Character traits
+ */
+
+
+#[derive(Accounts)]
+pub struct SetTraitsRoot<'info> {
+
+    // AdminSettings account
+    #[account(
+        seeds = [b"admin".as_ref()],
+        bump,
+    )]
+    pub admin_settings: Account<'info, AdminSettings>,
+    
+    // TraitsRegistry account
+    #[account(
+        mut,
+        seeds = [b"traits".as_ref()],
+        bump,
+    )]
+    pub traits_registry: Account<'info, TraitsRegistry>,
+    
+    // Staking program admin defined in AdminSettings
+    #[account(
+        constraint = admin_settings.admin_key == authority.key(),
+    )]
+    pub authority: Signer<'info>,
+}
+
+
+pub fn set_traits_root(ctx: Context<SetTraitsRoot>, root: [u8; 32]) -> Result<()> {
+
+    let traits_registry = &mut ctx.accounts.traits_registry;
+    traits_registry.root = root;
+    
     Ok(())
 }
 
@@ -301,31 +1119,48 @@ pub fn init_pools(ctx: Context<InitPools>, mine_manager: Pubkey, loot_manager: P
 
 /*
  *  Init NFT's staking accounts (Character, Miner and Looter accounts)
+ *  The TraitsRegistry account has to exist, deployments upgraded from before the registry call init_traits_registry first.
  */
- 
+
 
 #[derive(Accounts)]
 pub struct InitCharacter<'info> {
-    
+
     // Character account of the NFT
     #[account(
         init,
         payer = user,
         seeds = [b"character".as_ref(), nft_mint.key().as_ref(),],
         bump,
-        space = 8 + std::mem::size_of::<Character>(),
+        space = 8 + Character::LEN,
     )]
     pub character: Account<'info, Character>,
     
     // Whitelist account to be used for whitelist proof
+    #[account(mut)]
     whitelist: Account<'info, Whitelist>,
     
-    // Associated token account of the NFT
+    // Denylist account of the NFT, only initialized if the mint is denylisted
+    ///CHECKED: the address is derived from the NFT mint and only the existence of the account is inspected
+    #[account(
+        seeds = [b"denylist".as_ref(), nft_mint.key().as_ref()],
+        bump,
+    )]
+    pub denylist: UncheckedAccount<'info>,
+    
+    // TraitsRegistry account with the published Character traits
+    #[account(
+        seeds = [b"traits".as_ref()],
+        bump,
+    )]
+    pub traits_registry: Account<'info, TraitsRegistry>,
+    
+    // Token account holding the NFT
     #[account(
-        associated_token::mint = nft_mint,
-        associated_token::authority = user,
+        token::mint = nft_mint,
+        token::authority = user,
     )]
-    pub nft_ata: Account<'info, TokenAccount>,
+    pub nft_account: Account<'info, TokenAccount>,
     
     // Metadata account of the NFT
     ///CHECKED: custom logic checks for the validity of this account
@@ -340,24 +1175,217 @@ pub struct InitCharacter<'info> {
     
     // Associated token program
     pub associated_token_program: Program<'info, AssociatedToken>,
-
+    
     // System program
     pub system_program: Program<'info, System>,
 }
 
 
-pub fn init_character(ctx: Context<InitCharacter>) -> Result<()> {
+pub fn init_character(ctx: Context<InitCharacter>, proof: Option<WhitelistProof>, traits_proof: Option<TraitsProof>, referrer: Option<Pubkey>) -> Result<()> {
 
     require!(
-        ctx.accounts.nft_ata.amount == 1,
+        ctx.accounts.nft_account.amount == 1,
         QstakingErrors::NotHolder
     );
     
+    let clock = Clock::get()?;
     let whitelist = &mut ctx.accounts.whitelist;
-    whitelist.verify(&ctx.program_id, &whitelist.key(), &ctx.accounts.nft_mint.key(), &ctx.accounts.nft_metadata.to_account_info())?;
+    whitelist.verify(&ctx.program_id, &whitelist.key(), &ctx.accounts.nft_mint.key(), &ctx.accounts.nft_metadata.to_account_info(), &ctx.accounts.denylist.to_account_info(), &proof)?;
+    whitelist.register_character(clock.unix_timestamp)?;
+    
+    let traits = ctx.accounts.traits_registry.verify(&ctx.accounts.nft_mint.key(), &traits_proof)?;
     
     let character = &mut ctx.accounts.character;
-    character.initialize(*ctx.bumps.get("character").unwrap())?;
+    character.initialize(*ctx.bumps.get("character").unwrap(), &traits)?;
+    
+    // The Character account of the referrer NFT is passed as remaining account
+    if let Some(referrer) = referrer {
+        require!(
+            referrer != ctx.accounts.nft_mint.key() && !ctx.remaining_accounts.is_empty(),
+            QstakingErrors::InvalidReferrer
+        );
+        
+        let (referrer_character, _) = Pubkey::find_program_address(&[b"character".as_ref(), referrer.as_ref()], ctx.program_id);
+        require_keys_eq!(
+            ctx.remaining_accounts[0].key(),
+            referrer_character,
+            QstakingErrors::InvalidReferrer
+        );
+        
+        let _: Account<Character> = Account::try_from(&ctx.remaining_accounts[0])?;
+        character.set_referrer(clock.unix_timestamp, &referrer)?;
+    }
+    
+    Ok(())
+}
+
+
+
+/*
+ *  Close the Character account of an NFT held by the user and reclaim its rent
+ */
+
+
+#[derive(Accounts)]
+pub struct CloseCharacter<'info> {
+
+    // Mine staking pool account
+    #[account(
+        seeds = [b"mine".as_ref(),],
+        bump,
+    )]
+    pub mine: Account<'info, Mine>,
+    
+    // Character account of the NFT
+    #[account(
+        mut,
+        seeds = [b"character".as_ref(), nft_mint.key().as_ref(),],
+        bump,
+        close = user,
+    )]
+    pub character: Account<'info, Character>,
+    
+    // Token account holding the NFT
+    #[account(
+        token::mint = nft_mint,
+        token::authority = user,
+    )]
+    pub nft_account: Account<'info, TokenAccount>,
+    
+    // Mint account of the NFT
+    pub nft_mint: Account<'info, Mint>,
+    
+    // User account that holds the NFT
+    #[account(mut)]
+    pub user: Signer<'info>,
+    
+    // System program
+    pub system_program: Program<'info, System>,
+}
+
+
+pub fn close_character(ctx: Context<CloseCharacter>, archive: bool) -> Result<()> {
+
+    require!(
+        ctx.accounts.nft_account.amount == 1,
+        QstakingErrors::NotHolder
+    );
+    
+    require!(
+        ctx.accounts.character.staked == 0,
+        QstakingErrors::AlreadyStaked
+    );
+    
+    // Attached gear stays frozen with the Character as delegate, so it has to be detached first
+    require!(
+        ctx.accounts.character.equipped_gear() == 0,
+        QstakingErrors::GearEquipped
+    );
+    
+    // Guild membership counts have to be released by leaving or being kicked from the Guild first
+    require!(
+        ctx.accounts.character.guild == Pubkey::default(),
+        QstakingErrors::GuildMember
+    );
+    
+    // Withheld rewards have to be released or confiscated first, the looted ones are reserved out of the Loot fund
+    require!(
+        ctx.accounts.character.withheld_mining_rewards == 0 && ctx.accounts.character.withheld_looting_rewards == 0,
+        QstakingErrors::WithheldRewards
+    );
+    
+    // The NFT can initialize a new Character, which must not skip the respec cooldown, reset the referrer cap
+    // nor start a new referral window
+    let timestamp = Clock::get()?.unix_timestamp;
+    ctx.accounts.character.assert_respec_cooldown(timestamp, ctx.accounts.mine.respec_cooldown)?;
+    require!(
+        ctx.accounts.character.referral_earned == 0,
+        QstakingErrors::ReferralRewardsEarned
+    );
+    require!(
+        !ctx.accounts.character.in_referral_window(timestamp, ctx.accounts.mine.referral_window)?,
+        QstakingErrors::InReferralWindow
+    );
+    
+    if archive {
+        ctx.accounts.character.archive(&ctx.accounts.nft_mint.key(), timestamp)?;
+    }
+    
+    Ok(())
+}
+
+
+
+/*
+ *  Close the Character account of a burned NFT and reclaim its rent
+ */
+
+
+#[derive(Accounts)]
+pub struct AdminCloseCharacter<'info> {
+
+    // AdminSettings account
+    #[account(
+        seeds = [b"admin".as_ref()],
+        bump,
+    )]
+    pub admin_settings: Account<'info, AdminSettings>,
+    
+    // Character account of the NFT
+    #[account(
+        mut,
+        seeds = [b"character".as_ref(), nft_mint.key().as_ref(),],
+        bump,
+        close = authority,
+    )]
+    pub character: Account<'info, Character>,
+    
+    // Mint account of the burned NFT
+    #[account(
+        constraint = nft_mint.supply == 0 @ QstakingErrors::MintSupplyNotZero,
+    )]
+    pub nft_mint: Account<'info, Mint>,
+    
+    // Staking program admin defined in AdminSettings
+    #[account(
+        mut,
+        constraint = admin_settings.admin_key == authority.key(),
+    )]
+    pub authority: Signer<'info>,
+    
+    // System program
+    pub system_program: Program<'info, System>,
+}
+
+
+pub fn admin_close_character(ctx: Context<AdminCloseCharacter>, archive: bool) -> Result<()> {
+
+    require!(
+        ctx.accounts.character.staked == 0,
+        QstakingErrors::AlreadyStaked
+    );
+    
+    // Attached gear stays frozen with the Character as delegate, so it has to be detached first
+    require!(
+        ctx.accounts.character.equipped_gear() == 0,
+        QstakingErrors::GearEquipped
+    );
+    
+    // Guild membership counts have to be released by leaving or being kicked from the Guild first
+    require!(
+        ctx.accounts.character.guild == Pubkey::default(),
+        QstakingErrors::GuildMember
+    );
+    
+    // Withheld rewards have to be released or confiscated first, the looted ones are reserved out of the Loot fund
+    require!(
+        ctx.accounts.character.withheld_mining_rewards == 0 && ctx.accounts.character.withheld_looting_rewards == 0,
+        QstakingErrors::WithheldRewards
+    );
+    
+    if archive {
+        ctx.accounts.character.archive(&ctx.accounts.nft_mint.key(), Clock::get()?.unix_timestamp)?;
+    }
     
     Ok(())
 }
@@ -0,0 +1,262 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{Mint, TokenAccount};
+
+use crate::token_interface;
+use crate::states::*;
+use crate::errors::QstakingErrors;
+
+
+
+/*
+ *  Upgrade an attribute of an unstaked Character by burning Reward Tokens
+ */
+
+
+#[derive(Accounts)]
+pub struct UpgradeCharacter<'info> {
+
+    // Mine staking pool account
+    #[account(
+        seeds = [b"mine".as_ref(),],
+        bump,
+    )]
+    pub mine: Box<Account<'info, Mine>>,
+    
+    // UpgradeShop account with the cost curves
+    #[account(
+        seeds = [b"upgrades".as_ref()],
+        bump = upgrade_shop.bump,
+    )]
+    pub upgrade_shop: Box<Account<'info, UpgradeShop>>,
+    
+    // Character account of the NFT
+    #[account(
+        mut,
+        seeds = [b"character".as_ref(), nft_mint.key().as_ref(),],
+        bump,
+    )]
+    pub character: Box<Account<'info, Character>>,
+    
+    // Token account holding the Character NFT
+    #[account(
+        token::mint = nft_mint,
+        token::authority = user,
+    )]
+    pub nft_account: Box<Account<'info, TokenAccount>>,
+    
+    // Mint account of the Character NFT
+    pub nft_mint: Box<Account<'info, Mint>>,
+    
+    // Token account of the user the Reward Tokens are burned from
+    ///CHECKED: the account is unpacked and its mint and owner checked with the reward token program
+    #[account(mut)]
+    pub reward_account: UncheckedAccount<'info>,
+    
+    // Address of the reward mint, owned by the Token or the Token-2022 program
+    ///CHECKED: the address is checked against the Mine
+    #[account(
+        mut,
+        constraint = mine.mint == reward_mint.key(),
+    )]
+    pub reward_mint: UncheckedAccount<'info>,
+    
+    // User account that holds the Character NFT
+    pub user: Signer<'info>,
+    
+    // Token program of the reward mint
+    ///CHECKED: the address is checked against the owner of the reward mint
+    #[account(
+        constraint = reward_token_program.key() == *reward_mint.owner @ QstakingErrors::InvalidRewardTokenAccount,
+    )]
+    pub reward_token_program: UncheckedAccount<'info>,
+}
+
+
+pub fn upgrade_character(ctx: Context<UpgradeCharacter>, attribute: u8) -> Result<()> {
+
+    let character = &mut ctx.accounts.character;
+    
+    require!(
+        ctx.accounts.nft_account.amount == 1,
+        QstakingErrors::NotHolder
+    );
+    
+    require!(
+        character.staked == 0,
+        QstakingErrors::AlreadyStaked
+    );
+    
+    token_interface::assert_token_account(
+        &ctx.accounts.reward_account.to_account_info(),
+        &ctx.accounts.user.key(),
+        &ctx.accounts.reward_mint.key(),
+        &ctx.accounts.reward_token_program.key(),
+    )?;
+    
+    let level = *character.upgrade_levels.get(usize::from(attribute)).ok_or(QstakingErrors::InvalidUpgrade)?;
+    let (cost, points) = ctx.accounts.upgrade_shop.price(attribute, level)?;
+    character.upgrade(attribute, points)?;
+    
+    // Burn the cost of the upgrade from the user's reward token account
+    token_interface::burn(
+        ctx.accounts.reward_token_program.to_account_info(),
+        ctx.accounts.reward_account.to_account_info(),
+        ctx.accounts.reward_mint.to_account_info(),
+        ctx.accounts.user.to_account_info(),
+        cost,
+    )?;
+    
+    Ok(())
+}
+
+
+
+/*
+ *  Respec an unstaked Character by burning the respec fee.
+ *  The points allocated to mining points, looting points and bounty points are returned as free points.
+ */
+
+
+#[derive(Accounts)]
+pub struct RespecCharacter<'info> {
+
+    // Mine staking pool account
+    #[account(
+        seeds = [b"mine".as_ref(),],
+        bump,
+    )]
+    pub mine: Box<Account<'info, Mine>>,
+    
+    // Character account of the NFT
+    #[account(
+        mut,
+        seeds = [b"character".as_ref(), nft_mint.key().as_ref(),],
+        bump,
+    )]
+    pub character: Box<Account<'info, Character>>,
+    
+    // Token account holding the Character NFT
+    #[account(
+        token::mint = nft_mint,
+        token::authority = user,
+    )]
+    pub nft_account: Box<Account<'info, TokenAccount>>,
+    
+    // Mint account of the Character NFT
+    pub nft_mint: Box<Account<'info, Mint>>,
+    
+    // Token account of the user the respec fee is burned from
+    ///CHECKED: the account is unpacked and its mint and owner checked with the reward token program
+    #[account(mut)]
+    pub reward_account: UncheckedAccount<'info>,
+    
+    // Address of the reward mint, owned by the Token or the Token-2022 program
+    ///CHECKED: the address is checked against the Mine
+    #[account(
+        mut,
+        constraint = mine.mint == reward_mint.key(),
+    )]
+    pub reward_mint: UncheckedAccount<'info>,
+    
+    // User account that holds the Character NFT
+    pub user: Signer<'info>,
+    
+    // Token program of the reward mint
+    ///CHECKED: the address is checked against the owner of the reward mint
+    #[account(
+        constraint = reward_token_program.key() == *reward_mint.owner @ QstakingErrors::InvalidRewardTokenAccount,
+    )]
+    pub reward_token_program: UncheckedAccount<'info>,
+}
+
+
+pub fn respec(ctx: Context<RespecCharacter>) -> Result<()> {
+
+    let clock = Clock::get()?;
+    let mine = &ctx.accounts.mine;
+    let character = &mut ctx.accounts.character;
+    
+    require!(
+        ctx.accounts.nft_account.amount == 1,
+        QstakingErrors::NotHolder
+    );
+    
+    require!(
+        character.staked == 0,
+        QstakingErrors::AlreadyStaked
+    );
+    
+    token_interface::assert_token_account(
+        &ctx.accounts.reward_account.to_account_info(),
+        &ctx.accounts.user.key(),
+        &ctx.accounts.reward_mint.key(),
+        &ctx.accounts.reward_token_program.key(),
+    )?;
+    
+    character.respec(clock.unix_timestamp, mine.respec_cooldown)?;
+    
+    // Burn the respec fee from the user's reward token account
+    if mine.respec_fee > 0 {
+        token_interface::burn(
+            ctx.accounts.reward_token_program.to_account_info(),
+            ctx.accounts.reward_account.to_account_info(),
+            ctx.accounts.reward_mint.to_account_info(),
+            ctx.accounts.user.to_account_info(),
+            mine.respec_fee,
+        )?;
+    }
+    
+    Ok(())
+}
+
+
+
+/*
+ *  Allocate free points of an unstaked Character to mining points, looting points or bounty points
+ */
+
+
+#[derive(Accounts)]
+pub struct AllocatePoints<'info> {
+
+    // Character account of the NFT
+    #[account(
+        mut,
+        seeds = [b"character".as_ref(), nft_mint.key().as_ref(),],
+        bump,
+    )]
+    pub character: Box<Account<'info, Character>>,
+    
+    // Token account holding the Character NFT
+    #[account(
+        token::mint = nft_mint,
+        token::authority = user,
+    )]
+    pub nft_account: Box<Account<'info, TokenAccount>>,
+    
+    // Mint account of the Character NFT
+    pub nft_mint: Box<Account<'info, Mint>>,
+    
+    // User account that holds the Character NFT
+    pub user: Signer<'info>,
+}
+
+
+pub fn allocate_points(ctx: Context<AllocatePoints>, attribute: u8, points: u64) -> Result<()> {
+
+    let character = &mut ctx.accounts.character;
+    
+    require!(
+        ctx.accounts.nft_account.amount == 1,
+        QstakingErrors::NotHolder
+    );
+    
+    require!(
+        character.staked == 0,
+        QstakingErrors::AlreadyStaked
+    );
+    
+    character.allocate(attribute, points)?;
+    
+    Ok(())
+}
@@ -1,9 +1,14 @@
 use anchor_lang::prelude::*;
 use instructions::*;
+use states::{WhitelistProof, TraitsProof};
 
 pub mod instructions;
 pub mod states;
 pub mod errors;
+pub mod events;
+pub mod merkle;
+pub mod metadata;
+pub mod token_interface;
 
 
 declare_id!("4sjvE7PiZ5rzv6y7HxE6kTQqRrMAoERSYKv4hhwhNccb");
@@ -29,32 +34,128 @@ pub mod qstaking {
     }
     
     
+    pub fn add_denylist(ctx: Context<AddDenylist>, mint: Pubkey) -> Result<()> {
+        instructions::program_config::add_denylist(ctx, mint)?;
+        Ok(())
+    }
+    
+    
+    pub fn remove_denylist(ctx: Context<RemoveDenylist>, mint: Pubkey) -> Result<()> {
+        instructions::program_config::remove_denylist(ctx, mint)?;
+        Ok(())
+    }
+    
+    
+    pub fn settle_withheld_rewards(ctx: Context<SettleWithheldRewards>, release: bool) -> Result<()> {
+        instructions::program_config::settle_withheld_rewards(ctx, release)?;
+        Ok(())
+    }
+    
+    
     pub fn add_whitelist(ctx: Context<AddWhitelist>, reference_account: Pubkey, whitelist_type: u8) -> Result<()> {
         instructions::program_config::add_whitelist(ctx, reference_account, whitelist_type)?;
         Ok(())
     }
     
     
+    pub fn add_merkle_whitelist(ctx: Context<AddMerkleWhitelist>, reference_account: Pubkey, root: [u8; 32]) -> Result<()> {
+        instructions::program_config::add_merkle_whitelist(ctx, reference_account, root)?;
+        Ok(())
+    }
+    
+    
+    pub fn set_whitelist_root(ctx: Context<SetWhitelistRoot>, reference_account: Pubkey, root: [u8; 32]) -> Result<()> {
+        instructions::program_config::set_whitelist_root(ctx, reference_account, root)?;
+        Ok(())
+    }
+    
+    
+    pub fn set_whitelist_enabled(ctx: Context<SetWhitelistEnabled>, reference_account: Pubkey, enabled: bool) -> Result<()> {
+        instructions::program_config::set_whitelist_enabled(ctx, reference_account, enabled)?;
+        Ok(())
+    }
+    
+    
+    pub fn set_whitelist_limits(ctx: Context<SetWhitelistLimits>, reference_account: Pubkey, expiry_timestamp: i64, max_characters: u32) -> Result<()> {
+        instructions::program_config::set_whitelist_limits(ctx, reference_account, expiry_timestamp, max_characters)?;
+        Ok(())
+    }
+    
+    
     pub fn remove_whitelist(ctx: Context<RemoveWhitelist>, reference_account: Pubkey) -> Result<()> {
         instructions::program_config::remove_whitelist(ctx, reference_account)?;
         Ok(())
     }
     
     
+    pub fn add_gear_collection(ctx: Context<AddGearCollection>, creator: Pubkey, stat: u8, bonus_kind: u8, bonus: u64) -> Result<()> {
+        instructions::program_config::add_gear_collection(ctx, creator, stat, bonus_kind, bonus)?;
+        Ok(())
+    }
+    
+    
+    pub fn set_gear_collection_enabled(ctx: Context<SetGearCollectionEnabled>, creator: Pubkey, enabled: bool) -> Result<()> {
+        instructions::program_config::set_gear_collection_enabled(ctx, creator, enabled)?;
+        Ok(())
+    }
+    
+    
+    pub fn init_leaderboard(ctx: Context<InitLeaderboard>) -> Result<()> {
+        instructions::program_config::init_leaderboard(ctx)?;
+        Ok(())
+    }
+    
+    
+    pub fn start_season(ctx: Context<StartSeason>, season_id: u64) -> Result<()> {
+        instructions::program_config::start_season(ctx, season_id)?;
+        Ok(())
+    }
+    
+    
+    pub fn end_season(ctx: Context<EndSeason>) -> Result<()> {
+        instructions::program_config::end_season(ctx)?;
+        Ok(())
+    }
+    
+    
     pub fn init_pools(ctx: Context<InitPools>, mine_manager: Pubkey, loot_manager: Pubkey) -> Result<()> {
         instructions::program_config::init_pools(ctx, mine_manager, loot_manager)?;
         Ok(())
     }
     
     
-    pub fn init_character(ctx: Context<InitCharacter>) -> Result<()> {
-        instructions::program_config::init_character(ctx)?;
+    pub fn init_traits_registry(ctx: Context<InitTraitsRegistry>, root: [u8; 32]) -> Result<()> {
+        instructions::program_config::init_traits_registry(ctx, root)?;
+        Ok(())
+    }
+    
+    
+    pub fn set_traits_root(ctx: Context<SetTraitsRoot>, root: [u8; 32]) -> Result<()> {
+        instructions::program_config::set_traits_root(ctx, root)?;
+        Ok(())
+    }
+    
+    
+    pub fn init_character(ctx: Context<InitCharacter>, proof: Option<WhitelistProof>, traits_proof: Option<TraitsProof>, referrer: Option<Pubkey>) -> Result<()> {
+        instructions::program_config::init_character(ctx, proof, traits_proof, referrer)?;
+        Ok(())
+    }
+    
+    
+    pub fn close_character(ctx: Context<CloseCharacter>, archive: bool) -> Result<()> {
+        instructions::program_config::close_character(ctx, archive)?;
+        Ok(())
+    }
+    
+    
+    pub fn admin_close_character(ctx: Context<AdminCloseCharacter>, archive: bool) -> Result<()> {
+        instructions::program_config::admin_close_character(ctx, archive)?;
         Ok(())
     }
     
     
     // ----- Mine config functions ----
-
+    
     /*
     pub fn init_mine(ctx: Context<InitMine>, manager: Pubkey) -> Result<()> {
         instructions::mine_config::init(ctx, manager)?;
@@ -68,18 +169,90 @@ pub mod qstaking {
     }
     
     
+    pub fn set_mine_emission_cap(ctx: Context<SetMineEmissionCap>, emission_cap: u64) -> Result<()> {
+        instructions::mine_config::set_emission_cap(ctx, emission_cap)?;
+        Ok(())
+    }
+    
+    
+    pub fn set_mine_vesting(ctx: Context<SetMineVesting>, vesting_period: u64, vesting_penalty_bps: u16) -> Result<()> {
+        instructions::mine_config::set_vesting(ctx, vesting_period, vesting_penalty_bps)?;
+        Ok(())
+    }
+    
+    
+    pub fn set_mine_lock_tiers(ctx: Context<SetMineLockTiers>, lock_durations: [u64; 3], lock_multipliers_bps: [u16; 3]) -> Result<()> {
+        instructions::mine_config::set_lock_tiers(ctx, lock_durations, lock_multipliers_bps)?;
+        Ok(())
+    }
+    
+    
+    pub fn init_boost_registry(ctx: Context<InitBoostRegistry>) -> Result<()> {
+        instructions::mine_config::init_boost_registry(ctx)?;
+        Ok(())
+    }
+    
+    
+    pub fn set_mine_boost(ctx: Context<SetMineBoost>, slot: u8, mint: Pubkey, multiplier_bps: u16, duration: u64) -> Result<()> {
+        instructions::mine_config::set_boost(ctx, slot, mint, multiplier_bps, duration)?;
+        Ok(())
+    }
+    
+    
+    pub fn init_upgrade_shop(ctx: Context<InitUpgradeShop>) -> Result<()> {
+        instructions::mine_config::init_upgrade_shop(ctx)?;
+        Ok(())
+    }
+    
+    
+    pub fn set_mine_upgrade(ctx: Context<SetMineUpgrade>, attribute: u8, base_cost: u64, growth_bps: u16, points: u64, max_level: u8) -> Result<()> {
+        instructions::mine_config::set_upgrade(ctx, attribute, base_cost, growth_bps, points, max_level)?;
+        Ok(())
+    }
+    
+    
+    pub fn set_mine_guild_bonus(ctx: Context<SetMineGuildBonus>, guild_bonus_bps: u16, guild_bonus_cap_bps: u16) -> Result<()> {
+        instructions::mine_config::set_guild_bonus(ctx, guild_bonus_bps, guild_bonus_cap_bps)?;
+        Ok(())
+    }
+    
+    
+    pub fn set_mine_referral(ctx: Context<SetMineReferral>, referral_bps: u16, referral_window: u64, referral_cap: u64) -> Result<()> {
+        instructions::mine_config::set_referral(ctx, referral_bps, referral_window, referral_cap)?;
+        Ok(())
+    }
+    
+    
+    pub fn set_mine_respec(ctx: Context<SetMineRespec>, respec_fee: u64, respec_cooldown: u64) -> Result<()> {
+        instructions::mine_config::set_respec(ctx, respec_fee, respec_cooldown)?;
+        Ok(())
+    }
+    
+    
     pub fn set_mine_lock(ctx: Context<SetMineLock>, locked: bool) -> Result<()> {
         instructions::mine_config::set_locked(ctx, locked)?;
         Ok(())
     }
     
     
+    pub fn set_mine_custody(ctx: Context<SetMineCustody>, custody: u8) -> Result<()> {
+        instructions::mine_config::set_custody(ctx, custody)?;
+        Ok(())
+    }
+    
+    
     pub fn set_mine_parameters(ctx: Context<SetMineParameters>, rate: u64, price: u64, cooldown: u64) -> Result<()> {
         instructions::mine_config::set_parameters(ctx, rate, price, cooldown)?;
         Ok(())
     }
     
     
+    pub fn set_mine_schedule(ctx: Context<SetMineSchedule>, schedule: u8, step_timestamps: [i64; 8], decay_interval: u64, decay_bps: u16, floor_rate: u64) -> Result<()> {
+        instructions::mine_config::set_schedule(ctx, schedule, step_timestamps, decay_interval, decay_bps, floor_rate)?;
+        Ok(())
+    }
+    
+    
     pub fn update_mine(ctx: Context<UpdateMine>) -> Result<()> {
         instructions::mine_config::update(ctx)?;
         Ok(())
@@ -100,6 +273,12 @@ pub mod qstaking {
     }
     
     
+    pub fn set_loot_custody(ctx: Context<SetLootCustody>, custody: u8) -> Result<()> {
+        instructions::loot_config::set_custody(ctx, custody)?;
+        Ok(())
+    }
+    
+    
     pub fn set_loot_parameters(ctx: Context<SetLootParameters>, duration: u64, price: u64, cooldown: u64) -> Result<()> {
         instructions::loot_config::set_parameters(ctx, duration, price, cooldown)?;
         Ok(())
@@ -114,22 +293,172 @@ pub mod qstaking {
     
     // ----- Mine staking functions -----
     
-    pub fn stake_mine(ctx: Context<StakeMine>) -> Result<()> {
-        instructions::mine_staking::stake(ctx)?;
+    pub fn stake_mine<'info>(ctx: Context<'_, '_, '_, 'info, StakeMine<'info>>, proof: Option<WhitelistProof>, lock_tier: u8) -> Result<()> {
+        instructions::mine_staking::stake(ctx, proof, lock_tier)?;
         Ok(())
     }
     
     
-    pub fn unstake_mine(ctx: Context<UnstakeMine>) -> Result<()> {
+    pub fn unstake_mine<'info>(ctx: Context<'_, '_, '_, 'info, UnstakeMine<'info>>) -> Result<()> {
         instructions::mine_staking::unstake(ctx)?;
         Ok(())
     }
     
     
+    pub fn stake_mine_vault<'info>(ctx: Context<'_, '_, '_, 'info, StakeMineVault<'info>>, proof: Option<WhitelistProof>, lock_tier: u8) -> Result<()> {
+        instructions::mine_staking::stake_vault(ctx, proof, lock_tier)?;
+        Ok(())
+    }
+    
+    
+    pub fn unstake_mine_vault<'info>(ctx: Context<'_, '_, '_, 'info, UnstakeMineVault<'info>>) -> Result<()> {
+        instructions::mine_staking::unstake_vault(ctx)?;
+        Ok(())
+    }
+    
+    
+    pub fn stake_mine_batch<'info>(ctx: Context<'_, '_, '_, 'info, StakeMineBatch<'info>>, proofs: Vec<Option<WhitelistProof>>, lock_tier: u8) -> Result<()> {
+        instructions::batch_staking::stake_mine(ctx, proofs, lock_tier)?;
+        Ok(())
+    }
+    
+    
+    pub fn unstake_mine_batch<'info>(ctx: Context<'_, '_, '_, 'info, UnstakeMineBatch<'info>>, count: u8) -> Result<()> {
+        instructions::batch_staking::unstake_mine(ctx, count)?;
+        Ok(())
+    }
+    
+    
+    pub fn apply_boost(ctx: Context<ApplyBoost>) -> Result<()> {
+        instructions::mine_staking::apply_boost(ctx)?;
+        Ok(())
+    }
+    
+    
+    pub fn expire_boost(ctx: Context<ExpireBoost>) -> Result<()> {
+        instructions::mine_staking::expire_boost(ctx)?;
+        Ok(())
+    }
+    
+    
+    pub fn equip_gear(ctx: Context<EquipGear>) -> Result<()> {
+        instructions::equipment::equip(ctx)?;
+        Ok(())
+    }
+    
+    
+    pub fn unequip_gear(ctx: Context<UnequipGear>) -> Result<()> {
+        instructions::equipment::unequip(ctx)?;
+        Ok(())
+    }
+    
+    
+    pub fn upgrade_character(ctx: Context<UpgradeCharacter>, attribute: u8) -> Result<()> {
+        instructions::upgrade::upgrade_character(ctx, attribute)?;
+        Ok(())
+    }
+    
+    
+    pub fn respec(ctx: Context<RespecCharacter>) -> Result<()> {
+        instructions::upgrade::respec(ctx)?;
+        Ok(())
+    }
+    
+    
+    pub fn allocate_points(ctx: Context<AllocatePoints>, attribute: u8, points: u64) -> Result<()> {
+        instructions::upgrade::allocate_points(ctx, attribute, points)?;
+        Ok(())
+    }
+    
+    
+    pub fn create_guild(ctx: Context<CreateGuild>, cut_bps: u16) -> Result<()> {
+        instructions::guild::create(ctx, cut_bps)?;
+        Ok(())
+    }
+    
+    
+    pub fn set_guild_cut(ctx: Context<SetGuildCut>, cut_bps: u16) -> Result<()> {
+        instructions::guild::set_cut(ctx, cut_bps)?;
+        Ok(())
+    }
+    
+    
+    pub fn join_guild(ctx: Context<JoinGuild>) -> Result<()> {
+        instructions::guild::join(ctx)?;
+        Ok(())
+    }
+    
+    
+    pub fn leave_guild(ctx: Context<LeaveGuild>) -> Result<()> {
+        instructions::guild::leave(ctx)?;
+        Ok(())
+    }
+    
+    
+    pub fn kick_guild_member(ctx: Context<KickGuildMember>) -> Result<()> {
+        instructions::guild::kick(ctx)?;
+        Ok(())
+    }
+    
+    
+    pub fn withdraw_guild_treasury(ctx: Context<WithdrawGuildTreasury>, amount: u64) -> Result<()> {
+        instructions::guild::withdraw(ctx, amount)?;
+        Ok(())
+    }
+    
+    
+    pub fn create_operator(ctx: Context<CreateOperator>, max_lock_tier: u8) -> Result<()> {
+        instructions::operator::create(ctx, max_lock_tier)?;
+        Ok(())
+    }
+    
+    
+    pub fn revoke_operator(ctx: Context<RevokeOperator>) -> Result<()> {
+        instructions::operator::revoke(ctx)?;
+        Ok(())
+    }
+    
+    
+    pub fn stake_mine_operator<'info>(ctx: Context<'_, '_, '_, 'info, StakeMineOperator<'info>>, proof: Option<WhitelistProof>, lock_tier: u8) -> Result<()> {
+        instructions::operator::stake_mine(ctx, proof, lock_tier)?;
+        Ok(())
+    }
+    
+    
+    pub fn unstake_mine_operator<'info>(ctx: Context<'_, '_, '_, 'info, UnstakeMineOperator<'info>>) -> Result<()> {
+        instructions::operator::unstake_mine(ctx)?;
+        Ok(())
+    }
+    
+    
+    pub fn claim_vested_operator(ctx: Context<ClaimVestedOperator>) -> Result<()> {
+        instructions::operator::claim_vested(ctx)?;
+        Ok(())
+    }
+    
+    
+    pub fn claim_vested(ctx: Context<ClaimVested>) -> Result<()> {
+        instructions::mine_staking::claim_vested(ctx)?;
+        Ok(())
+    }
+    
+    
+    pub fn exit_vesting(ctx: Context<ExitVesting>) -> Result<()> {
+        instructions::mine_staking::exit_vesting(ctx)?;
+        Ok(())
+    }
+    
+    
+    pub fn force_unstake_mine<'info>(ctx: Context<'_, '_, '_, 'info, ForceUnstakeMine<'info>>) -> Result<()> {
+        instructions::mine_staking::force_unstake(ctx)?;
+        Ok(())
+    }
+    
+    
     // ----- Loot staking functions -----
     
-    pub fn stake_loot(ctx: Context<StakeLoot>) -> Result<()> {
-        instructions::loot_staking::stake(ctx)?;
+    pub fn stake_loot(ctx: Context<StakeLoot>, proof: Option<WhitelistProof>) -> Result<()> {
+        instructions::loot_staking::stake(ctx, proof)?;
         Ok(())
     }
     
@@ -138,4 +467,64 @@ pub mod qstaking {
         instructions::loot_staking::unstake(ctx)?;
         Ok(())
     }
+    
+    
+    pub fn stake_loot_vault(ctx: Context<StakeLootVault>, proof: Option<WhitelistProof>) -> Result<()> {
+        instructions::loot_staking::stake_vault(ctx, proof)?;
+        Ok(())
+    }
+    
+    
+    pub fn unstake_loot_vault(ctx: Context<UnstakeLootVault>) -> Result<()> {
+        instructions::loot_staking::unstake_vault(ctx)?;
+        Ok(())
+    }
+    
+    
+    pub fn force_unstake_loot(ctx: Context<ForceUnstakeLoot>) -> Result<()> {
+        instructions::loot_staking::force_unstake(ctx)?;
+        Ok(())
+    }
+    
+    
+    pub fn stake_loot_batch<'info>(ctx: Context<'_, '_, '_, 'info, StakeLootBatch<'info>>, proofs: Vec<Option<WhitelistProof>>) -> Result<()> {
+        instructions::batch_staking::stake_loot(ctx, proofs)?;
+        Ok(())
+    }
+    
+    
+    pub fn unstake_loot_batch<'info>(ctx: Context<'_, '_, '_, 'info, UnstakeLootBatch<'info>>, count: u8) -> Result<()> {
+        instructions::batch_staking::unstake_loot(ctx, count)?;
+        Ok(())
+    }
+    
+    
+    pub fn migrate_admin(ctx: Context<MigrateAdmin>) -> Result<()> {
+        instructions::migration::migrate_admin(ctx)?;
+        Ok(())
+    }
+    
+    
+    pub fn migrate_mine(ctx: Context<MigrateMine>) -> Result<()> {
+        instructions::migration::migrate_mine(ctx)?;
+        Ok(())
+    }
+    
+    
+    pub fn migrate_loot(ctx: Context<MigrateLoot>) -> Result<()> {
+        instructions::migration::migrate_loot(ctx)?;
+        Ok(())
+    }
+    
+    
+    pub fn migrate_whitelist(ctx: Context<MigrateWhitelist>, reference_account: Pubkey) -> Result<()> {
+        instructions::migration::migrate_whitelist(ctx, reference_account)?;
+        Ok(())
+    }
+    
+    
+    pub fn migrate_character(ctx: Context<MigrateCharacter>) -> Result<()> {
+        instructions::migration::migrate_character(ctx)?;
+        Ok(())
+    }
 }
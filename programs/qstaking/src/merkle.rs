@@ -0,0 +1,27 @@
+use solana_program::keccak;
+
+
+
+/*
+ *  Merkle tree helpers shared by the Merkle whitelist and the published Character traits
+ */
+
+
+pub fn leaf(key: &[u8], data: &[u8]) -> [u8; 32] {
+    keccak::hashv(&[&[0u8], key, data]).0
+}
+
+
+pub fn verify(leaf: [u8; 32], path: &[[u8; 32]], root: &[u8; 32]) -> bool {
+    
+    let mut node = leaf;
+    for sibling in path {
+        node = if node <= *sibling {
+            keccak::hashv(&[&[1u8], node.as_ref(), sibling.as_ref()]).0
+        } else {
+            keccak::hashv(&[&[1u8], sibling.as_ref(), node.as_ref()]).0
+        };
+    }
+    
+    node == *root
+}
@@ -0,0 +1,276 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+use anchor_lang::solana_program::{system_program, sysvar};
+use mpl_token_metadata as metaplex;
+
+use crate::errors::*;
+
+
+
+/*
+ *  Token Metadata helpers for programmable NFTs (pNFTs)
+ *  The pinned mpl-token-metadata crate predates pNFTs, so the token standard is read directly from the
+ *  metadata account and the Delegate/Revoke/Lock/Unlock instructions are serialized by hand. Optional
+ *  accounts that are not used (e.g. authorization rules) are passed as the Token Metadata program id.
+ */
+
+
+pub const TOKEN_STANDARD_PROGRAMMABLE_NON_FUNGIBLE: u8 = 4;
+
+const DELEGATE_INSTRUCTION: u8 = 44;
+const REVOKE_INSTRUCTION: u8 = 45;
+const LOCK_INSTRUCTION: u8 = 46;
+const UNLOCK_INSTRUCTION: u8 = 47;
+
+const STAKING_DELEGATE_V1: u8 = 5;
+
+
+pub fn assert_metadata_account(mint: &Pubkey, mint_metadata: &AccountInfo) -> Result<()> {
+    let metadata_program = metaplex::id();
+    
+    require_keys_eq!(
+        *mint_metadata.owner,
+        metadata_program,
+        QstakingErrors::InvalidMetadataAccount
+    );
+    
+    let seed = &[b"metadata".as_ref(), metadata_program.as_ref(), mint.as_ref(),];
+    let (metadata_account, _bump) = Pubkey::find_program_address(seed, &metadata_program);
+    require_keys_eq!(
+        metadata_account,
+        mint_metadata.key(),
+        QstakingErrors::InvalidMetadataAccount
+    );
+    
+    Ok(())
+}
+
+
+fn skip_bytes(data: &[u8], offset: usize, length: usize) -> Result<usize> {
+    let end = offset.checked_add(length).ok_or(QstakingErrors::InvalidMetadataAccount)?;
+    require!(
+        end <= data.len(),
+        QstakingErrors::InvalidMetadataAccount
+    );
+    Ok(end)
+}
+
+
+fn skip_string(data: &[u8], offset: usize) -> Result<usize> {
+    let end = skip_bytes(data, offset, 4)?;
+    let length = u32::from_le_bytes(data[offset..end].try_into().unwrap()) as usize;
+    skip_bytes(data, end, length)
+}
+
+
+pub fn token_standard(mint_metadata: &AccountInfo) -> Result<Option<u8>> {
+    let data = mint_metadata.try_borrow_data()?;
+    
+    // key, update authority and mint
+    let mut offset = skip_bytes(&data, 0, 1 + 32 + 32)?;
+    
+    // name, symbol, uri and seller fee basis points
+    offset = skip_string(&data, offset)?;
+    offset = skip_string(&data, offset)?;
+    offset = skip_string(&data, offset)?;
+    offset = skip_bytes(&data, offset, 2)?;
+    
+    // creators
+    offset = skip_bytes(&data, offset, 1)?;
+    if data[offset - 1] == 1 {
+        let end = skip_bytes(&data, offset, 4)?;
+        let creators = u32::from_le_bytes(data[offset..end].try_into().unwrap()) as usize;
+        offset = skip_bytes(&data, end, creators.checked_mul(34).ok_or(QstakingErrors::InvalidMetadataAccount)?)?;
+    }
+    
+    // primary sale happened and is mutable
+    offset = skip_bytes(&data, offset, 2)?;
+    
+    // edition nonce
+    offset = skip_bytes(&data, offset, 1)?;
+    if data[offset - 1] == 1 {
+        offset = skip_bytes(&data, offset, 1)?;
+    }
+    
+    // token standard, missing on metadata accounts created before v1.2
+    if offset + 2 > data.len() || data[offset] == 0 {
+        return Ok(None);
+    }
+    
+    Ok(Some(data[offset + 1]))
+}
+
+
+pub fn is_programmable(mint_metadata: &AccountInfo) -> Result<bool> {
+    Ok(token_standard(mint_metadata)? == Some(TOKEN_STANDARD_PROGRAMMABLE_NON_FUNGIBLE))
+}
+
+
+#[allow(clippy::too_many_arguments)]
+fn delegate_accounts(
+    program_id: Pubkey,
+    delegate: Pubkey,
+    metadata: Pubkey,
+    master_edition: Pubkey,
+    token_record: Pubkey,
+    mint: Pubkey,
+    token: Pubkey,
+    authority: Pubkey,
+    payer: Pubkey,
+    authorization_rules_program: Pubkey,
+    authorization_rules: Pubkey,
+) -> Vec<AccountMeta> {
+    vec![
+        AccountMeta::new_readonly(program_id, false),
+        AccountMeta::new_readonly(delegate, false),
+        AccountMeta::new(metadata, false),
+        AccountMeta::new_readonly(master_edition, false),
+        AccountMeta::new(token_record, false),
+        AccountMeta::new_readonly(mint, false),
+        AccountMeta::new(token, false),
+        AccountMeta::new_readonly(authority, true),
+        AccountMeta::new(payer, true),
+        AccountMeta::new_readonly(system_program::ID, false),
+        AccountMeta::new_readonly(sysvar::instructions::ID, false),
+        AccountMeta::new_readonly(anchor_spl::token::ID, false),
+        AccountMeta::new_readonly(authorization_rules_program, false),
+        AccountMeta::new_readonly(authorization_rules, false),
+    ]
+}
+
+
+#[allow(clippy::too_many_arguments)]
+fn lock_accounts(
+    authority: Pubkey,
+    token_owner: Pubkey,
+    token: Pubkey,
+    mint: Pubkey,
+    metadata: Pubkey,
+    edition: Pubkey,
+    token_record: Pubkey,
+    payer: Pubkey,
+    authorization_rules_program: Pubkey,
+    authorization_rules: Pubkey,
+) -> Vec<AccountMeta> {
+    vec![
+        AccountMeta::new_readonly(authority, true),
+        AccountMeta::new_readonly(token_owner, false),
+        AccountMeta::new(token, false),
+        AccountMeta::new_readonly(mint, false),
+        AccountMeta::new(metadata, false),
+        AccountMeta::new_readonly(edition, false),
+        AccountMeta::new(token_record, false),
+        AccountMeta::new(payer, true),
+        AccountMeta::new_readonly(system_program::ID, false),
+        AccountMeta::new_readonly(sysvar::instructions::ID, false),
+        AccountMeta::new_readonly(anchor_spl::token::ID, false),
+        AccountMeta::new_readonly(authorization_rules_program, false),
+        AccountMeta::new_readonly(authorization_rules, false),
+    ]
+}
+
+
+#[allow(clippy::too_many_arguments)]
+pub fn delegate_staking(
+    program_id: Pubkey,
+    delegate: Pubkey,
+    metadata: Pubkey,
+    master_edition: Pubkey,
+    token_record: Pubkey,
+    mint: Pubkey,
+    token: Pubkey,
+    authority: Pubkey,
+    payer: Pubkey,
+    authorization_rules_program: Pubkey,
+    authorization_rules: Pubkey,
+) -> Instruction {
+
+    // DelegateArgs::StakingV1 { amount: 1, authorization_data: None }
+    let mut data = vec![DELEGATE_INSTRUCTION, STAKING_DELEGATE_V1];
+    data.extend_from_slice(&1u64.to_le_bytes());
+    data.push(0);
+    
+    Instruction {
+        program_id,
+        accounts: delegate_accounts(program_id, delegate, metadata, master_edition, token_record, mint, token, authority, payer, authorization_rules_program, authorization_rules),
+        data,
+    }
+}
+
+
+#[allow(clippy::too_many_arguments)]
+pub fn revoke_staking(
+    program_id: Pubkey,
+    delegate: Pubkey,
+    metadata: Pubkey,
+    master_edition: Pubkey,
+    token_record: Pubkey,
+    mint: Pubkey,
+    token: Pubkey,
+    authority: Pubkey,
+    payer: Pubkey,
+    authorization_rules_program: Pubkey,
+    authorization_rules: Pubkey,
+) -> Instruction {
+
+    // RevokeArgs::StakingV1
+    let data = vec![REVOKE_INSTRUCTION, STAKING_DELEGATE_V1];
+    
+    Instruction {
+        program_id,
+        accounts: delegate_accounts(program_id, delegate, metadata, master_edition, token_record, mint, token, authority, payer, authorization_rules_program, authorization_rules),
+        data,
+    }
+}
+
+
+#[allow(clippy::too_many_arguments)]
+pub fn lock(
+    program_id: Pubkey,
+    authority: Pubkey,
+    token_owner: Pubkey,
+    token: Pubkey,
+    mint: Pubkey,
+    metadata: Pubkey,
+    edition: Pubkey,
+    token_record: Pubkey,
+    payer: Pubkey,
+    authorization_rules_program: Pubkey,
+    authorization_rules: Pubkey,
+) -> Instruction {
+
+    // LockArgs::V1 { authorization_data: None }
+    let data = vec![LOCK_INSTRUCTION, 0, 0];
+    
+    Instruction {
+        program_id,
+        accounts: lock_accounts(authority, token_owner, token, mint, metadata, edition, token_record, payer, authorization_rules_program, authorization_rules),
+        data,
+    }
+}
+
+
+#[allow(clippy::too_many_arguments)]
+pub fn unlock(
+    program_id: Pubkey,
+    authority: Pubkey,
+    token_owner: Pubkey,
+    token: Pubkey,
+    mint: Pubkey,
+    metadata: Pubkey,
+    edition: Pubkey,
+    token_record: Pubkey,
+    payer: Pubkey,
+    authorization_rules_program: Pubkey,
+    authorization_rules: Pubkey,
+) -> Instruction {
+
+    // UnlockArgs::V1 { authorization_data: None }
+    let data = vec![UNLOCK_INSTRUCTION, 0, 0];
+    
+    Instruction {
+        program_id,
+        accounts: lock_accounts(authority, token_owner, token, mint, metadata, edition, token_record, payer, authorization_rules_program, authorization_rules),
+        data,
+    }
+}
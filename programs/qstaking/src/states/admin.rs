@@ -1,11 +1,43 @@
 use anchor_lang::prelude::*;
 
+use crate::errors::*;
+
 #[account]
 pub struct AdminSettings {
-    
+
     // Bump used in generating the AdminSettings account
     pub bump: u8,
     
     // Address of the admin's account
     pub admin_key: Pubkey,
-} 
+    
+    // Layout version of the account
+    pub version: u8,
+    
+    // Reserved space for fields added in later versions
+    pub reserved: [u8; 32],
+}
+
+
+impl AdminSettings {
+
+    // Current layout version of the AdminSettings account
+    pub const VERSION: u8 = 1;
+    
+    // Serialized size of the AdminSettings account without the discriminator
+    pub const LEN: usize = 1 + 32 + 1 + 32;
+    
+    
+    pub fn migrate(&mut self) -> Result<()> {
+    
+        require!(
+            self.version < Self::VERSION,
+            QstakingErrors::AccountAlreadyMigrated
+        );
+        
+        self.version = Self::VERSION;
+        
+        Ok(())
+    }
+}
+
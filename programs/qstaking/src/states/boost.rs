@@ -0,0 +1,123 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::*;
+
+
+#[account]
+pub struct BoostRegistry {
+
+    // Bump used in generating the BoostRegistry account
+    pub bump: u8,
+    
+    // Boost items defined by the Mine manager, unused slots have the default mint
+    pub boosts: [Boost; 8],
+    
+    // Layout version of the account
+    pub version: u8,
+    
+    // Reserved space for fields added in later versions
+    pub reserved: [u8; 32],
+}
+
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default)]
+pub struct Boost {
+
+    // Fungible mint of the boost item
+    pub mint: Pubkey,
+    
+    // Stake weight multiplier while the boost is active in basis points
+    pub multiplier_bps: u16,
+    
+    // Duration of the boost in units of s
+    pub duration: u64,
+}
+
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default)]
+pub struct BoostExpiry {
+
+    // Timestamp at which the boosts of the entry end, 0 for a free entry
+    pub end_timestamp: i64,
+    
+    // Boost weight of the entry still counted in the Mine staked points
+    pub weight: u64,
+    
+    // Sum of the boost weight times the reward peg of the entry still counted in the Mine
+    pub peg_total: u128,
+    
+    // Accrued rewards per point of the Mine at the end timestamp, set once the entry expired
+    pub accrued_rewards: u128,
+    
+    // Number of boosts of the entry not yet settled by their Characters
+    pub boosts: u32,
+}
+
+
+impl BoostRegistry {
+
+    // Current layout version of the BoostRegistry account
+    pub const VERSION: u8 = 1;
+    
+    // Serialized size of the BoostRegistry account without the discriminator
+    pub const LEN: usize = 1 + 8 * (32 + 2 + 8) + 1 + 32;
+    
+    
+    pub fn initialize(&mut self, bump: u8) -> Result<()> {
+    
+        self.bump = bump;
+        self.boosts = [Boost::default(); 8];
+        self.version = Self::VERSION;
+        self.reserved = [0; 32];
+        
+        Ok(())
+    }
+    
+    
+    // Defines the boost item in the slot, the default mint clears the slot
+    pub fn set_boost(&mut self, slot: u8, mint: &Pubkey, multiplier_bps: u16, duration: u64) -> Result<()> {
+    
+        let slot = usize::from(slot);
+        require!(
+            slot < self.boosts.len(),
+            QstakingErrors::InvalidBoost
+        );
+        
+        if *mint == Pubkey::default() {
+            self.boosts[slot] = Boost::default();
+            return Ok(());
+        }
+        
+        require!(
+            multiplier_bps > 10000 && duration > 0 && i64::try_from(duration).is_ok(),
+            QstakingErrors::InvalidBoost
+        );
+        
+        // A mint can only be registered in one slot
+        require!(
+            self.boosts.iter().enumerate().all(|(index, boost)| index == slot || boost.mint != *mint),
+            QstakingErrors::InvalidBoost
+        );
+        
+        self.boosts[slot] = Boost {
+            mint: *mint,
+            multiplier_bps,
+            duration,
+        };
+        
+        Ok(())
+    }
+    
+    
+    pub fn find(&self, mint: &Pubkey) -> Result<Boost> {
+    
+        require!(
+            *mint != Pubkey::default(),
+            QstakingErrors::InvalidBoost
+        );
+        
+        let boost = self.boosts.iter().find(|boost| boost.mint == *mint).ok_or(QstakingErrors::InvalidBoost)?;
+        
+        Ok(*boost)
+    }
+}
@@ -1,11 +1,164 @@
 use anchor_lang::prelude::*;
+use anchor_lang::Discriminator;
 
 use crate::errors::*;
+use crate::events::CharacterArchived;
+use crate::states::CharacterTraits;
 
 
 #[account]
 pub struct Character { 
+
+    // Bump used in generating the Character account
+    pub bump: u8,
+    
+    // Level of the Character
+    pub level: u8,
+    
+    // Rarity tier of the NFT
+    pub tier: u8,
+    
+    // Comulative experience points
+    pub experience: u64,
+    
+    // Base mining points
+    pub mining_points: u64,
+    
+    // Maximum mining rewards capacity
+    pub mining_capacity: u64,
+    
+    // Base looting points
+    pub looting_points: u64,
+    
+    // Looting survival score
+    pub looting_survival: u64,
+    
+    // Base sentinel points
+    pub bounty_points: u64,
+    
+    // Number of available bounty hunt attempts
+    pub bounty_bullets: u16,
+    
+    // Comulative amount of mined Reward Tokens
+    pub mining_rewards: u128,
+    
+    // Comulative amount of looted Reward Tokens
+    pub looting_rewards: u128,
+    
+    // Comulative amount of bounty Reward Tokens
+    pub bounty_rewards: u128,
+    
+    // NFT staking status: 0 unstaked, 1 staked in mine, 2 staked in loot
+    pub staked: u8,
+    
+    // Custody mode the NFT was staked with: 0 frozen in the user's wallet, 1 escrowed in a pool vault
+    pub custody: u8,
+    
+    // Owner of the NFT while it is escrowed in a pool vault
+    pub vault_owner: Pubkey,
+    
+    // Token account holding the NFT while it is staked
+    pub nft_account: Pubkey,
+    
+    // Staking pool's commualtive accrued rewards per mining point at the moment of staking
+    pub staked_peg: u128,
+    
+    // Timestamp when the NFT was staked
+    pub staked_timestamp: i64,
+    
+    // Timestamp when the mine cooldown expires
+    pub mine_cooldown_timestamp: i64,
+    
+    // Timestamp when the loot cooldown expires
+    pub loot_cooldown_timestamp: i64,
+    
+    // Timestamp when bounty bullets were last claimed
+    pub bounty_bulltes_timestamp: i64,
+    
+    // Layout version of the account, fields added in later versions follow it so that it keeps its offset
+    pub version: u8,
+    
+    // Lock tier the NFT was staked in the Mine with, 0 for no lock
+    pub lock_tier: u8,
+    
+    // Timestamp when the lock-up expires
+    pub lock_end_timestamp: i64,
+    
+    // Mining points multiplied by the lock tier, added to the Mine while staked
+    pub staked_weight: u64,
+    
+    // Additional stake weight of the active boost, added to the Mine until the boost ends
+    pub boost_weight: u64,
+    
+    // Mine's comulative accrued rewards per mining point at the moment the boost was applied
+    pub boost_peg: u128,
+    
+    // Timestamp when the active boost expires
+    pub boost_end_timestamp: i64,
+    
+    // Rewards accrued by boosts that ended and by the guild bonus during the current stake
+    pub boost_rewards: u64,
+    
+    // Mints of the gear NFTs attached to the Character, unused slots have the default mint
+    pub gear_mints: [Pubkey; 3],
+    
+    // Guild the Character is a member of, the default key if it is in no guild
+    pub guild: Pubkey,
+    
+    // Guild cut of the mined rewards in basis points at the moment of staking
+    pub guild_cut_bps: u16,
+    
+    // NFT mint of the referrer Character, the default key if the Character was not referred
+    pub referrer: Pubkey,
+    
+    // Timestamp of the referral at which the referral window starts
+    pub referral_timestamp: i64,
+    
+    // Referral rewards earned as a referrer
+    pub referral_earned: u64,
+    
+    // Season of the season counters
+    pub season: u64,
+    
+    // Mining rewards earned in the season
+    pub season_mining_rewards: u128,
+    
+    // Looting rewards earned in the season
+    pub season_looting_rewards: u128,
     
+    // Experience points earned in the season
+    pub season_experience: u64,
+    
+    // Upgrade levels bought in the upgrade shop per attribute
+    pub upgrade_levels: [u8; 4],
+    
+    // Points allocated to each attribute on top of its base value
+    pub allocated_points: [u64; 4],
+    
+    // Points returned by respecs that can be allocated to an attribute
+    pub free_points: u64,
+    
+    // Timestamp of the last respec
+    pub respec_timestamp: i64,
+    
+    // Mined rewards withheld while the NFT was denylisted, minted once the admin releases them
+    pub withheld_mining_rewards: u64,
+    
+    // Looted rewards withheld while the NFT was denylisted, reserved in the Loot fund until the admin releases them
+    pub withheld_looting_rewards: u64,
+    
+    // Accrued guild bonus of the Guild when the Character was staked
+    pub guild_bonus_peg: u128,
+    
+    // Reserved space for fields added in later versions
+    pub reserved: [u8; 64],
+}
+
+
+// Layout of the Character account before versioning, kept to migrate legacy accounts
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct CharacterV0 {
+
     // Bump used in generating the Character account
     pub bump: u8,
     
@@ -58,22 +211,42 @@ pub struct Character {
     pub loot_cooldown_timestamp: i64,
     
     // Timestamp when bounty bullets were last claimed
-    pub bounty_bulltes_timestamp: i64
+    pub bounty_bulltes_timestamp: i64,
 }
 
 
 impl Character {
+
+    // Current layout version of the Character account
+    pub const VERSION: u8 = 1;
+    
+    // Attributes reset by a respec: mining points, looting points and bounty points
+    pub const RESPEC_ATTRIBUTES: [u8; 3] = [0, 2, 3];
+    
+    // Offset of the version byte in the account data, the byte is zero in accounts created before versioning
+    pub const VERSION_OFFSET: usize = 8 + 1 + 1 + 1 + 8 + 8 + 8 + 8 + 8 + 8 + 2 + 16 + 16 + 16 + 1 + 1 + 32 + 32 + 16 + 8 + 8 + 8 + 8;
     
-    pub fn initialize(&mut self, bump: u8) -> Result<()> {
+    // Serialized size of the Character account without the discriminator
+    pub const LEN: usize = 1 + 1 + 1 + 8 + 8 + 8 + 8 + 8 + 8 + 2 + 16 + 16 + 16 + 1 + 1 + 32 + 32 + 16 + 8 + 8 + 8 + 8 + 1 + 1 + 8 + 8 + 8 + 16 + 8 + 8 + 96 + 32 + 2 + 32 + 8 + 8 + 8 + 16 + 16 + 8 + 4 + 32 + 8 + 8 + 8 + 8 + 16 + 64;
     
+    
+    pub fn initialize(&mut self, bump: u8, traits: &CharacterTraits) -> Result<()> {
+    
+        // The Merkle proof only shows the traits were published, a Character without mining points could never earn
+        require!(
+            traits.mining_points > 0 && traits.tier <= CharacterTraits::MAX_TIER,
+            QstakingErrors::InvalidTraits
+        );
+        
         self.bump = bump;
         self.level = 1;
+        self.tier = traits.tier;
         self.experience = 0;
         
-        self.mining_points = 1;
-        self.mining_capacity = 1000000;
+        self.mining_points = traits.mining_points;
+        self.mining_capacity = traits.mining_capacity;
         
-        self.looting_points = 1;
+        self.looting_points = traits.looting_points;
         self.looting_survival = 0;
         
         self.bounty_points = 0;
@@ -84,19 +257,60 @@ impl Character {
         self.bounty_rewards = 0;
         
         self.staked = 0;
+        self.custody = 0;
+        self.vault_owner = Pubkey::default();
+        self.nft_account = Pubkey::default();
         self.staked_peg = 0;
         self.staked_timestamp = 0;
         self.mine_cooldown_timestamp = 0;
         self.loot_cooldown_timestamp = 0;
         self.bounty_bulltes_timestamp = 0;
+        self.version = Self::VERSION;
+        self.lock_tier = 0;
+        self.lock_end_timestamp = 0;
+        self.staked_weight = 0;
+        self.boost_weight = 0;
+        self.boost_peg = 0;
+        self.boost_end_timestamp = 0;
+        self.boost_rewards = 0;
+        self.gear_mints = [Pubkey::default(); 3];
+        self.guild = Pubkey::default();
+        self.guild_cut_bps = 0;
+        self.referrer = Pubkey::default();
+        self.referral_timestamp = 0;
+        self.referral_earned = 0;
+        self.season = 0;
+        self.season_mining_rewards = 0;
+        self.season_looting_rewards = 0;
+        self.season_experience = 0;
+        self.upgrade_levels = [0; 4];
+        self.allocated_points = [0; 4];
+        self.free_points = 0;
+        self.respec_timestamp = 0;
+        self.withheld_mining_rewards = 0;
+        self.withheld_looting_rewards = 0;
+        self.guild_bonus_peg = 0;
+        self.reserved = [0; 64];
         
         Ok(())
     }
     
     
-    pub fn stake_mine(&mut self, timestamp: i64, accrued_rewards: u128) -> Result<()> {
-        
+    pub fn staked_seconds(&self, timestamp: i64) -> Result<u64> {
+    
+        Ok(u64::try_from(
+            timestamp.checked_sub(self.staked_timestamp).ok_or(QstakingErrors::InvalidComputation).unwrap()
+        ).unwrap())
+    }
+    
+    
+    pub fn stake_mine(&mut self, timestamp: i64, season: u64, accrued_rewards: u128, custody: u8, nft_account: &Pubkey, vault_owner: &Pubkey) -> Result<()> {
+    
+        self.enter_season(season)?;
         self.staked = 1;
+        self.custody = custody;
+        self.vault_owner = *vault_owner;
+        self.nft_account = *nft_account;
         self.staked_peg = accrued_rewards;
         self.staked_timestamp = timestamp;
         
@@ -104,22 +318,73 @@ impl Character {
     }
     
     
-    pub fn unstake_mine(&mut self, timestamp: i64, cooldown: u64, mine_pool_strength: u64, loot_pool_strength: u64, mine_accrued_rewards: u128) -> Result<(u64, u64)> {
+    pub fn lock(&mut self, timestamp: i64, lock_tier: u8, lock_duration: u64, staked_weight: u64) -> Result<()> {
+    
+        self.lock_tier = lock_tier;
+        self.lock_end_timestamp = timestamp.checked_add(
+            i64::try_from(lock_duration).unwrap()
+        ).ok_or(QstakingErrors::InvalidComputation).unwrap();
+        self.staked_weight = staked_weight;
+        
+        Ok(())
+    }
+    
+    
+    pub fn boost(&mut self, timestamp: i64, accrued_rewards: u128, boost_weight: u64, boost_duration: u64) -> Result<()> {
+    
+        self.boost_weight = boost_weight;
+        self.boost_peg = accrued_rewards;
+        self.boost_end_timestamp = timestamp.checked_add(
+            i64::try_from(boost_duration).unwrap()
+        ).ok_or(QstakingErrors::InvalidComputation).unwrap();
+        
+        Ok(())
+    }
+    
+    
+    // Credits the rewards accrued by the boost weight up to the given accrued rewards per point of the Mine
+    pub fn end_boost(&mut self, accrued_rewards: u128) -> Result<()> {
+    
+        let boost_rewards = self.boost_weight.checked_mul(
+            u64::try_from(
+                accrued_rewards.checked_sub(self.boost_peg).ok_or(QstakingErrors::InvalidComputation).unwrap()
+            ).unwrap()
+        ).ok_or(QstakingErrors::InvalidComputation).unwrap();
+        
+        self.boost_rewards = self.boost_rewards.checked_add(boost_rewards).ok_or(QstakingErrors::InvalidComputation).unwrap();
+        self.boost_weight = 0;
+        self.boost_peg = 0;
+        self.boost_end_timestamp = 0;
         
-        let accrued_rewards_total = self.mining_points.checked_mul(
+        Ok(())
+    }
+    
+    
+    // Splits the rewards of the staked Character into the mined, looted and clamped rewards
+    fn mine_rewards(&self, mine_pool_strength: u64, loot_pool_strength: u64, mine_accrued_rewards: u128) -> Result<(u64, u64, u64)> {
+    
+        let accrued_rewards_total = self.staked_weight.checked_mul(
             u64::try_from(
                 mine_accrued_rewards.checked_sub(self.staked_peg).ok_or(QstakingErrors::InvalidComputation).unwrap()
             ).unwrap()
         ).ok_or(QstakingErrors::InvalidComputation).unwrap();
+        let accrued_rewards_total = accrued_rewards_total.checked_add(self.boost_rewards).ok_or(QstakingErrors::InvalidComputation).unwrap();
         
         let looted_rewards_nom = accrued_rewards_total.checked_mul(loot_pool_strength).ok_or(QstakingErrors::InvalidComputation).unwrap();
         let looted_rewards_denom = loot_pool_strength.checked_add(mine_pool_strength).ok_or(QstakingErrors::InvalidComputation).unwrap();
         let looted_rewards = looted_rewards_nom.checked_div(looted_rewards_denom).ok_or(QstakingErrors::InvalidComputation).unwrap();
         
-        let accrued_rewards = std::cmp::min(
-            accrued_rewards_total.checked_sub(looted_rewards).ok_or(QstakingErrors::InvalidComputation).unwrap(),
-            self.mining_capacity
-        );
+        let mined_rewards = accrued_rewards_total.checked_sub(looted_rewards).ok_or(QstakingErrors::InvalidComputation).unwrap();
+        let accrued_rewards = std::cmp::min(mined_rewards, self.mining_capacity);
+        let clamped_rewards = mined_rewards.checked_sub(accrued_rewards).ok_or(QstakingErrors::InvalidComputation).unwrap();
+        
+        Ok((accrued_rewards, looted_rewards, clamped_rewards))
+    }
+    
+    
+    pub fn unstake_mine(&mut self, timestamp: i64, cooldown: u64, mine_pool_strength: u64, loot_pool_strength: u64, mine_accrued_rewards: u128) -> Result<(u64, u64, u64)> {
+    
+        let (accrued_rewards, looted_rewards, clamped_rewards) = self.mine_rewards(mine_pool_strength, loot_pool_strength, mine_accrued_rewards)?;
         
         
         self.mine_cooldown_timestamp = timestamp.checked_add(
@@ -129,24 +394,124 @@ impl Character {
         self.mining_rewards = self.mining_rewards.checked_add(
             u128::try_from(accrued_rewards).unwrap()
         ).ok_or(QstakingErrors::InvalidComputation).unwrap();
+        self.season_mining_rewards = self.season_mining_rewards.checked_add(
+            u128::try_from(accrued_rewards).unwrap()
+        ).ok_or(QstakingErrors::InvalidComputation).unwrap();
         
         //TODO: Add leveling and mining_points increase logic
-        self.experience = self.experience.checked_add(
-            u64::try_from(
-                timestamp.checked_sub(self.staked_timestamp).ok_or(QstakingErrors::InvalidComputation).unwrap()
-            ).unwrap()
-        ).ok_or(QstakingErrors::InvalidComputation).unwrap();
+        self.add_experience(timestamp)?;
+        
+        self.clear_stake();
+        
+        return Ok((accrued_rewards, looted_rewards, clamped_rewards));
+    }
+    
+    
+    // Adds the staked seconds to the lifetime and season experience
+    fn add_experience(&mut self, timestamp: i64) -> Result<()> {
+    
+        let experience = u64::try_from(
+            timestamp.checked_sub(self.staked_timestamp).ok_or(QstakingErrors::InvalidComputation).unwrap()
+        ).unwrap();
+        
+        self.experience = self.experience.checked_add(experience).ok_or(QstakingErrors::InvalidComputation).unwrap();
+        self.season_experience = self.season_experience.checked_add(experience).ok_or(QstakingErrors::InvalidComputation).unwrap();
         
+        Ok(())
+    }
+    
+    
+    // Resets the season counters when the Character is staked for the first time in a new season,
+    // the rewards and experience of a stake count towards the season it was staked in
+    fn enter_season(&mut self, season: u64) -> Result<()> {
+    
+        if self.season != season {
+            self.season = season;
+            self.season_mining_rewards = 0;
+            self.season_looting_rewards = 0;
+            self.season_experience = 0;
+        }
+        
+        Ok(())
+    }
+    
+    
+    // Adds the guild bonus accrued by its Guild since the Character was staked to the rewards of the stake
+    pub fn add_guild_bonus(&mut self, bonus_accrued: u128) -> Result<()> {
+    
+        let guild_bonus = u128::from(self.staked_weight).checked_mul(
+            bonus_accrued.checked_sub(self.guild_bonus_peg).ok_or(QstakingErrors::InvalidComputation).unwrap()
+        ).ok_or(QstakingErrors::InvalidComputation).unwrap() / 10000;
+        
+        self.boost_rewards = self.boost_rewards.checked_add(u64::try_from(guild_bonus).unwrap()).ok_or(QstakingErrors::InvalidComputation).unwrap();
+        self.guild_bonus_peg = 0;
+        
+        Ok(())
+    }
+    
+    
+    // Clears the staking state of the Character
+    fn clear_stake(&mut self) {
+    
         self.staked = 0;
+        self.custody = 0;
+        self.vault_owner = Pubkey::default();
+        self.nft_account = Pubkey::default();
         self.staked_timestamp = 0;
+        self.lock_tier = 0;
+        self.lock_end_timestamp = 0;
+        self.staked_weight = 0;
+        self.boost_rewards = 0;
+    }
+    
+    
+    // Unstakes a denylisted Character from the Mine without crediting its rewards or experience
+    pub fn unstake_frozen_mine(&mut self, mine_pool_strength: u64, loot_pool_strength: u64, mine_accrued_rewards: u128) -> Result<(u64, u64, u64)> {
+    
+        let rewards = self.mine_rewards(mine_pool_strength, loot_pool_strength, mine_accrued_rewards)?;
+        self.clear_stake();
         
-        return Ok((accrued_rewards, looted_rewards));
+        Ok(rewards)
     }
     
     
-    pub fn stake_loot(&mut self, timestamp: i64, accrued_rewards: u128) -> Result<()> {
+    // Unstakes a denylisted Character from the Loot without crediting its rewards or experience
+    pub fn unstake_frozen_loot(&mut self, loot_accrued_rewards: u128) -> Result<u64> {
+    
+        let accrued_rewards = self.loot_rewards(loot_accrued_rewards)?;
+        self.clear_stake();
         
+        Ok(accrued_rewards)
+    }
+    
+    
+    pub fn withhold_rewards(&mut self, mining_rewards: u64, looting_rewards: u64) -> Result<()> {
+    
+        self.withheld_mining_rewards = self.withheld_mining_rewards.checked_add(mining_rewards).ok_or(QstakingErrors::InvalidComputation).unwrap();
+        self.withheld_looting_rewards = self.withheld_looting_rewards.checked_add(looting_rewards).ok_or(QstakingErrors::InvalidComputation).unwrap();
+        
+        Ok(())
+    }
+    
+    
+    // Clears the withheld rewards and returns the mined and looted amounts
+    pub fn take_withheld_rewards(&mut self) -> Result<(u64, u64)> {
+    
+        let withheld_rewards = (self.withheld_mining_rewards, self.withheld_looting_rewards);
+        self.withheld_mining_rewards = 0;
+        self.withheld_looting_rewards = 0;
+        
+        Ok(withheld_rewards)
+    }
+    
+    
+    pub fn stake_loot(&mut self, timestamp: i64, season: u64, accrued_rewards: u128, custody: u8, nft_account: &Pubkey, vault_owner: &Pubkey) -> Result<()> {
+    
+        self.enter_season(season)?;
         self.staked = 2;
+        self.custody = custody;
+        self.vault_owner = *vault_owner;
+        self.nft_account = *nft_account;
         self.staked_peg = accrued_rewards;
         self.staked_timestamp = timestamp;
         
@@ -154,14 +519,23 @@ impl Character {
     }
     
     
-    pub fn unstake_loot(&mut self, timestamp: i64, cooldown: u64, loot_accrued_rewards: u128) -> Result<u64> {
-        
+    // Looted rewards of the Character staked in the Loot
+    fn loot_rewards(&self, loot_accrued_rewards: u128) -> Result<u64> {
+    
         let accrued_rewards = self.looting_points.checked_mul(
             u64::try_from(
                 loot_accrued_rewards.checked_sub(self.staked_peg).ok_or(QstakingErrors::InvalidComputation).unwrap()
             ).unwrap()
         ).ok_or(QstakingErrors::InvalidComputation).unwrap();
         
+        Ok(accrued_rewards)
+    }
+    
+    
+    pub fn unstake_loot(&mut self, timestamp: i64, cooldown: u64, loot_accrued_rewards: u128) -> Result<u64> {
+    
+        let accrued_rewards = self.loot_rewards(loot_accrued_rewards)?;
+        
         
         self.loot_cooldown_timestamp = timestamp.checked_add(
             i64::try_from(cooldown).unwrap()
@@ -170,17 +544,251 @@ impl Character {
         self.looting_rewards = self.looting_rewards.checked_add(
             u128::try_from(accrued_rewards).unwrap()
         ).ok_or(QstakingErrors::InvalidComputation).unwrap();
+        self.season_looting_rewards = self.season_looting_rewards.checked_add(
+            u128::try_from(accrued_rewards).unwrap()
+        ).ok_or(QstakingErrors::InvalidComputation).unwrap();
         
         //TODO: Add leveling and mining_points increase logic
-        self.experience = self.experience.checked_add(
-            u64::try_from(
-                timestamp.checked_sub(self.staked_timestamp).ok_or(QstakingErrors::InvalidComputation).unwrap()
-            ).unwrap()
-        ).ok_or(QstakingErrors::InvalidComputation).unwrap();
+        self.add_experience(timestamp)?;
         
-        self.staked = 0;
-        self.staked_timestamp = 0;
+        self.clear_stake();
         
         return Ok(accrued_rewards);
     }
+    
+    
+    pub fn stat(&self, stat: u8) -> Result<u64> {
+    
+        match stat {
+            0 => Ok(self.mining_points),
+            1 => Ok(self.mining_capacity),
+            2 => Ok(self.looting_points),
+            _ => Err(error!(QstakingErrors::InvalidGearCollection)),
+        }
+    }
+    
+    
+    fn stat_mut(&mut self, stat: u8) -> Result<&mut u64> {
+    
+        match stat {
+            0 => Ok(&mut self.mining_points),
+            1 => Ok(&mut self.mining_capacity),
+            2 => Ok(&mut self.looting_points),
+            _ => Err(error!(QstakingErrors::InvalidGearCollection)),
+        }
+    }
+    
+    
+    // Attribute raised by upgrades: 0 mining points, 1 mining capacity, 2 looting points, 3 bounty points
+    fn attribute_mut(&mut self, attribute: u8) -> Result<&mut u64> {
+    
+        match attribute {
+            0 => Ok(&mut self.mining_points),
+            1 => Ok(&mut self.mining_capacity),
+            2 => Ok(&mut self.looting_points),
+            3 => Ok(&mut self.bounty_points),
+            _ => Err(error!(QstakingErrors::InvalidUpgrade)),
+        }
+    }
+    
+    
+    // Raises the attribute by the upgrade points and moves it to the next upgrade level
+    pub fn upgrade(&mut self, attribute: u8, points: u64) -> Result<()> {
+    
+        let value = self.attribute_mut(attribute)?;
+        *value = value.checked_add(points).ok_or(QstakingErrors::InvalidComputation).unwrap();
+        
+        let index = usize::from(attribute);
+        self.upgrade_levels[index] = self.upgrade_levels[index].checked_add(1).ok_or(QstakingErrors::InvalidComputation).unwrap();
+        self.allocated_points[index] = self.allocated_points[index].checked_add(points).ok_or(QstakingErrors::InvalidComputation).unwrap();
+        
+        Ok(())
+    }
+    
+    
+    // Fails while the cooldown after the last respec of the Character runs
+    pub fn assert_respec_cooldown(&self, timestamp: i64, cooldown: u64) -> Result<()> {
+    
+        if self.respec_timestamp > 0 {
+            let cooldown_end = self.respec_timestamp.checked_add(i64::try_from(cooldown).unwrap()).ok_or(QstakingErrors::InvalidComputation).unwrap();
+            require!(
+                timestamp >= cooldown_end,
+                QstakingErrors::RespecCooldown
+            );
+        }
+        
+        Ok(())
+    }
+    
+    
+    // Resets the respec attributes to their base values and returns their allocated points as free points
+    pub fn respec(&mut self, timestamp: i64, cooldown: u64) -> Result<()> {
+    
+        self.assert_respec_cooldown(timestamp, cooldown)?;
+        
+        for attribute in Self::RESPEC_ATTRIBUTES {
+            let index = usize::from(attribute);
+            let points = self.allocated_points[index];
+            
+            let value = self.attribute_mut(attribute)?;
+            *value = value.checked_sub(points).ok_or(QstakingErrors::InvalidComputation).unwrap();
+            
+            self.allocated_points[index] = 0;
+            self.free_points = self.free_points.checked_add(points).ok_or(QstakingErrors::InvalidComputation).unwrap();
+        }
+        
+        self.respec_timestamp = timestamp;
+        
+        Ok(())
+    }
+    
+    
+    // Allocates free points to one of the respec attributes
+    pub fn allocate(&mut self, attribute: u8, points: u64) -> Result<()> {
+    
+        require!(
+            Self::RESPEC_ATTRIBUTES.contains(&attribute) && points > 0 && points <= self.free_points,
+            QstakingErrors::InvalidUpgrade
+        );
+        
+        let value = self.attribute_mut(attribute)?;
+        *value = value.checked_add(points).ok_or(QstakingErrors::InvalidComputation).unwrap();
+        
+        let index = usize::from(attribute);
+        self.allocated_points[index] = self.allocated_points[index].checked_add(points).ok_or(QstakingErrors::InvalidComputation).unwrap();
+        self.free_points = self.free_points.checked_sub(points).ok_or(QstakingErrors::InvalidComputation).unwrap();
+        
+        Ok(())
+    }
+    
+    
+    pub fn set_referrer(&mut self, timestamp: i64, referrer: &Pubkey) -> Result<()> {
+    
+        self.referrer = *referrer;
+        self.referral_timestamp = timestamp;
+        
+        Ok(())
+    }
+    
+    
+    pub fn in_referral_window(&self, timestamp: i64, referral_window: u64) -> Result<bool> {
+    
+        if self.referrer == Pubkey::default() {
+            return Ok(false);
+        }
+        
+        let referral_end = self.referral_timestamp.checked_add(i64::try_from(referral_window).unwrap()).ok_or(QstakingErrors::InvalidComputation).unwrap();
+        
+        Ok(timestamp < referral_end)
+    }
+    
+    
+    pub fn add_referral_rewards(&mut self, amount: u64) -> Result<()> {
+    
+        self.referral_earned = self.referral_earned.checked_add(amount).ok_or(QstakingErrors::InvalidComputation).unwrap();
+        
+        Ok(())
+    }
+    
+    
+    pub fn equipped_gear(&self) -> usize {
+        self.gear_mints.iter().filter(|gear_mint| **gear_mint != Pubkey::default()).count()
+    }
+    
+    
+    pub fn equip(&mut self, gear_mint: &Pubkey, stat: u8, bonus: u64) -> Result<()> {
+    
+        let slot = self.gear_mints.iter().position(|mint| *mint == Pubkey::default()).ok_or(QstakingErrors::GearSlotsFull)?;
+        self.gear_mints[slot] = *gear_mint;
+        
+        let value = self.stat_mut(stat)?;
+        *value = value.checked_add(bonus).ok_or(QstakingErrors::InvalidComputation).unwrap();
+        
+        Ok(())
+    }
+    
+    
+    pub fn unequip(&mut self, gear_mint: &Pubkey, stat: u8, bonus: u64) -> Result<()> {
+    
+        let slot = self.gear_mints.iter().position(|mint| mint == gear_mint).ok_or(QstakingErrors::InvalidGearCollection)?;
+        self.gear_mints[slot] = Pubkey::default();
+        
+        let value = self.stat_mut(stat)?;
+        *value = value.checked_sub(bonus).ok_or(QstakingErrors::InvalidComputation).unwrap();
+        
+        Ok(())
+    }
+    
+    
+    pub fn archive(&self, mint: &Pubkey, timestamp: i64) -> Result<()> {
+    
+        emit!(CharacterArchived {
+            mint: *mint,
+            level: self.level,
+            tier: self.tier,
+            experience: self.experience,
+            mining_rewards: self.mining_rewards,
+            looting_rewards: self.looting_rewards,
+            bounty_rewards: self.bounty_rewards,
+            timestamp,
+        });
+        
+        Ok(())
+    }
+    
+    
+    // Reads a Character account created before versioning at version 0, None if the account is versioned
+    pub fn read_legacy(data: &[u8]) -> Result<Option<Self>> {
+    
+        if data.get(Self::VERSION_OFFSET).copied().unwrap_or(0) != 0 {
+            return Ok(None);
+        }
+        
+        require!(
+            data.len() >= 8 && data[..8] == Self::discriminator(),
+            ErrorCode::AccountDiscriminatorMismatch
+        );
+        let legacy = CharacterV0::deserialize(&mut &data[8..])?;
+        
+        // Fields added since the legacy layout start from their zero values
+        let mut character = Self::deserialize(&mut &vec![0; Self::LEN][..])?;
+        character.bump = legacy.bump;
+        character.level = legacy.level;
+        character.experience = legacy.experience;
+        character.mining_points = legacy.mining_points;
+        character.mining_capacity = legacy.mining_capacity;
+        character.looting_points = legacy.looting_points;
+        character.looting_survival = legacy.looting_survival;
+        character.bounty_points = legacy.bounty_points;
+        character.bounty_bullets = legacy.bounty_bullets;
+        character.mining_rewards = legacy.mining_rewards;
+        character.looting_rewards = legacy.looting_rewards;
+        character.bounty_rewards = legacy.bounty_rewards;
+        character.staked = legacy.staked;
+        character.staked_peg = legacy.staked_peg;
+        character.staked_timestamp = legacy.staked_timestamp;
+        character.mine_cooldown_timestamp = legacy.mine_cooldown_timestamp;
+        character.loot_cooldown_timestamp = legacy.loot_cooldown_timestamp;
+        character.bounty_bulltes_timestamp = legacy.bounty_bulltes_timestamp;
+        
+        Ok(Some(character))
+    }
+    
+    
+    pub fn migrate(&mut self) -> Result<()> {
+    
+        require!(
+            self.version < Self::VERSION,
+            QstakingErrors::AccountAlreadyMigrated
+        );
+        
+        // Characters staked before versioning keep their mining points as staked weight
+        if self.staked == 1 {
+            self.staked_weight = self.mining_points;
+        }
+        
+        self.version = Self::VERSION;
+        
+        Ok(())
+    }
 }
@@ -0,0 +1,48 @@
+use anchor_lang::prelude::*;
+
+
+#[account]
+pub struct Denylist {
+
+    // Bump used in generating the Denylist account
+    pub bump: u8,
+    
+    // Mint of the denylisted NFT
+    pub mint: Pubkey,
+    
+    // Timestamp when the mint was denylisted
+    pub timestamp: i64,
+    
+    // Layout version of the account
+    pub version: u8,
+    
+    // Reserved space for fields added in later versions
+    pub reserved: [u8; 32],
+}
+
+
+impl Denylist {
+
+    // Current layout version of the Denylist account
+    pub const VERSION: u8 = 1;
+    
+    // Serialized size of the Denylist account without the discriminator
+    pub const LEN: usize = 1 + 32 + 8 + 1 + 32;
+    
+    
+    pub fn initialize(&mut self, bump: u8, mint: &Pubkey, timestamp: i64) -> Result<()> {
+    
+        self.bump = bump;
+        self.mint = *mint;
+        self.timestamp = timestamp;
+        self.version = Self::VERSION;
+        self.reserved = [0; 32];
+        
+        Ok(())
+    }
+    
+    
+    pub fn is_denylisted(program_account: &Pubkey, denylist: &AccountInfo) -> bool {
+        denylist.owner == program_account && !denylist.data_is_empty()
+    }
+}
@@ -0,0 +1,164 @@
+use mpl_token_metadata as metaplex;
+use anchor_lang::prelude::*;
+
+use crate::errors::*;
+use crate::metadata;
+use crate::states::Character;
+
+
+#[account]
+pub struct GearCollection {
+
+    // Bump used in generating the GearCollection account
+    pub bump: u8,
+    
+    // Verified creator identifying the gear NFTs of the collection
+    pub creator: Pubkey,
+    
+    // Flag controlling if gear of the collection can currently be equipped
+    pub enabled: bool,
+    
+    // Character stat raised by the gear: 0 mining points, 1 mining capacity, 2 looting points
+    pub stat: u8,
+    
+    // Bonus kind: 0 flat amount, 1 percentage of the stat in basis points
+    pub bonus_kind: u8,
+    
+    // Flat bonus or percentage bonus in basis points
+    pub bonus: u64,
+    
+    // Layout version of the account
+    pub version: u8,
+    
+    // Reserved space for fields added in later versions
+    pub reserved: [u8; 32],
+}
+
+
+#[account]
+pub struct Equipment {
+
+    // Bump used in generating the Equipment account
+    pub bump: u8,
+    
+    // Mint of the Character NFT the gear is attached to
+    pub character_mint: Pubkey,
+    
+    // Mint of the gear NFT
+    pub gear_mint: Pubkey,
+    
+    // Token account holding the frozen gear NFT
+    pub gear_account: Pubkey,
+    
+    // Owner of the gear NFT who can detach it
+    pub owner: Pubkey,
+    
+    // Character stat raised by the gear
+    pub stat: u8,
+    
+    // Amount added to the stat when the gear was equipped
+    pub bonus: u64,
+    
+    // Layout version of the account
+    pub version: u8,
+    
+    // Reserved space for fields added in later versions
+    pub reserved: [u8; 32],
+}
+
+
+impl GearCollection {
+
+    // Current layout version of the GearCollection account
+    pub const VERSION: u8 = 1;
+    
+    // Serialized size of the GearCollection account without the discriminator
+    pub const LEN: usize = 1 + 32 + 1 + 1 + 1 + 8 + 1 + 32;
+    
+    
+    pub fn initialize(&mut self, bump: u8, creator: &Pubkey, stat: u8, bonus_kind: u8, bonus: u64) -> Result<()> {
+    
+        require!(
+            stat < 3 && bonus_kind < 2 && bonus > 0,
+            QstakingErrors::InvalidGearCollection
+        );
+        
+        self.bump = bump;
+        self.creator = *creator;
+        self.enabled = true;
+        self.stat = stat;
+        self.bonus_kind = bonus_kind;
+        self.bonus = bonus;
+        self.version = Self::VERSION;
+        self.reserved = [0; 32];
+        
+        Ok(())
+    }
+    
+    
+    pub fn verify(&self, mint: &Pubkey, mint_metadata: &AccountInfo) -> Result<()> {
+    
+        require!(
+            self.enabled,
+            QstakingErrors::InvalidGearCollection
+        );
+        
+        metadata::assert_metadata_account(mint, mint_metadata)?;
+        
+        // Programmable NFTs cannot be frozen with a delegate outside of Token Metadata
+        require!(
+            !metadata::is_programmable(mint_metadata)?,
+            QstakingErrors::ProgrammableGear
+        );
+        
+        let metadata: metaplex::state::Metadata = metaplex::state::TokenMetadataAccount::from_account_info(mint_metadata)?;
+        let creators = metadata.data.creators.unwrap_or_default();
+        require!(
+            creators.iter().any(|creator| creator.verified && creator.address == self.creator),
+            QstakingErrors::InvalidGearCollection
+        );
+        
+        Ok(())
+    }
+    
+    
+    // Amount the gear adds to the Character stat
+    pub fn bonus_for(&self, character: &Character) -> Result<u64> {
+    
+        if self.bonus_kind == 0 {
+            return Ok(self.bonus);
+        }
+        
+        let base = character.stat(self.stat)?;
+        let bonus = u128::from(base).checked_mul(u128::from(self.bonus)).ok_or(QstakingErrors::InvalidComputation).unwrap() / 10000;
+        
+        Ok(u64::try_from(bonus).unwrap())
+    }
+}
+
+
+impl Equipment {
+
+    // Current layout version of the Equipment account
+    pub const VERSION: u8 = 1;
+    
+    // Serialized size of the Equipment account without the discriminator
+    pub const LEN: usize = 1 + 32 + 32 + 32 + 32 + 1 + 8 + 1 + 32;
+    
+    
+    #[allow(clippy::too_many_arguments)]
+    pub fn initialize(&mut self, bump: u8, character_mint: &Pubkey, gear_mint: &Pubkey, gear_account: &Pubkey, owner: &Pubkey, stat: u8, bonus: u64) -> Result<()> {
+    
+        self.bump = bump;
+        self.character_mint = *character_mint;
+        self.gear_mint = *gear_mint;
+        self.gear_account = *gear_account;
+        self.owner = *owner;
+        self.stat = stat;
+        self.bonus = bonus;
+        self.version = Self::VERSION;
+        self.reserved = [0; 32];
+        
+        Ok(())
+    }
+}
@@ -0,0 +1,122 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::*;
+
+
+#[account]
+pub struct Guild {
+
+    // Bump used in generating the Guild account
+    pub bump: u8,
+    
+    // Leader of the Guild controlling its treasury
+    pub leader: Pubkey,
+    
+    // Token account of the Guild receiving the cut of the members' mined rewards
+    pub treasury: Pubkey,
+    
+    // Cut of the members' mined rewards paid into the treasury in basis points
+    pub cut_bps: u16,
+    
+    // Number of Characters in the Guild
+    pub members: u32,
+    
+    // Number of members currently staked in the Mine
+    pub staked_members: u32,
+    
+    // Layout version of the account
+    pub version: u8,
+    
+    // Guild bonus in basis points times the Mine accrued rewards per point, summed over the time members were staked
+    pub bonus_accrued: u128,
+    
+    // Mine accrued rewards per point up to which the guild bonus was accrued
+    pub bonus_checkpoint: u128,
+    
+    // Reserved space for fields added in later versions
+    pub reserved: [u8; 32],
+}
+
+
+impl Guild {
+
+    // Current layout version of the Guild account
+    pub const VERSION: u8 = 1;
+    
+    // Serialized size of the Guild account without the discriminator
+    pub const LEN: usize = 1 + 32 + 32 + 2 + 4 + 4 + 1 + 16 + 16 + 32;
+    
+    
+    pub fn initialize(&mut self, bump: u8, leader: &Pubkey, treasury: &Pubkey, cut_bps: u16) -> Result<()> {
+    
+        self.bump = bump;
+        self.leader = *leader;
+        self.treasury = *treasury;
+        self.members = 0;
+        self.staked_members = 0;
+        self.version = Self::VERSION;
+        self.bonus_accrued = 0;
+        self.bonus_checkpoint = 0;
+        self.reserved = [0; 32];
+        
+        self.set_cut(cut_bps)
+    }
+    
+    
+    pub fn set_cut(&mut self, cut_bps: u16) -> Result<()> {
+    
+        require!(
+            cut_bps <= 10000,
+            QstakingErrors::InvalidGuild
+        );
+        
+        self.cut_bps = cut_bps;
+        
+        Ok(())
+    }
+    
+    
+    pub fn add_member(&mut self) -> Result<()> {
+    
+        self.members = self.members.checked_add(1).ok_or(QstakingErrors::InvalidComputation).unwrap();
+        
+        Ok(())
+    }
+    
+    
+    pub fn remove_member(&mut self) -> Result<()> {
+    
+        self.members = self.members.checked_sub(1).ok_or(QstakingErrors::InvalidComputation).unwrap();
+        
+        Ok(())
+    }
+    
+    
+    // Accrues the guild bonus of the current staked members up to the Mine accrued rewards per point
+    pub fn accrue_bonus(&mut self, accrued_rewards: u128, bonus_bps: u64) -> Result<()> {
+    
+        let rewards = accrued_rewards.checked_sub(self.bonus_checkpoint).ok_or(QstakingErrors::InvalidComputation).unwrap();
+        self.bonus_accrued = self.bonus_accrued.checked_add(
+            rewards.checked_mul(u128::from(bonus_bps)).ok_or(QstakingErrors::InvalidComputation).unwrap()
+        ).ok_or(QstakingErrors::InvalidComputation).unwrap();
+        self.bonus_checkpoint = accrued_rewards;
+        
+        Ok(())
+    }
+    
+    
+    pub fn add_staked_member(&mut self) -> Result<()> {
+    
+        self.staked_members = self.staked_members.checked_add(1).ok_or(QstakingErrors::InvalidComputation).unwrap();
+        
+        Ok(())
+    }
+    
+    
+    pub fn remove_staked_member(&mut self) -> Result<()> {
+    
+        self.staked_members = self.staked_members.checked_sub(1).ok_or(QstakingErrors::InvalidComputation).unwrap();
+        
+        Ok(())
+    }
+}
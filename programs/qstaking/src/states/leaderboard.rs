@@ -0,0 +1,139 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::*;
+use crate::states::Character;
+
+
+// Number of Characters ranked on each board
+pub const LEADERBOARD_SIZE: usize = 32;
+
+
+#[account(zero_copy)]
+pub struct Leaderboard {
+
+    // Season of the rankings, increased on every reset
+    pub season: u64,
+    
+    // Top Characters by season mining rewards in descending order
+    pub mining_rewards: [LeaderboardEntry; LEADERBOARD_SIZE],
+    
+    // Top Characters by season looting rewards in descending order
+    pub looting_rewards: [LeaderboardEntry; LEADERBOARD_SIZE],
+    
+    // Top Characters by season experience in descending order
+    pub experience: [LeaderboardEntry; LEADERBOARD_SIZE],
+    
+    // Bump used in generating the Leaderboard account
+    pub bump: u8,
+    
+    // Layout version of the account
+    pub version: u8,
+    
+    // Reserved space for fields added in later versions
+    pub reserved: [u8; 30],
+}
+
+
+#[zero_copy]
+#[derive(Default)]
+pub struct LeaderboardEntry {
+
+    // Mint of the ranked Character NFT, the default key for empty entries
+    pub nft_mint: Pubkey,
+    
+    // Ranked value of the Character
+    pub value: u64,
+}
+
+
+impl Leaderboard {
+
+    // Current layout version of the Leaderboard account
+    pub const VERSION: u8 = 1;
+    
+    // Size of the Leaderboard account without the discriminator
+    pub const LEN: usize = 8 + 3 * LEADERBOARD_SIZE * (32 + 8) + 1 + 1 + 30;
+    
+    
+    pub fn initialize(&mut self, bump: u8) -> Result<()> {
+    
+        self.season = 0;
+        self.reset();
+        self.bump = bump;
+        self.version = Self::VERSION;
+        self.reserved = [0; 30];
+        
+        Ok(())
+    }
+    
+    
+    pub fn start_season(&mut self, season: u64) -> Result<()> {
+    
+        require!(
+            season > self.season,
+            QstakingErrors::InvalidSeason
+        );
+        
+        self.season = season;
+        self.reset();
+        
+        Ok(())
+    }
+    
+    
+    fn reset(&mut self) {
+        self.mining_rewards = [LeaderboardEntry::default(); LEADERBOARD_SIZE];
+        self.looting_rewards = [LeaderboardEntry::default(); LEADERBOARD_SIZE];
+        self.experience = [LeaderboardEntry::default(); LEADERBOARD_SIZE];
+    }
+    
+    
+    // Ranks the season counters of the Character, which only count on the board of the season they were earned in
+    pub fn update(&mut self, nft_mint: &Pubkey, character: &Character) {
+    
+        if character.season != self.season {
+            return;
+        }
+        
+        Self::rank(&mut self.mining_rewards, nft_mint, u64::try_from(character.season_mining_rewards).unwrap_or(u64::MAX));
+        Self::rank(&mut self.looting_rewards, nft_mint, u64::try_from(character.season_looting_rewards).unwrap_or(u64::MAX));
+        Self::rank(&mut self.experience, nft_mint, character.season_experience);
+    }
+    
+    
+    // Moves the NFT to its position on the board, dropping the lowest entry when the board is full
+    fn rank(board: &mut [LeaderboardEntry; LEADERBOARD_SIZE], nft_mint: &Pubkey, value: u64) {
+    
+        if let Some(index) = board.iter().position(|entry| entry.nft_mint == *nft_mint) {
+            board.copy_within(index + 1.., index);
+            board[LEADERBOARD_SIZE - 1] = LeaderboardEntry::default();
+        }
+        
+        if value == 0 {
+            return;
+        }
+        
+        if let Some(index) = board.iter().position(|entry| entry.nft_mint == Pubkey::default() || entry.value < value) {
+            board.copy_within(index..LEADERBOARD_SIZE - 1, index + 1);
+            board[index] = LeaderboardEntry {
+                nft_mint: *nft_mint,
+                value,
+            };
+        }
+    }
+    
+    
+    // Updates the rankings of the NFT when the writable Leaderboard is passed among the remaining accounts
+    pub fn record(program_id: &Pubkey, remaining_accounts: &[AccountInfo], nft_mint: &Pubkey, character: &Character) -> Result<()> {
+    
+        let leaderboard = remaining_accounts.iter()
+            .filter(|account| account.owner == program_id && account.is_writable)
+            .find_map(|account| AccountLoader::<Leaderboard>::try_from(account).ok());
+        
+        if let Some(leaderboard) = leaderboard {
+            leaderboard.load_mut()?.update(nft_mint, character);
+        }
+        
+        Ok(())
+    }
+}
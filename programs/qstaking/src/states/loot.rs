@@ -1,4 +1,5 @@
 use anchor_lang::prelude::*;
+use anchor_lang::Discriminator;
 
 use crate::errors::*;
 
@@ -12,6 +13,74 @@ pub struct Loot {
     // Lock controling if NFTs can be staked into the pool
     pub locked: bool,
     
+    // Custody mode of newly staked NFTs: 0 frozen in the user's wallet, 1 escrowed in a pool vault
+    pub custody: u8,
+    
+    // Loot manager
+    pub manager: Pubkey,
+    
+    // Total reward fund
+    pub fund: u128,
+    
+    // Duration ower which the reward fund will be distributed in units of s
+    pub duration: u64,
+    
+    // Mining reward rate in units of Reward Tokens / mining point / s
+    pub rate: u64,
+    
+    // Base price for staking in the Mine pool
+    pub price: u64,
+    
+    // Cooldown period for re-staking in Mine pool
+    pub cooldown: u64,
+    
+    // Number of staked NFTs
+    pub staked_characters: u16,
+    
+    // Total number of staked mining points
+    pub staked_points: u64,
+    
+    // Comulative accrued rewards per mining point
+    pub accrued_rewards: u128,
+    
+    // Timestamp the of the last comulative accrued reward update
+    pub accrued_timestamp: i64,
+    
+    // Layout version of the account
+    pub version: u8,
+    
+    // Comulative amount of Reward Tokens looted from the Mine
+    pub total_received: u64,
+    
+    // Comulative amount of Reward Tokens paid to looters
+    pub total_paid: u64,
+    
+    // Total number of stake sessions
+    pub total_sessions: u64,
+    
+    // Total number of seconds NFTs were staked in the Loot
+    pub total_staked_seconds: u64,
+    
+    // Current season of the game, zero before the first season
+    pub season: u64,
+    
+    // Flag if the pool was locked by the end of the season and reopens with the next season
+    pub season_locked: bool,
+    
+    // Reserved space for fields added in later versions
+    pub reserved: [u8; 31],
+}
+
+// Layout of the Loot account before versioning, kept to migrate legacy accounts
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct LootV0 {
+
+    // Bump used in generating the Loot account
+    pub bump: u8,
+    
+    // Lock controling if NFTs can be staked into the pool
+    pub locked: bool,
+    
     // Loot manager
     pub manager: Pubkey,
     
@@ -45,10 +114,21 @@ pub struct Loot {
 
 impl Loot {
 
+    // Current layout version of the Loot account
+    pub const VERSION: u8 = 1;
+    
+    // Offset of the version byte in the account data, the byte is zero in accounts created before versioning
+    pub const VERSION_OFFSET: usize = 8 + 1 + 1 + 1 + 32 + 16 + 8 + 8 + 8 + 8 + 2 + 8 + 16 + 8;
+    
+    // Serialized size of the Loot account without the discriminator
+    pub const LEN: usize = 1 + 1 + 1 + 32 + 16 + 8 + 8 + 8 + 8 + 2 + 8 + 16 + 8 + 1 + 8 + 8 + 8 + 8 + 8 + 1 + 31;
+    
+    
     pub fn initialize(&mut self, bump: u8, manager: &Pubkey) -> Result<()> {
     
         self.bump = bump;
         self.locked = true;
+        self.custody = 0;
         self.manager = *manager;
         self.fund = 0;
         self.duration = 0;
@@ -59,12 +139,26 @@ impl Loot {
         self.staked_points = 0;
         self.accrued_rewards = 0;
         self.accrued_timestamp = 0;
+        self.version = Self::VERSION;
+        self.total_received = 0;
+        self.total_paid = 0;
+        self.total_sessions = 0;
+        self.total_staked_seconds = 0;
+        self.season = 0;
+        self.season_locked = false;
+        self.reserved = [0; 31];
         
         Ok(())
     }
     
-
+    
     pub fn update_accrued_rewards(&mut self, timestamp: i64) -> Result<()> {
+    
+        // Nothing accrues while no Character is staked in the Loot
+        if self.staked_points == 0 {
+            self.accrued_timestamp = timestamp;
+            return Ok(());
+        }
         
         let timestamp_delta_signed: i64 = timestamp.checked_sub(self.accrued_timestamp).ok_or(QstakingErrors::InvalidComputation).unwrap();
         let timestamp_delta: u128 = u128::try_from(timestamp_delta_signed).unwrap();
@@ -89,6 +183,7 @@ impl Loot {
         self.fund = self.fund.checked_add(
             u128::try_from(amount).unwrap()
         ).ok_or(QstakingErrors::InvalidComputation).unwrap();
+        self.total_received = self.total_received.checked_add(amount).ok_or(QstakingErrors::InvalidComputation).unwrap();
         
         self.recompute_rate()?;
         
@@ -101,6 +196,7 @@ impl Loot {
         
         self.staked_characters = self.staked_characters.checked_add(1).ok_or(QstakingErrors::InvalidComputation).unwrap();
         self.staked_points = self.staked_points.checked_add(points).ok_or(QstakingErrors::InvalidComputation).unwrap();
+        self.total_sessions = self.total_sessions.checked_add(1).ok_or(QstakingErrors::InvalidComputation).unwrap();
         
         self.rate = u64::try_from(
             self.fund.checked_div(
@@ -114,10 +210,12 @@ impl Loot {
     }
     
     
-    pub fn remove_character(&mut self, points: u64, reward: u64) -> Result<()> {
-        
+    pub fn remove_character(&mut self, points: u64, reward: u64, staked_seconds: u64) -> Result<()> {
+    
         self.staked_characters = self.staked_characters.checked_sub(1).ok_or(QstakingErrors::InvalidComputation).unwrap();
         self.staked_points = self.staked_points.checked_sub(points).ok_or(QstakingErrors::InvalidComputation).unwrap();
+        self.total_paid = self.total_paid.checked_add(reward).ok_or(QstakingErrors::InvalidComputation).unwrap();
+        self.total_staked_seconds = self.total_staked_seconds.checked_add(staked_seconds).ok_or(QstakingErrors::InvalidComputation).unwrap();
         
         self.fund = self.fund.checked_sub(
             u128::try_from(reward).unwrap()
@@ -129,6 +227,22 @@ impl Loot {
     }
     
     
+    // Returns confiscated withheld rewards, which were counted as paid but never transferred, to the fund
+    pub fn restore_funds(&mut self, timestamp: i64, amount: u64) -> Result<()> {
+    
+        self.update_accrued_rewards(timestamp)?;
+        
+        self.fund = self.fund.checked_add(
+            u128::try_from(amount).unwrap()
+        ).ok_or(QstakingErrors::InvalidComputation).unwrap();
+        self.total_paid = self.total_paid.checked_sub(amount).ok_or(QstakingErrors::InvalidComputation).unwrap();
+        
+        self.recompute_rate()?;
+        
+        Ok(())
+    }
+    
+    
     pub fn recompute_rate(&mut self) -> Result<()> {
         if self.staked_points > 0 {
             self.rate = u64::try_from(
@@ -144,5 +258,76 @@ impl Loot {
         
         Ok(())
     }
-
+    
+    
+    pub fn start_season(&mut self, season: u64) -> Result<()> {
+    
+        require!(
+            season == self.season.checked_add(1).ok_or(QstakingErrors::InvalidComputation).unwrap(),
+            QstakingErrors::InvalidSeason
+        );
+        
+        self.season = season;
+        
+        if self.season_locked {
+            self.locked = false;
+            self.season_locked = false;
+        }
+        
+        Ok(())
+    }
+    
+    
+    pub fn end_season(&mut self) -> Result<()> {
+    
+        self.season_locked = !self.locked;
+        self.locked = true;
+        
+        Ok(())
+    }
+    
+    
+    // Reads a Loot account created before versioning at version 0, None if the account is versioned
+    pub fn read_legacy(data: &[u8]) -> Result<Option<Self>> {
+    
+        if data.get(Self::VERSION_OFFSET).copied().unwrap_or(0) != 0 {
+            return Ok(None);
+        }
+        
+        require!(
+            data.len() >= 8 && data[..8] == Self::discriminator(),
+            ErrorCode::AccountDiscriminatorMismatch
+        );
+        let legacy = LootV0::deserialize(&mut &data[8..])?;
+        
+        // Fields added since the legacy layout start from their zero values
+        let mut loot = Self::deserialize(&mut &vec![0; Self::LEN][..])?;
+        loot.bump = legacy.bump;
+        loot.locked = legacy.locked;
+        loot.manager = legacy.manager;
+        loot.fund = legacy.fund;
+        loot.duration = legacy.duration;
+        loot.rate = legacy.rate;
+        loot.price = legacy.price;
+        loot.cooldown = legacy.cooldown;
+        loot.staked_characters = legacy.staked_characters;
+        loot.staked_points = legacy.staked_points;
+        loot.accrued_rewards = legacy.accrued_rewards;
+        loot.accrued_timestamp = legacy.accrued_timestamp;
+        
+        Ok(Some(loot))
+    }
+    
+    
+    pub fn migrate(&mut self) -> Result<()> {
+    
+        require!(
+            self.version < Self::VERSION,
+            QstakingErrors::AccountAlreadyMigrated
+        );
+        
+        self.version = Self::VERSION;
+        
+        Ok(())
+    }
 }
@@ -1,6 +1,8 @@
 use anchor_lang::prelude::*;
+use anchor_lang::Discriminator;
 
 use crate::errors::*;
+use crate::states::BoostExpiry;
 
 
 #[account]
@@ -12,6 +14,137 @@ pub struct Mine {
     // Lock controling if NFTs can be staked into the pool
     pub locked: bool,
     
+    // Custody mode of newly staked NFTs: 0 frozen in the user's wallet, 1 escrowed in a pool vault
+    pub custody: u8,
+    
+    // Mine manager
+    pub manager: Pubkey,
+    
+    // Account of the mint that is being mined
+    pub mint: Pubkey,
+    
+    // Mining reward rate in units of Reward Tokens / mining point / s
+    pub rate: u64,
+    
+    // Base price for staking in the Mine pool
+    pub price: u64,
+    
+    // Cooldown period for re-staking in Mine pool
+    pub cooldown: u64,
+    
+    // Number of staked NFTs
+    pub staked_characters: u16,
+    
+    // Total number of staked mining points, multiplied by the lock tier of each Character
+    pub staked_points: u64,
+    
+    // Comulative accrued rewards per mining point
+    pub accrued_rewards: u128,
+    
+    // Timestamp the of the last comulative accrued reward update
+    pub accrued_timestamp: i64,
+    
+    // Layout version of the account
+    pub version: u8,
+    
+    // Comulative amount of Reward Tokens minted to miners
+    pub total_mined: u64,
+    
+    // Comulative amount of mined Reward Tokens diverted to the Loot
+    pub total_looted: u64,
+    
+    // Comulative amount of mined Reward Tokens forfeited to the mining capacity of the Characters
+    pub total_clamped: u64,
+    
+    // Total number of stake sessions
+    pub total_sessions: u64,
+    
+    // Total number of seconds NFTs were staked in the Mine
+    pub total_staked_seconds: u64,
+    
+    // Emission schedule: 0 flat rate, 1 rate halvings at the step timestamps, 2 exponential decay of the rate
+    pub schedule: u8,
+    
+    // Ascending timestamps at which the rate is halved, unused entries are 0 (only used by schedule 1)
+    pub step_timestamps: [i64; 8],
+    
+    // Period after which the rate decays in units of s (only used by schedule 2)
+    pub decay_interval: u64,
+    
+    // Rate decay per period in basis points (only used by schedule 2)
+    pub decay_bps: u16,
+    
+    // Rate below which the schedule does not reduce the rate any further
+    pub floor_rate: u64,
+    
+    // Timestamp of the next scheduled rate change, 0 if there is none
+    pub next_step_timestamp: i64,
+    
+    // Maximum amount of Reward Tokens the Mine can emit to miners and the Loot, 0 for no limit
+    pub emission_cap: u64,
+    
+    // Sum of staked weights times the accrued rewards peg of the staked Characters
+    pub staked_peg_total: u128,
+    
+    // Period over which mined Reward Tokens vest linearly in units of s, 0 to pay them out directly
+    pub vesting_period: u64,
+    
+    // Share of the unvested Reward Tokens forfeited to the Loot on an early exit in basis points
+    pub vesting_penalty_bps: u16,
+    
+    // Lock-up duration of the lock tiers 1 to 3 in units of s, 0 if the tier is disabled (tier 0 is no lock)
+    pub lock_durations: [u64; 3],
+    
+    // Mining points multiplier of the lock tiers 1 to 3 in basis points
+    pub lock_multipliers_bps: [u16; 3],
+    
+    // Reward bonus per guild member staked in the Mine in basis points
+    pub guild_bonus_bps: u16,
+    
+    // Maximum reward bonus of guild members in basis points
+    pub guild_bonus_cap_bps: u16,
+    
+    // Share of a referred Character's mined rewards minted to its referrer in basis points
+    pub referral_bps: u16,
+    
+    // Duration after the initialization of a referred Character during which its referrer is rewarded in units of s
+    pub referral_window: u64,
+    
+    // Maximum referral rewards of a referrer, zero when uncapped
+    pub referral_cap: u64,
+    
+    // Current season of the game, zero before the first season
+    pub season: u64,
+    
+    // Flag if the current season is running
+    pub season_active: bool,
+    
+    // Flag if the pool was locked by the end of the season and reopens with the next season
+    pub season_locked: bool,
+    
+    // Reward Tokens burned to respec a Character
+    pub respec_fee: u64,
+    
+    // Minimum time between two respecs of a Character in units of s
+    pub respec_cooldown: u64,
+    
+    // Active boosts grouped by their end timestamp, an entry leaves the staked points once its boosts end
+    pub boost_expiries: [BoostExpiry; 16],
+    
+    // Reserved space for fields added in later versions
+    pub reserved: [u8; 23],
+}
+
+// Layout of the Mine account before versioning, kept to migrate legacy accounts
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct MineV0 {
+
+    // Bump used in generating the Mine account
+    pub bump: u8,
+    
+    // Lock controling if NFTs can be staked into the pool
+    pub locked: bool,
+    
     // Mine manager
     pub manager: Pubkey,
     
@@ -42,10 +175,21 @@ pub struct Mine {
 
 impl Mine {
 
+    // Current layout version of the Mine account
+    pub const VERSION: u8 = 1;
+    
+    // Offset of the version byte in the account data, the byte is zero in accounts created before versioning
+    pub const VERSION_OFFSET: usize = 8 + 1 + 1 + 1 + 32 + 32 + 8 + 8 + 8 + 2 + 8 + 16 + 8;
+    
+    // Serialized size of the Mine account without the discriminator
+    pub const LEN: usize = 1 + 1 + 1 + 32 + 32 + 8 + 8 + 8 + 2 + 8 + 16 + 8 + 1 + 8 + 8 + 8 + 8 + 8 + 1 + 64 + 8 + 2 + 8 + 8 + 8 + 16 + 8 + 2 + 24 + 6 + 2 + 2 + 2 + 8 + 8 + 8 + 1 + 1 + 8 + 8 + 16 * (8 + 8 + 16 + 16 + 4) + 23;
+    
+    
     pub fn initialize(&mut self, bump: u8, manager: &Pubkey, mint: &Pubkey) -> Result<()> {
     
         self.bump = bump;
         self.locked = true;
+        self.custody = 0;
         self.manager = *manager;
         self.mint = *mint;
         self.rate = 0;
@@ -55,18 +199,84 @@ impl Mine {
         self.staked_points = 0;
         self.accrued_rewards = 0;
         self.accrued_timestamp = 0;
+        self.version = Self::VERSION;
+        self.total_mined = 0;
+        self.total_looted = 0;
+        self.total_clamped = 0;
+        self.total_sessions = 0;
+        self.total_staked_seconds = 0;
+        self.schedule = 0;
+        self.step_timestamps = [0; 8];
+        self.decay_interval = 0;
+        self.decay_bps = 0;
+        self.floor_rate = 0;
+        self.next_step_timestamp = 0;
+        self.emission_cap = 0;
+        self.staked_peg_total = 0;
+        self.vesting_period = 0;
+        self.vesting_penalty_bps = 0;
+        self.lock_durations = [0; 3];
+        self.lock_multipliers_bps = [0; 3];
+        self.guild_bonus_bps = 0;
+        self.guild_bonus_cap_bps = 0;
+        self.referral_bps = 0;
+        self.referral_window = 0;
+        self.referral_cap = 0;
+        self.season = 0;
+        self.season_active = false;
+        self.season_locked = false;
+        self.respec_fee = 0;
+        self.respec_cooldown = 0;
+        self.boost_expiries = [BoostExpiry::default(); 16];
+        self.reserved = [0; 23];
         
         Ok(())
     }
     
-
+    
     pub fn update_accrued_rewards(&mut self, timestamp: i64) -> Result<()> {
+    
+        // Accrue each segment of constant rate and staked points up to the rate changes and boost ends that have passed
+        loop {
+            let expiry_index = self.next_expiry(timestamp);
+            let expiry_timestamp = expiry_index.map(|index| self.boost_expiries[index].end_timestamp);
+            let step_due = self.next_step_timestamp != 0 && self.next_step_timestamp <= timestamp;
+            
+            match expiry_timestamp {
+                Some(end_timestamp) if !step_due || end_timestamp <= self.next_step_timestamp => {
+                    self.accrue_rewards(end_timestamp)?;
+                    self.expire_boosts(expiry_index.unwrap())?;
+                }
+                _ if step_due => {
+                    let step_timestamp = self.next_step_timestamp;
+                    self.accrue_rewards(step_timestamp)?;
+                    self.apply_step(step_timestamp)?;
+                }
+                _ => break,
+            }
+        }
+        
+        self.accrue_rewards(timestamp)?;
         
+        Ok(())
+    }
+    
+    
+    fn accrue_rewards(&mut self, timestamp: i64) -> Result<()> {
+    
         let timestamp_delta_signed: i64 = timestamp.checked_sub(self.accrued_timestamp).ok_or(QstakingErrors::InvalidComputation).unwrap();
         let timestamp_delta: u128 = u128::try_from(timestamp_delta_signed).unwrap();
         let mut newly_accrued_rewards: u128 =  u128::try_from(self.rate).unwrap();
         newly_accrued_rewards = newly_accrued_rewards.checked_mul(timestamp_delta).ok_or(QstakingErrors::InvalidComputation).unwrap();
         
+        // Accrual tapers off so that the rewards owed to staked Characters stay within the emission cap
+        if self.emission_cap > 0 && self.staked_points > 0 {
+            let available_rewards = self.available_emission()?.checked_div(
+                u128::try_from(self.staked_points).unwrap()
+            ).ok_or(QstakingErrors::InvalidComputation).unwrap();
+            newly_accrued_rewards = std::cmp::min(newly_accrued_rewards, available_rewards);
+        }
+        
         self.accrued_rewards = self.accrued_rewards.checked_add(newly_accrued_rewards).ok_or(QstakingErrors::InvalidComputation).unwrap();
         self.accrued_timestamp = timestamp;
         
@@ -74,25 +284,472 @@ impl Mine {
     }
     
     
-    pub fn add_character(&mut self, timestamp: i64, mining_points: u64) -> Result<()> {
+    // Index of the earliest boost entry that ended by the timestamp and is still counted in the staked points
+    fn next_expiry(&self, timestamp: i64) -> Option<usize> {
+        self.boost_expiries.iter().enumerate()
+            .filter(|(_, expiry)| expiry.weight > 0 && expiry.end_timestamp <= timestamp)
+            .min_by_key(|(_, expiry)| expiry.end_timestamp)
+            .map(|(index, _)| index)
+    }
+    
+    
+    // Removes the ended boosts of the entry from the staked points and checkpoints the accrued rewards at their end
+    fn expire_boosts(&mut self, index: usize) -> Result<()> {
+    
+        let expiry = &mut self.boost_expiries[index];
+        
+        // Saturates for boosts applied before the peg total was tracked
+        self.staked_peg_total = self.staked_peg_total.saturating_sub(expiry.peg_total);
+        self.staked_points = self.staked_points.checked_sub(expiry.weight).ok_or(QstakingErrors::InvalidComputation).unwrap();
+        expiry.accrued_rewards = self.accrued_rewards;
+        expiry.weight = 0;
+        expiry.peg_total = 0;
+        
+        Ok(())
+    }
+    
+    
+    fn apply_step(&mut self, step_timestamp: i64) -> Result<()> {
+    
+        let reduced_rate = if self.schedule == 1 {
+            self.rate / 2
+        } else {
+            u64::try_from(
+                u128::from(self.rate).checked_mul(u128::from(10000 - self.decay_bps)).ok_or(QstakingErrors::InvalidComputation).unwrap() / 10000
+            ).unwrap()
+        };
+        self.rate = std::cmp::max(reduced_rate, self.floor_rate);
+        self.next_step_timestamp = self.following_step(step_timestamp)?;
+        
+        Ok(())
+    }
+    
+    
+    fn following_step(&self, timestamp: i64) -> Result<i64> {
+    
+        // The schedule stops once the rate reached its floor
+        if self.rate <= self.floor_rate {
+            return Ok(0);
+        }
+        
+        let next_step_timestamp = match self.schedule {
+            1 => self.step_timestamps.iter().copied().find(|step| *step > timestamp).unwrap_or(0),
+            2 => timestamp.checked_add(i64::try_from(self.decay_interval).unwrap()).ok_or(QstakingErrors::InvalidComputation).unwrap(),
+            _ => 0,
+        };
+        
+        Ok(next_step_timestamp)
+    }
     
+    
+    pub fn emitted(&self) -> Result<u64> {
+        Ok(self.total_mined.checked_add(self.total_looted).ok_or(QstakingErrors::InvalidComputation).unwrap())
+    }
+    
+    
+    // Emission budget not yet minted nor owed to the staked Characters
+    fn available_emission(&self) -> Result<u128> {
+    
+        let owed_rewards = u128::from(self.staked_points).checked_mul(self.accrued_rewards).ok_or(QstakingErrors::InvalidComputation).unwrap()
+            .saturating_sub(self.staked_peg_total);
+        let committed_rewards = u128::from(self.emitted()?).checked_add(owed_rewards).ok_or(QstakingErrors::InvalidComputation).unwrap();
+        
+        Ok(u128::from(self.emission_cap).saturating_sub(committed_rewards))
+    }
+    
+    
+    // Scales the rewards of an unstaked Character down to the emission budget left under the cap
+    pub fn cap_rewards(&self, accrued_reward: u64, looted_reward: u64) -> Result<(u64, u64)> {
+    
+        if self.emission_cap == 0 {
+            return Ok((accrued_reward, looted_reward));
+        }
+        
+        let remaining = u128::from(self.emission_cap.saturating_sub(self.emitted()?));
+        let total = u128::from(accrued_reward).checked_add(u128::from(looted_reward)).ok_or(QstakingErrors::InvalidComputation).unwrap();
+        if total <= remaining {
+            return Ok((accrued_reward, looted_reward));
+        }
+        
+        let capped_accrued = u128::from(accrued_reward).checked_mul(remaining).ok_or(QstakingErrors::InvalidComputation).unwrap() / total;
+        let capped_looted = remaining.checked_sub(capped_accrued).ok_or(QstakingErrors::InvalidComputation).unwrap();
+        
+        Ok((u64::try_from(capped_accrued).unwrap(), u64::try_from(capped_looted).unwrap()))
+    }
+    
+    
+    pub fn set_emission_cap(&mut self, timestamp: i64, emission_cap: u64) -> Result<()> {
+    
+        require!(
+            emission_cap == 0 || emission_cap >= self.emitted()?,
+            QstakingErrors::EmissionCapBelowEmitted
+        );
+        
         self.update_accrued_rewards(timestamp)?;
+        self.emission_cap = emission_cap;
         
-        self.staked_characters = self.staked_characters.checked_add(1).ok_or(QstakingErrors::InvalidComputation).unwrap();
-        self.staked_points = self.staked_points.checked_add(mining_points).ok_or(QstakingErrors::InvalidComputation).unwrap();
+        Ok(())
+    }
+    
+    
+    pub fn set_rate(&mut self, timestamp: i64, rate: u64) -> Result<()> {
+    
+        self.update_accrued_rewards(timestamp)?;
+        self.rate = rate;
+        
+        // A schedule that stopped at its floor resumes when the rate is raised above it
+        if self.next_step_timestamp == 0 {
+            self.next_step_timestamp = self.following_step(timestamp)?;
+        }
+        
+        Ok(())
+    }
+    
+    
+    pub fn set_schedule(&mut self, timestamp: i64, schedule: u8, step_timestamps: [i64; 8], decay_interval: u64, decay_bps: u16, floor_rate: u64) -> Result<()> {
+    
+        require!(
+            schedule < 3,
+            QstakingErrors::InvalidEmissionSchedule
+        );
+        
+        if schedule == 1 {
+            // Used step timestamps are ascending and followed only by unused entries
+            let used_steps = step_timestamps.iter().take_while(|step| **step != 0).count();
+            require!(
+                used_steps > 0
+                    && step_timestamps[used_steps..].iter().all(|step| *step == 0)
+                    && step_timestamps[..used_steps].windows(2).all(|steps| steps[0] < steps[1]),
+                QstakingErrors::InvalidEmissionSchedule
+            );
+        }
+        
+        if schedule == 2 {
+            require!(
+                decay_interval > 0 && decay_bps > 0 && decay_bps < 10000,
+                QstakingErrors::InvalidEmissionSchedule
+            );
+        }
+        
+        self.update_accrued_rewards(timestamp)?;
+        
+        self.schedule = schedule;
+        self.step_timestamps = step_timestamps;
+        self.decay_interval = decay_interval;
+        self.decay_bps = decay_bps;
+        self.floor_rate = floor_rate;
+        self.next_step_timestamp = self.following_step(timestamp)?;
+        
+        Ok(())
+    }
+    
+    
+    pub fn set_vesting(&mut self, vesting_period: u64, vesting_penalty_bps: u16) -> Result<()> {
+    
+        require!(
+            vesting_penalty_bps <= 10000 && i64::try_from(vesting_period).is_ok(),
+            QstakingErrors::InvalidVestingParameters
+        );
+        
+        self.vesting_period = vesting_period;
+        self.vesting_penalty_bps = vesting_penalty_bps;
+        
+        Ok(())
+    }
+    
+    
+    pub fn set_lock_tiers(&mut self, lock_durations: [u64; 3], lock_multipliers_bps: [u16; 3]) -> Result<()> {
+    
+        // Each tier is either disabled or locks for a positive duration without reducing the mining points
+        for (duration, multiplier_bps) in lock_durations.iter().zip(lock_multipliers_bps.iter()) {
+            require!(
+                (*duration == 0 && *multiplier_bps == 0)
+                    || (*duration > 0 && i64::try_from(*duration).is_ok() && *multiplier_bps >= 10000),
+                QstakingErrors::InvalidLockTier
+            );
+        }
+        
+        self.lock_durations = lock_durations;
+        self.lock_multipliers_bps = lock_multipliers_bps;
+        
+        Ok(())
+    }
+    
+    
+    // Staked weight and lock-up duration of a Character staked with the lock tier
+    pub fn lock_tier(&self, lock_tier: u8, mining_points: u64) -> Result<(u64, u64)> {
+    
+        if lock_tier == 0 {
+            return Ok((mining_points, 0));
+        }
+        
+        let tier_index = usize::from(lock_tier - 1);
+        require!(
+            tier_index < 3 && self.lock_durations[tier_index] > 0,
+            QstakingErrors::InvalidLockTier
+        );
+        
+        let staked_weight = u128::from(mining_points).checked_mul(
+            u128::from(self.lock_multipliers_bps[tier_index])
+        ).ok_or(QstakingErrors::InvalidComputation).unwrap() / 10000;
+        
+        Ok((u64::try_from(staked_weight).unwrap(), self.lock_durations[tier_index]))
+    }
+    
+    
+    pub fn set_guild_bonus(&mut self, guild_bonus_bps: u16, guild_bonus_cap_bps: u16) -> Result<()> {
+    
+        self.guild_bonus_bps = guild_bonus_bps;
+        self.guild_bonus_cap_bps = guild_bonus_cap_bps;
+        
+        Ok(())
+    }
+    
+    
+    // Guild bonus in basis points of the members of a Guild with the given number of staked members
+    pub fn guild_bonus_bps(&self, staked_members: u32) -> Result<u64> {
+    
+        let bonus_bps = std::cmp::min(
+            u64::from(self.guild_bonus_bps).checked_mul(u64::from(staked_members)).ok_or(QstakingErrors::InvalidComputation).unwrap(),
+            u64::from(self.guild_bonus_cap_bps)
+        );
+        
+        Ok(bonus_bps)
+    }
+    
+    
+    pub fn set_referral(&mut self, referral_bps: u16, referral_window: u64, referral_cap: u64) -> Result<()> {
+    
+        require!(
+            referral_bps <= 10000 && i64::try_from(referral_window).is_ok(),
+            QstakingErrors::InvalidReferrer
+        );
+        
+        self.referral_bps = referral_bps;
+        self.referral_window = referral_window;
+        self.referral_cap = referral_cap;
         
         Ok(())
     }
     
     
-    pub fn remove_character(&mut self, timestamp: i64, mining_points: u64) -> Result<()> {
+    pub fn set_respec(&mut self, respec_fee: u64, respec_cooldown: u64) -> Result<()> {
+    
+        require!(
+            i64::try_from(respec_cooldown).is_ok(),
+            QstakingErrors::InvalidUpgrade
+        );
+        
+        self.respec_fee = respec_fee;
+        self.respec_cooldown = respec_cooldown;
         
+        Ok(())
+    }
+    
+    
+    // Referral reward of the mined rewards of a referred Character, limited by the referrer cap and the emission cap
+    pub fn referral_reward(&self, accrued_reward: u64, referral_earned: u64) -> Result<u64> {
+    
+        let referral_reward = u128::from(accrued_reward).checked_mul(u128::from(self.referral_bps)).ok_or(QstakingErrors::InvalidComputation).unwrap() / 10000;
+        let mut referral_reward = u64::try_from(referral_reward).unwrap();
+        
+        if self.referral_cap > 0 {
+            referral_reward = std::cmp::min(referral_reward, self.referral_cap.saturating_sub(referral_earned));
+        }
+        
+        if self.emission_cap > 0 {
+            referral_reward = std::cmp::min(referral_reward, self.emission_cap.saturating_sub(self.emitted()?));
+        }
+        
+        Ok(referral_reward)
+    }
+    
+    
+    pub fn start_season(&mut self, season: u64) -> Result<()> {
+    
+        require!(
+            !self.season_active && season == self.season.checked_add(1).ok_or(QstakingErrors::InvalidComputation).unwrap(),
+            QstakingErrors::InvalidSeason
+        );
+        
+        self.season = season;
+        self.season_active = true;
+        
+        if self.season_locked {
+            self.locked = false;
+            self.season_locked = false;
+        }
+        
+        Ok(())
+    }
+    
+    
+    pub fn end_season(&mut self) -> Result<()> {
+    
+        require!(
+            self.season_active,
+            QstakingErrors::InvalidSeason
+        );
+        
+        self.season_active = false;
+        self.season_locked = !self.locked;
+        self.locked = true;
+        
+        Ok(())
+    }
+    
+    
+    pub fn add_character(&mut self, timestamp: i64, staked_weight: u64) -> Result<()> {
+    
+        self.update_accrued_rewards(timestamp)?;
+        
+        self.staked_peg_total = self.staked_peg_total.checked_add(
+            u128::from(staked_weight).checked_mul(self.accrued_rewards).ok_or(QstakingErrors::InvalidComputation).unwrap()
+        ).ok_or(QstakingErrors::InvalidComputation).unwrap();
+        self.staked_characters = self.staked_characters.checked_add(1).ok_or(QstakingErrors::InvalidComputation).unwrap();
+        self.staked_points = self.staked_points.checked_add(staked_weight).ok_or(QstakingErrors::InvalidComputation).unwrap();
+        self.total_sessions = self.total_sessions.checked_add(1).ok_or(QstakingErrors::InvalidComputation).unwrap();
+        
+        Ok(())
+    }
+    
+    
+    pub fn remove_character(&mut self, timestamp: i64, staked_weight: u64, staked_peg: u128, staked_seconds: u64) -> Result<()> {
+    
         self.update_accrued_rewards(timestamp)?;
         
+        // Saturates for Characters staked before the peg total was tracked
+        self.staked_peg_total = self.staked_peg_total.saturating_sub(
+            u128::from(staked_weight).checked_mul(staked_peg).ok_or(QstakingErrors::InvalidComputation).unwrap()
+        );
         self.staked_characters = self.staked_characters.checked_sub(1).ok_or(QstakingErrors::InvalidComputation).unwrap();
-        self.staked_points = self.staked_points.checked_sub(mining_points).ok_or(QstakingErrors::InvalidComputation).unwrap();
+        self.staked_points = self.staked_points.checked_sub(staked_weight).ok_or(QstakingErrors::InvalidComputation).unwrap();
+        self.total_staked_seconds = self.total_staked_seconds.checked_add(staked_seconds).ok_or(QstakingErrors::InvalidComputation).unwrap();
+        
+        Ok(())
+    }
+    
+    
+    pub fn add_boost(&mut self, timestamp: i64, end_timestamp: i64, boost_weight: u64) -> Result<()> {
+    
+        self.update_accrued_rewards(timestamp)?;
+        
+        // Boosts ending at the same time share an entry, otherwise a free entry is taken
+        let index = self.boost_expiries.iter().position(|expiry| expiry.end_timestamp == end_timestamp)
+            .or_else(|| self.boost_expiries.iter().position(|expiry| expiry.end_timestamp == 0))
+            .ok_or(QstakingErrors::BoostScheduleFull)?;
+        
+        let boost_peg = u128::from(boost_weight).checked_mul(self.accrued_rewards).ok_or(QstakingErrors::InvalidComputation).unwrap();
+        let expiry = &mut self.boost_expiries[index];
+        expiry.end_timestamp = end_timestamp;
+        expiry.weight = expiry.weight.checked_add(boost_weight).ok_or(QstakingErrors::InvalidComputation).unwrap();
+        expiry.peg_total = expiry.peg_total.checked_add(boost_peg).ok_or(QstakingErrors::InvalidComputation).unwrap();
+        expiry.boosts = expiry.boosts.checked_add(1).ok_or(QstakingErrors::InvalidComputation).unwrap();
+        
+        self.staked_peg_total = self.staked_peg_total.checked_add(boost_peg).ok_or(QstakingErrors::InvalidComputation).unwrap();
+        self.staked_points = self.staked_points.checked_add(boost_weight).ok_or(QstakingErrors::InvalidComputation).unwrap();
+        
+        Ok(())
+    }
+    
+    
+    // Settles a boost of a Character and returns the accrued rewards per point up to which the boost earned rewards
+    pub fn end_boost(&mut self, timestamp: i64, end_timestamp: i64, boost_weight: u64, boost_peg: u128) -> Result<u128> {
+    
+        self.update_accrued_rewards(timestamp)?;
+        
+        let boost_total = u128::from(boost_weight).checked_mul(boost_peg).ok_or(QstakingErrors::InvalidComputation).unwrap();
+        let index = self.boost_expiries.iter().position(|expiry| expiry.end_timestamp == end_timestamp && expiry.boosts > 0);
+        
+        // Boosts applied before the expiry schedule are not in an entry and are removed when they are settled
+        if index.is_none() {
+            self.staked_peg_total = self.staked_peg_total.saturating_sub(boost_total);
+            self.staked_points = self.staked_points.checked_sub(boost_weight).ok_or(QstakingErrors::InvalidComputation).unwrap();
+            return Ok(self.accrued_rewards);
+        }
+        
+        let index = index.unwrap();
+        let expiry = &mut self.boost_expiries[index];
+        
+        // An ended boost earned up to the checkpoint of its entry and already left the staked points
+        let accrued_rewards = if end_timestamp <= timestamp {
+            expiry.accrued_rewards
+        } else {
+            expiry.weight = expiry.weight.checked_sub(boost_weight).ok_or(QstakingErrors::InvalidComputation).unwrap();
+            expiry.peg_total = expiry.peg_total.saturating_sub(boost_total);
+            self.staked_peg_total = self.staked_peg_total.saturating_sub(boost_total);
+            self.staked_points = self.staked_points.checked_sub(boost_weight).ok_or(QstakingErrors::InvalidComputation).unwrap();
+            self.accrued_rewards
+        };
+        
+        expiry.boosts -= 1;
+        if expiry.boosts == 0 {
+            *expiry = BoostExpiry::default();
+        }
+        
+        Ok(accrued_rewards)
+    }
+    
+    
+    pub fn record_rewards(&mut self, mined: u64, looted: u64, clamped: u64) -> Result<()> {
+    
+        self.total_mined = self.total_mined.checked_add(mined).ok_or(QstakingErrors::InvalidComputation).unwrap();
+        self.total_looted = self.total_looted.checked_add(looted).ok_or(QstakingErrors::InvalidComputation).unwrap();
+        self.total_clamped = self.total_clamped.checked_add(clamped).ok_or(QstakingErrors::InvalidComputation).unwrap();
+        
+        Ok(())
+    }
+    
+    
+    // Returns confiscated withheld rewards, which were recorded as mined but never minted, to the emission budget
+    pub fn forfeit_rewards(&mut self, mined: u64) -> Result<()> {
+    
+        self.total_mined = self.total_mined.checked_sub(mined).ok_or(QstakingErrors::InvalidComputation).unwrap();
+        
+        Ok(())
+    }
+    
+    
+    // Reads a Mine account created before versioning at version 0, None if the account is versioned
+    pub fn read_legacy(data: &[u8]) -> Result<Option<Self>> {
+    
+        if data.get(Self::VERSION_OFFSET).copied().unwrap_or(0) != 0 {
+            return Ok(None);
+        }
+        
+        require!(
+            data.len() >= 8 && data[..8] == Self::discriminator(),
+            ErrorCode::AccountDiscriminatorMismatch
+        );
+        let legacy = MineV0::deserialize(&mut &data[8..])?;
+        
+        // Fields added since the legacy layout start from their zero values
+        let mut mine = Self::deserialize(&mut &vec![0; Self::LEN][..])?;
+        mine.bump = legacy.bump;
+        mine.locked = legacy.locked;
+        mine.manager = legacy.manager;
+        mine.mint = legacy.mint;
+        mine.rate = legacy.rate;
+        mine.price = legacy.price;
+        mine.cooldown = legacy.cooldown;
+        mine.staked_characters = legacy.staked_characters;
+        mine.staked_points = legacy.staked_points;
+        mine.accrued_rewards = legacy.accrued_rewards;
+        mine.accrued_timestamp = legacy.accrued_timestamp;
+        
+        Ok(Some(mine))
+    }
+    
+    
+    pub fn migrate(&mut self) -> Result<()> {
+    
+        require!(
+            self.version < Self::VERSION,
+            QstakingErrors::AccountAlreadyMigrated
+        );
+        
+        self.version = Self::VERSION;
         
         Ok(())
     }
-
 }
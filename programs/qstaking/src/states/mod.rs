@@ -1,11 +1,31 @@
 pub mod admin;
 pub mod whitelist;
+pub mod denylist;
 pub mod mine;
 pub mod loot;
 pub mod character;
+pub mod traits;
+pub mod vesting;
+pub mod boost;
+pub mod gear;
+pub mod guild;
+pub mod leaderboard;
+pub mod season;
+pub mod operator;
+pub mod upgrade;
 
 pub use admin::*;
 pub use whitelist::*;
+pub use denylist::*;
 pub use mine::*;
 pub use loot::*;
 pub use character::*;
+pub use traits::*;
+pub use vesting::*;
+pub use boost::*;
+pub use gear::*;
+pub use guild::*;
+pub use leaderboard::*;
+pub use season::*;
+pub use operator::*;
+pub use upgrade::*;
@@ -0,0 +1,58 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::*;
+
+
+#[account]
+pub struct Operator {
+
+    // Bump used in generating the Operator account
+    pub bump: u8,
+    
+    // Holder of the NFT granting the delegation, receives all rewards
+    pub holder: Pubkey,
+    
+    // Mint of the Character NFT the delegation applies to
+    pub nft_mint: Pubkey,
+    
+    // Key allowed to stake, unstake and claim in the Mine on behalf of the holder
+    pub authority: Pubkey,
+    
+    // Highest lock tier the operator may stake the NFT with, chosen by the holder
+    pub max_lock_tier: u8,
+    
+    // Layout version of the account
+    pub version: u8,
+    
+    // Reserved space for fields added in later versions
+    pub reserved: [u8; 32],
+}
+
+
+impl Operator {
+
+    // Current layout version of the Operator account
+    pub const VERSION: u8 = 1;
+    
+    // Serialized size of the Operator account without the discriminator
+    pub const LEN: usize = 1 + 32 + 32 + 32 + 1 + 1 + 32;
+    
+    
+    pub fn initialize(&mut self, bump: u8, holder: &Pubkey, nft_mint: &Pubkey, authority: &Pubkey, max_lock_tier: u8) -> Result<()> {
+    
+        require!(
+            authority != holder,
+            QstakingErrors::InvalidOperator
+        );
+        
+        self.bump = bump;
+        self.holder = *holder;
+        self.nft_mint = *nft_mint;
+        self.authority = *authority;
+        self.max_lock_tier = max_lock_tier;
+        self.version = Self::VERSION;
+        self.reserved = [0; 32];
+        
+        Ok(())
+    }
+}
@@ -0,0 +1,108 @@
+use anchor_lang::prelude::*;
+
+use crate::states::{Mine, Loot};
+
+
+#[account]
+pub struct Season {
+
+    // Bump used in generating the Season account
+    pub bump: u8,
+    
+    // Number of the season, starting at 1
+    pub season_id: u64,
+    
+    // Timestamp when the season started
+    pub start_timestamp: i64,
+    
+    // Timestamp when the season ended, zero while it is running
+    pub end_timestamp: i64,
+    
+    // Total Reward Tokens mined from the Mine at the end of the season
+    pub mine_total_mined: u64,
+    
+    // Total Reward Tokens looted from the Mine at the end of the season
+    pub mine_total_looted: u64,
+    
+    // Total staking sessions of the Mine at the end of the season
+    pub mine_total_sessions: u64,
+    
+    // Total staked seconds of the Mine at the end of the season
+    pub mine_total_staked_seconds: u64,
+    
+    // Characters staked in the Mine at the end of the season
+    pub mine_staked_characters: u16,
+    
+    // Total Reward Tokens received by the Loot at the end of the season
+    pub loot_total_received: u64,
+    
+    // Total Reward Tokens paid from the Loot at the end of the season
+    pub loot_total_paid: u64,
+    
+    // Total staking sessions of the Loot at the end of the season
+    pub loot_total_sessions: u64,
+    
+    // Total staked seconds of the Loot at the end of the season
+    pub loot_total_staked_seconds: u64,
+    
+    // Characters staked in the Loot at the end of the season
+    pub loot_staked_characters: u16,
+    
+    // Layout version of the account
+    pub version: u8,
+    
+    // Reserved space for fields added in later versions
+    pub reserved: [u8; 32],
+}
+
+
+impl Season {
+
+    // Current layout version of the Season account
+    pub const VERSION: u8 = 1;
+    
+    // Serialized size of the Season account without the discriminator
+    pub const LEN: usize = 1 + 8 + 8 + 8 + 8 + 8 + 8 + 8 + 2 + 8 + 8 + 8 + 8 + 2 + 1 + 32;
+    
+    
+    pub fn initialize(&mut self, bump: u8, season_id: u64, timestamp: i64) -> Result<()> {
+    
+        self.bump = bump;
+        self.season_id = season_id;
+        self.start_timestamp = timestamp;
+        self.end_timestamp = 0;
+        self.mine_total_mined = 0;
+        self.mine_total_looted = 0;
+        self.mine_total_sessions = 0;
+        self.mine_total_staked_seconds = 0;
+        self.mine_staked_characters = 0;
+        self.loot_total_received = 0;
+        self.loot_total_paid = 0;
+        self.loot_total_sessions = 0;
+        self.loot_total_staked_seconds = 0;
+        self.loot_staked_characters = 0;
+        self.version = Self::VERSION;
+        self.reserved = [0; 32];
+        
+        Ok(())
+    }
+    
+    
+    // Snapshots the pool totals at the end of the season
+    pub fn end(&mut self, timestamp: i64, mine: &Mine, loot: &Loot) -> Result<()> {
+    
+        self.end_timestamp = timestamp;
+        self.mine_total_mined = mine.total_mined;
+        self.mine_total_looted = mine.total_looted;
+        self.mine_total_sessions = mine.total_sessions;
+        self.mine_total_staked_seconds = mine.total_staked_seconds;
+        self.mine_staked_characters = mine.staked_characters;
+        self.loot_total_received = loot.total_received;
+        self.loot_total_paid = loot.total_paid;
+        self.loot_total_sessions = loot.total_sessions;
+        self.loot_total_staked_seconds = loot.total_staked_seconds;
+        self.loot_staked_characters = loot.staked_characters;
+        
+        Ok(())
+    }
+}
@@ -0,0 +1,106 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::*;
+use crate::merkle;
+
+
+#[account]
+pub struct TraitsRegistry {
+
+    // Bump used in generating the TraitsRegistry account
+    pub bump: u8,
+    
+    // Merkle root of the published per-mint Character traits, all zeros if no traits are published
+    pub root: [u8; 32],
+    
+    // Layout version of the account
+    pub version: u8,
+    
+    // Reserved space for fields added in later versions
+    pub reserved: [u8; 32],
+}
+
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct CharacterTraits {
+
+    // Rarity tier of the NFT
+    pub tier: u8,
+    
+    // Starting mining points
+    pub mining_points: u64,
+    
+    // Starting maximum mining rewards capacity
+    pub mining_capacity: u64,
+    
+    // Starting looting points
+    pub looting_points: u64,
+}
+
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct TraitsProof {
+
+    // Traits of the NFT as published by the admin
+    pub traits: CharacterTraits,
+    
+    // Sibling hashes from the traits leaf up to the Merkle root
+    pub path: Vec<[u8; 32]>,
+}
+
+
+impl CharacterTraits {
+
+    // Highest rarity tier of an NFT
+    pub const MAX_TIER: u8 = 4;
+}
+
+
+impl Default for CharacterTraits {
+
+    fn default() -> Self {
+        CharacterTraits {
+            tier: 0,
+            mining_points: 1,
+            mining_capacity: 1000000,
+            looting_points: 1,
+        }
+    }
+}
+
+
+impl TraitsRegistry {
+
+    // Current layout version of the TraitsRegistry account
+    pub const VERSION: u8 = 1;
+    
+    // Serialized size of the TraitsRegistry account without the discriminator
+    pub const LEN: usize = 1 + 32 + 1 + 32;
+    
+    
+    pub fn is_published(&self) -> bool {
+        self.root != [0u8; 32]
+    }
+    
+    
+    pub fn verify(&self, mint: &Pubkey, proof: &Option<TraitsProof>) -> Result<CharacterTraits> {
+    
+        if !self.is_published() {
+            require!(
+                proof.is_none(),
+                QstakingErrors::InvalidTraitsProof
+            );
+            return Ok(CharacterTraits::default());
+        }
+        
+        let proof = proof.as_ref().ok_or(QstakingErrors::InvalidTraitsProof)?;
+        let data = proof.traits.try_to_vec()?;
+        
+        require!(
+            merkle::verify(merkle::leaf(mint.as_ref(), &data), &proof.path, &self.root),
+            QstakingErrors::InvalidTraitsProof
+        );
+        
+        Ok(proof.traits.clone())
+    }
+}
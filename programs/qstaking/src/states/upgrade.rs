@@ -0,0 +1,113 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::*;
+
+
+// Number of Character attributes that can be upgraded
+pub const UPGRADE_ATTRIBUTES: usize = 4;
+
+
+#[account]
+pub struct UpgradeShop {
+
+    // Bump used in generating the UpgradeShop account
+    pub bump: u8,
+    
+    // Cost curves defined by the Mine manager per attribute:
+    // 0 mining points, 1 mining capacity, 2 looting points, 3 bounty points
+    pub upgrades: [UpgradeCurve; UPGRADE_ATTRIBUTES],
+    
+    // Layout version of the account
+    pub version: u8,
+    
+    // Reserved space for fields added in later versions
+    pub reserved: [u8; 32],
+}
+
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default)]
+pub struct UpgradeCurve {
+
+    // Reward Tokens burned for the first upgrade level
+    pub base_cost: u64,
+    
+    // Cost increase of every following level in basis points of the previous level's cost
+    pub growth_bps: u16,
+    
+    // Points added to the attribute per upgrade level
+    pub points: u64,
+    
+    // Number of upgrade levels available, zero disables upgrades of the attribute
+    pub max_level: u8,
+}
+
+
+impl UpgradeShop {
+
+    // Current layout version of the UpgradeShop account
+    pub const VERSION: u8 = 1;
+    
+    // Serialized size of the UpgradeShop account without the discriminator
+    pub const LEN: usize = 1 + UPGRADE_ATTRIBUTES * (8 + 2 + 8 + 1) + 1 + 32;
+    
+    
+    pub fn initialize(&mut self, bump: u8) -> Result<()> {
+    
+        self.bump = bump;
+        self.upgrades = [UpgradeCurve::default(); UPGRADE_ATTRIBUTES];
+        self.version = Self::VERSION;
+        self.reserved = [0; 32];
+        
+        Ok(())
+    }
+    
+    
+    // Defines the cost curve of the attribute, a zero maximum level disables its upgrades
+    pub fn set_upgrade(&mut self, attribute: u8, base_cost: u64, growth_bps: u16, points: u64, max_level: u8) -> Result<()> {
+    
+        require!(
+            usize::from(attribute) < UPGRADE_ATTRIBUTES,
+            QstakingErrors::InvalidUpgrade
+        );
+        
+        require!(
+            max_level == 0 || (base_cost > 0 && growth_bps >= 10000 && points > 0),
+            QstakingErrors::InvalidUpgrade
+        );
+        
+        self.upgrades[usize::from(attribute)] = UpgradeCurve {
+            base_cost,
+            growth_bps,
+            points,
+            max_level,
+        };
+        
+        // The cost of the last level has to fit in a u64, so every upgrade of the curve can be priced
+        if max_level > 0 {
+            self.price(attribute, max_level - 1)?;
+        }
+        
+        Ok(())
+    }
+    
+    
+    // Returns the cost and the points of the next upgrade of an attribute at the given level
+    pub fn price(&self, attribute: u8, level: u8) -> Result<(u64, u64)> {
+    
+        let upgrade = self.upgrades.get(usize::from(attribute)).ok_or(QstakingErrors::InvalidUpgrade)?;
+        
+        require!(
+            level < upgrade.max_level,
+            QstakingErrors::UpgradeMaxLevel
+        );
+        
+        let mut cost = u128::from(upgrade.base_cost);
+        for _ in 0..level {
+            cost = cost.checked_mul(u128::from(upgrade.growth_bps)).ok_or(QstakingErrors::InvalidComputation)? / 10000;
+        }
+        
+        let cost = u64::try_from(cost).map_err(|_| QstakingErrors::InvalidComputation)?;
+        
+        Ok((cost, upgrade.points))
+    }
+}
@@ -0,0 +1,153 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::*;
+
+
+#[account]
+pub struct Vesting {
+
+    // Bump used in generating the Vesting account
+    pub bump: u8,
+    
+    // User the vesting Reward Tokens are released to
+    pub owner: Pubkey,
+    
+    // Mint of the NFT whose mining rewards are vesting
+    pub nft_mint: Pubkey,
+    
+    // Amount of Reward Tokens vesting in the current schedule
+    pub total_amount: u64,
+    
+    // Amount of Reward Tokens of the current schedule already released to the owner
+    pub released_amount: u64,
+    
+    // Amount of Reward Tokens of earlier schedules that vested but were not released yet
+    pub carried_amount: u64,
+    
+    // Timestamp when the current schedule started
+    pub start_timestamp: i64,
+    
+    // Timestamp when the current schedule is fully vested
+    pub end_timestamp: i64,
+    
+    // Layout version of the account
+    pub version: u8,
+    
+    // Reserved space for fields added in later versions
+    pub reserved: [u8; 32],
+}
+
+
+impl Vesting {
+
+    // Current layout version of the Vesting account
+    pub const VERSION: u8 = 1;
+    
+    // Serialized size of the Vesting account without the discriminator
+    pub const LEN: usize = 1 + 32 + 32 + 8 + 8 + 8 + 8 + 8 + 1 + 32;
+    
+    
+    pub fn initialize(&mut self, bump: u8, owner: &Pubkey, nft_mint: &Pubkey) -> Result<()> {
+    
+        self.bump = bump;
+        self.owner = *owner;
+        self.nft_mint = *nft_mint;
+        self.total_amount = 0;
+        self.released_amount = 0;
+        self.carried_amount = 0;
+        self.start_timestamp = 0;
+        self.end_timestamp = 0;
+        self.version = Self::VERSION;
+        self.reserved = [0; 32];
+        
+        Ok(())
+    }
+    
+    
+    // Restarts the schedule with the newly mined rewards and the amount not vested yet,
+    // the vested amount not released yet is carried over and stays claimable
+    pub fn add_rewards(&mut self, timestamp: i64, vesting_period: u64, amount: u64) -> Result<()> {
+    
+        let vested_amount = self.vested_amount(timestamp)?;
+        let unvested_amount = self.total_amount.checked_sub(vested_amount).ok_or(QstakingErrors::InvalidComputation).unwrap();
+        
+        self.carried_amount = self.carried_amount.checked_add(
+            vested_amount.checked_sub(self.released_amount).ok_or(QstakingErrors::InvalidComputation).unwrap()
+        ).ok_or(QstakingErrors::InvalidComputation).unwrap();
+        
+        self.total_amount = unvested_amount.checked_add(amount).ok_or(QstakingErrors::InvalidComputation).unwrap();
+        self.released_amount = 0;
+        self.start_timestamp = timestamp;
+        self.end_timestamp = timestamp.checked_add(
+            i64::try_from(vesting_period).unwrap()
+        ).ok_or(QstakingErrors::InvalidComputation).unwrap();
+        
+        Ok(())
+    }
+    
+    
+    pub fn vested_amount(&self, timestamp: i64) -> Result<u64> {
+    
+        if timestamp >= self.end_timestamp {
+            return Ok(self.total_amount);
+        }
+        
+        let elapsed = u128::try_from(
+            timestamp.checked_sub(self.start_timestamp).ok_or(QstakingErrors::InvalidComputation).unwrap()
+        ).unwrap();
+        let duration = u128::try_from(
+            self.end_timestamp.checked_sub(self.start_timestamp).ok_or(QstakingErrors::InvalidComputation).unwrap()
+        ).unwrap();
+        
+        let vested_amount = u128::from(self.total_amount).checked_mul(elapsed).ok_or(QstakingErrors::InvalidComputation).unwrap()
+            .checked_div(duration).ok_or(QstakingErrors::InvalidComputation).unwrap();
+        
+        Ok(u64::try_from(vested_amount).unwrap())
+    }
+    
+    
+    pub fn claim(&mut self, timestamp: i64) -> Result<u64> {
+    
+        let vested_amount = self.vested_amount(timestamp)?;
+        let claimable_amount = vested_amount.checked_sub(self.released_amount).ok_or(QstakingErrors::InvalidComputation).unwrap()
+            .checked_add(self.carried_amount).ok_or(QstakingErrors::InvalidComputation).unwrap();
+        
+        require!(
+            claimable_amount > 0,
+            QstakingErrors::NothingToClaim
+        );
+        
+        self.released_amount = vested_amount;
+        self.carried_amount = 0;
+        
+        Ok(claimable_amount)
+    }
+    
+    
+    // Releases everything at once, a share of the amount not vested yet is forfeited
+    pub fn exit_early(&mut self, timestamp: i64, penalty_bps: u16) -> Result<(u64, u64)> {
+    
+        let vested_amount = self.vested_amount(timestamp)?;
+        let unvested_amount = self.total_amount.checked_sub(vested_amount).ok_or(QstakingErrors::InvalidComputation).unwrap();
+        let unreleased_amount = self.total_amount.checked_sub(self.released_amount).ok_or(QstakingErrors::InvalidComputation).unwrap()
+            .checked_add(self.carried_amount).ok_or(QstakingErrors::InvalidComputation).unwrap();
+        
+        require!(
+            unreleased_amount > 0,
+            QstakingErrors::NothingToClaim
+        );
+        
+        let forfeited_amount = u128::from(unvested_amount).checked_mul(u128::from(penalty_bps)).ok_or(QstakingErrors::InvalidComputation).unwrap()
+            .checked_div(10000).ok_or(QstakingErrors::InvalidComputation).unwrap();
+        let forfeited_amount = u64::try_from(forfeited_amount).unwrap();
+        let released_amount = unreleased_amount.checked_sub(forfeited_amount).ok_or(QstakingErrors::InvalidComputation).unwrap();
+        
+        self.total_amount = 0;
+        self.released_amount = 0;
+        self.carried_amount = 0;
+        self.start_timestamp = 0;
+        self.end_timestamp = 0;
+        
+        Ok((released_amount, forfeited_amount))
+    }
+}
@@ -4,6 +4,9 @@ use mpl_token_metadata as metaplex;
 use anchor_lang::prelude::*;
 
 use crate::errors::*;
+use crate::merkle;
+use crate::metadata;
+use crate::states::Denylist;
 
 // bitflags! {
 //     pub struct WhitelistType: u8 {
@@ -19,34 +22,86 @@ pub struct Whitelist {
     // Bump used in generating the Whitelist account
     pub bump: u8,
     
-    // whitelist type
+    // whitelist type: 0 mint, 1 creator, 2 Merkle root of allowed mints
     pub whitelist_type: u8,
     
+    // Merkle root of the allowed mint leaves (only used by whitelist type 2)
+    pub root: [u8; 32],
+    
+    // Flag controlling if the whitelist entry can currently be used
+    pub enabled: bool,
+    
+    // Timestamp after which no new Characters can be created under this entry, 0 for no expiry
+    pub expiry_timestamp: i64,
+    
+    // Maximum number of Characters that can be created under this entry, 0 for no limit
+    pub max_characters: u32,
+    
+    // Number of Characters created under this entry
+    pub initialized_characters: u32,
+    
+    // Layout version of the account
+    pub version: u8,
+    
+    // Reserved space for fields added in later versions
+    pub reserved: [u8; 32],
+}
+
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct WhitelistProof {
+
+    // Optional per-mint attributes hashed into the Merkle leaf together with the mint
+    pub attributes: Vec<u8>,
+    
+    // Sibling hashes from the leaf up to the Merkle root
+    pub path: Vec<[u8; 32]>,
 }
 
 
 impl Whitelist {
 
-    fn assert_metadata(&self, mint: &Pubkey, mint_metadata: &AccountInfo) -> Result<()> {
-        let metadata_program = metaplex::id();
+    // Current layout version of the Whitelist account
+    pub const VERSION: u8 = 1;
+    
+    // Serialized size of the Whitelist account without the discriminator
+    pub const LEN: usize = 1 + 1 + 32 + 1 + 8 + 4 + 4 + 1 + 32;
+    
+    
+    pub fn initialize(&mut self, bump: u8, whitelist_type: u8, root: [u8; 32]) -> Result<()> {
+    
+        self.bump = bump;
+        self.whitelist_type = whitelist_type;
+        self.root = root;
+        self.enabled = true;
+        self.expiry_timestamp = 0;
+        self.max_characters = 0;
+        self.initialized_characters = 0;
+        self.version = Self::VERSION;
+        self.reserved = [0; 32];
         
-        require_keys_eq!(
-            *mint_metadata.owner,
-            metadata_program,
-            QstakingErrors::InvalidMetadataAccount
+        Ok(())
+    }
+    
+    
+    pub fn register_character(&mut self, timestamp: i64) -> Result<()> {
+    
+        require!(
+            self.expiry_timestamp == 0 || timestamp <= self.expiry_timestamp,
+            QstakingErrors::WhitelistExpired
         );
         
-        let seed = &[b"metadata".as_ref(), metadata_program.as_ref(), mint.as_ref(),];
-        let (metadata_account, _bump) = Pubkey::find_program_address(seed, &metadata_program);
-        require_keys_eq!(
-            metadata_account,
-            mint_metadata.key(),
-            QstakingErrors::InvalidMetadataAccount
+        require!(
+            self.max_characters == 0 || self.initialized_characters < self.max_characters,
+            QstakingErrors::WhitelistQuotaReached
         );
         
+        self.initialized_characters = self.initialized_characters.checked_add(1).ok_or(QstakingErrors::InvalidComputation).unwrap();
+        
         Ok(())
     }
     
+    
     fn assert_whitelist(&self, seed_account: &Pubkey, program_account: &Pubkey, whitelist: &Pubkey) -> Result<()> {
         let seed = &[b"whitelist".as_ref(), seed_account.as_ref(),];
         let (whitelist_account, _bump) = Pubkey::find_program_address(seed, program_account);
@@ -60,16 +115,37 @@ impl Whitelist {
         
         Ok(())
     }
-
-    pub fn verify(&self, program_account: &Pubkey, whitelist: &Pubkey, mint: &Pubkey, mint_metadata: &AccountInfo) -> Result<()> {
     
-        self.assert_metadata(mint, mint_metadata)?;
+    fn assert_merkle_proof(&self, mint: &Pubkey, proof: &Option<WhitelistProof>) -> Result<()> {
+        let proof = proof.as_ref().ok_or(QstakingErrors::InvalidWhitelistProof)?;
+        
+        require!(
+            merkle::verify(merkle::leaf(mint.as_ref(), &proof.attributes), &proof.path, &self.root),
+            QstakingErrors::InvalidWhitelistProof
+        );
+        
+        Ok(())
+    }
+    
+    pub fn verify(&self, program_account: &Pubkey, whitelist: &Pubkey, mint: &Pubkey, mint_metadata: &AccountInfo, denylist: &AccountInfo, proof: &Option<WhitelistProof>) -> Result<()> {
+    
+        require!(
+            !Denylist::is_denylisted(program_account, denylist),
+            QstakingErrors::Denylisted
+        );
+        
+        require!(
+            self.enabled,
+            QstakingErrors::WhitelistDisabled
+        );
+        
+        metadata::assert_metadata_account(mint, mint_metadata)?;
         
         if self.whitelist_type == 0 {
             //msg!("Checking mint proof");
             self.assert_whitelist(&mint, &program_account, &whitelist)?;
             return Ok(())
-            
+        
         } else if self.whitelist_type == 1 {
             //msg!("Checking creator proof");
             //let metadata = metaplex::state::Metadata::from_account_info(mint_metadata)?;
@@ -85,10 +161,33 @@ impl Whitelist {
                     Err(_e) => continue,
                 }
             }
+        
+        } else if self.whitelist_type == 2 {
+            self.assert_merkle_proof(mint, proof)?;
+            return Ok(())
         }
         
         Err(error!(QstakingErrors::InvalidWhitelistProof))
     }
-
+    
+    
+    pub fn migrate(&mut self) -> Result<()> {
+    
+        require!(
+            self.version < Self::VERSION,
+            QstakingErrors::AccountAlreadyMigrated
+        );
+        
+        // Whitelists created before versioning stay enabled without expiry nor Character quota
+        if self.version == 0 {
+            self.enabled = true;
+            self.expiry_timestamp = 0;
+            self.max_characters = 0;
+        }
+        
+        self.version = Self::VERSION;
+        
+        Ok(())
+    }
 }
 
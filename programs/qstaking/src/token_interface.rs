@@ -0,0 +1,402 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+use anchor_lang::solana_program::program::invoke_signed;
+use anchor_lang::solana_program::program_pack::Pack;
+use anchor_lang::solana_program::system_instruction;
+use anchor_spl::token::spl_token;
+use anchor_spl::associated_token;
+
+use crate::errors::*;
+
+
+
+/*
+ *  Token interface helpers for the reward token
+ *  The reward mint, the loot proceeds account and the reward ATAs may be owned either by the Token program
+ *  or by the Token-2022 program. The pinned anchor-spl crate only knows about the Token program, so the
+ *  base layouts are read directly, the Token-2022 extensions are read from the TLV data following the base
+ *  account and the instructions shared by both programs are serialized by hand.
+ */
+
+
+pub mod token_2022 {
+    use anchor_lang::declare_id;
+    declare_id!("TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb");
+}
+
+
+const BASE_ACCOUNT_LENGTH: usize = spl_token::state::Account::LEN;
+const ACCOUNT_TYPE_MINT: u8 = 1;
+
+const EXTENSION_UNINITIALIZED: u16 = 0;
+const EXTENSION_TRANSFER_FEE_CONFIG: u16 = 1;
+const EXTENSION_MINT_CLOSE_AUTHORITY: u16 = 3;
+const EXTENSION_INTEREST_BEARING_CONFIG: u16 = 10;
+const EXTENSION_METADATA_POINTER: u16 = 18;
+const EXTENSION_TOKEN_METADATA: u16 = 19;
+
+// Size of the TransferFeeAmount account extension including its TLV header
+const TRANSFER_FEE_AMOUNT_LENGTH: usize = 4 + 8;
+
+const MINT_TO_INSTRUCTION: u8 = 7;
+const BURN_INSTRUCTION: u8 = 8;
+const TRANSFER_CHECKED_INSTRUCTION: u8 = 12;
+const INITIALIZE_ACCOUNT3_INSTRUCTION: u8 = 18;
+const TRANSFER_FEE_EXTENSION_INSTRUCTION: u8 = 26;
+const TRANSFER_CHECKED_WITH_FEE_INSTRUCTION: u8 = 1;
+
+const MAX_FEE_BASIS_POINTS: u128 = 10_000;
+
+
+pub fn is_token_program(token_program: &Pubkey) -> bool {
+    *token_program == spl_token::ID || *token_program == token_2022::ID
+}
+
+
+pub fn unpack_mint(mint: &AccountInfo, token_program: &Pubkey) -> Result<spl_token::state::Mint> {
+    require!(
+        is_token_program(token_program) && mint.owner == token_program,
+        QstakingErrors::InvalidRewardTokenAccount
+    );
+    
+    let data = mint.try_borrow_data()?;
+    require!(
+        data.len() >= spl_token::state::Mint::LEN,
+        QstakingErrors::InvalidRewardTokenAccount
+    );
+    
+    let mint_data = spl_token::state::Mint::unpack_from_slice(&data[..spl_token::state::Mint::LEN])?;
+    require!(
+        mint_data.is_initialized,
+        QstakingErrors::InvalidRewardTokenAccount
+    );
+    
+    Ok(mint_data)
+}
+
+
+pub fn unpack_account(account: &AccountInfo, token_program: &Pubkey) -> Result<spl_token::state::Account> {
+    require!(
+        is_token_program(token_program) && account.owner == token_program,
+        QstakingErrors::InvalidRewardTokenAccount
+    );
+    
+    let data = account.try_borrow_data()?;
+    require!(
+        data.len() >= BASE_ACCOUNT_LENGTH,
+        QstakingErrors::InvalidRewardTokenAccount
+    );
+    
+    let account_data = spl_token::state::Account::unpack_from_slice(&data[..BASE_ACCOUNT_LENGTH])?;
+    require!(
+        account_data.state == spl_token::state::AccountState::Initialized,
+        QstakingErrors::InvalidRewardTokenAccount
+    );
+    
+    Ok(account_data)
+}
+
+
+pub fn assert_token_account(account: &AccountInfo, owner: &Pubkey, mint: &Pubkey, token_program: &Pubkey) -> Result<()> {
+    let account_data = unpack_account(account, token_program)?;
+    
+    require!(
+        account_data.owner == *owner && account_data.mint == *mint,
+        QstakingErrors::InvalidRewardTokenAccount
+    );
+    
+    Ok(())
+}
+
+
+pub fn assert_associated_token_account(account: &AccountInfo, owner: &Pubkey, mint: &Pubkey, token_program: &Pubkey) -> Result<()> {
+    let seed = &[owner.as_ref(), token_program.as_ref(), mint.as_ref()];
+    let (associated_account, _bump) = Pubkey::find_program_address(seed, &associated_token::ID);
+    require_keys_eq!(
+        associated_account,
+        account.key(),
+        QstakingErrors::InvalidRewardTokenAccount
+    );
+    
+    assert_token_account(account, owner, mint, token_program)
+}
+
+
+// Checks the reward mint, the user's reward ATA and the loot proceeds account against the reward token program
+pub fn assert_reward_accounts(
+    token_program: &Pubkey,
+    reward_mint: &AccountInfo,
+    reward_ata: &AccountInfo,
+    user: &Pubkey,
+    loot_proceeds: &AccountInfo,
+    loot: &Pubkey,
+) -> Result<()> {
+    unpack_mint(reward_mint, token_program)?;
+    assert_associated_token_account(reward_ata, user, &reward_mint.key(), token_program)?;
+    assert_token_account(loot_proceeds, loot, &reward_mint.key(), token_program)
+}
+
+
+fn mint_extension(data: &[u8], extension_type: u16) -> Option<&[u8]> {
+    if data.len() <= BASE_ACCOUNT_LENGTH || data[BASE_ACCOUNT_LENGTH] != ACCOUNT_TYPE_MINT {
+        return None;
+    }
+    
+    let mut offset = BASE_ACCOUNT_LENGTH + 1;
+    while offset + 4 <= data.len() {
+        let current_type = u16::from_le_bytes([data[offset], data[offset + 1]]);
+        let length = u16::from_le_bytes([data[offset + 2], data[offset + 3]]) as usize;
+        let end = (offset + 4).checked_add(length)?;
+    
+        if current_type == EXTENSION_UNINITIALIZED || end > data.len() {
+            return None;
+        }
+        if current_type == extension_type {
+            return Some(&data[offset + 4..end]);
+        }
+        offset = end;
+    }
+    
+    None
+}
+
+
+fn mint_extension_types(data: &[u8]) -> Vec<u16> {
+    let mut extension_types = Vec::new();
+    if data.len() <= BASE_ACCOUNT_LENGTH || data[BASE_ACCOUNT_LENGTH] != ACCOUNT_TYPE_MINT {
+        return extension_types;
+    }
+    
+    let mut offset = BASE_ACCOUNT_LENGTH + 1;
+    while offset + 4 <= data.len() {
+        let current_type = u16::from_le_bytes([data[offset], data[offset + 1]]);
+        let length = u16::from_le_bytes([data[offset + 2], data[offset + 3]]) as usize;
+    
+        if current_type == EXTENSION_UNINITIALIZED {
+            break;
+        }
+        extension_types.push(current_type);
+        offset = offset.saturating_add(4).saturating_add(length);
+    }
+    
+    extension_types
+}
+
+
+// Only mint extensions that need no extra accounts or custom handling on transfers are supported
+pub fn assert_supported_mint(mint: &AccountInfo) -> Result<()> {
+    let data = mint.try_borrow_data()?;
+    
+    for extension_type in mint_extension_types(&data) {
+        require!(
+            matches!(
+                extension_type,
+                EXTENSION_TRANSFER_FEE_CONFIG
+                | EXTENSION_MINT_CLOSE_AUTHORITY
+                | EXTENSION_INTEREST_BEARING_CONFIG
+                | EXTENSION_METADATA_POINTER
+                | EXTENSION_TOKEN_METADATA
+            ),
+            QstakingErrors::UnsupportedMintExtension
+        );
+    }
+    
+    Ok(())
+}
+
+
+pub fn has_transfer_fee(mint: &AccountInfo) -> Result<bool> {
+    let data = mint.try_borrow_data()?;
+    Ok(mint_extension(&data, EXTENSION_TRANSFER_FEE_CONFIG).is_some())
+}
+
+
+// Size of a token account for the mint, including the account extensions required by the mint
+pub fn account_length(mint: &AccountInfo) -> Result<usize> {
+    if has_transfer_fee(mint)? {
+        Ok(BASE_ACCOUNT_LENGTH + 1 + TRANSFER_FEE_AMOUNT_LENGTH)
+    } else {
+        Ok(BASE_ACCOUNT_LENGTH)
+    }
+}
+
+
+// Transfer fee withheld by the Token-2022 program when transferring amount in the given epoch
+pub fn transfer_fee(mint: &AccountInfo, amount: u64, epoch: u64) -> Result<u64> {
+    let data = mint.try_borrow_data()?;
+    let config = match mint_extension(&data, EXTENSION_TRANSFER_FEE_CONFIG) {
+        Some(config) => config,
+        None => return Ok(0),
+    };
+    
+    // authorities and withheld amount, followed by the older and newer transfer fees
+    require!(
+        config.len() >= 32 + 32 + 8 + 18 + 18,
+        QstakingErrors::InvalidRewardTokenAccount
+    );
+    let older_fee = &config[72..90];
+    let newer_fee = &config[90..108];
+    
+    let newer_epoch = u64::from_le_bytes(newer_fee[0..8].try_into().unwrap());
+    let fee = if epoch >= newer_epoch { newer_fee } else { older_fee };
+    let maximum_fee = u64::from_le_bytes(fee[8..16].try_into().unwrap());
+    let basis_points = u16::from_le_bytes(fee[16..18].try_into().unwrap()) as u128;
+    
+    if basis_points == 0 || amount == 0 {
+        return Ok(0);
+    }
+    
+    let raw_fee = (amount as u128)
+        .checked_mul(basis_points).ok_or(QstakingErrors::InvalidComputation)?
+        .checked_add(MAX_FEE_BASIS_POINTS - 1).ok_or(QstakingErrors::InvalidComputation)?
+        .checked_div(MAX_FEE_BASIS_POINTS).ok_or(QstakingErrors::InvalidComputation)?;
+    
+    Ok(u64::try_from(raw_fee).unwrap_or(u64::MAX).min(maximum_fee))
+}
+
+
+/*
+ *  CPI helpers
+ */
+
+
+pub fn create_token_account<'info>(
+    token_program: AccountInfo<'info>,
+    account: AccountInfo<'info>,
+    mint: AccountInfo<'info>,
+    owner: &Pubkey,
+    payer: AccountInfo<'info>,
+    system_program: AccountInfo<'info>,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+
+    let space = account_length(&mint)?;
+    let lamports = Rent::get()?.minimum_balance(space);
+    
+    invoke_signed(
+        &system_instruction::create_account(payer.key, account.key, lamports, space as u64, token_program.key),
+        &[payer, account.clone(), system_program],
+        signer_seeds,
+    )?;
+    
+    let mut data = vec![INITIALIZE_ACCOUNT3_INSTRUCTION];
+    data.extend_from_slice(owner.as_ref());
+    
+    invoke_signed(
+        &Instruction {
+            program_id: token_program.key(),
+            accounts: vec![
+                AccountMeta::new(account.key(), false),
+                AccountMeta::new_readonly(mint.key(), false),
+            ],
+            data,
+        },
+        &[account, mint],
+        &[],
+    )?;
+    
+    Ok(())
+}
+
+
+pub fn mint_to<'info>(
+    token_program: AccountInfo<'info>,
+    mint: AccountInfo<'info>,
+    to: AccountInfo<'info>,
+    authority: AccountInfo<'info>,
+    amount: u64,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+
+    let mut data = vec![MINT_TO_INSTRUCTION];
+    data.extend_from_slice(&amount.to_le_bytes());
+    
+    invoke_signed(
+        &Instruction {
+            program_id: token_program.key(),
+            accounts: vec![
+                AccountMeta::new(mint.key(), false),
+                AccountMeta::new(to.key(), false),
+                AccountMeta::new_readonly(authority.key(), true),
+            ],
+            data,
+        },
+        &[mint, to, authority],
+        signer_seeds,
+    )?;
+    
+    Ok(())
+}
+
+
+pub fn burn<'info>(
+    token_program: AccountInfo<'info>,
+    account: AccountInfo<'info>,
+    mint: AccountInfo<'info>,
+    authority: AccountInfo<'info>,
+    amount: u64,
+) -> Result<()> {
+
+    let mut data = vec![BURN_INSTRUCTION];
+    data.extend_from_slice(&amount.to_le_bytes());
+    
+    invoke_signed(
+        &Instruction {
+            program_id: token_program.key(),
+            accounts: vec![
+                AccountMeta::new(account.key(), false),
+                AccountMeta::new(mint.key(), false),
+                AccountMeta::new_readonly(authority.key(), true),
+            ],
+            data,
+        },
+        &[account, mint, authority],
+        &[],
+    )?;
+    
+    Ok(())
+}
+
+
+// Uses TransferCheckedWithFee for mints with a transfer fee so the withheld fee is asserted by the token program
+#[allow(clippy::too_many_arguments)]
+pub fn transfer_checked<'info>(
+    token_program: AccountInfo<'info>,
+    from: AccountInfo<'info>,
+    mint: AccountInfo<'info>,
+    to: AccountInfo<'info>,
+    authority: AccountInfo<'info>,
+    amount: u64,
+    decimals: u8,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+
+    let mut data = if has_transfer_fee(&mint)? {
+        vec![TRANSFER_FEE_EXTENSION_INSTRUCTION, TRANSFER_CHECKED_WITH_FEE_INSTRUCTION]
+    } else {
+        vec![TRANSFER_CHECKED_INSTRUCTION]
+    };
+    data.extend_from_slice(&amount.to_le_bytes());
+    data.push(decimals);
+    if data[0] == TRANSFER_FEE_EXTENSION_INSTRUCTION {
+        let fee = transfer_fee(&mint, amount, Clock::get()?.epoch)?;
+        data.extend_from_slice(&fee.to_le_bytes());
+    }
+    
+    invoke_signed(
+        &Instruction {
+            program_id: token_program.key(),
+            accounts: vec![
+                AccountMeta::new(from.key(), false),
+                AccountMeta::new_readonly(mint.key(), false),
+                AccountMeta::new(to.key(), false),
+                AccountMeta::new_readonly(authority.key(), true),
+            ],
+            data,
+        },
+        &[from, mint, to, authority],
+        signer_seeds,
+    )?;
+    
+    Ok(())
+}
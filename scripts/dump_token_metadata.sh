@@ -0,0 +1,13 @@
+#!/bin/sh
+# Dumps the mainnet Token Metadata program with programmable NFT support loaded by the local test validator.
+# The binary is not committed, it is only dumped when missing.
+set -e
+
+PROGRAM_ID="metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s"
+PROGRAM_FILE="tests/fixtures/mpl_token_metadata.so"
+
+cd "$(dirname "$0")/.."
+
+if [ ! -f "$PROGRAM_FILE" ]; then
+    solana program dump -u m "$PROGRAM_ID" "$PROGRAM_FILE"
+fi
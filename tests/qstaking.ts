@@ -5,6 +5,7 @@ import { Program } from "@project-serum/anchor";
 import { PublicKey, Keypair } from '@solana/web3.js';
 import { Metaplex, keypairIdentity } from "@metaplex-foundation/js";
 import { Qstaking } from "../target/types/qstaking";
+import { keccak256 } from "js-sha3";
 
 describe("qstaking", () => {
     const program = anchor.workspace.Qstaking as Program<Qstaking>;
@@ -28,10 +29,18 @@ describe("qstaking", () => {
     let mineAccount: PublicKey;
     let lootAccount: PublicKey;
     let lootProceedsAccount: PublicKey;
+    let vestingEscrowAccount: PublicKey;
+    let traitsRegistryAccount: PublicKey;
+    let leaderboardAccount: PublicKey;
     let rewardMintAccount: PublicKey;
+    let rewardTokenProgram: PublicKey;
     
     let nft1 = null;
     let nft2 = null;
+    let nft3 = null;
+    let nft4 = null;
+    let nft5 = null;
+    let pnft1 = null;
     
     const creatorWhitelist = false;
     const mintWhitelist = [];
@@ -45,6 +54,10 @@ describe("qstaking", () => {
     const loot_price = 1000000;
     const loot_cooldown = 3600;
     
+    // The reward mint is a Token-2022 mint with a transfer fee, withheld on loot payouts
+    const reward_fee_bps = 100;
+    const reward_max_fee = BigInt(1000000000);
+    
     before( async () => {
         
         const airdropSignature0 = await provider.connection.requestAirdrop(adminKeypair.publicKey, 1e9);
@@ -70,13 +83,17 @@ describe("qstaking", () => {
         [mineAccount, bump] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("mine")], program.programId);
         [lootAccount, bump] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("loot")], program.programId);
         [lootProceedsAccount, bump] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("proceeds"), lootAccount.toBuffer()], program.programId);
+        [vestingEscrowAccount, bump] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("escrow"), mineAccount.toBuffer()], program.programId);
+        [traitsRegistryAccount, bump] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("traits")], program.programId);
+        [leaderboardAccount, bump] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("leaderboard")], program.programId);
         
         if (!await provider.connection.getAccountInfo(mineAccount)) {
-            rewardMintAccount = await spl.createMint(provider.connection, authorityKeypair, mineAccount, authorityKeypair.publicKey, 6);
+            rewardMintAccount = await createTransferFeeMint(provider.connection, authorityKeypair, mineAccount, 6, reward_fee_bps, reward_max_fee);
         } else {
             const mineAccountData = await program.account.mine.fetch(mineAccount);
             rewardMintAccount = mineAccountData.mint;
         }
+        rewardTokenProgram = (await provider.connection.getAccountInfo(rewardMintAccount)).owner;
         
         const programAaccountInfo = await provider.connection.getAccountInfo(program.programId);
         try {
@@ -87,9 +104,14 @@ describe("qstaking", () => {
         
         nft1 = await createNFT(provider.connection, creatorKeypair, userKeypair1, 'Bastard #1', 'https://raffles-test.s3.amazonaws.com/NFT1.jpg');
         nft2 = await createNFT(provider.connection, creatorKeypair, userKeypair2, 'Bastard #2', 'https://raffles-test.s3.amazonaws.com/NFT2.jpg');
+        nft3 = await createNFT(provider.connection, creatorKeypair, userKeypair1, 'Bastard #4', 'https://raffles-test.s3.amazonaws.com/NFT1.jpg');
+        nft4 = await createNFT(provider.connection, creatorKeypair, userKeypair2, 'Bastard #5', 'https://raffles-test.s3.amazonaws.com/NFT2.jpg');
+        nft5 = await createNFT(provider.connection, creatorKeypair, userKeypair2, 'Bastard #6', 'https://raffles-test.s3.amazonaws.com/NFT2.jpg');
+        
+        pnft1 = await createProgrammableNFT(provider.connection, tokenMetadataProgram, creatorKeypair, userKeypair1, 'Bastard #3', 'https://raffles-test.s3.amazonaws.com/NFT3.jpg');
         
         if (!creatorWhitelist) {
-            mintWhitelist.push(nft1, nft2);
+            mintWhitelist.push(nft1, nft2, nft3, nft4, nft5, pnft1);
         }
         
     });
@@ -176,20 +198,408 @@ describe("qstaking", () => {
         const signature = await anchor.web3.sendAndConfirmTransaction(provider.connection, tx, [adminKeypair], {skipPreflight: true});
         console.log(`\tAdd creator whitelist account transaction: ${signature}`);
         
+        const whitelistAccountData = await program.account.whitelist.fetch(await getWhitelistAccount(creatorKeypair, program.programId, creatorWhitelist, nft1));
+        assert.equal(whitelistAccountData.enabled, true);
+        assert.equal(whitelistAccountData.expiryTimestamp, 0);
+        assert.equal(whitelistAccountData.maxCharacters, 0);
+        assert.equal(whitelistAccountData.initializedCharacters, 0);
+        
+    });
+    
+    
+    it("Traits registry initialized!", async () => {
+        
+        const emptyRoot = new Array(32).fill(0);
+        
+        if (await provider.connection.getAccountInfo(traitsRegistryAccount)) {
+            
+            const tx = program.transaction.setTraitsRoot(emptyRoot, {
+                accounts: {
+                    adminSettings: adminSettingsAccount,
+                    traitsRegistry: traitsRegistryAccount,
+                    authority: adminKeypair.publicKey,
+                }
+            });
+            
+            const signature = await anchor.web3.sendAndConfirmTransaction(provider.connection, tx, [adminKeypair], {skipPreflight: true});
+            console.log(`\tSet traits root transaction: ${signature}`);
+            
+        } else {
+            
+            const tx = program.transaction.initTraitsRegistry(emptyRoot, {
+                accounts: {
+                    adminSettings: adminSettingsAccount,
+                    traitsRegistry: traitsRegistryAccount,
+                    authority: adminKeypair.publicKey,
+                    systemProgram: anchor.web3.SystemProgram.programId,
+                }
+            });
+            
+            const signature = await anchor.web3.sendAndConfirmTransaction(provider.connection, tx, [adminKeypair], {skipPreflight: true});
+            console.log(`\tInit traits registry transaction: ${signature}`);
+        }
+        
+        const traitsRegistryData = await program.account.traitsRegistry.fetch(traitsRegistryAccount);
+        assert.deepEqual(traitsRegistryData.root, emptyRoot);
+    });
+    
+    
+    it("Character initialized with published traits!", async () => {
+    
+        const traitsNft = await createNFT(provider.connection, creatorKeypair, userKeypair1, 'Bastard #7', 'https://raffles-test.s3.amazonaws.com/NFT1.jpg');
+        mintWhitelist.push(traitsNft);
+        
+        const [characterAccount, bump] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("character"), traitsNft.mintAddress.toBuffer()], program.programId);
+        const [denylistAccount, bump2] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("denylist"), traitsNft.mintAddress.toBuffer()], program.programId);
+        const [whitelistAccount, bump3] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("whitelist"), traitsNft.mintAddress.toBuffer()], program.programId);
+        const nftTokenAccount = await spl.getAssociatedTokenAddress(traitsNft.mintAddress, userKeypair1.publicKey);
+        
+        const whitelistTx = program.transaction.addWhitelist(traitsNft.mintAddress, new anchor.BN(0), {
+            accounts: {
+                adminSettings: adminSettingsAccount,
+                whitelist: whitelistAccount,
+                authority: adminKeypair.publicKey,
+                systemProgram: anchor.web3.SystemProgram.programId,
+            }
+        });
+        
+        const whitelistSignature = await anchor.web3.sendAndConfirmTransaction(provider.connection, whitelistTx, [adminKeypair], {skipPreflight: true});
+        console.log(`\tAdd traits NFT whitelist account transaction: ${whitelistSignature}`);
+        
+        // The published tree holds the traits of the new NFT and of NFT #1
+        const traits = {tier: 3, miningPoints: new anchor.BN(5), miningCapacity: new anchor.BN(2000000), lootingPoints: new anchor.BN(4)};
+        const otherTraits = {tier: 1, miningPoints: new anchor.BN(2), miningCapacity: new anchor.BN(1000000), lootingPoints: new anchor.BN(1)};
+        const tree = getMerkleTree([
+            getMerkleLeaf(traitsNft.mintAddress.toBuffer(), borshTraits(traits)),
+            getMerkleLeaf(nft1.mintAddress.toBuffer(), borshTraits(otherTraits)),
+        ]);
+        
+        const getRootTx = (root: number[]) => program.transaction.setTraitsRoot(root, {
+            accounts: {
+                adminSettings: adminSettingsAccount,
+                traitsRegistry: traitsRegistryAccount,
+                authority: adminKeypair.publicKey,
+            }
+        });
+        
+        const rootSignature = await anchor.web3.sendAndConfirmTransaction(provider.connection, getRootTx(tree.root), [adminKeypair], {skipPreflight: true});
+        console.log(`\tPublish traits root transaction: ${rootSignature}`);
+        
+        const traitsRegistryData = await program.account.traitsRegistry.fetch(traitsRegistryAccount);
+        assert.deepEqual(traitsRegistryData.root, tree.root);
+        
+        const getInitTx = (traitsProof) => program.transaction.initCharacter(null, traitsProof, null, {
+            accounts: {
+                character: characterAccount,
+                whitelist: whitelistAccount,
+                denylist: denylistAccount,
+                traitsRegistry: traitsRegistryAccount,
+                nftAccount: nftTokenAccount,
+                nftMetadata: traitsNft.metadataAddress,
+                nftMint: traitsNft.mintAddress,
+                user: userKeypair1.publicKey,
+                associatedTokenProgram: spl.ASSOCIATED_TOKEN_PROGRAM_ID,
+                systemProgram: anchor.web3.SystemProgram.programId,
+            },
+        });
+        
+        // Once traits are published, a Character cannot be initialized without them nor with the traits of another mint
+        await assert.rejects(anchor.web3.sendAndConfirmTransaction(provider.connection, getInitTx(null), [userKeypair1]));
+        await assert.rejects(anchor.web3.sendAndConfirmTransaction(provider.connection, getInitTx({traits: otherTraits, path: tree.paths[0]}), [userKeypair1]));
+        assert.equal(await provider.connection.getAccountInfo(characterAccount), null);
+        
+        const signature = await anchor.web3.sendAndConfirmTransaction(provider.connection, getInitTx({traits: traits, path: tree.paths[0]}), [userKeypair1], {skipPreflight: true});
+        console.log(`\tTraits character account initialization transaction: ${signature}`);
+        
+        const characterAccountData = await program.account.character.fetch(characterAccount);
+        assert.equal(characterAccountData.tier, 3);
+        assert.equal(characterAccountData.miningPoints.toNumber(), 5);
+        assert.equal(characterAccountData.miningCapacity.toNumber(), 2000000);
+        assert.equal(characterAccountData.lootingPoints.toNumber(), 4);
+        
+        // The other Characters of the tests are initialized without traits
+        const emptyRoot = new Array(32).fill(0);
+        const emptyRootSignature = await anchor.web3.sendAndConfirmTransaction(provider.connection, getRootTx(emptyRoot), [adminKeypair], {skipPreflight: true});
+        console.log(`\tUnpublish traits root transaction: ${emptyRootSignature}`);
+        
+        assert.deepEqual((await program.account.traitsRegistry.fetch(traitsRegistryAccount)).root, emptyRoot);
+    });
+    
+    
+    it("Merkle whitelist added and its root rotated!", async () => {
+    
+        // A Merkle whitelist covers NFTs that have no mint nor creator whitelist of their own
+        const collectionKey = Keypair.generate().publicKey;
+        const [whitelistAccount, bump] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("whitelist"), collectionKey.toBuffer()], program.programId);
+        const merkleNft1 = await createNFT(provider.connection, creatorKeypair, userKeypair1, 'Bastard #8', 'https://raffles-test.s3.amazonaws.com/NFT1.jpg');
+        const merkleNft2 = await createNFT(provider.connection, creatorKeypair, userKeypair1, 'Bastard #9', 'https://raffles-test.s3.amazonaws.com/NFT1.jpg');
+        
+        const attributes1 = Buffer.from("gold");
+        const attributes2 = Buffer.from("silver");
+        const tree1 = getMerkleTree([
+            getMerkleLeaf(merkleNft1.mintAddress.toBuffer(), attributes1),
+            getMerkleLeaf(nft1.mintAddress.toBuffer(), attributes2),
+        ]);
+        const tree2 = getMerkleTree([
+            getMerkleLeaf(merkleNft1.mintAddress.toBuffer(), attributes1),
+            getMerkleLeaf(merkleNft2.mintAddress.toBuffer(), attributes2),
+        ]);
+        
+        const addTx = program.transaction.addMerkleWhitelist(collectionKey, tree1.root, {
+            accounts: {
+                adminSettings: adminSettingsAccount,
+                whitelist: whitelistAccount,
+                authority: adminKeypair.publicKey,
+                systemProgram: anchor.web3.SystemProgram.programId,
+            }
+        });
+        
+        const addSignature = await anchor.web3.sendAndConfirmTransaction(provider.connection, addTx, [adminKeypair], {skipPreflight: true});
+        console.log(`\tAdd Merkle whitelist account transaction: ${addSignature}`);
+        
+        let whitelistAccountData = await program.account.whitelist.fetch(whitelistAccount);
+        assert.equal(whitelistAccountData.whitelistType, 2);
+        assert.deepEqual(whitelistAccountData.root, tree1.root);
+        
+        const getInitTx = async (nft, proof) => program.transaction.initCharacter(proof, null, null, {
+            accounts: {
+                character: (await anchor.web3.PublicKey.findProgramAddress([Buffer.from("character"), nft.mintAddress.toBuffer()], program.programId))[0],
+                whitelist: whitelistAccount,
+                denylist: (await anchor.web3.PublicKey.findProgramAddress([Buffer.from("denylist"), nft.mintAddress.toBuffer()], program.programId))[0],
+                traitsRegistry: traitsRegistryAccount,
+                nftAccount: await spl.getAssociatedTokenAddress(nft.mintAddress, userKeypair1.publicKey),
+                nftMetadata: nft.metadataAddress,
+                nftMint: nft.mintAddress,
+                user: userKeypair1.publicKey,
+                associatedTokenProgram: spl.ASSOCIATED_TOKEN_PROGRAM_ID,
+                systemProgram: anchor.web3.SystemProgram.programId,
+            },
+        });
+        
+        // The proof is required and has to match the attributes published for the mint
+        await assert.rejects(anchor.web3.sendAndConfirmTransaction(provider.connection, await getInitTx(merkleNft1, null), [userKeypair1]));
+        await assert.rejects(anchor.web3.sendAndConfirmTransaction(provider.connection, await getInitTx(merkleNft1, {attributes: attributes2, path: tree1.paths[0]}), [userKeypair1]));
+        
+        const initSignature = await anchor.web3.sendAndConfirmTransaction(provider.connection, await getInitTx(merkleNft1, {attributes: attributes1, path: tree1.paths[0]}), [userKeypair1], {skipPreflight: true});
+        console.log(`\tMerkle whitelisted character account initialization transaction: ${initSignature}`);
+        
+        // The second NFT is only covered by the rotated root
+        await assert.rejects(anchor.web3.sendAndConfirmTransaction(provider.connection, await getInitTx(merkleNft2, {attributes: attributes2, path: tree2.paths[1]}), [userKeypair1]));
+        
+        const getRootTx = (referenceAccount: PublicKey, whitelist: PublicKey, authority: PublicKey) => program.transaction.setWhitelistRoot(referenceAccount, tree2.root, {
+            accounts: {
+                adminSettings: adminSettingsAccount,
+                whitelist: whitelist,
+                authority: authority,
+            }
+        });
+        
+        // Only the admin can rotate the root, and only of a Merkle whitelist
+        await assert.rejects(anchor.web3.sendAndConfirmTransaction(provider.connection, getRootTx(collectionKey, whitelistAccount, userKeypair1.publicKey), [userKeypair1]));
+        const mintWhitelistAccount = await getWhitelistAccount(creatorKeypair, program.programId, creatorWhitelist, nft1);
+        await assert.rejects(anchor.web3.sendAndConfirmTransaction(provider.connection, getRootTx(creatorWhitelist ? creatorKeypair.publicKey : nft1.mintAddress, mintWhitelistAccount, adminKeypair.publicKey), [adminKeypair]));
+        
+        const rootSignature = await anchor.web3.sendAndConfirmTransaction(provider.connection, getRootTx(collectionKey, whitelistAccount, adminKeypair.publicKey), [adminKeypair], {skipPreflight: true});
+        console.log(`\tRotate Merkle whitelist root transaction: ${rootSignature}`);
+        
+        whitelistAccountData = await program.account.whitelist.fetch(whitelistAccount);
+        assert.deepEqual(whitelistAccountData.root, tree2.root);
+        
+        const initSignature2 = await anchor.web3.sendAndConfirmTransaction(provider.connection, await getInitTx(merkleNft2, {attributes: attributes2, path: tree2.paths[1]}), [userKeypair1], {skipPreflight: true});
+        console.log(`\tMerkle whitelisted character account initialization transaction: ${initSignature2}`);
+        
+        whitelistAccountData = await program.account.whitelist.fetch(whitelistAccount);
+        assert.equal(whitelistAccountData.initializedCharacters, 2);
+        
+        const removeTx = program.transaction.removeWhitelist(collectionKey, {
+            accounts: {
+                adminSettings: adminSettingsAccount,
+                whitelist: whitelistAccount,
+                authority: adminKeypair.publicKey,
+                systemProgram: anchor.web3.SystemProgram.programId,
+            }
+        });
+        
+        const removeSignature = await anchor.web3.sendAndConfirmTransaction(provider.connection, removeTx, [adminKeypair], {skipPreflight: true});
+        console.log(`\tRemove Merkle whitelist account transaction: ${removeSignature}`);
+    
+    });
+    
+    
+    it("Whitelist entry disabled, expired and limited!", async () => {
+    
+        const collectionKey = Keypair.generate().publicKey;
+        const [whitelistAccount, bump] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("whitelist"), collectionKey.toBuffer()], program.programId);
+        const limitedNft1 = await createNFT(provider.connection, creatorKeypair, userKeypair2, 'Bastard #10', 'https://raffles-test.s3.amazonaws.com/NFT2.jpg');
+        const limitedNft2 = await createNFT(provider.connection, creatorKeypair, userKeypair2, 'Bastard #11', 'https://raffles-test.s3.amazonaws.com/NFT2.jpg');
+        
+        const attributes = Buffer.alloc(0);
+        const tree = getMerkleTree([
+            getMerkleLeaf(limitedNft1.mintAddress.toBuffer(), attributes),
+            getMerkleLeaf(limitedNft2.mintAddress.toBuffer(), attributes),
+        ]);
+        
+        const addTx = program.transaction.addMerkleWhitelist(collectionKey, tree.root, {
+            accounts: {
+                adminSettings: adminSettingsAccount,
+                whitelist: whitelistAccount,
+                authority: adminKeypair.publicKey,
+                systemProgram: anchor.web3.SystemProgram.programId,
+            }
+        });
+        
+        const addSignature = await anchor.web3.sendAndConfirmTransaction(provider.connection, addTx, [adminKeypair], {skipPreflight: true});
+        console.log(`\tAdd Merkle whitelist account transaction: ${addSignature}`);
+        
+        const getInitTx = async (nft, index: number) => program.transaction.initCharacter({attributes: attributes, path: tree.paths[index]}, null, null, {
+            accounts: {
+                character: (await anchor.web3.PublicKey.findProgramAddress([Buffer.from("character"), nft.mintAddress.toBuffer()], program.programId))[0],
+                whitelist: whitelistAccount,
+                denylist: (await anchor.web3.PublicKey.findProgramAddress([Buffer.from("denylist"), nft.mintAddress.toBuffer()], program.programId))[0],
+                traitsRegistry: traitsRegistryAccount,
+                nftAccount: await spl.getAssociatedTokenAddress(nft.mintAddress, userKeypair2.publicKey),
+                nftMetadata: nft.metadataAddress,
+                nftMint: nft.mintAddress,
+                user: userKeypair2.publicKey,
+                associatedTokenProgram: spl.ASSOCIATED_TOKEN_PROGRAM_ID,
+                systemProgram: anchor.web3.SystemProgram.programId,
+            },
+        });
+        
+        const getEnabledTx = (enabled: boolean, authority: PublicKey) => program.transaction.setWhitelistEnabled(collectionKey, enabled, {
+            accounts: {
+                adminSettings: adminSettingsAccount,
+                whitelist: whitelistAccount,
+                authority: authority,
+            }
+        });
+        
+        const getLimitsTx = (expiryTimestamp: number, maxCharacters: number, authority: PublicKey) => program.transaction.setWhitelistLimits(collectionKey, new anchor.BN(expiryTimestamp), maxCharacters, {
+            accounts: {
+                adminSettings: adminSettingsAccount,
+                whitelist: whitelistAccount,
+                authority: authority,
+            }
+        });
+        
+        // Only the admin can change the entry
+        await assert.rejects(anchor.web3.sendAndConfirmTransaction(provider.connection, getEnabledTx(false, userKeypair2.publicKey), [userKeypair2]));
+        await assert.rejects(anchor.web3.sendAndConfirmTransaction(provider.connection, getLimitsTx(0, 1, userKeypair2.publicKey), [userKeypair2]));
+        
+        // A disabled entry accepts no proof
+        const disableSignature = await anchor.web3.sendAndConfirmTransaction(provider.connection, getEnabledTx(false, adminKeypair.publicKey), [adminKeypair], {skipPreflight: true});
+        console.log(`\tDisable whitelist transaction: ${disableSignature}`);
+        assert.equal((await program.account.whitelist.fetch(whitelistAccount)).enabled, false);
+        
+        await assert.rejects(anchor.web3.sendAndConfirmTransaction(provider.connection, await getInitTx(limitedNft1, 0), [userKeypair2]));
+        
+        const enableSignature = await anchor.web3.sendAndConfirmTransaction(provider.connection, getEnabledTx(true, adminKeypair.publicKey), [adminKeypair], {skipPreflight: true});
+        console.log(`\tEnable whitelist transaction: ${enableSignature}`);
+        
+        // An expired entry accepts no new Characters
+        const expirySignature = await anchor.web3.sendAndConfirmTransaction(provider.connection, getLimitsTx(1, 0, adminKeypair.publicKey), [adminKeypair], {skipPreflight: true});
+        console.log(`\tExpire whitelist transaction: ${expirySignature}`);
+        
+        await assert.rejects(anchor.web3.sendAndConfirmTransaction(provider.connection, await getInitTx(limitedNft1, 0), [userKeypair2]));
+        
+        // The entry accepts a single Character until its quota is raised
+        const quotaSignature = await anchor.web3.sendAndConfirmTransaction(provider.connection, getLimitsTx(0, 1, adminKeypair.publicKey), [adminKeypair], {skipPreflight: true});
+        console.log(`\tLimit whitelist quota transaction: ${quotaSignature}`);
+        
+        const initSignature = await anchor.web3.sendAndConfirmTransaction(provider.connection, await getInitTx(limitedNft1, 0), [userKeypair2], {skipPreflight: true});
+        console.log(`\tLimited character account initialization transaction: ${initSignature}`);
+        
+        await assert.rejects(anchor.web3.sendAndConfirmTransaction(provider.connection, await getInitTx(limitedNft2, 1), [userKeypair2]));
+        
+        let whitelistAccountData = await program.account.whitelist.fetch(whitelistAccount);
+        assert.equal(whitelistAccountData.expiryTimestamp.toNumber(), 0);
+        assert.equal(whitelistAccountData.maxCharacters, 1);
+        assert.equal(whitelistAccountData.initializedCharacters, 1);
+        
+        const raiseSignature = await anchor.web3.sendAndConfirmTransaction(provider.connection, getLimitsTx(0, 2, adminKeypair.publicKey), [adminKeypair], {skipPreflight: true});
+        console.log(`\tRaise whitelist quota transaction: ${raiseSignature}`);
+        
+        const initSignature2 = await anchor.web3.sendAndConfirmTransaction(provider.connection, await getInitTx(limitedNft2, 1), [userKeypair2], {skipPreflight: true});
+        console.log(`\tLimited character account initialization transaction: ${initSignature2}`);
+        
+        whitelistAccountData = await program.account.whitelist.fetch(whitelistAccount);
+        assert.equal(whitelistAccountData.initializedCharacters, 2);
+        
+        const removeTx = program.transaction.removeWhitelist(collectionKey, {
+            accounts: {
+                adminSettings: adminSettingsAccount,
+                whitelist: whitelistAccount,
+                authority: adminKeypair.publicKey,
+                systemProgram: anchor.web3.SystemProgram.programId,
+            }
+        });
+        
+        const removeSignature = await anchor.web3.sendAndConfirmTransaction(provider.connection, removeTx, [adminKeypair], {skipPreflight: true});
+        console.log(`\tRemove Merkle whitelist account transaction: ${removeSignature}`);
+    
+    });
+    
+    
+    it("Gear collection added!", async () => {
+        
+        const gearCreatorKeypair = Keypair.generate();
+        const [gearCollectionAccount, bump] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("gear"), gearCreatorKeypair.publicKey.toBuffer()], program.programId);
+        
+        const tx = program.transaction.addGearCollection(gearCreatorKeypair.publicKey, 0, 1, new anchor.BN(2500), {
+            accounts: {
+                adminSettings: adminSettingsAccount,
+                gearCollection: gearCollectionAccount,
+                authority: adminKeypair.publicKey,
+                systemProgram: anchor.web3.SystemProgram.programId,
+            }
+        });
+        
+        const signature = await anchor.web3.sendAndConfirmTransaction(provider.connection, tx, [adminKeypair], {skipPreflight: true});
+        console.log(`\tAdd gear collection transaction: ${signature}`);
+        
+        const gearCollectionData = await program.account.gearCollection.fetch(gearCollectionAccount);
+        assert.equal(gearCollectionData.creator.toString(), gearCreatorKeypair.publicKey.toString());
+        assert.equal(gearCollectionData.enabled, true);
+        assert.equal(gearCollectionData.bonus.toNumber(), 2500);
+    });
+    
+    
+    it("Leaderboard initialized!", async () => {
+        
+        if (!await provider.connection.getAccountInfo(leaderboardAccount)) {
+            const initTx = program.transaction.initLeaderboard({
+                accounts: {
+                    adminSettings: adminSettingsAccount,
+                    leaderboard: leaderboardAccount,
+                    authority: adminKeypair.publicKey,
+                    systemProgram: anchor.web3.SystemProgram.programId,
+                },
+            });
+            
+            const initSignature = await anchor.web3.sendAndConfirmTransaction(provider.connection, initTx, [adminKeypair], {skipPreflight: true});
+            console.log(`\tInit leaderboard transaction: ${initSignature}`);
+        }
+        
+        const leaderboardAccountData = await program.account.leaderboard.fetch(leaderboardAccount);
+        assert.equal(leaderboardAccountData.version, 1);
+        assert.equal(leaderboardAccountData.miningRewards[0].value.toNumber(), 0);
     });
     
     
     it("Character #1 account initialized!", async () => {
         
         const [characterAccount, bump] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("character"), nft1.mintAddress.toBuffer()], program.programId);
+        const [denylistAccount, bump3] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("denylist"), nft1.mintAddress.toBuffer()], program.programId);
         const whitelistAccount = await getWhitelistAccount(creatorKeypair, program.programId, creatorWhitelist, nft1);
         const nftTokenAccount = await spl.getAssociatedTokenAddress(nft1.mintAddress, userKeypair1.publicKey);
         
-        const tx = program.transaction.initCharacter({
+        const tx = program.transaction.initCharacter(null, null, null, {
             accounts: {
                 character: characterAccount,
                 whitelist: whitelistAccount,
-                nftAta: nftTokenAccount,
+                denylist: denylistAccount,
+                traitsRegistry: traitsRegistryAccount,
+                nftAccount: nftTokenAccount,
                 nftMetadata: nft1.metadataAddress,
                 nftMint: nft1.mintAddress,
                 user: userKeypair1.publicKey,
@@ -207,25 +617,125 @@ describe("qstaking", () => {
     it("Character #2 account initialized!", async () => {
         
         const [characterAccount, bump] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("character"), nft2.mintAddress.toBuffer()], program.programId);
+        const [denylistAccount, bump3] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("denylist"), nft2.mintAddress.toBuffer()], program.programId);
         const whitelistAccount = await getWhitelistAccount(creatorKeypair, program.programId, creatorWhitelist, nft2);
         const nftTokenAccount = await spl.getAssociatedTokenAddress(nft2.mintAddress, userKeypair2.publicKey);
         
-        const tx = program.transaction.initCharacter({
+        const [referrerCharacterAccount, bump2] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("character"), nft1.mintAddress.toBuffer()], program.programId);
+        
+        const tx = program.transaction.initCharacter(null, null, nft1.mintAddress, {
             accounts: {
                 character: characterAccount,
                 whitelist: whitelistAccount,
-                nftAta: nftTokenAccount,
+                denylist: denylistAccount,
+                traitsRegistry: traitsRegistryAccount,
+                nftAccount: nftTokenAccount,
                 nftMetadata: nft2.metadataAddress,
                 nftMint: nft2.mintAddress,
                 user: userKeypair2.publicKey,
                 associatedTokenProgram: spl.ASSOCIATED_TOKEN_PROGRAM_ID,
                 systemProgram: anchor.web3.SystemProgram.programId,
             },
+            remainingAccounts: [
+                {pubkey: referrerCharacterAccount, isWritable: false, isSigner: false},
+            ],
         });
         
         const signature = await anchor.web3.sendAndConfirmTransaction(provider.connection, tx, [userKeypair2], {skipPreflight: true});
         console.log(`\tCharacter #2 account initialization transaction: ${signature}`);
         
+        const characterAccountData = await program.account.character.fetch(characterAccount);
+        assert.equal(characterAccountData.referrer.toString(), nft1.mintAddress.toString());
+    });
+    
+    
+    it("Legacy character migrated!", async () => {
+        
+        // Fixture accounts loaded by the test validator, see Anchor.toml
+        const legacyMintV0 = new PublicKey("k7FaK87WHGVXzkaoHb7CdVPgkKDQhZ29VLDeBVbDfYn");
+        const legacyNftAccountV0 = new PublicKey("ws91DX9HBAAxGW77BZs5FogRDwpRtcUpiLBpKdPTfWu");
+        
+        const [characterAccountV0, bump0] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("character"), legacyMintV0.toBuffer()], program.programId);
+        
+        const tx = new anchor.web3.Transaction();
+        
+        // The Character staked before versioning passes the token account holding its NFT
+        const ix1 = program.instruction.migrateCharacter({
+            accounts: {
+                character: characterAccountV0,
+                nftMint: legacyMintV0,
+                payer: userKeypair1.publicKey,
+                systemProgram: anchor.web3.SystemProgram.programId,
+            },
+            remainingAccounts: [
+                {pubkey: legacyNftAccountV0, isWritable: false, isSigner: false},
+            ],
+        });
+        tx.add(ix1);
+        
+        const signature = await anchor.web3.sendAndConfirmTransaction(provider.connection, tx, [userKeypair1], {skipPreflight: true});
+        console.log(`\tLegacy character migration transaction: ${signature}`);
+        
+        const characterV0Data = await program.account.character.fetch(characterAccountV0);
+        assert.equal(characterV0Data.version, 1);
+        assert.equal(characterV0Data.bump, bump0);
+        assert.equal(characterV0Data.level, 3);
+        assert.equal(characterV0Data.tier, 0);
+        assert.equal(characterV0Data.experience.toNumber(), 7200);
+        assert.equal(characterV0Data.miningPoints.toNumber(), 5);
+        assert.equal(characterV0Data.miningCapacity.toNumber(), 2000000);
+        assert.equal(characterV0Data.lootingPoints.toNumber(), 2);
+        assert.equal(characterV0Data.bountyPoints.toNumber(), 1);
+        assert.equal(characterV0Data.bountyBullets, 4);
+        assert.equal(characterV0Data.miningRewards.toNumber(), 123456);
+        assert.equal(characterV0Data.lootingRewards.toNumber(), 7890);
+        assert.equal(characterV0Data.staked, 1);
+        assert.equal(characterV0Data.custody, 0);
+        assert.equal(characterV0Data.nftAccount.toString(), legacyNftAccountV0.toString());
+        assert.equal(characterV0Data.stakedPeg.toNumber(), 1000);
+        assert.equal(characterV0Data.stakedTimestamp.toNumber(), 1650000000);
+        assert.equal(characterV0Data.lootCooldownTimestamp.toNumber(), 1650003600);
+        assert.equal(characterV0Data.stakedWeight.toNumber(), 5);
+        assert.equal(characterV0Data.gearMints.filter((gearMint) => !gearMint.equals(PublicKey.default)).length, 0);
+        
+        // Migrating again is rejected
+        const retryTx = new anchor.web3.Transaction().add(ix1);
+        await assert.rejects(anchor.web3.sendAndConfirmTransaction(provider.connection, retryTx, [userKeypair1]));
+    });
+    
+    
+    it("Legacy whitelist migrated!", async () => {
+    
+        // Fixture account loaded by the test validator, see Anchor.toml
+        const legacyMintV0 = new PublicKey("k7FaK87WHGVXzkaoHb7CdVPgkKDQhZ29VLDeBVbDfYn");
+        const [whitelistAccount, bump] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("whitelist"), legacyMintV0.toBuffer()], program.programId);
+        
+        const migrateTx = (authority: Keypair) => program.transaction.migrateWhitelist(legacyMintV0, {
+            accounts: {
+                adminSettings: adminSettingsAccount,
+                whitelist: whitelistAccount,
+                authority: authority.publicKey,
+                systemProgram: anchor.web3.SystemProgram.programId,
+            },
+        });
+        
+        // Only the admin migrates whitelists
+        await assert.rejects(anchor.web3.sendAndConfirmTransaction(provider.connection, migrateTx(userKeypair1), [userKeypair1]));
+        
+        const signature = await anchor.web3.sendAndConfirmTransaction(provider.connection, migrateTx(adminKeypair), [adminKeypair], {skipPreflight: true});
+        console.log(`\tLegacy whitelist migration transaction: ${signature}`);
+        
+        // Whitelists created before versioning stay enabled without expiry nor Character quota
+        const whitelistAccountData = await program.account.whitelist.fetch(whitelistAccount);
+        assert.equal(whitelistAccountData.version, 1);
+        assert.equal(whitelistAccountData.bump, bump);
+        assert.equal(whitelistAccountData.whitelistType, 0);
+        assert.equal(whitelistAccountData.enabled, true);
+        assert.equal(whitelistAccountData.expiryTimestamp.toNumber(), 0);
+        assert.equal(whitelistAccountData.maxCharacters, 0);
+        
+        // Migrating again is rejected
+        await assert.rejects(anchor.web3.sendAndConfirmTransaction(provider.connection, migrateTx(adminKeypair), [adminKeypair]));
     });
     
     
@@ -273,7 +783,7 @@ describe("qstaking", () => {
                     lootProceeds: lootProceedsAccount,
                     mint: rewardMintAccount,
                     authority: adminKeypair.publicKey,
-                    tokenProgram: spl.TOKEN_PROGRAM_ID,
+                    tokenProgram: rewardTokenProgram,
                     systemProgram: anchor.web3.SystemProgram.programId,
                     rent: anchor.web3.SYSVAR_RENT_PUBKEY
                 },
@@ -285,6 +795,7 @@ describe("qstaking", () => {
             const mineAccountData = await program.account.mine.fetch(mineAccount);
             assert.equal(mineAccountData.locked, true);
             assert.equal(mineAccountData.manager.toString(), managerKeyepair.publicKey.toString());
+            assert.equal(mineAccountData.version, 1);
             assert.equal(mineAccountData.mint.toString(), rewardMintAccount.toString());
             assert.equal(mineAccountData.rate, 0);
             assert.equal(mineAccountData.price, 0);
@@ -311,6 +822,51 @@ describe("qstaking", () => {
     
     
     /*
+    it("Current admin and pool accounts not migrated again!", async () => {
+    
+        const adminSettingsInfo = await provider.connection.getAccountInfo(adminSettingsAccount);
+        const mineInfo = await provider.connection.getAccountInfo(mineAccount);
+        const lootInfo = await provider.connection.getAccountInfo(lootAccount);
+        
+        const adminTx = program.transaction.migrateAdmin({
+            accounts: {
+                adminSettings: adminSettingsAccount,
+                program: program.programId,
+                programData: programDataAccount,
+                authority: authorityKeypair.publicKey,
+                systemProgram: anchor.web3.SystemProgram.programId,
+            },
+        });
+        
+        const mineTx = program.transaction.migrateMine({
+            accounts: {
+                adminSettings: adminSettingsAccount,
+                mine: mineAccount,
+                authority: adminKeypair.publicKey,
+                systemProgram: anchor.web3.SystemProgram.programId,
+            },
+        });
+        
+        const lootTx = program.transaction.migrateLoot({
+            accounts: {
+                adminSettings: adminSettingsAccount,
+                loot: lootAccount,
+                authority: adminKeypair.publicKey,
+                systemProgram: anchor.web3.SystemProgram.programId,
+            },
+        });
+        
+        await assert.rejects(anchor.web3.sendAndConfirmTransaction(provider.connection, adminTx, [authorityKeypair]));
+        await assert.rejects(anchor.web3.sendAndConfirmTransaction(provider.connection, mineTx, [adminKeypair]));
+        await assert.rejects(anchor.web3.sendAndConfirmTransaction(provider.connection, lootTx, [adminKeypair]));
+        
+        // Accounts at the current layout version are left untouched
+        assert.ok((await provider.connection.getAccountInfo(adminSettingsAccount)).data.equals(adminSettingsInfo.data));
+        assert.ok((await provider.connection.getAccountInfo(mineAccount)).data.equals(mineInfo.data));
+        assert.ok((await provider.connection.getAccountInfo(lootAccount)).data.equals(lootInfo.data));
+    });
+    
+    
     it("Mine is initialized!", async () => {
         
         if (await provider.connection.getAccountInfo(mineAccount)) {
@@ -380,9 +936,11 @@ describe("qstaking", () => {
     });
     
     
-    it("Mine lock set!", async () => {
+    it("Mine schedule set!", async () => {
         
-        const tx = program.transaction.setMineLock(false, {
+        const decayInterval = 3600;
+        const stepTimestamps = Array(8).fill(new anchor.BN(0));
+        const tx = program.transaction.setMineSchedule(2, stepTimestamps, new anchor.BN(decayInterval), 5000, new anchor.BN(1), {
             accounts: {
                 mine: mineAccount,
                 manager: managerKeyepair.publicKey,
@@ -390,61 +948,322 @@ describe("qstaking", () => {
         });
         
         const signature = await anchor.web3.sendAndConfirmTransaction(provider.connection, tx, [managerKeyepair], {skipPreflight: true});
-        console.log(`\tSet mine rate transaction: ${signature}`);
+        console.log(`\tSet mine schedule transaction: ${signature}`);
         
         const mineAccountData = await program.account.mine.fetch(mineAccount);
-        assert.equal(mineAccountData.locked, false);
+        assert.equal(mineAccountData.schedule, 2);
+        assert.equal(mineAccountData.decayBps, 5000);
+        assert.equal(mineAccountData.rate, mine_rate);
+        assert.equal(mineAccountData.nextStepTimestamp.toNumber(), mineAccountData.accruedTimestamp.toNumber() + decayInterval);
     });
     
     
-    it("Mine updated!", async () => {
-        
-        const mineAccountData1 = await program.account.mine.fetch(mineAccount);
-        
-        await new Promise(f => setTimeout(f, 2000));
-        
-        const keypair = Keypair.generate();
-        const airdropSignature = await provider.connection.requestAirdrop(keypair.publicKey, 1e9);
-        await provider.connection.confirmTransaction(airdropSignature);
+    it("Mine emission cap set!", async () => {
         
-        const tx = program.transaction.updateMine({
+        const emissionCap = new anchor.BN("1000000000000000");
+        const tx = program.transaction.setMineEmissionCap(emissionCap, {
             accounts: {
+                adminSettings: adminSettingsAccount,
                 mine: mineAccount,
+                authority: adminKeypair.publicKey,
             },
         });
         
-        const signature = await anchor.web3.sendAndConfirmTransaction(provider.connection, tx, [managerKeyepair], {skipPreflight: true});
-        console.log(`\tUpdate mine transaction: ${signature}`);
-        
-        const mineAccountData2 = await program.account.mine.fetch(mineAccount);
+        const signature = await anchor.web3.sendAndConfirmTransaction(provider.connection, tx, [adminKeypair], {skipPreflight: true});
+        console.log(`\tSet mine emission cap transaction: ${signature}`);
         
-        const expectedReward = mineAccountData1.accruedRewards.toNumber() + mineAccountData1.rate.toNumber() * (mineAccountData2.accruedTimestamp.toNumber() - mineAccountData1.accruedTimestamp.toNumber());
-        assert.equal(mineAccountData2.accruedRewards, expectedReward);
+        const mineAccountData = await program.account.mine.fetch(mineAccount);
+        assert.equal(mineAccountData.emissionCap.toString(), emissionCap.toString());
     });
     
     
-    it("Loot parameters set!", async () => {
+    it("Mine lock tiers set!", async () => {
         
-        const tx = program.transaction.setLootParameters(new anchor.BN(loot_duration), new anchor.BN(loot_price), new anchor.BN(loot_cooldown), {
+        const day = 24 * 3600;
+        const lockDurations = [new anchor.BN(7 * day), new anchor.BN(30 * day), new anchor.BN(90 * day)];
+        const tx = program.transaction.setMineLockTiers(lockDurations, [11000, 12500, 15000], {
             accounts: {
-                loot: lootAccount,
+                mine: mineAccount,
                 manager: managerKeyepair.publicKey,
             },
         });
         
         const signature = await anchor.web3.sendAndConfirmTransaction(provider.connection, tx, [managerKeyepair], {skipPreflight: true});
-        console.log(`\tSet loot parameters transaction: ${signature}`);
+        console.log(`\tSet mine lock tiers transaction: ${signature}`);
         
-        const lootAccountData = await program.account.loot.fetch(lootAccount);
-        assert.equal(lootAccountData.duration, loot_duration);
-        assert.equal(lootAccountData.price, loot_price);
-        assert.equal(lootAccountData.cooldown, loot_cooldown);
+        const mineAccountData = await program.account.mine.fetch(mineAccount);
+        assert.equal(mineAccountData.lockDurations[2].toNumber(), 90 * day);
+        assert.equal(mineAccountData.lockMultipliersBps[2], 15000);
     });
     
     
-    it("Loot lock set!", async () => {
+    it("Boost registry initialized!", async () => {
         
-        const tx = program.transaction.setLootLock(false, {
+        const [boostRegistryAccount, bump] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("boosts")], program.programId);
+        
+        if (!await provider.connection.getAccountInfo(boostRegistryAccount)) {
+            const initTx = program.transaction.initBoostRegistry({
+                accounts: {
+                    mine: mineAccount,
+                    boostRegistry: boostRegistryAccount,
+                    manager: managerKeyepair.publicKey,
+                    systemProgram: anchor.web3.SystemProgram.programId,
+                },
+            });
+            
+            const initSignature = await anchor.web3.sendAndConfirmTransaction(provider.connection, initTx, [managerKeyepair], {skipPreflight: true});
+            console.log(`\tInit boost registry transaction: ${initSignature}`);
+        }
+        
+        const boostMintAccount = await spl.createMint(provider.connection, authorityKeypair, authorityKeypair.publicKey, null, 0);
+        const tx = program.transaction.setMineBoost(0, boostMintAccount, 20000, new anchor.BN(3600), {
+            accounts: {
+                mine: mineAccount,
+                boostRegistry: boostRegistryAccount,
+                manager: managerKeyepair.publicKey,
+            },
+        });
+        
+        const signature = await anchor.web3.sendAndConfirmTransaction(provider.connection, tx, [managerKeyepair], {skipPreflight: true});
+        console.log(`\tSet mine boost transaction: ${signature}`);
+        
+        const boostRegistryAccountData = await program.account.boostRegistry.fetch(boostRegistryAccount);
+        assert.equal(boostRegistryAccountData.boosts[0].mint.toString(), boostMintAccount.toString());
+        assert.equal(boostRegistryAccountData.boosts[0].multiplierBps, 20000);
+    });
+    
+    
+    it("Upgrade shop initialized!", async () => {
+        
+        const [upgradeShopAccount, bump] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("upgrades")], program.programId);
+        
+        if (!await provider.connection.getAccountInfo(upgradeShopAccount)) {
+            const initTx = program.transaction.initUpgradeShop({
+                accounts: {
+                    mine: mineAccount,
+                    upgradeShop: upgradeShopAccount,
+                    manager: managerKeyepair.publicKey,
+                    systemProgram: anchor.web3.SystemProgram.programId,
+                },
+            });
+            
+            const initSignature = await anchor.web3.sendAndConfirmTransaction(provider.connection, initTx, [managerKeyepair], {skipPreflight: true});
+            console.log(`\tInit upgrade shop transaction: ${initSignature}`);
+        }
+        
+        const tx = program.transaction.setMineUpgrade(0, new anchor.BN(1), 15000, new anchor.BN(10), 5, {
+            accounts: {
+                mine: mineAccount,
+                upgradeShop: upgradeShopAccount,
+                manager: managerKeyepair.publicKey,
+            },
+        });
+        
+        const signature = await anchor.web3.sendAndConfirmTransaction(provider.connection, tx, [managerKeyepair], {skipPreflight: true});
+        console.log(`\tSet mine upgrade transaction: ${signature}`);
+        
+        const upgradeShopAccountData = await program.account.upgradeShop.fetch(upgradeShopAccount);
+        assert.equal(upgradeShopAccountData.upgrades[0].baseCost.toNumber(), 1);
+        assert.equal(upgradeShopAccountData.upgrades[0].maxLevel, 5);
+        
+        // Curves whose last level costs more than a u64 are rejected
+        const overflowTx = program.transaction.setMineUpgrade(1, new anchor.BN(1e9), 60000, new anchor.BN(10), 40, {
+            accounts: {
+                mine: mineAccount,
+                upgradeShop: upgradeShopAccount,
+                manager: managerKeyepair.publicKey,
+            },
+        });
+        
+        await assert.rejects(anchor.web3.sendAndConfirmTransaction(provider.connection, overflowTx, [managerKeyepair]));
+    });
+    
+    
+    it("Guild created!", async () => {
+        
+        const guildBonusTx = program.transaction.setMineGuildBonus(500, 2500, {
+            accounts: {
+                mine: mineAccount,
+                manager: managerKeyepair.publicKey,
+            },
+        });
+        
+        const guildBonusSignature = await anchor.web3.sendAndConfirmTransaction(provider.connection, guildBonusTx, [managerKeyepair], {skipPreflight: true});
+        console.log(`\tSet mine guild bonus transaction: ${guildBonusSignature}`);
+        
+        const [guildAccount, bump] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("guild"), userKeypair2.publicKey.toBuffer()], program.programId);
+        const [guildTreasuryAccount, bump2] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("treasury"), guildAccount.toBuffer()], program.programId);
+        
+        const tx = program.transaction.createGuild(1000, {
+            accounts: {
+                mine: mineAccount,
+                guild: guildAccount,
+                guildTreasury: guildTreasuryAccount,
+                rewardMint: rewardMintAccount,
+                leader: userKeypair2.publicKey,
+                rewardTokenProgram: rewardTokenProgram,
+                systemProgram: anchor.web3.SystemProgram.programId,
+            },
+        });
+        
+        const signature = await anchor.web3.sendAndConfirmTransaction(provider.connection, tx, [userKeypair2], {skipPreflight: true});
+        console.log(`\tCreate guild transaction: ${signature}`);
+        
+        const mineAccountData = await program.account.mine.fetch(mineAccount);
+        assert.equal(mineAccountData.guildBonusBps, 500);
+        assert.equal(mineAccountData.guildBonusCapBps, 2500);
+        
+        const guildAccountData = await program.account.guild.fetch(guildAccount);
+        assert.equal(guildAccountData.leader.toString(), userKeypair2.publicKey.toString());
+        assert.equal(guildAccountData.treasury.toString(), guildTreasuryAccount.toString());
+        assert.equal(guildAccountData.cutBps, 1000);
+        assert.equal(guildAccountData.members, 0);
+        assert.equal(guildAccountData.version, 1);
+        assert.equal(guildAccountData.bonusAccrued.toNumber(), 0);
+    });
+    
+    
+    it("Mine referral set!", async () => {
+        
+        const tx = program.transaction.setMineReferral(500, new anchor.BN(30 * 24 * 3600), new anchor.BN(1e9), {
+            accounts: {
+                mine: mineAccount,
+                manager: managerKeyepair.publicKey,
+            },
+        });
+        
+        const signature = await anchor.web3.sendAndConfirmTransaction(provider.connection, tx, [managerKeyepair], {skipPreflight: true});
+        console.log(`\tSet mine referral transaction: ${signature}`);
+        
+        const mineAccountData = await program.account.mine.fetch(mineAccount);
+        assert.equal(mineAccountData.referralBps, 500);
+        assert.equal(mineAccountData.referralCap.toNumber(), 1e9);
+    });
+    
+    
+    it("Season started!", async () => {
+        
+        let mineAccountData = await program.account.mine.fetch(mineAccount);
+        const locked = mineAccountData.locked;
+        
+        if (mineAccountData.seasonActive) {
+            const [currentSeasonAccount, bump] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("season"), mineAccountData.season.toArrayLike(Buffer, "le", 8)], program.programId);
+            const endTx = program.transaction.endSeason({
+                accounts: {
+                    adminSettings: adminSettingsAccount,
+                    mine: mineAccount,
+                    loot: lootAccount,
+                    season: currentSeasonAccount,
+                    authority: adminKeypair.publicKey,
+                },
+            });
+            
+            const endSignature = await anchor.web3.sendAndConfirmTransaction(provider.connection, endTx, [adminKeypair], {skipPreflight: true});
+            console.log(`\tEnd season transaction: ${endSignature}`);
+            
+            const seasonAccountData = await program.account.season.fetch(currentSeasonAccount);
+            assert.ok(seasonAccountData.endTimestamp.toNumber() > 0);
+        }
+        
+        const seasonId = mineAccountData.season.addn(1);
+        const [seasonAccount, bump] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("season"), seasonId.toArrayLike(Buffer, "le", 8)], program.programId);
+        
+        const tx = program.transaction.startSeason(seasonId, {
+            accounts: {
+                adminSettings: adminSettingsAccount,
+                mine: mineAccount,
+                loot: lootAccount,
+                leaderboard: leaderboardAccount,
+                season: seasonAccount,
+                authority: adminKeypair.publicKey,
+                systemProgram: anchor.web3.SystemProgram.programId,
+            },
+        });
+        
+        const signature = await anchor.web3.sendAndConfirmTransaction(provider.connection, tx, [adminKeypair], {skipPreflight: true});
+        console.log(`\tStart season transaction: ${signature}`);
+        
+        mineAccountData = await program.account.mine.fetch(mineAccount);
+        assert.equal(mineAccountData.season.toString(), seasonId.toString());
+        assert.equal(mineAccountData.seasonActive, true);
+        assert.equal(mineAccountData.locked, locked);
+        
+        const lootAccountData = await program.account.loot.fetch(lootAccount);
+        assert.equal(lootAccountData.season.toString(), seasonId.toString());
+        
+        const leaderboardAccountData = await program.account.leaderboard.fetch(leaderboardAccount);
+        assert.equal(leaderboardAccountData.season.toString(), seasonId.toString());
+        
+        const seasonAccountData = await program.account.season.fetch(seasonAccount);
+        assert.equal(seasonAccountData.seasonId.toString(), seasonId.toString());
+    });
+    
+    
+    it("Mine lock set!", async () => {
+        
+        const tx = program.transaction.setMineLock(false, {
+            accounts: {
+                mine: mineAccount,
+                manager: managerKeyepair.publicKey,
+            },
+        });
+        
+        const signature = await anchor.web3.sendAndConfirmTransaction(provider.connection, tx, [managerKeyepair], {skipPreflight: true});
+        console.log(`\tSet mine rate transaction: ${signature}`);
+        
+        const mineAccountData = await program.account.mine.fetch(mineAccount);
+        assert.equal(mineAccountData.locked, false);
+    });
+    
+    
+    it("Mine updated!", async () => {
+        
+        const mineAccountData1 = await program.account.mine.fetch(mineAccount);
+        
+        await new Promise(f => setTimeout(f, 2000));
+        
+        const keypair = Keypair.generate();
+        const airdropSignature = await provider.connection.requestAirdrop(keypair.publicKey, 1e9);
+        await provider.connection.confirmTransaction(airdropSignature);
+        
+        const tx = program.transaction.updateMine({
+            accounts: {
+                mine: mineAccount,
+            },
+        });
+        
+        const signature = await anchor.web3.sendAndConfirmTransaction(provider.connection, tx, [managerKeyepair], {skipPreflight: true});
+        console.log(`\tUpdate mine transaction: ${signature}`);
+        
+        const mineAccountData2 = await program.account.mine.fetch(mineAccount);
+        
+        const expectedReward = mineAccountData1.accruedRewards.toNumber() + mineAccountData1.rate.toNumber() * (mineAccountData2.accruedTimestamp.toNumber() - mineAccountData1.accruedTimestamp.toNumber());
+        assert.equal(mineAccountData2.accruedRewards, expectedReward);
+    });
+    
+    
+    it("Loot parameters set!", async () => {
+        
+        const tx = program.transaction.setLootParameters(new anchor.BN(loot_duration), new anchor.BN(loot_price), new anchor.BN(loot_cooldown), {
+            accounts: {
+                loot: lootAccount,
+                manager: managerKeyepair.publicKey,
+            },
+        });
+        
+        const signature = await anchor.web3.sendAndConfirmTransaction(provider.connection, tx, [managerKeyepair], {skipPreflight: true});
+        console.log(`\tSet loot parameters transaction: ${signature}`);
+        
+        const lootAccountData = await program.account.loot.fetch(lootAccount);
+        assert.equal(lootAccountData.duration, loot_duration);
+        assert.equal(lootAccountData.price, loot_price);
+        assert.equal(lootAccountData.cooldown, loot_cooldown);
+    });
+    
+    
+    it("Loot lock set!", async () => {
+        
+        const tx = program.transaction.setLootLock(false, {
             accounts: {
                 loot: lootAccount,
                 manager: managerKeyepair.publicKey,
@@ -482,21 +1301,27 @@ describe("qstaking", () => {
     it("User #1 miner staked!", async () => {
         
         const [characterAccount, bump] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("character"), nft1.mintAddress.toBuffer()], program.programId);
+        const [denylistAccount, bump3] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("denylist"), nft1.mintAddress.toBuffer()], program.programId);
         const [nftEditionAccount, bump2] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("metadata"), tokenMetadataProgram.toBuffer(), nft1.mintAddress.toBuffer(), Buffer.from("edition")], tokenMetadataProgram);
         const whitelistAccount = await getWhitelistAccount(creatorKeypair, program.programId, creatorWhitelist, nft1);
         const nftTokenAccount = await spl.getAssociatedTokenAddress(nft1.mintAddress, userKeypair1.publicKey);
         
-        const tx = program.transaction.stakeMine({
+        const tx = program.transaction.stakeMine(null, 0, {
            
             accounts: {
                 mine: mineAccount,
                 character: characterAccount,
                 whitelist: whitelistAccount,
-                nftAta: nftTokenAccount,
+                denylist: denylistAccount,
+                nftAccount: nftTokenAccount,
                 nftEdition: nftEditionAccount,
                 nftMetadata: nft1.metadataAddress,
                 nftMint: nft1.mintAddress,
                 user: userKeypair1.publicKey,
+                nftTokenRecord: getTokenRecordAccount(tokenMetadataProgram, nft1.mintAddress, nftTokenAccount),
+                authorizationRules: tokenMetadataProgram,
+                authorizationRulesProgram: tokenMetadataProgram,
+                sysvarInstructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
                 tokenMetadataProgram: tokenMetadataProgram,
                 tokenProgram: spl.TOKEN_PROGRAM_ID,
                 associatedTokenProgram: spl.ASSOCIATED_TOKEN_PROGRAM_ID,
@@ -515,8 +1340,11 @@ describe("qstaking", () => {
         
         const characterAccountData = await program.account.character.fetch(characterAccount);
         assert.equal(characterAccountData.staked, 1);
+        assert.equal(characterAccountData.nftAccount.toString(), nftTokenAccount.toString());
         assert.ok(characterAccountData.stakedTimestamp > 0);
         assert.ok(characterAccountData.stakedPeg > 0);
+        assert.equal(characterAccountData.lockTier, 0);
+        assert.equal(characterAccountData.stakedWeight.toString(), characterAccountData.miningPoints.toString());
         
     });
     
@@ -524,21 +1352,27 @@ describe("qstaking", () => {
     it("User #2 looter staked!", async () => {
         
         const [characterAccount, bump] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("character"), nft2.mintAddress.toBuffer()], program.programId);
+        const [denylistAccount, bump3] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("denylist"), nft2.mintAddress.toBuffer()], program.programId);
         const [nftEditionAccount, bump2] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("metadata"), tokenMetadataProgram.toBuffer(), nft2.mintAddress.toBuffer(), Buffer.from("edition")], tokenMetadataProgram);
         const whitelistAccount = await getWhitelistAccount(creatorKeypair, program.programId, creatorWhitelist, nft2);
         const nftTokenAccount = await spl.getAssociatedTokenAddress(nft2.mintAddress, userKeypair2.publicKey);
         
-        const tx = program.transaction.stakeLoot({
+        const tx = program.transaction.stakeLoot(null, {
            
             accounts: {
                 loot: lootAccount,
                 character: characterAccount,
                 whitelist: whitelistAccount,
-                nftAta: nftTokenAccount,
+                denylist: denylistAccount,
+                nftAccount: nftTokenAccount,
                 nftEdition: nftEditionAccount,
                 nftMetadata: nft2.metadataAddress,
                 nftMint: nft2.mintAddress,
                 user: userKeypair2.publicKey,
+                nftTokenRecord: getTokenRecordAccount(tokenMetadataProgram, nft2.mintAddress, nftTokenAccount),
+                authorizationRules: tokenMetadataProgram,
+                authorizationRulesProgram: tokenMetadataProgram,
+                sysvarInstructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
                 tokenMetadataProgram: tokenMetadataProgram,
                 tokenProgram: spl.TOKEN_PROGRAM_ID,
                 associatedTokenProgram: spl.ASSOCIATED_TOKEN_PROGRAM_ID,
@@ -557,6 +1391,7 @@ describe("qstaking", () => {
         
         const characterAccountData = await program.account.character.fetch(characterAccount);
         assert.equal(characterAccountData.staked, 2);
+        assert.equal(characterAccountData.nftAccount.toString(), nftTokenAccount.toString());
         assert.ok(characterAccountData.stakedTimestamp > 0);
         assert.equal(characterAccountData.stakedPeg, 0);
         
@@ -568,29 +1403,41 @@ describe("qstaking", () => {
         await new Promise(f => setTimeout(f, 10000));
         
         const [characterAccount, bump] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("character"), nft1.mintAddress.toBuffer()], program.programId);
+        const [denylistAccount, bump3] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("denylist"), nft1.mintAddress.toBuffer()], program.programId);
         const [nftEditionAccount, bump2] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("metadata"), tokenMetadataProgram.toBuffer(), nft1.mintAddress.toBuffer(), Buffer.from("edition")], tokenMetadataProgram);
         const whitelistAccount = await getWhitelistAccount(creatorKeypair, program.programId, creatorWhitelist, nft1);
         const nftTokenAccount = await spl.getAssociatedTokenAddress(nft1.mintAddress, userKeypair1.publicKey);
-        const rewardAta = await spl.getOrCreateAssociatedTokenAccount(provider.connection, userKeypair1, rewardMintAccount, userKeypair1.publicKey);
+        const rewardAta = await spl.getOrCreateAssociatedTokenAccount(provider.connection, userKeypair1, rewardMintAccount, userKeypair1.publicKey, false, undefined, undefined, rewardTokenProgram);
         
         const tx = program.transaction.unstakeMine({
             accounts: {
                 mine: mineAccount,
                 loot: lootAccount,
                 character: characterAccount,
+                denylist: denylistAccount,
                 lootProceeds: lootProceedsAccount,
-                nftAta: nftTokenAccount,
+                nftAccount: nftTokenAccount,
                 nftEdition: nftEditionAccount,
+                nftMetadata: nft1.metadataAddress,
                 nftMint: nft1.mintAddress,
                 rewardAta: rewardAta.address,
                 rewardMint: rewardMintAccount,
+                vesting: getVestingAccount(program.programId, nft1.mintAddress, userKeypair1.publicKey),
+                vestingEscrow: vestingEscrowAccount,
                 user: userKeypair1.publicKey,
+                nftTokenRecord: getTokenRecordAccount(tokenMetadataProgram, nft1.mintAddress, nftTokenAccount),
+                authorizationRules: tokenMetadataProgram,
+                authorizationRulesProgram: tokenMetadataProgram,
+                sysvarInstructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
                 tokenMetadataProgram: tokenMetadataProgram,
                 tokenProgram: spl.TOKEN_PROGRAM_ID,
+                rewardTokenProgram: rewardTokenProgram,
                 associatedTokenProgram: spl.ASSOCIATED_TOKEN_PROGRAM_ID,
                 systemProgram: anchor.web3.SystemProgram.programId,
             },
-            
+            remainingAccounts: [
+                {pubkey: leaderboardAccount, isWritable: true, isSigner: false},
+            ],
         });
         
         const signature = await anchor.web3.sendAndConfirmTransaction(provider.connection, tx, [userKeypair1], {skipPreflight: true});
@@ -608,6 +1455,15 @@ describe("qstaking", () => {
         assert.ok(characterAccountData.miningRewards > 0);
         assert.ok(characterAccountData.experience > 0);
         
+        const mineAccountData = await program.account.mine.fetch(mineAccount);
+        assert.equal(mineAccountData.totalSessions.toNumber(), 1);
+        assert.equal(mineAccountData.totalMined.toString(), characterAccountData.miningRewards.toString());
+        assert.ok(mineAccountData.totalStakedSeconds > 0);
+        
+        const leaderboardAccountData = await program.account.leaderboard.fetch(leaderboardAccount);
+        assert.equal(leaderboardAccountData.miningRewards[0].nftMint.toString(), nft1.mintAddress.toString());
+        assert.equal(leaderboardAccountData.experience[0].value.toString(), characterAccountData.experience.toString());
+        
         console.log(await program.account.loot.fetch(lootAccount));
         
     });
@@ -618,23 +1474,34 @@ describe("qstaking", () => {
         await new Promise(f => setTimeout(f, 10000));
         
         const [characterAccount, bump] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("character"), nft2.mintAddress.toBuffer()], program.programId);
+        const [denylistAccount, bump3] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("denylist"), nft2.mintAddress.toBuffer()], program.programId);
         const [nftEditionAccount, bump2] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("metadata"), tokenMetadataProgram.toBuffer(), nft2.mintAddress.toBuffer(), Buffer.from("edition")], tokenMetadataProgram);
         const whitelistAccount = await getWhitelistAccount(creatorKeypair, program.programId, creatorWhitelist, nft2);
         const nftTokenAccount = await spl.getAssociatedTokenAddress(nft2.mintAddress, userKeypair2.publicKey);
-        const rewardAta = await spl.getOrCreateAssociatedTokenAccount(provider.connection, userKeypair2, rewardMintAccount, userKeypair2.publicKey);
+        const rewardAta = await spl.getOrCreateAssociatedTokenAccount(provider.connection, userKeypair2, rewardMintAccount, userKeypair2.publicKey, false, undefined, undefined, rewardTokenProgram);
+        const proceedsAmountBefore = (await spl.getAccount(provider.connection, lootProceedsAccount, undefined, rewardTokenProgram)).amount;
+        const rewardAmountBefore = (await spl.getAccount(provider.connection, rewardAta.address, undefined, rewardTokenProgram)).amount;
         
         const tx = program.transaction.unstakeLoot({
             accounts: {
                 loot: lootAccount,
                 character: characterAccount,
+                denylist: denylistAccount,
                 lootProceeds: lootProceedsAccount,
-                nftAta: nftTokenAccount,
+                nftAccount: nftTokenAccount,
                 nftEdition: nftEditionAccount,
+                nftMetadata: nft2.metadataAddress,
                 nftMint: nft2.mintAddress,
                 rewardAta: rewardAta.address,
+                rewardMint: rewardMintAccount,
                 user: userKeypair2.publicKey,
+                nftTokenRecord: getTokenRecordAccount(tokenMetadataProgram, nft2.mintAddress, nftTokenAccount),
+                authorizationRules: tokenMetadataProgram,
+                authorizationRulesProgram: tokenMetadataProgram,
+                sysvarInstructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
                 tokenMetadataProgram: tokenMetadataProgram,
                 tokenProgram: spl.TOKEN_PROGRAM_ID,
+                rewardTokenProgram: rewardTokenProgram,
                 associatedTokenProgram: spl.ASSOCIATED_TOKEN_PROGRAM_ID,
                 systemProgram: anchor.web3.SystemProgram.programId,
             },
@@ -657,78 +1524,2446 @@ describe("qstaking", () => {
         assert.ok(characterAccountData.lootingRewards > 0);
         assert.ok(characterAccountData.experience > 0);
         
-    });
-
-    
-    it("Whitelist account(s) removed!", async () => {
-        
-        const tx = new anchor.web3.Transaction();
+        const lootAccountData = await program.account.loot.fetch(lootAccount);
+        assert.equal(lootAccountData.totalPaid.toString(), characterAccountData.lootingRewards.toString());
+        assert.ok(lootAccountData.totalReceived > 0);
         
-        if (creatorWhitelist) {
-            
-            const [whitelistAccount, bump] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("whitelist"), creatorKeypair.publicKey.toBuffer()], program.programId);
-            
-            const ix = program.instruction.removeWhitelist(creatorKeypair.publicKey, {
-                accounts: {
-                    adminSettings: adminSettingsAccount,
-                    whitelist: whitelistAccount,
-                    authority: adminKeypair.publicKey,
-                    systemProgram: anchor.web3.SystemProgram.programId,
-                }
-            });
-            tx.add(ix);
-        }
+        // The loot proceeds pay out the full looting rewards, the transfer fee is withheld from what the looter receives
+        const lootingRewards = BigInt(characterAccountData.lootingRewards.toString());
+        const transferFee = spl.calculateFee(spl.getTransferFeeConfig(await spl.getMint(provider.connection, rewardMintAccount, undefined, rewardTokenProgram)).newerTransferFee, lootingRewards);
+        assert.ok(transferFee > BigInt(0));
         
-        for (let nft of mintWhitelist) {
-            
-            const [whitelistAccount, bump] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("whitelist"), nft.mintAddress.toBuffer()], program.programId);
-            
-            const ix = program.instruction.removeWhitelist(nft.mintAddress, {
-                accounts: {
-                    adminSettings: adminSettingsAccount,
-                    whitelist: whitelistAccount,
-                    authority: adminKeypair.publicKey,
-                    systemProgram: anchor.web3.SystemProgram.programId,
-                }
-            });
-            tx.add(ix);
-        }
+        const proceedsAmountAfter = (await spl.getAccount(provider.connection, lootProceedsAccount, undefined, rewardTokenProgram)).amount;
+        assert.equal((proceedsAmountBefore - proceedsAmountAfter).toString(), lootingRewards.toString());
         
-        const signature = await anchor.web3.sendAndConfirmTransaction(provider.connection, tx, [adminKeypair], {skipPreflight: true});
-        console.log(`\tRemove creator whitelist account transaction: ${signature}`);
+        const rewardAtaData = await spl.getAccount(provider.connection, rewardAta.address, undefined, rewardTokenProgram);
+        assert.equal((rewardAtaData.amount - rewardAmountBefore).toString(), (lootingRewards - transferFee).toString());
+        assert.equal(spl.getTransferFeeAmount(rewardAtaData).withheldAmount.toString(), transferFee.toString());
         
     });
     
-});
-
-
-async function createNFT(connection: anchor.web3.Connection, creatorKeypair: Keypair, ownerKeypair: Keypair, name: string, uri: string): Promise<PublicKey> {
-    const metaplex = new Metaplex(connection);
-    metaplex.use(keypairIdentity(creatorKeypair));
-    
-    const nftTask = metaplex.nfts().create({
-        name: name,
-        uri: uri,
-        sellerFeeBasisPoints: 500, // Represents 5.00%.
-        payer: ownerKeypair,
-        updateAuthority: creatorKeypair,
-        tokenOwner: ownerKeypair.publicKey,
-        symbol: "BSTD",
-    });
     
-    const output = await nftTask.run();
+    it("User #1 gear equipped, locked while staked and unequipped!", async () => {
     
-    return output;
-}
-
-
-async function getWhitelistAccount(creatorKeypair, program_id, creatorWhitelist, nft): Promise<PublicKey> {
-    let bump;
-    let whitelistAccount: PublicKey;
-    if (creatorWhitelist) {
-        [whitelistAccount, bump] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("whitelist"), creatorKeypair.publicKey.toBuffer()], program_id);
-    } else {
-        [whitelistAccount, bump] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("whitelist"), nft.mintAddress.toBuffer()], program_id);
-    }
-    return whitelistAccount
+        // Gear of this collection adds a flat 2 mining points
+        const gearCreatorKeypair = Keypair.generate();
+        const [gearCollectionAccount, bump4] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("gear"), gearCreatorKeypair.publicKey.toBuffer()], program.programId);
+        
+        const collectionTx = program.transaction.addGearCollection(gearCreatorKeypair.publicKey, 0, 0, new anchor.BN(2), {
+            accounts: {
+                adminSettings: adminSettingsAccount,
+                gearCollection: gearCollectionAccount,
+                authority: adminKeypair.publicKey,
+                systemProgram: anchor.web3.SystemProgram.programId,
+            }
+        });
+        
+        const collectionSignature = await anchor.web3.sendAndConfirmTransaction(provider.connection, collectionTx, [adminKeypair], {skipPreflight: true});
+        console.log(`\tAdd gear collection transaction: ${collectionSignature}`);
+        
+        const gearNft1 = await createNFT(provider.connection, gearCreatorKeypair, userKeypair1, 'Pickaxe #1', 'https://raffles-test.s3.amazonaws.com/NFT1.jpg');
+        const gearNft2 = await createNFT(provider.connection, gearCreatorKeypair, userKeypair1, 'Pickaxe #2', 'https://raffles-test.s3.amazonaws.com/NFT1.jpg');
+        
+        const [characterAccount, bump] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("character"), nft1.mintAddress.toBuffer()], program.programId);
+        const [denylistAccount, bump3] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("denylist"), nft1.mintAddress.toBuffer()], program.programId);
+        const [nftEditionAccount, bump2] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("metadata"), tokenMetadataProgram.toBuffer(), nft1.mintAddress.toBuffer(), Buffer.from("edition")], tokenMetadataProgram);
+        const whitelistAccount = await getWhitelistAccount(creatorKeypair, program.programId, creatorWhitelist, nft1);
+        const nftTokenAccount = await spl.getAssociatedTokenAddress(nft1.mintAddress, userKeypair1.publicKey);
+        const rewardAta = await spl.getOrCreateAssociatedTokenAccount(provider.connection, userKeypair1, rewardMintAccount, userKeypair1.publicKey, false, undefined, undefined, rewardTokenProgram);
+        
+        const getEquipTx = async (gearNft) => program.transaction.equipGear({
+            accounts: {
+                gearCollection: gearCollectionAccount,
+                character: characterAccount,
+                nftAccount: nftTokenAccount,
+                nftMint: nft1.mintAddress,
+                equipment: (await anchor.web3.PublicKey.findProgramAddress([Buffer.from("equipment"), gearNft.mintAddress.toBuffer()], program.programId))[0],
+                gearAccount: await spl.getAssociatedTokenAddress(gearNft.mintAddress, userKeypair1.publicKey),
+                gearEdition: (await anchor.web3.PublicKey.findProgramAddress([Buffer.from("metadata"), tokenMetadataProgram.toBuffer(), gearNft.mintAddress.toBuffer(), Buffer.from("edition")], tokenMetadataProgram))[0],
+                gearMetadata: gearNft.metadataAddress,
+                gearMint: gearNft.mintAddress,
+                user: userKeypair1.publicKey,
+                tokenMetadataProgram: tokenMetadataProgram,
+                tokenProgram: spl.TOKEN_PROGRAM_ID,
+                systemProgram: anchor.web3.SystemProgram.programId,
+            },
+        });
+        
+        const getUnequipTx = async (gearNft) => program.transaction.unequipGear({
+            accounts: {
+                character: characterAccount,
+                nftMint: nft1.mintAddress,
+                equipment: (await anchor.web3.PublicKey.findProgramAddress([Buffer.from("equipment"), gearNft.mintAddress.toBuffer()], program.programId))[0],
+                gearAccount: await spl.getAssociatedTokenAddress(gearNft.mintAddress, userKeypair1.publicKey),
+                gearEdition: (await anchor.web3.PublicKey.findProgramAddress([Buffer.from("metadata"), tokenMetadataProgram.toBuffer(), gearNft.mintAddress.toBuffer(), Buffer.from("edition")], tokenMetadataProgram))[0],
+                gearMint: gearNft.mintAddress,
+                user: userKeypair1.publicKey,
+                tokenMetadataProgram: tokenMetadataProgram,
+                tokenProgram: spl.TOKEN_PROGRAM_ID,
+            },
+        });
+        
+        // Only NFTs of an added gear collection can be equipped
+        await assert.rejects(anchor.web3.sendAndConfirmTransaction(provider.connection, await getEquipTx(nft3), [userKeypair1]));
+        
+        const characterAccountData1 = await program.account.character.fetch(characterAccount);
+        
+        const equipSignature = await anchor.web3.sendAndConfirmTransaction(provider.connection, await getEquipTx(gearNft1), [userKeypair1], {skipPreflight: true});
+        console.log(`\tUser #1 equip gear transaction: ${equipSignature}`);
+        
+        // The gear stays in the wallet, frozen with the Character as delegate
+        const gearAccount = await spl.getAssociatedTokenAddress(gearNft1.mintAddress, userKeypair1.publicKey);
+        let gearAccountData = await spl.getAccount(provider.connection, gearAccount);
+        assert.equal(gearAccountData.isFrozen, true);
+        assert.equal(gearAccountData.delegate.toString(), characterAccount.toString());
+        assert.equal(gearAccountData.amount, 1);
+        
+        const characterAccountData2 = await program.account.character.fetch(characterAccount);
+        assert.equal(characterAccountData2.miningPoints.toNumber(), characterAccountData1.miningPoints.toNumber() + 2);
+        assert.equal(characterAccountData2.gearMints[0].toString(), gearNft1.mintAddress.toString());
+        
+        const stakeTx = program.transaction.stakeMine(null, 0, {
+            accounts: {
+                mine: mineAccount,
+                character: characterAccount,
+                whitelist: whitelistAccount,
+                denylist: denylistAccount,
+                nftAccount: nftTokenAccount,
+                nftEdition: nftEditionAccount,
+                nftMetadata: nft1.metadataAddress,
+                nftMint: nft1.mintAddress,
+                user: userKeypair1.publicKey,
+                nftTokenRecord: getTokenRecordAccount(tokenMetadataProgram, nft1.mintAddress, nftTokenAccount),
+                authorizationRules: tokenMetadataProgram,
+                authorizationRulesProgram: tokenMetadataProgram,
+                sysvarInstructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+                tokenMetadataProgram: tokenMetadataProgram,
+                tokenProgram: spl.TOKEN_PROGRAM_ID,
+                associatedTokenProgram: spl.ASSOCIATED_TOKEN_PROGRAM_ID,
+                systemProgram: anchor.web3.SystemProgram.programId,
+            },
+        });
+        
+        const stakeSignature = await anchor.web3.sendAndConfirmTransaction(provider.connection, stakeTx, [userKeypair1], {skipPreflight: true});
+        console.log(`\tUser #1 stake geared miner transaction: ${stakeSignature}`);
+        
+        // The gear bonus is part of the stake weight
+        const stakedCharacterData = await program.account.character.fetch(characterAccount);
+        assert.equal(stakedCharacterData.stakedWeight.toString(), characterAccountData2.miningPoints.toString());
+        
+        // Gear can neither be equipped nor unequipped while the Character is staked
+        await assert.rejects(anchor.web3.sendAndConfirmTransaction(provider.connection, await getEquipTx(gearNft2), [userKeypair1]));
+        await assert.rejects(anchor.web3.sendAndConfirmTransaction(provider.connection, await getUnequipTx(gearNft1), [userKeypair1]));
+        
+        const unstakeTx = program.transaction.unstakeMine({
+            accounts: {
+                mine: mineAccount,
+                loot: lootAccount,
+                character: characterAccount,
+                denylist: denylistAccount,
+                lootProceeds: lootProceedsAccount,
+                nftAccount: nftTokenAccount,
+                nftEdition: nftEditionAccount,
+                nftMetadata: nft1.metadataAddress,
+                nftMint: nft1.mintAddress,
+                rewardAta: rewardAta.address,
+                rewardMint: rewardMintAccount,
+                vesting: getVestingAccount(program.programId, nft1.mintAddress, userKeypair1.publicKey),
+                vestingEscrow: vestingEscrowAccount,
+                user: userKeypair1.publicKey,
+                nftTokenRecord: getTokenRecordAccount(tokenMetadataProgram, nft1.mintAddress, nftTokenAccount),
+                authorizationRules: tokenMetadataProgram,
+                authorizationRulesProgram: tokenMetadataProgram,
+                sysvarInstructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+                tokenMetadataProgram: tokenMetadataProgram,
+                tokenProgram: spl.TOKEN_PROGRAM_ID,
+                rewardTokenProgram: rewardTokenProgram,
+                associatedTokenProgram: spl.ASSOCIATED_TOKEN_PROGRAM_ID,
+                systemProgram: anchor.web3.SystemProgram.programId,
+            },
+        });
+        
+        const unstakeSignature = await anchor.web3.sendAndConfirmTransaction(provider.connection, unstakeTx, [userKeypair1], {skipPreflight: true});
+        console.log(`\tUser #1 unstake geared miner transaction: ${unstakeSignature}`);
+        
+        // The unstake leaves the gear frozen on the Character
+        gearAccountData = await spl.getAccount(provider.connection, gearAccount);
+        assert.equal(gearAccountData.isFrozen, true);
+        
+        const unequipSignature = await anchor.web3.sendAndConfirmTransaction(provider.connection, await getUnequipTx(gearNft1), [userKeypair1], {skipPreflight: true});
+        console.log(`\tUser #1 unequip gear transaction: ${unequipSignature}`);
+        
+        gearAccountData = await spl.getAccount(provider.connection, gearAccount);
+        assert.equal(gearAccountData.isFrozen, false);
+        assert.equal(gearAccountData.delegate, null);
+        
+        const characterAccountData3 = await program.account.character.fetch(characterAccount);
+        assert.equal(characterAccountData3.miningPoints.toNumber(), characterAccountData1.miningPoints.toNumber());
+        assert.equal(characterAccountData3.gearMints[0].toString(), PublicKey.default.toString());
+        
+        const [equipmentAccount, bump5] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("equipment"), gearNft1.mintAddress.toBuffer()], program.programId);
+        assert.equal(await provider.connection.getAccountInfo(equipmentAccount), null);
+    
+    });
+    
+    
+    it("User #2 boosted miner unstaked after the boost ended!", async () => {
+        
+        const boostDuration = 2;
+        const [boostRegistryAccount, bump4] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("boosts")], program.programId);
+        const boostMintAccount = await spl.createMint(provider.connection, authorityKeypair, authorityKeypair.publicKey, null, 0);
+        const boostAta = await spl.getOrCreateAssociatedTokenAccount(provider.connection, userKeypair2, boostMintAccount, userKeypair2.publicKey);
+        await spl.mintTo(provider.connection, authorityKeypair, boostMintAccount, boostAta.address, authorityKeypair, 1);
+        
+        const boostTx = program.transaction.setMineBoost(1, boostMintAccount, 30000, new anchor.BN(boostDuration), {
+            accounts: {
+                mine: mineAccount,
+                boostRegistry: boostRegistryAccount,
+                manager: managerKeyepair.publicKey,
+            },
+        });
+        
+        const boostSignature = await anchor.web3.sendAndConfirmTransaction(provider.connection, boostTx, [managerKeyepair], {skipPreflight: true});
+        console.log(`\tSet mine boost transaction: ${boostSignature}`);
+        
+        const [characterAccount, bump] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("character"), nft2.mintAddress.toBuffer()], program.programId);
+        const [denylistAccount, bump3] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("denylist"), nft2.mintAddress.toBuffer()], program.programId);
+        const [nftEditionAccount, bump2] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("metadata"), tokenMetadataProgram.toBuffer(), nft2.mintAddress.toBuffer(), Buffer.from("edition")], tokenMetadataProgram);
+        const [referrerCharacterAccount, bump5] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("character"), nft1.mintAddress.toBuffer()], program.programId);
+        const whitelistAccount = await getWhitelistAccount(creatorKeypair, program.programId, creatorWhitelist, nft2);
+        const nftTokenAccount = await spl.getAssociatedTokenAddress(nft2.mintAddress, userKeypair2.publicKey);
+        const referrerNftAccount = await spl.getAssociatedTokenAddress(nft1.mintAddress, userKeypair1.publicKey);
+        const referrerRewardAta = await spl.getOrCreateAssociatedTokenAccount(provider.connection, userKeypair1, rewardMintAccount, userKeypair1.publicKey, false, undefined, undefined, rewardTokenProgram);
+        const rewardAta = await spl.getOrCreateAssociatedTokenAccount(provider.connection, userKeypair2, rewardMintAccount, userKeypair2.publicKey, false, undefined, undefined, rewardTokenProgram);
+        
+        const mineAccountDataBefore = await program.account.mine.fetch(mineAccount);
+        
+        const stakeTx = program.transaction.stakeMine(null, 0, {
+            accounts: {
+                mine: mineAccount,
+                character: characterAccount,
+                whitelist: whitelistAccount,
+                denylist: denylistAccount,
+                nftAccount: nftTokenAccount,
+                nftEdition: nftEditionAccount,
+                nftMetadata: nft2.metadataAddress,
+                nftMint: nft2.mintAddress,
+                user: userKeypair2.publicKey,
+                nftTokenRecord: getTokenRecordAccount(tokenMetadataProgram, nft2.mintAddress, nftTokenAccount),
+                authorizationRules: tokenMetadataProgram,
+                authorizationRulesProgram: tokenMetadataProgram,
+                sysvarInstructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+                tokenMetadataProgram: tokenMetadataProgram,
+                tokenProgram: spl.TOKEN_PROGRAM_ID,
+                associatedTokenProgram: spl.ASSOCIATED_TOKEN_PROGRAM_ID,
+                systemProgram: anchor.web3.SystemProgram.programId,
+            },
+            remainingAccounts: [
+                {pubkey: boostRegistryAccount, isWritable: false, isSigner: false},
+                {pubkey: boostMintAccount, isWritable: true, isSigner: false},
+                {pubkey: boostAta.address, isWritable: true, isSigner: false},
+            ],
+        });
+        
+        const stakeSignature = await anchor.web3.sendAndConfirmTransaction(provider.connection, stakeTx, [userKeypair2], {skipPreflight: true});
+        console.log(`\tUser #2 stake boosted miner transaction: ${stakeSignature}`);
+        
+        const stakedCharacterData = await program.account.character.fetch(characterAccount);
+        assert.equal(stakedCharacterData.boostWeight.toString(), stakedCharacterData.stakedWeight.muln(2).toString());
+        assert.equal(stakedCharacterData.boostEndTimestamp.toNumber(), stakedCharacterData.stakedTimestamp.toNumber() + boostDuration);
+        
+        const stakedMineData = await program.account.mine.fetch(mineAccount);
+        assert.equal(stakedMineData.stakedPoints.toString(), mineAccountDataBefore.stakedPoints.add(stakedCharacterData.stakedWeight).add(stakedCharacterData.boostWeight).toString());
+        
+        // The boost ends long before the unstake and expire_boost is never called
+        await new Promise(f => setTimeout(f, 10000));
+        
+        const referrerDataBefore = await program.account.character.fetch(referrerCharacterAccount);
+        
+        const unstakeTx = program.transaction.unstakeMine({
+            accounts: {
+                mine: mineAccount,
+                loot: lootAccount,
+                character: characterAccount,
+                denylist: denylistAccount,
+                lootProceeds: lootProceedsAccount,
+                nftAccount: nftTokenAccount,
+                nftEdition: nftEditionAccount,
+                nftMetadata: nft2.metadataAddress,
+                nftMint: nft2.mintAddress,
+                rewardAta: rewardAta.address,
+                rewardMint: rewardMintAccount,
+                vesting: getVestingAccount(program.programId, nft2.mintAddress, userKeypair2.publicKey),
+                vestingEscrow: vestingEscrowAccount,
+                user: userKeypair2.publicKey,
+                nftTokenRecord: getTokenRecordAccount(tokenMetadataProgram, nft2.mintAddress, nftTokenAccount),
+                authorizationRules: tokenMetadataProgram,
+                authorizationRulesProgram: tokenMetadataProgram,
+                sysvarInstructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+                tokenMetadataProgram: tokenMetadataProgram,
+                tokenProgram: spl.TOKEN_PROGRAM_ID,
+                rewardTokenProgram: rewardTokenProgram,
+                associatedTokenProgram: spl.ASSOCIATED_TOKEN_PROGRAM_ID,
+                systemProgram: anchor.web3.SystemProgram.programId,
+            },
+            remainingAccounts: [
+                {pubkey: referrerCharacterAccount, isWritable: true, isSigner: false},
+                {pubkey: referrerNftAccount, isWritable: false, isSigner: false},
+                {pubkey: referrerRewardAta.address, isWritable: true, isSigner: false},
+            ],
+        });
+        
+        const unstakeSignature = await anchor.web3.sendAndConfirmTransaction(provider.connection, unstakeTx, [userKeypair2], {skipPreflight: true});
+        console.log(`\tUser #2 unstake boosted miner transaction: ${unstakeSignature}`);
+        
+        const characterAccountData = await program.account.character.fetch(characterAccount);
+        assert.equal(characterAccountData.staked, 0);
+        assert.equal(characterAccountData.boostEndTimestamp, 0);
+        
+        const mineAccountData = await program.account.mine.fetch(mineAccount);
+        assert.equal(mineAccountData.stakedPoints.toString(), mineAccountDataBefore.stakedPoints.toString());
+        assert.ok(mineAccountData.boostExpiries.every(expiry => expiry.endTimestamp.toNumber() == 0 && expiry.boosts == 0));
+        
+        // The boost weight earned only over the boost duration, the staked weight up to the unstake
+        const referrerDataAfter = await program.account.character.fetch(referrerCharacterAccount);
+        const minedRewards = mineAccountData.totalMined.sub(mineAccountDataBefore.totalMined)
+            .sub(referrerDataAfter.referralEarned.sub(referrerDataBefore.referralEarned))
+            .add(mineAccountData.totalLooted.sub(mineAccountDataBefore.totalLooted))
+            .add(mineAccountData.totalClamped.sub(mineAccountDataBefore.totalClamped));
+        const expectedRewards = stakedCharacterData.stakedWeight.mul(mineAccountData.accruedRewards.sub(stakedCharacterData.stakedPeg))
+            .add(stakedCharacterData.boostWeight.mul(stakedMineData.rate).muln(boostDuration));
+        assert.equal(minedRewards.toString(), expectedRewards.toString());
+        
+    });
+
+    
+    it("User #2 boost applied mid-stake and expired!", async () => {
+    
+        const boostDuration = 2;
+        const [boostRegistryAccount, bump4] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("boosts")], program.programId);
+        const boostMintAccount = await spl.createMint(provider.connection, authorityKeypair, authorityKeypair.publicKey, null, 0);
+        const boostAta = await spl.getOrCreateAssociatedTokenAccount(provider.connection, userKeypair2, boostMintAccount, userKeypair2.publicKey);
+        await spl.mintTo(provider.connection, authorityKeypair, boostMintAccount, boostAta.address, authorityKeypair, 2);
+        
+        const boostTx = program.transaction.setMineBoost(2, boostMintAccount, 30000, new anchor.BN(boostDuration), {
+            accounts: {
+                mine: mineAccount,
+                boostRegistry: boostRegistryAccount,
+                manager: managerKeyepair.publicKey,
+            },
+        });
+        
+        const boostSignature = await anchor.web3.sendAndConfirmTransaction(provider.connection, boostTx, [managerKeyepair], {skipPreflight: true});
+        console.log(`\tSet mine boost transaction: ${boostSignature}`);
+        
+        const [characterAccount, bump] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("character"), nft2.mintAddress.toBuffer()], program.programId);
+        const [denylistAccount, bump3] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("denylist"), nft2.mintAddress.toBuffer()], program.programId);
+        const [nftEditionAccount, bump2] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("metadata"), tokenMetadataProgram.toBuffer(), nft2.mintAddress.toBuffer(), Buffer.from("edition")], tokenMetadataProgram);
+        const [referrerCharacterAccount, bump5] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("character"), nft1.mintAddress.toBuffer()], program.programId);
+        const whitelistAccount = await getWhitelistAccount(creatorKeypair, program.programId, creatorWhitelist, nft2);
+        const nftTokenAccount = await spl.getAssociatedTokenAddress(nft2.mintAddress, userKeypair2.publicKey);
+        const referrerNftAccount = await spl.getAssociatedTokenAddress(nft1.mintAddress, userKeypair1.publicKey);
+        const referrerRewardAta = await spl.getOrCreateAssociatedTokenAccount(provider.connection, userKeypair1, rewardMintAccount, userKeypair1.publicKey, false, undefined, undefined, rewardTokenProgram);
+        const rewardAta = await spl.getOrCreateAssociatedTokenAccount(provider.connection, userKeypair2, rewardMintAccount, userKeypair2.publicKey, false, undefined, undefined, rewardTokenProgram);
+        
+        const getApplyTx = (user: PublicKey) => program.transaction.applyBoost({
+            accounts: {
+                mine: mineAccount,
+                boostRegistry: boostRegistryAccount,
+                character: characterAccount,
+                nftAccount: nftTokenAccount,
+                nftMint: nft2.mintAddress,
+                boostMint: boostMintAccount,
+                boostAccount: boostAta.address,
+                user: user,
+                tokenProgram: spl.TOKEN_PROGRAM_ID,
+            },
+        });
+        
+        const getExpireTx = () => program.transaction.expireBoost({
+            accounts: {
+                mine: mineAccount,
+                character: characterAccount,
+                nftMint: nft2.mintAddress,
+            },
+        });
+        
+        // A boost can only be applied to a staked Character
+        await assert.rejects(anchor.web3.sendAndConfirmTransaction(provider.connection, getApplyTx(userKeypair2.publicKey), [userKeypair2]));
+        
+        const stakeTx = program.transaction.stakeMine(null, 0, {
+            accounts: {
+                mine: mineAccount,
+                character: characterAccount,
+                whitelist: whitelistAccount,
+                denylist: denylistAccount,
+                nftAccount: nftTokenAccount,
+                nftEdition: nftEditionAccount,
+                nftMetadata: nft2.metadataAddress,
+                nftMint: nft2.mintAddress,
+                user: userKeypair2.publicKey,
+                nftTokenRecord: getTokenRecordAccount(tokenMetadataProgram, nft2.mintAddress, nftTokenAccount),
+                authorizationRules: tokenMetadataProgram,
+                authorizationRulesProgram: tokenMetadataProgram,
+                sysvarInstructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+                tokenMetadataProgram: tokenMetadataProgram,
+                tokenProgram: spl.TOKEN_PROGRAM_ID,
+                associatedTokenProgram: spl.ASSOCIATED_TOKEN_PROGRAM_ID,
+                systemProgram: anchor.web3.SystemProgram.programId,
+            },
+        });
+        
+        const stakeSignature = await anchor.web3.sendAndConfirmTransaction(provider.connection, stakeTx, [userKeypair2], {skipPreflight: true});
+        console.log(`\tUser #2 stake miner transaction: ${stakeSignature}`);
+        
+        const stakedCharacterData = await program.account.character.fetch(characterAccount);
+        assert.equal(stakedCharacterData.boostEndTimestamp.toNumber(), 0);
+        const stakedMineData = await program.account.mine.fetch(mineAccount);
+        
+        // Only the holder of the staked NFT can boost it
+        await assert.rejects(anchor.web3.sendAndConfirmTransaction(provider.connection, getApplyTx(userKeypair1.publicKey), [userKeypair1]));
+        
+        const applySignature = await anchor.web3.sendAndConfirmTransaction(provider.connection, getApplyTx(userKeypair2.publicKey), [userKeypair2], {skipPreflight: true});
+        console.log(`\tUser #2 apply boost transaction: ${applySignature}`);
+        
+        const boostedCharacterData = await program.account.character.fetch(characterAccount);
+        assert.equal(boostedCharacterData.boostWeight.toString(), stakedCharacterData.stakedWeight.muln(2).toString());
+        assert.ok(boostedCharacterData.boostEndTimestamp.toNumber() >= stakedCharacterData.stakedTimestamp.toNumber() + boostDuration);
+        assert.equal((await spl.getAccount(provider.connection, boostAta.address)).amount.toString(), "1");
+        
+        const boostedMineData = await program.account.mine.fetch(mineAccount);
+        assert.equal(boostedMineData.stakedPoints.toString(), stakedMineData.stakedPoints.add(boostedCharacterData.boostWeight).toString());
+        
+        // A second boost waits for the first one to end, which cannot be expired early
+        await assert.rejects(anchor.web3.sendAndConfirmTransaction(provider.connection, getApplyTx(userKeypair2.publicKey), [userKeypair2]));
+        await assert.rejects(anchor.web3.sendAndConfirmTransaction(provider.connection, getExpireTx(), [userKeypair1]));
+        
+        await new Promise(f => setTimeout(f, 5000));
+        
+        // Anyone can expire an ended boost
+        const expireSignature = await anchor.web3.sendAndConfirmTransaction(provider.connection, getExpireTx(), [userKeypair1], {skipPreflight: true});
+        console.log(`\tExpire boost transaction: ${expireSignature}`);
+        
+        const expiredCharacterData = await program.account.character.fetch(characterAccount);
+        assert.equal(expiredCharacterData.staked, 1);
+        assert.equal(expiredCharacterData.boostWeight.toNumber(), 0);
+        assert.equal(expiredCharacterData.boostEndTimestamp.toNumber(), 0);
+        assert.equal(expiredCharacterData.boostRewards.sub(boostedCharacterData.boostRewards).toString(), boostedCharacterData.boostWeight.mul(boostedMineData.rate).muln(boostDuration).toString());
+        
+        const expiredMineData = await program.account.mine.fetch(mineAccount);
+        assert.equal(expiredMineData.stakedPoints.toString(), stakedMineData.stakedPoints.toString());
+        assert.ok(expiredMineData.boostExpiries.every(expiry => expiry.endTimestamp.toNumber() == 0 && expiry.boosts == 0));
+        
+        await assert.rejects(anchor.web3.sendAndConfirmTransaction(provider.connection, getExpireTx(), [userKeypair1]));
+        
+        const unstakeTx = program.transaction.unstakeMine({
+            accounts: {
+                mine: mineAccount,
+                loot: lootAccount,
+                character: characterAccount,
+                denylist: denylistAccount,
+                lootProceeds: lootProceedsAccount,
+                nftAccount: nftTokenAccount,
+                nftEdition: nftEditionAccount,
+                nftMetadata: nft2.metadataAddress,
+                nftMint: nft2.mintAddress,
+                rewardAta: rewardAta.address,
+                rewardMint: rewardMintAccount,
+                vesting: getVestingAccount(program.programId, nft2.mintAddress, userKeypair2.publicKey),
+                vestingEscrow: vestingEscrowAccount,
+                user: userKeypair2.publicKey,
+                nftTokenRecord: getTokenRecordAccount(tokenMetadataProgram, nft2.mintAddress, nftTokenAccount),
+                authorizationRules: tokenMetadataProgram,
+                authorizationRulesProgram: tokenMetadataProgram,
+                sysvarInstructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+                tokenMetadataProgram: tokenMetadataProgram,
+                tokenProgram: spl.TOKEN_PROGRAM_ID,
+                rewardTokenProgram: rewardTokenProgram,
+                associatedTokenProgram: spl.ASSOCIATED_TOKEN_PROGRAM_ID,
+                systemProgram: anchor.web3.SystemProgram.programId,
+            },
+            remainingAccounts: [
+                {pubkey: referrerCharacterAccount, isWritable: true, isSigner: false},
+                {pubkey: referrerNftAccount, isWritable: false, isSigner: false},
+                {pubkey: referrerRewardAta.address, isWritable: true, isSigner: false},
+            ],
+        });
+        
+        const unstakeSignature = await anchor.web3.sendAndConfirmTransaction(provider.connection, unstakeTx, [userKeypair2], {skipPreflight: true});
+        console.log(`\tUser #2 unstake miner transaction: ${unstakeSignature}`);
+        
+        const characterAccountData = await program.account.character.fetch(characterAccount);
+        assert.equal(characterAccountData.staked, 0);
+        assert.equal(characterAccountData.boostRewards.toNumber(), 0);
+    
+    });
+    
+    
+    it("User #2 guild member staked, unstaked and removed!", async () => {
+    
+        const [characterAccount, bump] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("character"), nft2.mintAddress.toBuffer()], program.programId);
+        const [denylistAccount, bump3] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("denylist"), nft2.mintAddress.toBuffer()], program.programId);
+        const [nftEditionAccount, bump2] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("metadata"), tokenMetadataProgram.toBuffer(), nft2.mintAddress.toBuffer(), Buffer.from("edition")], tokenMetadataProgram);
+        const [referrerCharacterAccount, bump5] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("character"), nft1.mintAddress.toBuffer()], program.programId);
+        const [guildAccount, bump6] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("guild"), userKeypair2.publicKey.toBuffer()], program.programId);
+        const [guildTreasuryAccount, bump7] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("treasury"), guildAccount.toBuffer()], program.programId);
+        const whitelistAccount = await getWhitelistAccount(creatorKeypair, program.programId, creatorWhitelist, nft2);
+        const nftTokenAccount = await spl.getAssociatedTokenAddress(nft2.mintAddress, userKeypair2.publicKey);
+        const referrerNftAccount = await spl.getAssociatedTokenAddress(nft1.mintAddress, userKeypair1.publicKey);
+        const referrerRewardAta = await spl.getOrCreateAssociatedTokenAccount(provider.connection, userKeypair1, rewardMintAccount, userKeypair1.publicKey, false, undefined, undefined, rewardTokenProgram);
+        const rewardAta = await spl.getOrCreateAssociatedTokenAccount(provider.connection, userKeypair2, rewardMintAccount, userKeypair2.publicKey, false, undefined, undefined, rewardTokenProgram);
+        
+        const cutTx = (cutBps: number) => program.transaction.setGuildCut(cutBps, {
+            accounts: {
+                guild: guildAccount,
+                leader: userKeypair2.publicKey,
+            },
+        });
+        
+        await assert.rejects(anchor.web3.sendAndConfirmTransaction(provider.connection, cutTx(10001), [userKeypair2]));
+        
+        const cutSignature = await anchor.web3.sendAndConfirmTransaction(provider.connection, cutTx(2000), [userKeypair2], {skipPreflight: true});
+        console.log(`\tSet guild cut transaction: ${cutSignature}`);
+        
+        let guildAccountData = await program.account.guild.fetch(guildAccount);
+        assert.equal(guildAccountData.cutBps, 2000);
+        
+        const joinTx = program.transaction.joinGuild({
+            accounts: {
+                guild: guildAccount,
+                character: characterAccount,
+                nftAccount: nftTokenAccount,
+                nftMint: nft2.mintAddress,
+                user: userKeypair2.publicKey,
+            },
+        });
+        
+        const joinSignature = await anchor.web3.sendAndConfirmTransaction(provider.connection, joinTx, [userKeypair2], {skipPreflight: true});
+        console.log(`\tUser #2 join guild transaction: ${joinSignature}`);
+        
+        guildAccountData = await program.account.guild.fetch(guildAccount);
+        assert.equal(guildAccountData.members, 1);
+        
+        let characterAccountData = await program.account.character.fetch(characterAccount);
+        assert.equal(characterAccountData.guild.toString(), guildAccount.toString());
+        
+        const stakeTx = program.transaction.stakeMine(null, 0, {
+            accounts: {
+                mine: mineAccount,
+                character: characterAccount,
+                whitelist: whitelistAccount,
+                denylist: denylistAccount,
+                nftAccount: nftTokenAccount,
+                nftEdition: nftEditionAccount,
+                nftMetadata: nft2.metadataAddress,
+                nftMint: nft2.mintAddress,
+                user: userKeypair2.publicKey,
+                nftTokenRecord: getTokenRecordAccount(tokenMetadataProgram, nft2.mintAddress, nftTokenAccount),
+                authorizationRules: tokenMetadataProgram,
+                authorizationRulesProgram: tokenMetadataProgram,
+                sysvarInstructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+                tokenMetadataProgram: tokenMetadataProgram,
+                tokenProgram: spl.TOKEN_PROGRAM_ID,
+                associatedTokenProgram: spl.ASSOCIATED_TOKEN_PROGRAM_ID,
+                systemProgram: anchor.web3.SystemProgram.programId,
+            },
+            remainingAccounts: [
+                {pubkey: guildAccount, isWritable: true, isSigner: false},
+            ],
+        });
+        
+        const stakeSignature = await anchor.web3.sendAndConfirmTransaction(provider.connection, stakeTx, [userKeypair2], {skipPreflight: true});
+        console.log(`\tUser #2 stake guild member transaction: ${stakeSignature}`);
+        
+        const stakedCharacterData = await program.account.character.fetch(characterAccount);
+        const stakedGuildData = await program.account.guild.fetch(guildAccount);
+        assert.equal(stakedGuildData.stakedMembers, 1);
+        assert.equal(stakedCharacterData.guildCutBps, 2000);
+        assert.equal(stakedCharacterData.guildBonusPeg.toString(), stakedGuildData.bonusAccrued.toString());
+        
+        // Staked members can neither leave nor be removed from the Guild
+        const leaveTx = program.transaction.leaveGuild({
+            accounts: {
+                guild: guildAccount,
+                character: characterAccount,
+                nftAccount: nftTokenAccount,
+                nftMint: nft2.mintAddress,
+                user: userKeypair2.publicKey,
+            },
+        });
+        
+        const kickTx = program.transaction.kickGuildMember({
+            accounts: {
+                guild: guildAccount,
+                character: characterAccount,
+                nftMint: nft2.mintAddress,
+                leader: userKeypair2.publicKey,
+            },
+        });
+        
+        await assert.rejects(anchor.web3.sendAndConfirmTransaction(provider.connection, leaveTx, [userKeypair2]));
+        await assert.rejects(anchor.web3.sendAndConfirmTransaction(provider.connection, kickTx, [userKeypair2]));
+        
+        await new Promise(f => setTimeout(f, 4000));
+        
+        const mineAccountDataBefore = await program.account.mine.fetch(mineAccount);
+        const referrerDataBefore = await program.account.character.fetch(referrerCharacterAccount);
+        const rewardAmountBefore = (await spl.getAccount(provider.connection, rewardAta.address, undefined, rewardTokenProgram)).amount;
+        const treasuryAmountBefore = (await spl.getAccount(provider.connection, guildTreasuryAccount, undefined, rewardTokenProgram)).amount;
+        
+        const unstakeTx = program.transaction.unstakeMine({
+            accounts: {
+                mine: mineAccount,
+                loot: lootAccount,
+                character: characterAccount,
+                denylist: denylistAccount,
+                lootProceeds: lootProceedsAccount,
+                nftAccount: nftTokenAccount,
+                nftEdition: nftEditionAccount,
+                nftMetadata: nft2.metadataAddress,
+                nftMint: nft2.mintAddress,
+                rewardAta: rewardAta.address,
+                rewardMint: rewardMintAccount,
+                vesting: getVestingAccount(program.programId, nft2.mintAddress, userKeypair2.publicKey),
+                vestingEscrow: vestingEscrowAccount,
+                user: userKeypair2.publicKey,
+                nftTokenRecord: getTokenRecordAccount(tokenMetadataProgram, nft2.mintAddress, nftTokenAccount),
+                authorizationRules: tokenMetadataProgram,
+                authorizationRulesProgram: tokenMetadataProgram,
+                sysvarInstructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+                tokenMetadataProgram: tokenMetadataProgram,
+                tokenProgram: spl.TOKEN_PROGRAM_ID,
+                rewardTokenProgram: rewardTokenProgram,
+                associatedTokenProgram: spl.ASSOCIATED_TOKEN_PROGRAM_ID,
+                systemProgram: anchor.web3.SystemProgram.programId,
+            },
+            remainingAccounts: [
+                {pubkey: guildAccount, isWritable: true, isSigner: false},
+                {pubkey: guildTreasuryAccount, isWritable: true, isSigner: false},
+                {pubkey: referrerCharacterAccount, isWritable: true, isSigner: false},
+                {pubkey: referrerNftAccount, isWritable: false, isSigner: false},
+                {pubkey: referrerRewardAta.address, isWritable: true, isSigner: false},
+            ],
+        });
+        
+        const unstakeSignature = await anchor.web3.sendAndConfirmTransaction(provider.connection, unstakeTx, [userKeypair2], {skipPreflight: true});
+        console.log(`\tUser #2 unstake guild member transaction: ${unstakeSignature}`);
+        
+        guildAccountData = await program.account.guild.fetch(guildAccount);
+        assert.equal(guildAccountData.stakedMembers, 0);
+        
+        // The only staked member earned the bonus of one staked member on top of its staked weight
+        const mineAccountData = await program.account.mine.fetch(mineAccount);
+        const referrerDataAfter = await program.account.character.fetch(referrerCharacterAccount);
+        const accruedDelta = mineAccountData.accruedRewards.sub(stakedCharacterData.stakedPeg);
+        const bonusBps = Math.min(mineAccountData.guildBonusBps, mineAccountData.guildBonusCapBps);
+        assert.equal(guildAccountData.bonusAccrued.sub(stakedGuildData.bonusAccrued).toString(), accruedDelta.muln(bonusBps).toString());
+        
+        const minedRewards = mineAccountData.totalMined.sub(mineAccountDataBefore.totalMined)
+            .sub(referrerDataAfter.referralEarned.sub(referrerDataBefore.referralEarned))
+            .add(mineAccountData.totalLooted.sub(mineAccountDataBefore.totalLooted))
+            .add(mineAccountData.totalClamped.sub(mineAccountDataBefore.totalClamped));
+        const expectedRewards = stakedCharacterData.stakedWeight.mul(accruedDelta)
+            .add(stakedCharacterData.stakedWeight.mul(accruedDelta).muln(bonusBps).divn(10000));
+        assert.equal(minedRewards.toString(), expectedRewards.toString());
+        
+        // The guild cut of the mined rewards left to the member is minted to the treasury
+        const rewardAmountAfter = (await spl.getAccount(provider.connection, rewardAta.address, undefined, rewardTokenProgram)).amount;
+        const treasuryAmountAfter = (await spl.getAccount(provider.connection, guildTreasuryAccount, undefined, rewardTokenProgram)).amount;
+        const memberReward = rewardAmountAfter - rewardAmountBefore;
+        const guildCut = treasuryAmountAfter - treasuryAmountBefore;
+        assert.ok(guildCut > BigInt(0));
+        assert.equal(guildCut.toString(), ((memberReward + guildCut) * BigInt(2000) / BigInt(10000)).toString());
+        
+        const leaveSignature = await anchor.web3.sendAndConfirmTransaction(provider.connection, leaveTx, [userKeypair2], {skipPreflight: true});
+        console.log(`\tUser #2 leave guild transaction: ${leaveSignature}`);
+        
+        guildAccountData = await program.account.guild.fetch(guildAccount);
+        assert.equal(guildAccountData.members, 0);
+        
+        characterAccountData = await program.account.character.fetch(characterAccount);
+        assert.equal(characterAccountData.guild.toString(), PublicKey.default.toString());
+        
+        // The leader removes an unstaked member
+        const rejoinSignature = await anchor.web3.sendAndConfirmTransaction(provider.connection, joinTx, [userKeypair2], {skipPreflight: true});
+        console.log(`\tUser #2 join guild transaction: ${rejoinSignature}`);
+        
+        const kickSignature = await anchor.web3.sendAndConfirmTransaction(provider.connection, kickTx, [userKeypair2], {skipPreflight: true});
+        console.log(`\tKick guild member transaction: ${kickSignature}`);
+        
+        guildAccountData = await program.account.guild.fetch(guildAccount);
+        assert.equal(guildAccountData.members, 0);
+        
+        characterAccountData = await program.account.character.fetch(characterAccount);
+        assert.equal(characterAccountData.guild.toString(), PublicKey.default.toString());
+        
+        // Only the leader withdraws from the treasury
+        const withdrawTx = (leader: Keypair) => program.transaction.withdrawGuildTreasury(new anchor.BN(guildCut.toString()), {
+            accounts: {
+                guild: guildAccount,
+                guildTreasury: guildTreasuryAccount,
+                destination: rewardAta.address,
+                rewardMint: rewardMintAccount,
+                leader: leader.publicKey,
+                rewardTokenProgram: rewardTokenProgram,
+            },
+        });
+        
+        await assert.rejects(anchor.web3.sendAndConfirmTransaction(provider.connection, withdrawTx(userKeypair1), [userKeypair1]));
+        
+        const withdrawSignature = await anchor.web3.sendAndConfirmTransaction(provider.connection, withdrawTx(userKeypair2), [userKeypair2], {skipPreflight: true});
+        console.log(`\tWithdraw guild treasury transaction: ${withdrawSignature}`);
+        
+        const treasuryAmountWithdrawn = (await spl.getAccount(provider.connection, guildTreasuryAccount, undefined, rewardTokenProgram)).amount;
+        assert.equal(treasuryAmountWithdrawn, treasuryAmountBefore);
+        
+        const rewardAmountWithdrawn = (await spl.getAccount(provider.connection, rewardAta.address, undefined, rewardTokenProgram)).amount;
+        assert.ok(rewardAmountWithdrawn - rewardAmountAfter > BigInt(0));
+        assert.ok(rewardAmountWithdrawn - rewardAmountAfter <= guildCut);
+    
+    });
+    
+    
+    it("User #1 operator created and revoked!", async () => {
+        
+        const [characterAccount, bump] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("character"), nft1.mintAddress.toBuffer()], program.programId);
+        const [operatorAccount, bump2] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("operator"), nft1.mintAddress.toBuffer(), userKeypair1.publicKey.toBuffer()], program.programId);
+        const nftTokenAccount = await spl.getAssociatedTokenAddress(nft1.mintAddress, userKeypair1.publicKey);
+        
+        const tx = program.transaction.createOperator(0, {
+            accounts: {
+                mine: mineAccount,
+                operator: operatorAccount,
+                character: characterAccount,
+                nftAccount: nftTokenAccount,
+                nftMint: nft1.mintAddress,
+                authority: userKeypair2.publicKey,
+                holder: userKeypair1.publicKey,
+                tokenProgram: spl.TOKEN_PROGRAM_ID,
+                systemProgram: anchor.web3.SystemProgram.programId,
+            },
+        });
+        
+        const signature = await anchor.web3.sendAndConfirmTransaction(provider.connection, tx, [userKeypair1], {skipPreflight: true});
+        console.log(`\tUser #1 create operator transaction: ${signature}`);
+        
+        const operatorAccountData = await program.account.operator.fetch(operatorAccount);
+        assert.equal(operatorAccountData.holder.toString(), userKeypair1.publicKey.toString());
+        assert.equal(operatorAccountData.authority.toString(), userKeypair2.publicKey.toString());
+        assert.equal(operatorAccountData.maxLockTier, 0);
+        
+        let nftTokenAccountData = await spl.getAccount(provider.connection, nftTokenAccount);
+        assert.equal(nftTokenAccountData.delegate.toString(), mineAccount.toString());
+        
+        const tx2 = program.transaction.revokeOperator({
+            accounts: {
+                mine: mineAccount,
+                operator: operatorAccount,
+                nftAccount: nftTokenAccount,
+                holder: userKeypair1.publicKey,
+                tokenProgram: spl.TOKEN_PROGRAM_ID,
+            },
+        });
+        
+        const signature2 = await anchor.web3.sendAndConfirmTransaction(provider.connection, tx2, [userKeypair1], {skipPreflight: true});
+        console.log(`\tUser #1 revoke operator transaction: ${signature2}`);
+        
+        nftTokenAccountData = await spl.getAccount(provider.connection, nftTokenAccount);
+        assert.equal(nftTokenAccountData.delegate, null);
+        assert.equal(await provider.connection.getAccountInfo(operatorAccount), null);
+        
+    });
+
+    
+    it("User #1 character upgraded!", async () => {
+        
+        const [characterAccount, bump] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("character"), nft1.mintAddress.toBuffer()], program.programId);
+        const [upgradeShopAccount, bump2] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("upgrades")], program.programId);
+        const nftTokenAccount = await spl.getAssociatedTokenAddress(nft1.mintAddress, userKeypair1.publicKey);
+        const rewardAta = await spl.getAssociatedTokenAddress(rewardMintAccount, userKeypair1.publicKey, false, rewardTokenProgram);
+        
+        const characterAccountData = await program.account.character.fetch(characterAccount);
+        const rewardAtaData = await spl.getAccount(provider.connection, rewardAta, undefined, rewardTokenProgram);
+        
+        const tx = program.transaction.upgradeCharacter(0, {
+            accounts: {
+                mine: mineAccount,
+                upgradeShop: upgradeShopAccount,
+                character: characterAccount,
+                nftAccount: nftTokenAccount,
+                nftMint: nft1.mintAddress,
+                rewardAccount: rewardAta,
+                rewardMint: rewardMintAccount,
+                user: userKeypair1.publicKey,
+                rewardTokenProgram: rewardTokenProgram,
+            },
+        });
+        
+        const signature = await anchor.web3.sendAndConfirmTransaction(provider.connection, tx, [userKeypair1], {skipPreflight: true});
+        console.log(`\tUser #1 upgrade character transaction: ${signature}`);
+        
+        const upgradedCharacterAccountData = await program.account.character.fetch(characterAccount);
+        assert.equal(upgradedCharacterAccountData.miningPoints.toNumber(), characterAccountData.miningPoints.toNumber() + 10);
+        assert.equal(upgradedCharacterAccountData.upgradeLevels[0], 1);
+        assert.equal(upgradedCharacterAccountData.allocatedPoints[0].toNumber(), 10);
+        
+        const upgradedRewardAtaData = await spl.getAccount(provider.connection, rewardAta, undefined, rewardTokenProgram);
+        assert.equal(upgradedRewardAtaData.amount, rewardAtaData.amount - BigInt(1));
+        
+    });
+
+    
+    it("User #1 character respecced!", async () => {
+        
+        const [characterAccount, bump] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("character"), nft1.mintAddress.toBuffer()], program.programId);
+        const nftTokenAccount = await spl.getAssociatedTokenAddress(nft1.mintAddress, userKeypair1.publicKey);
+        const rewardAta = await spl.getAssociatedTokenAddress(rewardMintAccount, userKeypair1.publicKey, false, rewardTokenProgram);
+        
+        const setTx = program.transaction.setMineRespec(new anchor.BN(1), new anchor.BN(3600), {
+            accounts: {
+                mine: mineAccount,
+                manager: managerKeyepair.publicKey,
+            },
+        });
+        
+        const setSignature = await anchor.web3.sendAndConfirmTransaction(provider.connection, setTx, [managerKeyepair], {skipPreflight: true});
+        console.log(`\tSet mine respec transaction: ${setSignature}`);
+        
+        const characterAccountData = await program.account.character.fetch(characterAccount);
+        
+        const tx = program.transaction.respec({
+            accounts: {
+                mine: mineAccount,
+                character: characterAccount,
+                nftAccount: nftTokenAccount,
+                nftMint: nft1.mintAddress,
+                rewardAccount: rewardAta,
+                rewardMint: rewardMintAccount,
+                user: userKeypair1.publicKey,
+                rewardTokenProgram: rewardTokenProgram,
+            },
+        });
+        
+        const signature = await anchor.web3.sendAndConfirmTransaction(provider.connection, tx, [userKeypair1], {skipPreflight: true});
+        console.log(`\tUser #1 respec character transaction: ${signature}`);
+        
+        let respeccedCharacterAccountData = await program.account.character.fetch(characterAccount);
+        assert.equal(respeccedCharacterAccountData.miningPoints.toNumber(), characterAccountData.miningPoints.toNumber() - 10);
+        assert.equal(respeccedCharacterAccountData.allocatedPoints[0].toNumber(), 0);
+        assert.equal(respeccedCharacterAccountData.freePoints.toNumber(), 10);
+        assert.ok(respeccedCharacterAccountData.respecTimestamp > 0);
+        
+        const allocateTx = program.transaction.allocatePoints(2, new anchor.BN(10), {
+            accounts: {
+                character: characterAccount,
+                nftAccount: nftTokenAccount,
+                nftMint: nft1.mintAddress,
+                user: userKeypair1.publicKey,
+            },
+        });
+        
+        const allocateSignature = await anchor.web3.sendAndConfirmTransaction(provider.connection, allocateTx, [userKeypair1], {skipPreflight: true});
+        console.log(`\tUser #1 allocate points transaction: ${allocateSignature}`);
+        
+        respeccedCharacterAccountData = await program.account.character.fetch(characterAccount);
+        assert.equal(respeccedCharacterAccountData.lootingPoints.toNumber(), characterAccountData.lootingPoints.toNumber() + 10);
+        assert.equal(respeccedCharacterAccountData.allocatedPoints[2].toNumber(), 10);
+        assert.equal(respeccedCharacterAccountData.freePoints.toNumber(), 0);
+        
+    });
+    
+    
+    it("User #1 respecced looter staked and unstaked!", async () => {
+    
+        const [characterAccount, bump] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("character"), nft1.mintAddress.toBuffer()], program.programId);
+        const [denylistAccount, bump3] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("denylist"), nft1.mintAddress.toBuffer()], program.programId);
+        const [nftEditionAccount, bump2] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("metadata"), tokenMetadataProgram.toBuffer(), nft1.mintAddress.toBuffer(), Buffer.from("edition")], tokenMetadataProgram);
+        const whitelistAccount = await getWhitelistAccount(creatorKeypair, program.programId, creatorWhitelist, nft1);
+        const nftTokenAccount = await spl.getAssociatedTokenAddress(nft1.mintAddress, userKeypair1.publicKey);
+        const rewardAta = await spl.getOrCreateAssociatedTokenAccount(provider.connection, userKeypair1, rewardMintAccount, userKeypair1.publicKey, false, undefined, undefined, rewardTokenProgram);
+        
+        // The respec moved the allocated points to the looting points, so they differ from the mining points
+        const characterAccountData = await program.account.character.fetch(characterAccount);
+        assert.notEqual(characterAccountData.lootingPoints.toNumber(), characterAccountData.miningPoints.toNumber());
+        
+        const lootAccountDataBefore = await program.account.loot.fetch(lootAccount);
+        
+        const stakeTx = program.transaction.stakeLoot(null, {
+            accounts: {
+                loot: lootAccount,
+                character: characterAccount,
+                whitelist: whitelistAccount,
+                denylist: denylistAccount,
+                nftAccount: nftTokenAccount,
+                nftEdition: nftEditionAccount,
+                nftMetadata: nft1.metadataAddress,
+                nftMint: nft1.mintAddress,
+                user: userKeypair1.publicKey,
+                nftTokenRecord: getTokenRecordAccount(tokenMetadataProgram, nft1.mintAddress, nftTokenAccount),
+                authorizationRules: tokenMetadataProgram,
+                authorizationRulesProgram: tokenMetadataProgram,
+                sysvarInstructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+                tokenMetadataProgram: tokenMetadataProgram,
+                tokenProgram: spl.TOKEN_PROGRAM_ID,
+                associatedTokenProgram: spl.ASSOCIATED_TOKEN_PROGRAM_ID,
+                systemProgram: anchor.web3.SystemProgram.programId,
+            },
+        });
+        
+        const stakeSignature = await anchor.web3.sendAndConfirmTransaction(provider.connection, stakeTx, [userKeypair1], {skipPreflight: true});
+        console.log(`\tUser #1 stake respecced looter transaction: ${stakeSignature}`);
+        
+        const stakedLootData = await program.account.loot.fetch(lootAccount);
+        assert.equal(stakedLootData.stakedPoints.toString(), lootAccountDataBefore.stakedPoints.add(characterAccountData.lootingPoints).toString());
+        
+        await new Promise(f => setTimeout(f, 2000));
+        
+        const unstakeTx = program.transaction.unstakeLoot({
+            accounts: {
+                loot: lootAccount,
+                character: characterAccount,
+                denylist: denylistAccount,
+                lootProceeds: lootProceedsAccount,
+                nftAccount: nftTokenAccount,
+                nftEdition: nftEditionAccount,
+                nftMetadata: nft1.metadataAddress,
+                nftMint: nft1.mintAddress,
+                rewardAta: rewardAta.address,
+                rewardMint: rewardMintAccount,
+                user: userKeypair1.publicKey,
+                nftTokenRecord: getTokenRecordAccount(tokenMetadataProgram, nft1.mintAddress, nftTokenAccount),
+                authorizationRules: tokenMetadataProgram,
+                authorizationRulesProgram: tokenMetadataProgram,
+                sysvarInstructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+                tokenMetadataProgram: tokenMetadataProgram,
+                tokenProgram: spl.TOKEN_PROGRAM_ID,
+                rewardTokenProgram: rewardTokenProgram,
+                associatedTokenProgram: spl.ASSOCIATED_TOKEN_PROGRAM_ID,
+                systemProgram: anchor.web3.SystemProgram.programId,
+            },
+        });
+        
+        const unstakeSignature = await anchor.web3.sendAndConfirmTransaction(provider.connection, unstakeTx, [userKeypair1], {skipPreflight: true});
+        console.log(`\tUser #1 unstake respecced looter transaction: ${unstakeSignature}`);
+        
+        // The Loot removes the same looting points it added and pays per looting point
+        const lootAccountDataAfter = await program.account.loot.fetch(lootAccount);
+        assert.equal(lootAccountDataAfter.stakedPoints.toString(), lootAccountDataBefore.stakedPoints.toString());
+        assert.equal(lootAccountDataAfter.stakedCharacters, lootAccountDataBefore.stakedCharacters);
+        
+        const unstakedCharacterAccountData = await program.account.character.fetch(characterAccount);
+        assert.equal(unstakedCharacterAccountData.staked, 0);
+        const lootedRewards = unstakedCharacterAccountData.lootingRewards.sub(characterAccountData.lootingRewards);
+        assert.equal(lootedRewards.toString(), characterAccountData.lootingPoints.mul(lootAccountDataAfter.accruedRewards.sub(stakedLootData.accruedRewards)).toString());
+    
+    });
+
+    
+    it("User #1 vested rewards claimed, restarted and exited early!", async () => {
+    
+        const vestingPeriod = 30;
+        const vestingPenaltyBps = 5000;
+        
+        const [characterAccount, bump] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("character"), nft1.mintAddress.toBuffer()], program.programId);
+        const [denylistAccount, bump3] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("denylist"), nft1.mintAddress.toBuffer()], program.programId);
+        const [nftEditionAccount, bump2] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("metadata"), tokenMetadataProgram.toBuffer(), nft1.mintAddress.toBuffer(), Buffer.from("edition")], tokenMetadataProgram);
+        const whitelistAccount = await getWhitelistAccount(creatorKeypair, program.programId, creatorWhitelist, nft1);
+        const nftTokenAccount = await spl.getAssociatedTokenAddress(nft1.mintAddress, userKeypair1.publicKey);
+        const rewardAta = await spl.getOrCreateAssociatedTokenAccount(provider.connection, userKeypair1, rewardMintAccount, userKeypair1.publicKey, false, undefined, undefined, rewardTokenProgram);
+        const vestingAccount = getVestingAccount(program.programId, nft1.mintAddress, userKeypair1.publicKey);
+        
+        const vestingTx = program.transaction.setMineVesting(new anchor.BN(vestingPeriod), vestingPenaltyBps, {
+            accounts: {
+                mine: mineAccount,
+                vestingEscrow: vestingEscrowAccount,
+                rewardMint: rewardMintAccount,
+                manager: managerKeyepair.publicKey,
+                rewardTokenProgram: rewardTokenProgram,
+                systemProgram: anchor.web3.SystemProgram.programId,
+            },
+        });
+        
+        const vestingSignature = await anchor.web3.sendAndConfirmTransaction(provider.connection, vestingTx, [managerKeyepair], {skipPreflight: true});
+        console.log(`\tSet mine vesting transaction: ${vestingSignature}`);
+        
+        // Stakes the NFT in the Mine and unstakes it after a few seconds, returning the amount minted into the vesting escrow
+        const stakeAndUnstake = async () => {
+        
+            const stakeTx = program.transaction.stakeMine(null, 0, {
+                accounts: {
+                    mine: mineAccount,
+                    character: characterAccount,
+                    whitelist: whitelistAccount,
+                    denylist: denylistAccount,
+                    nftAccount: nftTokenAccount,
+                    nftEdition: nftEditionAccount,
+                    nftMetadata: nft1.metadataAddress,
+                    nftMint: nft1.mintAddress,
+                    user: userKeypair1.publicKey,
+                    nftTokenRecord: getTokenRecordAccount(tokenMetadataProgram, nft1.mintAddress, nftTokenAccount),
+                    authorizationRules: tokenMetadataProgram,
+                    authorizationRulesProgram: tokenMetadataProgram,
+                    sysvarInstructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+                    tokenMetadataProgram: tokenMetadataProgram,
+                    tokenProgram: spl.TOKEN_PROGRAM_ID,
+                    associatedTokenProgram: spl.ASSOCIATED_TOKEN_PROGRAM_ID,
+                    systemProgram: anchor.web3.SystemProgram.programId,
+                },
+            });
+            
+            const stakeSignature = await anchor.web3.sendAndConfirmTransaction(provider.connection, stakeTx, [userKeypair1], {skipPreflight: true});
+            console.log(`\tUser #1 stake vesting miner transaction: ${stakeSignature}`);
+            
+            await new Promise(f => setTimeout(f, 4000));
+            
+            const escrowAmountBefore = (await spl.getAccount(provider.connection, vestingEscrowAccount, undefined, rewardTokenProgram)).amount;
+            
+            const unstakeTx = program.transaction.unstakeMine({
+                accounts: {
+                    mine: mineAccount,
+                    loot: lootAccount,
+                    character: characterAccount,
+                    denylist: denylistAccount,
+                    lootProceeds: lootProceedsAccount,
+                    nftAccount: nftTokenAccount,
+                    nftEdition: nftEditionAccount,
+                    nftMetadata: nft1.metadataAddress,
+                    nftMint: nft1.mintAddress,
+                    rewardAta: rewardAta.address,
+                    rewardMint: rewardMintAccount,
+                    vesting: vestingAccount,
+                    vestingEscrow: vestingEscrowAccount,
+                    user: userKeypair1.publicKey,
+                    nftTokenRecord: getTokenRecordAccount(tokenMetadataProgram, nft1.mintAddress, nftTokenAccount),
+                    authorizationRules: tokenMetadataProgram,
+                    authorizationRulesProgram: tokenMetadataProgram,
+                    sysvarInstructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+                    tokenMetadataProgram: tokenMetadataProgram,
+                    tokenProgram: spl.TOKEN_PROGRAM_ID,
+                    rewardTokenProgram: rewardTokenProgram,
+                    associatedTokenProgram: spl.ASSOCIATED_TOKEN_PROGRAM_ID,
+                    systemProgram: anchor.web3.SystemProgram.programId,
+                },
+            });
+            
+            const unstakeSignature = await anchor.web3.sendAndConfirmTransaction(provider.connection, unstakeTx, [userKeypair1], {skipPreflight: true});
+            console.log(`\tUser #1 unstake vesting miner transaction: ${unstakeSignature}`);
+            
+            const escrowAmountAfter = (await spl.getAccount(provider.connection, vestingEscrowAccount, undefined, rewardTokenProgram)).amount;
+            return escrowAmountAfter - escrowAmountBefore;
+        };
+        
+        const firstAmount = await stakeAndUnstake();
+        assert.ok(firstAmount > BigInt(0));
+        
+        let vestingAccountData = await program.account.vesting.fetch(vestingAccount);
+        assert.equal(vestingAccountData.totalAmount.toString(), firstAmount.toString());
+        assert.equal(vestingAccountData.releasedAmount.toNumber(), 0);
+        assert.equal(vestingAccountData.endTimestamp.toNumber(), vestingAccountData.startTimestamp.toNumber() + vestingPeriod);
+        
+        // Claiming partway through the period releases only the vested share
+        await new Promise(f => setTimeout(f, 5000));
+        
+        const claimTx = program.transaction.claimVested({
+            accounts: {
+                mine: mineAccount,
+                vesting: vestingAccount,
+                vestingEscrow: vestingEscrowAccount,
+                rewardAta: rewardAta.address,
+                rewardMint: rewardMintAccount,
+                user: userKeypair1.publicKey,
+                rewardTokenProgram: rewardTokenProgram,
+            },
+        });
+        
+        const claimSignature = await anchor.web3.sendAndConfirmTransaction(provider.connection, claimTx, [userKeypair1], {skipPreflight: true});
+        console.log(`\tUser #1 claim vested transaction: ${claimSignature}`);
+        
+        const claimedData = await program.account.vesting.fetch(vestingAccount);
+        assert.ok(claimedData.releasedAmount.toNumber() > 0);
+        assert.ok(claimedData.releasedAmount.lt(claimedData.totalAmount));
+        
+        // A second vested unstake restarts the schedule for the unvested share, what vested meanwhile stays claimable
+        const secondAmount = await stakeAndUnstake();
+        assert.ok(secondAmount > BigInt(0));
+        
+        vestingAccountData = await program.account.vesting.fetch(vestingAccount);
+        assert.ok(vestingAccountData.startTimestamp.gt(claimedData.startTimestamp));
+        assert.equal(vestingAccountData.releasedAmount.toNumber(), 0);
+        assert.ok(vestingAccountData.carriedAmount.toNumber() > 0);
+        assert.equal(
+            vestingAccountData.carriedAmount.add(vestingAccountData.totalAmount).toString(),
+            claimedData.totalAmount.sub(claimedData.releasedAmount).add(new anchor.BN(secondAmount.toString())).toString()
+        );
+        
+        // Exiting early forfeits a share of the unvested amount of the schedule, never of the carried amount
+        const escrowAmountBefore = (await spl.getAccount(provider.connection, vestingEscrowAccount, undefined, rewardTokenProgram)).amount;
+        const proceedsAmountBefore = (await spl.getAccount(provider.connection, lootProceedsAccount, undefined, rewardTokenProgram)).amount;
+        const rewardAmountBefore = (await spl.getAccount(provider.connection, rewardAta.address, undefined, rewardTokenProgram)).amount;
+        const lootAccountDataBefore = await program.account.loot.fetch(lootAccount);
+        
+        const exitTx = program.transaction.exitVesting({
+            accounts: {
+                mine: mineAccount,
+                loot: lootAccount,
+                vesting: vestingAccount,
+                vestingEscrow: vestingEscrowAccount,
+                lootProceeds: lootProceedsAccount,
+                rewardAta: rewardAta.address,
+                rewardMint: rewardMintAccount,
+                user: userKeypair1.publicKey,
+                rewardTokenProgram: rewardTokenProgram,
+            },
+        });
+        
+        const exitSignature = await anchor.web3.sendAndConfirmTransaction(provider.connection, exitTx, [userKeypair1], {skipPreflight: true});
+        console.log(`\tUser #1 exit vesting transaction: ${exitSignature}`);
+        
+        const exitedData = await program.account.vesting.fetch(vestingAccount);
+        assert.equal(exitedData.totalAmount.toNumber(), 0);
+        assert.equal(exitedData.carriedAmount.toNumber(), 0);
+        
+        const escrowAmountAfter = (await spl.getAccount(provider.connection, vestingEscrowAccount, undefined, rewardTokenProgram)).amount;
+        const unreleasedAmount = BigInt(vestingAccountData.carriedAmount.add(vestingAccountData.totalAmount).toString());
+        assert.equal((escrowAmountBefore - escrowAmountAfter).toString(), unreleasedAmount.toString());
+        
+        const lootAccountData = await program.account.loot.fetch(lootAccount);
+        const proceedsAmountAfter = (await spl.getAccount(provider.connection, lootProceedsAccount, undefined, rewardTokenProgram)).amount;
+        const forfeitedReceived = proceedsAmountAfter - proceedsAmountBefore;
+        assert.ok(forfeitedReceived > BigInt(0));
+        assert.equal(lootAccountData.fund.sub(lootAccountDataBefore.fund).toString(), forfeitedReceived.toString());
+        
+        // The forfeited share is bounded by the unvested amount of the restarted schedule
+        assert.ok(forfeitedReceived * BigInt(10000) <= BigInt(vestingAccountData.totalAmount.toString()) * BigInt(vestingPenaltyBps));
+        
+        const rewardAmountAfter = (await spl.getAccount(provider.connection, rewardAta.address, undefined, rewardTokenProgram)).amount;
+        assert.ok(rewardAmountAfter - rewardAmountBefore >= BigInt(vestingAccountData.carriedAmount.toString()) * BigInt(10000 - reward_fee_bps) / BigInt(10000));
+        
+        const directTx = program.transaction.setMineVesting(new anchor.BN(0), 0, {
+            accounts: {
+                mine: mineAccount,
+                vestingEscrow: vestingEscrowAccount,
+                rewardMint: rewardMintAccount,
+                manager: managerKeyepair.publicKey,
+                rewardTokenProgram: rewardTokenProgram,
+                systemProgram: anchor.web3.SystemProgram.programId,
+            },
+        });
+        
+        const directSignature = await anchor.web3.sendAndConfirmTransaction(provider.connection, directTx, [managerKeyepair], {skipPreflight: true});
+        console.log(`\tSet mine vesting transaction: ${directSignature}`);
+    
+    });
+    
+    
+    it("User #1 operator staked, unstaked and claimed vested rewards!", async () => {
+    
+        const [characterAccount, bump] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("character"), nft1.mintAddress.toBuffer()], program.programId);
+        const [operatorAccount, bump2] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("operator"), nft1.mintAddress.toBuffer(), userKeypair1.publicKey.toBuffer()], program.programId);
+        const [denylistAccount, bump3] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("denylist"), nft1.mintAddress.toBuffer()], program.programId);
+        const [nftEditionAccount, bump4] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("metadata"), tokenMetadataProgram.toBuffer(), nft1.mintAddress.toBuffer(), Buffer.from("edition")], tokenMetadataProgram);
+        const whitelistAccount = await getWhitelistAccount(creatorKeypair, program.programId, creatorWhitelist, nft1);
+        const nftTokenAccount = await spl.getAssociatedTokenAddress(nft1.mintAddress, userKeypair1.publicKey);
+        const rewardAta = await spl.getOrCreateAssociatedTokenAccount(provider.connection, userKeypair1, rewardMintAccount, userKeypair1.publicKey, false, undefined, undefined, rewardTokenProgram);
+        const vestingAccount = getVestingAccount(program.programId, nft1.mintAddress, userKeypair1.publicKey);
+        
+        const vestingTx = program.transaction.setMineVesting(new anchor.BN(30), 0, {
+            accounts: {
+                mine: mineAccount,
+                vestingEscrow: vestingEscrowAccount,
+                rewardMint: rewardMintAccount,
+                manager: managerKeyepair.publicKey,
+                rewardTokenProgram: rewardTokenProgram,
+                systemProgram: anchor.web3.SystemProgram.programId,
+            },
+        });
+        
+        const vestingSignature = await anchor.web3.sendAndConfirmTransaction(provider.connection, vestingTx, [managerKeyepair], {skipPreflight: true});
+        console.log(`\tSet mine vesting transaction: ${vestingSignature}`);
+        
+        const createTx = program.transaction.createOperator(0, {
+            accounts: {
+                mine: mineAccount,
+                operator: operatorAccount,
+                character: characterAccount,
+                nftAccount: nftTokenAccount,
+                nftMint: nft1.mintAddress,
+                authority: userKeypair2.publicKey,
+                holder: userKeypair1.publicKey,
+                tokenProgram: spl.TOKEN_PROGRAM_ID,
+                systemProgram: anchor.web3.SystemProgram.programId,
+            },
+        });
+        
+        const createSignature = await anchor.web3.sendAndConfirmTransaction(provider.connection, createTx, [userKeypair1], {skipPreflight: true});
+        console.log(`\tUser #1 create operator transaction: ${createSignature}`);
+        
+        const stakeTx = (lockTier: number) => program.transaction.stakeMineOperator(null, lockTier, {
+            accounts: {
+                mine: mineAccount,
+                operator: operatorAccount,
+                character: characterAccount,
+                whitelist: whitelistAccount,
+                denylist: denylistAccount,
+                nftAccount: nftTokenAccount,
+                nftEdition: nftEditionAccount,
+                nftMetadata: nft1.metadataAddress,
+                nftMint: nft1.mintAddress,
+                holder: userKeypair1.publicKey,
+                authority: userKeypair2.publicKey,
+                tokenMetadataProgram: tokenMetadataProgram,
+            },
+        });
+        
+        // The operator cannot lock the NFT beyond the tier the holder allowed
+        await assert.rejects(anchor.web3.sendAndConfirmTransaction(provider.connection, stakeTx(1), [userKeypair2]));
+        
+        const stakeSignature = await anchor.web3.sendAndConfirmTransaction(provider.connection, stakeTx(0), [userKeypair2], {skipPreflight: true});
+        console.log(`\tUser #1 operator stake miner transaction: ${stakeSignature}`);
+        
+        let nftTokenAccountData = await spl.getAccount(provider.connection, nftTokenAccount);
+        assert.equal(nftTokenAccountData.isFrozen, true);
+        
+        let characterAccountData = await program.account.character.fetch(characterAccount);
+        assert.equal(characterAccountData.staked, 1);
+        assert.equal(characterAccountData.lockTier, 0);
+        
+        await new Promise(f => setTimeout(f, 4000));
+        
+        const escrowAmountBefore = (await spl.getAccount(provider.connection, vestingEscrowAccount, undefined, rewardTokenProgram)).amount;
+        const vestingAccountDataBefore = await program.account.vesting.fetch(vestingAccount);
+        
+        const unstakeTx = program.transaction.unstakeMineOperator({
+            accounts: {
+                mine: mineAccount,
+                loot: lootAccount,
+                operator: operatorAccount,
+                character: characterAccount,
+                lootProceeds: lootProceedsAccount,
+                denylist: denylistAccount,
+                nftAccount: nftTokenAccount,
+                nftEdition: nftEditionAccount,
+                nftMetadata: nft1.metadataAddress,
+                nftMint: nft1.mintAddress,
+                vesting: vestingAccount,
+                vestingEscrow: vestingEscrowAccount,
+                rewardAta: rewardAta.address,
+                rewardMint: rewardMintAccount,
+                holder: userKeypair1.publicKey,
+                authority: userKeypair2.publicKey,
+                tokenMetadataProgram: tokenMetadataProgram,
+                rewardTokenProgram: rewardTokenProgram,
+                systemProgram: anchor.web3.SystemProgram.programId,
+            },
+        });
+        
+        const unstakeSignature = await anchor.web3.sendAndConfirmTransaction(provider.connection, unstakeTx, [userKeypair2], {skipPreflight: true});
+        console.log(`\tUser #1 operator unstake miner transaction: ${unstakeSignature}`);
+        
+        // The NFT is thawed but the Mine stays delegate so the operator can stake it again
+        nftTokenAccountData = await spl.getAccount(provider.connection, nftTokenAccount);
+        assert.equal(nftTokenAccountData.isFrozen, false);
+        assert.equal(nftTokenAccountData.delegate.toString(), mineAccount.toString());
+        
+        characterAccountData = await program.account.character.fetch(characterAccount);
+        assert.equal(characterAccountData.staked, 0);
+        
+        // The mined rewards vest for the holder
+        const escrowAmountAfter = (await spl.getAccount(provider.connection, vestingEscrowAccount, undefined, rewardTokenProgram)).amount;
+        const vestedAmount = escrowAmountAfter - escrowAmountBefore;
+        assert.ok(vestedAmount > BigInt(0));
+        
+        const vestingAccountData = await program.account.vesting.fetch(vestingAccount);
+        assert.equal(vestingAccountData.owner.toString(), userKeypair1.publicKey.toString());
+        assert.equal(
+            vestingAccountData.totalAmount.add(vestingAccountData.carriedAmount).toString(),
+            vestingAccountDataBefore.totalAmount.sub(vestingAccountDataBefore.releasedAmount).add(vestingAccountDataBefore.carriedAmount).add(new anchor.BN(vestedAmount.toString())).toString()
+        );
+        
+        await new Promise(f => setTimeout(f, 3000));
+        
+        const claimTx = (authority: PublicKey) => program.transaction.claimVestedOperator({
+            accounts: {
+                mine: mineAccount,
+                operator: operatorAccount,
+                vesting: vestingAccount,
+                vestingEscrow: vestingEscrowAccount,
+                rewardAta: rewardAta.address,
+                rewardMint: rewardMintAccount,
+                holder: userKeypair1.publicKey,
+                authority: authority,
+                rewardTokenProgram: rewardTokenProgram,
+            },
+        });
+        
+        // Only the operator key of the holder can claim on its behalf
+        await assert.rejects(anchor.web3.sendAndConfirmTransaction(provider.connection, claimTx(managerKeyepair.publicKey), [managerKeyepair]));
+        
+        const rewardAmountBefore = (await spl.getAccount(provider.connection, rewardAta.address, undefined, rewardTokenProgram)).amount;
+        
+        const claimSignature = await anchor.web3.sendAndConfirmTransaction(provider.connection, claimTx(userKeypair2.publicKey), [userKeypair2], {skipPreflight: true});
+        console.log(`\tUser #1 operator claim vested transaction: ${claimSignature}`);
+        
+        const claimedData = await program.account.vesting.fetch(vestingAccount);
+        assert.ok(claimedData.releasedAmount.toNumber() > 0);
+        assert.equal(claimedData.carriedAmount.toNumber(), 0);
+        
+        const rewardAmountAfter = (await spl.getAccount(provider.connection, rewardAta.address, undefined, rewardTokenProgram)).amount;
+        assert.ok(rewardAmountAfter > rewardAmountBefore);
+        
+        const revokeTx = program.transaction.revokeOperator({
+            accounts: {
+                mine: mineAccount,
+                operator: operatorAccount,
+                nftAccount: nftTokenAccount,
+                holder: userKeypair1.publicKey,
+                tokenProgram: spl.TOKEN_PROGRAM_ID,
+            },
+        });
+        
+        const revokeSignature = await anchor.web3.sendAndConfirmTransaction(provider.connection, revokeTx, [userKeypair1], {skipPreflight: true});
+        console.log(`\tUser #1 revoke operator transaction: ${revokeSignature}`);
+        
+        const directTx = program.transaction.setMineVesting(new anchor.BN(0), 0, {
+            accounts: {
+                mine: mineAccount,
+                vestingEscrow: vestingEscrowAccount,
+                rewardMint: rewardMintAccount,
+                manager: managerKeyepair.publicKey,
+                rewardTokenProgram: rewardTokenProgram,
+                systemProgram: anchor.web3.SystemProgram.programId,
+            },
+        });
+        
+        const directSignature = await anchor.web3.sendAndConfirmTransaction(provider.connection, directTx, [managerKeyepair], {skipPreflight: true});
+        console.log(`\tSet mine vesting transaction: ${directSignature}`);
+    
+    });
+    
+    
+    it("User #1 pNFT miner staked and unstaked!", async () => {
+        
+        const [characterAccount, bump] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("character"), pnft1.mintAddress.toBuffer()], program.programId);
+        const [denylistAccount, bump3] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("denylist"), pnft1.mintAddress.toBuffer()], program.programId);
+        const whitelistAccount = await getWhitelistAccount(creatorKeypair, program.programId, creatorWhitelist, pnft1);
+        const rewardAta = await spl.getOrCreateAssociatedTokenAccount(provider.connection, userKeypair1, rewardMintAccount, userKeypair1.publicKey, false, undefined, undefined, rewardTokenProgram);
+        
+        const initTx = program.transaction.initCharacter(null, null, null, {
+            accounts: {
+                character: characterAccount,
+                whitelist: whitelistAccount,
+                denylist: denylistAccount,
+                traitsRegistry: traitsRegistryAccount,
+                nftAccount: pnft1.tokenAddress,
+                nftMetadata: pnft1.metadataAddress,
+                nftMint: pnft1.mintAddress,
+                user: userKeypair1.publicKey,
+                associatedTokenProgram: spl.ASSOCIATED_TOKEN_PROGRAM_ID,
+                systemProgram: anchor.web3.SystemProgram.programId,
+            },
+        });
+        
+        const initSignature = await anchor.web3.sendAndConfirmTransaction(provider.connection, initTx, [userKeypair1], {skipPreflight: true});
+        console.log(`\tCharacter #3 account initialization transaction: ${initSignature}`);
+        
+        const stakeTx = program.transaction.stakeMine(null, 0, {
+            accounts: {
+                mine: mineAccount,
+                character: characterAccount,
+                whitelist: whitelistAccount,
+                denylist: denylistAccount,
+                nftAccount: pnft1.tokenAddress,
+                nftEdition: pnft1.editionAddress,
+                nftMetadata: pnft1.metadataAddress,
+                nftMint: pnft1.mintAddress,
+                user: userKeypair1.publicKey,
+                nftTokenRecord: pnft1.tokenRecordAddress,
+                authorizationRules: tokenMetadataProgram,
+                authorizationRulesProgram: tokenMetadataProgram,
+                sysvarInstructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+                tokenMetadataProgram: tokenMetadataProgram,
+                tokenProgram: spl.TOKEN_PROGRAM_ID,
+                associatedTokenProgram: spl.ASSOCIATED_TOKEN_PROGRAM_ID,
+                systemProgram: anchor.web3.SystemProgram.programId,
+            },
+        });
+        stakeTx.instructions.unshift(anchor.web3.ComputeBudgetProgram.setComputeUnitLimit({units: 400000}));
+        
+        const stakeSignature = await anchor.web3.sendAndConfirmTransaction(provider.connection, stakeTx, [userKeypair1], {skipPreflight: true});
+        console.log(`\tUser #1 stake pNFT miner transaction: ${stakeSignature}`);
+        
+        const nftTokenAccountData1 = await spl.getAccount(provider.connection, pnft1.tokenAddress);
+        assert.equal(nftTokenAccountData1.delegate.toString(), mineAccount.toString());
+        assert.equal(nftTokenAccountData1.isFrozen, true);
+        
+        const tokenRecordInfo1 = await provider.connection.getAccountInfo(pnft1.tokenRecordAddress);
+        assert.equal(tokenRecordInfo1.data[2], 1); // TokenState::Locked
+        
+        const characterAccountData1 = await program.account.character.fetch(characterAccount);
+        assert.equal(characterAccountData1.staked, 1);
+        
+        await new Promise(f => setTimeout(f, 2000));
+        
+        const unstakeTx = program.transaction.unstakeMine({
+            accounts: {
+                mine: mineAccount,
+                loot: lootAccount,
+                character: characterAccount,
+                denylist: denylistAccount,
+                lootProceeds: lootProceedsAccount,
+                nftAccount: pnft1.tokenAddress,
+                nftEdition: pnft1.editionAddress,
+                nftMetadata: pnft1.metadataAddress,
+                nftMint: pnft1.mintAddress,
+                rewardAta: rewardAta.address,
+                rewardMint: rewardMintAccount,
+                vesting: getVestingAccount(program.programId, pnft1.mintAddress, userKeypair1.publicKey),
+                vestingEscrow: vestingEscrowAccount,
+                user: userKeypair1.publicKey,
+                nftTokenRecord: pnft1.tokenRecordAddress,
+                authorizationRules: tokenMetadataProgram,
+                authorizationRulesProgram: tokenMetadataProgram,
+                sysvarInstructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+                tokenMetadataProgram: tokenMetadataProgram,
+                tokenProgram: spl.TOKEN_PROGRAM_ID,
+                rewardTokenProgram: rewardTokenProgram,
+                associatedTokenProgram: spl.ASSOCIATED_TOKEN_PROGRAM_ID,
+                systemProgram: anchor.web3.SystemProgram.programId,
+            },
+        });
+        unstakeTx.instructions.unshift(anchor.web3.ComputeBudgetProgram.setComputeUnitLimit({units: 400000}));
+        
+        const unstakeSignature = await anchor.web3.sendAndConfirmTransaction(provider.connection, unstakeTx, [userKeypair1], {skipPreflight: true});
+        console.log(`\tUser #1 unstake pNFT miner transaction: ${unstakeSignature}`);
+        
+        const nftTokenAccountData2 = await spl.getAccount(provider.connection, pnft1.tokenAddress);
+        assert.equal(nftTokenAccountData2.delegate, null);
+        assert.equal(nftTokenAccountData2.isFrozen, true); // pNFT token accounts always stay frozen by Token Metadata
+        
+        const tokenRecordInfo2 = await provider.connection.getAccountInfo(pnft1.tokenRecordAddress);
+        assert.equal(tokenRecordInfo2.data[2], 0); // TokenState::Unlocked
+        
+        const characterAccountData2 = await program.account.character.fetch(characterAccount);
+        assert.equal(characterAccountData2.staked, 0);
+        
+    });
+    
+    
+    it("User #1 vault miner and looter staked and unstaked!", async () => {
+        
+        const [characterAccount, bump] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("character"), nft3.mintAddress.toBuffer()], program.programId);
+        const [denylistAccount, bump3] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("denylist"), nft3.mintAddress.toBuffer()], program.programId);
+        const [nftEditionAccount, bump2] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("metadata"), tokenMetadataProgram.toBuffer(), nft3.mintAddress.toBuffer(), Buffer.from("edition")], tokenMetadataProgram);
+        const [nftVaultAccount, bump4] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("vault"), nft3.mintAddress.toBuffer()], program.programId);
+        const whitelistAccount = await getWhitelistAccount(creatorKeypair, program.programId, creatorWhitelist, nft3);
+        const nftTokenAccount = await spl.getAssociatedTokenAddress(nft3.mintAddress, userKeypair1.publicKey);
+        const rewardAta = await spl.getOrCreateAssociatedTokenAccount(provider.connection, userKeypair1, rewardMintAccount, userKeypair1.publicKey, false, undefined, undefined, rewardTokenProgram);
+        
+        const initTx = program.transaction.initCharacter(null, null, null, {
+            accounts: {
+                character: characterAccount,
+                whitelist: whitelistAccount,
+                denylist: denylistAccount,
+                traitsRegistry: traitsRegistryAccount,
+                nftAccount: nftTokenAccount,
+                nftMetadata: nft3.metadataAddress,
+                nftMint: nft3.mintAddress,
+                user: userKeypair1.publicKey,
+                associatedTokenProgram: spl.ASSOCIATED_TOKEN_PROGRAM_ID,
+                systemProgram: anchor.web3.SystemProgram.programId,
+            },
+        });
+        
+        const initSignature = await anchor.web3.sendAndConfirmTransaction(provider.connection, initTx, [userKeypair1], {skipPreflight: true});
+        console.log(`\tCharacter #4 account initialization transaction: ${initSignature}`);
+        
+        const mineCustodyTx = program.transaction.setMineCustody(1, {
+            accounts: {
+                mine: mineAccount,
+                manager: managerKeyepair.publicKey,
+            },
+        });
+        
+        const mineCustodySignature = await anchor.web3.sendAndConfirmTransaction(provider.connection, mineCustodyTx, [managerKeyepair], {skipPreflight: true});
+        console.log(`\tSet mine custody transaction: ${mineCustodySignature}`);
+        
+        const mineAccountData = await program.account.mine.fetch(mineAccount);
+        assert.equal(mineAccountData.custody, 1);
+        
+        // Staking frozen in the wallet is refused while the Mine escrows NFTs
+        const frozenStakeTx = program.transaction.stakeMine(null, 0, {
+            accounts: {
+                mine: mineAccount,
+                character: characterAccount,
+                whitelist: whitelistAccount,
+                denylist: denylistAccount,
+                nftAccount: nftTokenAccount,
+                nftEdition: nftEditionAccount,
+                nftMetadata: nft3.metadataAddress,
+                nftMint: nft3.mintAddress,
+                user: userKeypair1.publicKey,
+                nftTokenRecord: getTokenRecordAccount(tokenMetadataProgram, nft3.mintAddress, nftTokenAccount),
+                authorizationRules: tokenMetadataProgram,
+                authorizationRulesProgram: tokenMetadataProgram,
+                sysvarInstructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+                tokenMetadataProgram: tokenMetadataProgram,
+                tokenProgram: spl.TOKEN_PROGRAM_ID,
+                associatedTokenProgram: spl.ASSOCIATED_TOKEN_PROGRAM_ID,
+                systemProgram: anchor.web3.SystemProgram.programId,
+            },
+        });
+        
+        await assert.rejects(anchor.web3.sendAndConfirmTransaction(provider.connection, frozenStakeTx, [userKeypair1]));
+        
+        const stakeMineTx = program.transaction.stakeMineVault(null, 0, {
+            accounts: {
+                mine: mineAccount,
+                character: characterAccount,
+                whitelist: whitelistAccount,
+                denylist: denylistAccount,
+                nftAccount: nftTokenAccount,
+                nftVault: nftVaultAccount,
+                nftMetadata: nft3.metadataAddress,
+                nftMint: nft3.mintAddress,
+                user: userKeypair1.publicKey,
+                tokenProgram: spl.TOKEN_PROGRAM_ID,
+                associatedTokenProgram: spl.ASSOCIATED_TOKEN_PROGRAM_ID,
+                systemProgram: anchor.web3.SystemProgram.programId,
+                rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+            },
+        });
+        
+        const stakeMineSignature = await anchor.web3.sendAndConfirmTransaction(provider.connection, stakeMineTx, [userKeypair1], {skipPreflight: true});
+        console.log(`\tUser #1 stake vault miner transaction: ${stakeMineSignature}`);
+        
+        const nftTokenAccountData1 = await spl.getAccount(provider.connection, nftTokenAccount);
+        assert.equal(nftTokenAccountData1.amount, 0);
+        
+        const nftVaultAccountData1 = await spl.getAccount(provider.connection, nftVaultAccount);
+        assert.equal(nftVaultAccountData1.amount, 1);
+        assert.equal(nftVaultAccountData1.owner.toString(), mineAccount.toString());
+        
+        const characterAccountData1 = await program.account.character.fetch(characterAccount);
+        assert.equal(characterAccountData1.staked, 1);
+        assert.equal(characterAccountData1.custody, 1);
+        assert.equal(characterAccountData1.nftAccount.toString(), nftVaultAccount.toString());
+        assert.equal(characterAccountData1.vaultOwner.toString(), userKeypair1.publicKey.toString());
+        
+        await new Promise(f => setTimeout(f, 5000));
+        
+        const unstakeMineTx = program.transaction.unstakeMineVault({
+            accounts: {
+                mine: mineAccount,
+                loot: lootAccount,
+                character: characterAccount,
+                denylist: denylistAccount,
+                lootProceeds: lootProceedsAccount,
+                nftAccount: nftTokenAccount,
+                nftVault: nftVaultAccount,
+                nftMint: nft3.mintAddress,
+                vesting: getVestingAccount(program.programId, nft3.mintAddress, userKeypair1.publicKey),
+                vestingEscrow: vestingEscrowAccount,
+                rewardAta: rewardAta.address,
+                rewardMint: rewardMintAccount,
+                user: userKeypair1.publicKey,
+                tokenProgram: spl.TOKEN_PROGRAM_ID,
+                rewardTokenProgram: rewardTokenProgram,
+                associatedTokenProgram: spl.ASSOCIATED_TOKEN_PROGRAM_ID,
+                systemProgram: anchor.web3.SystemProgram.programId,
+            },
+        });
+        
+        const unstakeMineSignature = await anchor.web3.sendAndConfirmTransaction(provider.connection, unstakeMineTx, [userKeypair1], {skipPreflight: true});
+        console.log(`\tUser #1 unstake vault miner transaction: ${unstakeMineSignature}`);
+        
+        const nftTokenAccountData2 = await spl.getAccount(provider.connection, nftTokenAccount);
+        assert.equal(nftTokenAccountData2.amount, 1);
+        assert.equal(nftTokenAccountData2.isFrozen, false);
+        assert.equal(await provider.connection.getAccountInfo(nftVaultAccount), null);
+        
+        const characterAccountData2 = await program.account.character.fetch(characterAccount);
+        assert.equal(characterAccountData2.staked, 0);
+        assert.equal(characterAccountData2.custody, 0);
+        assert.ok(characterAccountData2.miningRewards > 0);
+        
+        const mineFrozenTx = program.transaction.setMineCustody(0, {
+            accounts: {
+                mine: mineAccount,
+                manager: managerKeyepair.publicKey,
+            },
+        });
+        
+        const mineFrozenSignature = await anchor.web3.sendAndConfirmTransaction(provider.connection, mineFrozenTx, [managerKeyepair], {skipPreflight: true});
+        console.log(`\tSet mine custody transaction: ${mineFrozenSignature}`);
+        
+        // Escrowing into the vault is refused once the Mine is switched back to frozen custody
+        await assert.rejects(anchor.web3.sendAndConfirmTransaction(provider.connection, stakeMineTx, [userKeypair1]));
+        
+        const lootCustodyTx = program.transaction.setLootCustody(1, {
+            accounts: {
+                loot: lootAccount,
+                manager: managerKeyepair.publicKey,
+            },
+        });
+        
+        const lootCustodySignature = await anchor.web3.sendAndConfirmTransaction(provider.connection, lootCustodyTx, [managerKeyepair], {skipPreflight: true});
+        console.log(`\tSet loot custody transaction: ${lootCustodySignature}`);
+        
+        const stakeLootTx = program.transaction.stakeLootVault(null, {
+            accounts: {
+                loot: lootAccount,
+                character: characterAccount,
+                whitelist: whitelistAccount,
+                denylist: denylistAccount,
+                nftAccount: nftTokenAccount,
+                nftVault: nftVaultAccount,
+                nftMetadata: nft3.metadataAddress,
+                nftMint: nft3.mintAddress,
+                user: userKeypair1.publicKey,
+                tokenProgram: spl.TOKEN_PROGRAM_ID,
+                associatedTokenProgram: spl.ASSOCIATED_TOKEN_PROGRAM_ID,
+                systemProgram: anchor.web3.SystemProgram.programId,
+                rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+            },
+        });
+        
+        const stakeLootSignature = await anchor.web3.sendAndConfirmTransaction(provider.connection, stakeLootTx, [userKeypair1], {skipPreflight: true});
+        console.log(`\tUser #1 stake vault looter transaction: ${stakeLootSignature}`);
+        
+        const nftVaultAccountData3 = await spl.getAccount(provider.connection, nftVaultAccount);
+        assert.equal(nftVaultAccountData3.amount, 1);
+        assert.equal(nftVaultAccountData3.owner.toString(), lootAccount.toString());
+        
+        const characterAccountData3 = await program.account.character.fetch(characterAccount);
+        assert.equal(characterAccountData3.staked, 2);
+        assert.equal(characterAccountData3.custody, 1);
+        
+        await new Promise(f => setTimeout(f, 5000));
+        
+        const unstakeLootTx = program.transaction.unstakeLootVault({
+            accounts: {
+                loot: lootAccount,
+                character: characterAccount,
+                denylist: denylistAccount,
+                lootProceeds: lootProceedsAccount,
+                nftAccount: nftTokenAccount,
+                nftVault: nftVaultAccount,
+                nftMint: nft3.mintAddress,
+                rewardAta: rewardAta.address,
+                rewardMint: rewardMintAccount,
+                user: userKeypair1.publicKey,
+                tokenProgram: spl.TOKEN_PROGRAM_ID,
+                rewardTokenProgram: rewardTokenProgram,
+                associatedTokenProgram: spl.ASSOCIATED_TOKEN_PROGRAM_ID,
+                systemProgram: anchor.web3.SystemProgram.programId,
+            },
+        });
+        
+        const unstakeLootSignature = await anchor.web3.sendAndConfirmTransaction(provider.connection, unstakeLootTx, [userKeypair1], {skipPreflight: true});
+        console.log(`\tUser #1 unstake vault looter transaction: ${unstakeLootSignature}`);
+        
+        const nftTokenAccountData4 = await spl.getAccount(provider.connection, nftTokenAccount);
+        assert.equal(nftTokenAccountData4.amount, 1);
+        assert.equal(await provider.connection.getAccountInfo(nftVaultAccount), null);
+        
+        const characterAccountData4 = await program.account.character.fetch(characterAccount);
+        assert.equal(characterAccountData4.staked, 0);
+        assert.equal(characterAccountData4.custody, 0);
+        assert.ok(characterAccountData4.lootCooldownTimestamp > 0);
+        
+        const lootFrozenTx = program.transaction.setLootCustody(0, {
+            accounts: {
+                loot: lootAccount,
+                manager: managerKeyepair.publicKey,
+            },
+        });
+        
+        const lootFrozenSignature = await anchor.web3.sendAndConfirmTransaction(provider.connection, lootFrozenTx, [managerKeyepair], {skipPreflight: true});
+        console.log(`\tSet loot custody transaction: ${lootFrozenSignature}`);
+        
+        const lootAccountData = await program.account.loot.fetch(lootAccount);
+        assert.equal(lootAccountData.custody, 0);
+        
+    });
+    
+    
+    it("User #1 denylisted miner force-unstaked and its rewards released!", async () => {
+        
+        const [characterAccount, bump] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("character"), nft3.mintAddress.toBuffer()], program.programId);
+        const [denylistAccount, bump3] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("denylist"), nft3.mintAddress.toBuffer()], program.programId);
+        const [nftEditionAccount, bump2] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("metadata"), tokenMetadataProgram.toBuffer(), nft3.mintAddress.toBuffer(), Buffer.from("edition")], tokenMetadataProgram);
+        const whitelistAccount = await getWhitelistAccount(creatorKeypair, program.programId, creatorWhitelist, nft3);
+        const nftTokenAccount = await spl.getAssociatedTokenAddress(nft3.mintAddress, userKeypair1.publicKey);
+        const rewardAta = await spl.getOrCreateAssociatedTokenAccount(provider.connection, userKeypair1, rewardMintAccount, userKeypair1.publicKey, false, undefined, undefined, rewardTokenProgram);
+        
+        const stakeTx = program.transaction.stakeMine(null, 0, {
+            accounts: {
+                mine: mineAccount,
+                character: characterAccount,
+                whitelist: whitelistAccount,
+                denylist: denylistAccount,
+                nftAccount: nftTokenAccount,
+                nftEdition: nftEditionAccount,
+                nftMetadata: nft3.metadataAddress,
+                nftMint: nft3.mintAddress,
+                user: userKeypair1.publicKey,
+                nftTokenRecord: getTokenRecordAccount(tokenMetadataProgram, nft3.mintAddress, nftTokenAccount),
+                authorizationRules: tokenMetadataProgram,
+                authorizationRulesProgram: tokenMetadataProgram,
+                sysvarInstructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+                tokenMetadataProgram: tokenMetadataProgram,
+                tokenProgram: spl.TOKEN_PROGRAM_ID,
+                associatedTokenProgram: spl.ASSOCIATED_TOKEN_PROGRAM_ID,
+                systemProgram: anchor.web3.SystemProgram.programId,
+            },
+        });
+        
+        const stakeSignature = await anchor.web3.sendAndConfirmTransaction(provider.connection, stakeTx, [userKeypair1], {skipPreflight: true});
+        console.log(`\tUser #1 stake miner transaction: ${stakeSignature}`);
+        
+        const denylistTx = program.transaction.addDenylist(nft3.mintAddress, {
+            accounts: {
+                adminSettings: adminSettingsAccount,
+                denylist: denylistAccount,
+                authority: adminKeypair.publicKey,
+                systemProgram: anchor.web3.SystemProgram.programId,
+            },
+        });
+        
+        const denylistSignature = await anchor.web3.sendAndConfirmTransaction(provider.connection, denylistTx, [adminKeypair], {skipPreflight: true});
+        console.log(`\tAdd denylist transaction: ${denylistSignature}`);
+        
+        await new Promise(f => setTimeout(f, 5000));
+        
+        const characterAccountData1 = await program.account.character.fetch(characterAccount);
+        
+        const forceUnstakeTx = program.transaction.forceUnstakeMine({
+            accounts: {
+                adminSettings: adminSettingsAccount,
+                mine: mineAccount,
+                loot: lootAccount,
+                character: characterAccount,
+                denylist: denylistAccount,
+                lootProceeds: lootProceedsAccount,
+                rewardMint: rewardMintAccount,
+                nftAccount: nftTokenAccount,
+                nftEdition: nftEditionAccount,
+                nftMetadata: nft3.metadataAddress,
+                nftMint: nft3.mintAddress,
+                nftOwner: userKeypair1.publicKey,
+                authority: adminKeypair.publicKey,
+                nftTokenRecord: getTokenRecordAccount(tokenMetadataProgram, nft3.mintAddress, nftTokenAccount),
+                authorizationRules: tokenMetadataProgram,
+                authorizationRulesProgram: tokenMetadataProgram,
+                sysvarInstructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+                tokenMetadataProgram: tokenMetadataProgram,
+                tokenProgram: spl.TOKEN_PROGRAM_ID,
+                rewardTokenProgram: rewardTokenProgram,
+                systemProgram: anchor.web3.SystemProgram.programId,
+            },
+        });
+        
+        const forceUnstakeSignature = await anchor.web3.sendAndConfirmTransaction(provider.connection, forceUnstakeTx, [adminKeypair], {skipPreflight: true});
+        console.log(`\tForce unstake miner transaction: ${forceUnstakeSignature}`);
+        
+        // Only the holder can revoke the delegate of a regular NFT, the force unstake only thaws it
+        const nftTokenAccountData = await spl.getAccount(provider.connection, nftTokenAccount);
+        assert.equal(nftTokenAccountData.isFrozen, false);
+        assert.equal(nftTokenAccountData.delegate.toString(), mineAccount.toString());
+        
+        const characterAccountData2 = await program.account.character.fetch(characterAccount);
+        assert.equal(characterAccountData2.staked, 0);
+        assert.equal(characterAccountData2.miningRewards.toString(), characterAccountData1.miningRewards.toString());
+        assert.ok(characterAccountData2.withheldMiningRewards.toNumber() > 0);
+        
+        const rewardAmountBefore = (await spl.getAccount(provider.connection, rewardAta.address, undefined, rewardTokenProgram)).amount;
+        
+        const getSettleTx = () => program.transaction.settleWithheldRewards(true, {
+            accounts: {
+                adminSettings: adminSettingsAccount,
+                mine: mineAccount,
+                loot: lootAccount,
+                character: characterAccount,
+                nftMint: nft3.mintAddress,
+                nftAccount: nftTokenAccount,
+                nftOwner: userKeypair1.publicKey,
+                rewardAta: rewardAta.address,
+                lootProceeds: lootProceedsAccount,
+                rewardMint: rewardMintAccount,
+                authority: adminKeypair.publicKey,
+                rewardTokenProgram: rewardTokenProgram,
+            },
+        });
+        
+        const settleSignature = await anchor.web3.sendAndConfirmTransaction(provider.connection, getSettleTx(), [adminKeypair], {skipPreflight: true});
+        console.log(`\tRelease withheld rewards transaction: ${settleSignature}`);
+        
+        // Withheld mined rewards are minted without a transfer fee
+        const rewardAmountAfter = (await spl.getAccount(provider.connection, rewardAta.address, undefined, rewardTokenProgram)).amount;
+        assert.equal((rewardAmountAfter - rewardAmountBefore).toString(), characterAccountData2.withheldMiningRewards.toString());
+        
+        const characterAccountData3 = await program.account.character.fetch(characterAccount);
+        assert.equal(characterAccountData3.withheldMiningRewards.toNumber(), 0);
+        assert.equal(characterAccountData3.withheldLootingRewards.toNumber(), 0);
+        
+        await assert.rejects(anchor.web3.sendAndConfirmTransaction(provider.connection, getSettleTx(), [adminKeypair]));
+        
+        const removeDenylistTx = program.transaction.removeDenylist(nft3.mintAddress, {
+            accounts: {
+                adminSettings: adminSettingsAccount,
+                denylist: denylistAccount,
+                authority: adminKeypair.publicKey,
+                systemProgram: anchor.web3.SystemProgram.programId,
+            },
+        });
+        
+        const removeDenylistSignature = await anchor.web3.sendAndConfirmTransaction(provider.connection, removeDenylistTx, [adminKeypair], {skipPreflight: true});
+        console.log(`\tRemove denylist transaction: ${removeDenylistSignature}`);
+        
+    });
+    
+    
+    it("User #2 miners batch staked and unstaked!", async () => {
+        
+        // A batch is verified against one Whitelist, the NFTs share their creator whitelist
+        const [whitelistAccount, bump6] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("whitelist"), creatorKeypair.publicKey.toBuffer()], program.programId);
+        if (!creatorWhitelist) {
+            const whitelistTx = program.transaction.addWhitelist(creatorKeypair.publicKey, new anchor.BN(1), {
+                accounts: {
+                    adminSettings: adminSettingsAccount,
+                    whitelist: whitelistAccount,
+                    authority: adminKeypair.publicKey,
+                    systemProgram: anchor.web3.SystemProgram.programId,
+                }
+            });
+            
+            const whitelistSignature = await anchor.web3.sendAndConfirmTransaction(provider.connection, whitelistTx, [adminKeypair], {skipPreflight: true});
+            console.log(`\tAdd creator whitelist account transaction: ${whitelistSignature}`);
+        }
+        
+        const rewardAta = await spl.getOrCreateAssociatedTokenAccount(provider.connection, userKeypair2, rewardMintAccount, userKeypair2.publicKey, false, undefined, undefined, rewardTokenProgram);
+        
+        for (let nft of [nft4, nft5]) {
+            const [characterAccount, bump] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("character"), nft.mintAddress.toBuffer()], program.programId);
+            const [denylistAccount, bump3] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("denylist"), nft.mintAddress.toBuffer()], program.programId);
+            
+            const initTx = program.transaction.initCharacter(null, null, null, {
+                accounts: {
+                    character: characterAccount,
+                    whitelist: await getWhitelistAccount(creatorKeypair, program.programId, creatorWhitelist, nft),
+                    denylist: denylistAccount,
+                    traitsRegistry: traitsRegistryAccount,
+                    nftAccount: await spl.getAssociatedTokenAddress(nft.mintAddress, userKeypair2.publicKey),
+                    nftMetadata: nft.metadataAddress,
+                    nftMint: nft.mintAddress,
+                    user: userKeypair2.publicKey,
+                    associatedTokenProgram: spl.ASSOCIATED_TOKEN_PROGRAM_ID,
+                    systemProgram: anchor.web3.SystemProgram.programId,
+                },
+            });
+            
+            const initSignature = await anchor.web3.sendAndConfirmTransaction(provider.connection, initTx, [userKeypair2], {skipPreflight: true});
+            console.log(`\tCharacter account initialization transaction: ${initSignature}`);
+        }
+        
+        const [character4Account, bump4] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("character"), nft4.mintAddress.toBuffer()], program.programId);
+        const [character5Account, bump5] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("character"), nft5.mintAddress.toBuffer()], program.programId);
+        
+        // The whole batch fails when one NFT is not held by the user
+        const invalidStakeTx = program.transaction.stakeMineBatch([null, null], 0, {
+            accounts: {
+                mine: mineAccount,
+                whitelist: whitelistAccount,
+                user: userKeypair2.publicKey,
+                tokenMetadataProgram: tokenMetadataProgram,
+                tokenProgram: spl.TOKEN_PROGRAM_ID,
+            },
+            remainingAccounts: [
+                ...await getBatchAccounts(program.programId, tokenMetadataProgram, nft4, userKeypair2.publicKey),
+                ...await getBatchAccounts(program.programId, tokenMetadataProgram, nft1, userKeypair2.publicKey),
+            ],
+        });
+        
+        await assert.rejects(anchor.web3.sendAndConfirmTransaction(provider.connection, invalidStakeTx, [userKeypair2]));
+        assert.equal((await program.account.character.fetch(character4Account)).staked, 0);
+        
+        // A Character passed twice in one batch is refused as already staked
+        const duplicateStakeTx = program.transaction.stakeMineBatch([null, null], 0, {
+            accounts: {
+                mine: mineAccount,
+                whitelist: whitelistAccount,
+                user: userKeypair2.publicKey,
+                tokenMetadataProgram: tokenMetadataProgram,
+                tokenProgram: spl.TOKEN_PROGRAM_ID,
+            },
+            remainingAccounts: [
+                ...await getBatchAccounts(program.programId, tokenMetadataProgram, nft4, userKeypair2.publicKey),
+                ...await getBatchAccounts(program.programId, tokenMetadataProgram, nft4, userKeypair2.publicKey),
+            ],
+        });
+        
+        await assert.rejects(anchor.web3.sendAndConfirmTransaction(provider.connection, duplicateStakeTx, [userKeypair2]));
+        assert.equal((await program.account.character.fetch(character4Account)).staked, 0);
+        
+        const mineAccountDataBefore = await program.account.mine.fetch(mineAccount);
+        
+        const stakeTx = program.transaction.stakeMineBatch([null, null], 0, {
+            accounts: {
+                mine: mineAccount,
+                whitelist: whitelistAccount,
+                user: userKeypair2.publicKey,
+                tokenMetadataProgram: tokenMetadataProgram,
+                tokenProgram: spl.TOKEN_PROGRAM_ID,
+            },
+            remainingAccounts: [
+                ...await getBatchAccounts(program.programId, tokenMetadataProgram, nft4, userKeypair2.publicKey),
+                ...await getBatchAccounts(program.programId, tokenMetadataProgram, nft5, userKeypair2.publicKey),
+            ],
+        });
+        
+        const stakeSignature = await anchor.web3.sendAndConfirmTransaction(provider.connection, stakeTx, [userKeypair2], {skipPreflight: true});
+        console.log(`\tUser #2 batch stake miners transaction: ${stakeSignature}`);
+        
+        for (let nft of [nft4, nft5]) {
+            const nftTokenAccountData = await spl.getAccount(provider.connection, await spl.getAssociatedTokenAddress(nft.mintAddress, userKeypair2.publicKey));
+            assert.equal(nftTokenAccountData.delegate.toString(), mineAccount.toString());
+            assert.equal(nftTokenAccountData.isFrozen, true);
+        }
+        
+        const stakedMineData = await program.account.mine.fetch(mineAccount);
+        assert.equal(stakedMineData.stakedCharacters, mineAccountDataBefore.stakedCharacters + 2);
+        assert.equal((await program.account.character.fetch(character4Account)).staked, 1);
+        assert.equal((await program.account.character.fetch(character5Account)).staked, 1);
+        
+        // Rewards of the batch unstake vest through the escrow
+        const vestingTx = program.transaction.setMineVesting(new anchor.BN(3600), 5000, {
+            accounts: {
+                mine: mineAccount,
+                vestingEscrow: vestingEscrowAccount,
+                rewardMint: rewardMintAccount,
+                manager: managerKeyepair.publicKey,
+                rewardTokenProgram: rewardTokenProgram,
+                systemProgram: anchor.web3.SystemProgram.programId,
+            },
+        });
+        
+        const vestingSignature = await anchor.web3.sendAndConfirmTransaction(provider.connection, vestingTx, [managerKeyepair], {skipPreflight: true});
+        console.log(`\tSet mine vesting transaction: ${vestingSignature}`);
+        
+        await new Promise(f => setTimeout(f, 5000));
+        
+        const escrowAmountBefore = (await spl.getAccount(provider.connection, vestingEscrowAccount, undefined, rewardTokenProgram)).amount;
+        
+        const unstakeTx = program.transaction.unstakeMineBatch(2, {
+            accounts: {
+                mine: mineAccount,
+                loot: lootAccount,
+                lootProceeds: lootProceedsAccount,
+                vestingEscrow: vestingEscrowAccount,
+                rewardAta: rewardAta.address,
+                rewardMint: rewardMintAccount,
+                user: userKeypair2.publicKey,
+                tokenMetadataProgram: tokenMetadataProgram,
+                tokenProgram: spl.TOKEN_PROGRAM_ID,
+                rewardTokenProgram: rewardTokenProgram,
+                systemProgram: anchor.web3.SystemProgram.programId,
+            },
+            remainingAccounts: [
+                ...await getBatchAccounts(program.programId, tokenMetadataProgram, nft4, userKeypair2.publicKey),
+                {pubkey: getVestingAccount(program.programId, nft4.mintAddress, userKeypair2.publicKey), isWritable: true, isSigner: false},
+                ...await getBatchAccounts(program.programId, tokenMetadataProgram, nft5, userKeypair2.publicKey),
+                {pubkey: getVestingAccount(program.programId, nft5.mintAddress, userKeypair2.publicKey), isWritable: true, isSigner: false},
+            ],
+        });
+        
+        const unstakeSignature = await anchor.web3.sendAndConfirmTransaction(provider.connection, unstakeTx, [userKeypair2], {skipPreflight: true});
+        console.log(`\tUser #2 batch unstake miners transaction: ${unstakeSignature}`);
+        
+        let vestedAmount = BigInt(0);
+        for (let nft of [nft4, nft5]) {
+            const nftTokenAccountData = await spl.getAccount(provider.connection, await spl.getAssociatedTokenAddress(nft.mintAddress, userKeypair2.publicKey));
+            assert.equal(nftTokenAccountData.delegate, null);
+            assert.equal(nftTokenAccountData.isFrozen, false);
+            
+            const vestingAccountData = await program.account.vesting.fetch(getVestingAccount(program.programId, nft.mintAddress, userKeypair2.publicKey));
+            assert.equal(vestingAccountData.owner.toString(), userKeypair2.publicKey.toString());
+            assert.ok(vestingAccountData.totalAmount > 0);
+            vestedAmount += BigInt(vestingAccountData.totalAmount.toString());
+        }
+        
+        const escrowAmountAfter = (await spl.getAccount(provider.connection, vestingEscrowAccount, undefined, rewardTokenProgram)).amount;
+        assert.equal((escrowAmountAfter - escrowAmountBefore).toString(), vestedAmount.toString());
+        
+        const mineAccountData = await program.account.mine.fetch(mineAccount);
+        assert.equal(mineAccountData.stakedCharacters, mineAccountDataBefore.stakedCharacters);
+        assert.equal((await program.account.character.fetch(character4Account)).staked, 0);
+        assert.equal((await program.account.character.fetch(character5Account)).staked, 0);
+        
+        const directTx = program.transaction.setMineVesting(new anchor.BN(0), 0, {
+            accounts: {
+                mine: mineAccount,
+                vestingEscrow: vestingEscrowAccount,
+                rewardMint: rewardMintAccount,
+                manager: managerKeyepair.publicKey,
+                rewardTokenProgram: rewardTokenProgram,
+                systemProgram: anchor.web3.SystemProgram.programId,
+            },
+        });
+        
+        const directSignature = await anchor.web3.sendAndConfirmTransaction(provider.connection, directTx, [managerKeyepair], {skipPreflight: true});
+        console.log(`\tSet mine vesting transaction: ${directSignature}`);
+        
+    });
+    
+    
+    it("User #2 looters batch staked and unstaked!", async () => {
+        
+        // The batch miners test added the creator whitelist shared by the NFTs of the batch
+        const [whitelistAccount, bump6] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("whitelist"), creatorKeypair.publicKey.toBuffer()], program.programId);
+        const [character4Account, bump4] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("character"), nft4.mintAddress.toBuffer()], program.programId);
+        const [character5Account, bump5] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("character"), nft5.mintAddress.toBuffer()], program.programId);
+        const rewardAta = await spl.getOrCreateAssociatedTokenAccount(provider.connection, userKeypair2, rewardMintAccount, userKeypair2.publicKey, false, undefined, undefined, rewardTokenProgram);
+        
+        const character4Data = await program.account.character.fetch(character4Account);
+        const character5Data = await program.account.character.fetch(character5Account);
+        const lootAccountDataBefore = await program.account.loot.fetch(lootAccount);
+        
+        const stakeTx = program.transaction.stakeLootBatch([null, null], {
+            accounts: {
+                loot: lootAccount,
+                whitelist: whitelistAccount,
+                user: userKeypair2.publicKey,
+                tokenMetadataProgram: tokenMetadataProgram,
+                tokenProgram: spl.TOKEN_PROGRAM_ID,
+            },
+            remainingAccounts: [
+                ...await getBatchAccounts(program.programId, tokenMetadataProgram, nft4, userKeypair2.publicKey),
+                ...await getBatchAccounts(program.programId, tokenMetadataProgram, nft5, userKeypair2.publicKey),
+            ],
+        });
+        
+        const stakeSignature = await anchor.web3.sendAndConfirmTransaction(provider.connection, stakeTx, [userKeypair2], {skipPreflight: true});
+        console.log(`\tUser #2 batch stake looters transaction: ${stakeSignature}`);
+        
+        for (let nft of [nft4, nft5]) {
+            const nftTokenAccountData = await spl.getAccount(provider.connection, await spl.getAssociatedTokenAddress(nft.mintAddress, userKeypair2.publicKey));
+            assert.equal(nftTokenAccountData.delegate.toString(), lootAccount.toString());
+            assert.equal(nftTokenAccountData.isFrozen, true);
+        }
+        
+        // The Loot counts the looting points of the batch
+        const stakedLootData = await program.account.loot.fetch(lootAccount);
+        assert.equal(stakedLootData.stakedCharacters, lootAccountDataBefore.stakedCharacters + 2);
+        assert.equal(stakedLootData.stakedPoints.toString(), lootAccountDataBefore.stakedPoints.add(character4Data.lootingPoints).add(character5Data.lootingPoints).toString());
+        assert.equal((await program.account.character.fetch(character4Account)).staked, 2);
+        assert.equal((await program.account.character.fetch(character5Account)).staked, 2);
+        
+        await new Promise(f => setTimeout(f, 2000));
+        
+        const rewardAmountBefore = (await spl.getAccount(provider.connection, rewardAta.address, undefined, rewardTokenProgram)).amount;
+        
+        const unstakeTx = program.transaction.unstakeLootBatch(2, {
+            accounts: {
+                loot: lootAccount,
+                lootProceeds: lootProceedsAccount,
+                rewardAta: rewardAta.address,
+                rewardMint: rewardMintAccount,
+                user: userKeypair2.publicKey,
+                tokenMetadataProgram: tokenMetadataProgram,
+                tokenProgram: spl.TOKEN_PROGRAM_ID,
+                rewardTokenProgram: rewardTokenProgram,
+            },
+            remainingAccounts: [
+                ...await getBatchAccounts(program.programId, tokenMetadataProgram, nft4, userKeypair2.publicKey),
+                ...await getBatchAccounts(program.programId, tokenMetadataProgram, nft5, userKeypair2.publicKey),
+                {pubkey: leaderboardAccount, isWritable: true, isSigner: false},
+            ],
+        });
+        
+        const unstakeSignature = await anchor.web3.sendAndConfirmTransaction(provider.connection, unstakeTx, [userKeypair2], {skipPreflight: true});
+        console.log(`\tUser #2 batch unstake looters transaction: ${unstakeSignature}`);
+        
+        for (let nft of [nft4, nft5]) {
+            const nftTokenAccountData = await spl.getAccount(provider.connection, await spl.getAssociatedTokenAddress(nft.mintAddress, userKeypair2.publicKey));
+            assert.equal(nftTokenAccountData.delegate, null);
+            assert.equal(nftTokenAccountData.isFrozen, false);
+        }
+        
+        const lootAccountData = await program.account.loot.fetch(lootAccount);
+        assert.equal(lootAccountData.stakedCharacters, lootAccountDataBefore.stakedCharacters);
+        assert.equal(lootAccountData.stakedPoints.toString(), lootAccountDataBefore.stakedPoints.toString());
+        
+        // The looted rewards of the batch are paid in one transfer
+        const unstaked4Data = await program.account.character.fetch(character4Account);
+        const unstaked5Data = await program.account.character.fetch(character5Account);
+        assert.equal(unstaked4Data.staked, 0);
+        assert.equal(unstaked5Data.staked, 0);
+        const lootedRewards = unstaked4Data.lootingRewards.sub(character4Data.lootingRewards).add(unstaked5Data.lootingRewards.sub(character5Data.lootingRewards));
+        assert.equal(lootAccountData.totalPaid.sub(lootAccountDataBefore.totalPaid).toString(), lootedRewards.toString());
+        
+        const rewardAmountAfter = (await spl.getAccount(provider.connection, rewardAta.address, undefined, rewardTokenProgram)).amount;
+        const transferFee = spl.calculateFee(spl.getTransferFeeConfig(await spl.getMint(provider.connection, rewardMintAccount, undefined, rewardTokenProgram)).newerTransferFee, BigInt(lootedRewards.toString()));
+        assert.equal((rewardAmountAfter - rewardAmountBefore).toString(), (BigInt(lootedRewards.toString()) - transferFee).toString());
+        
+        if (!creatorWhitelist) {
+            const removeTx = program.transaction.removeWhitelist(creatorKeypair.publicKey, {
+                accounts: {
+                    adminSettings: adminSettingsAccount,
+                    whitelist: whitelistAccount,
+                    authority: adminKeypair.publicKey,
+                    systemProgram: anchor.web3.SystemProgram.programId,
+                }
+            });
+            
+            const removeSignature = await anchor.web3.sendAndConfirmTransaction(provider.connection, removeTx, [adminKeypair], {skipPreflight: true});
+            console.log(`\tRemove creator whitelist account transaction: ${removeSignature}`);
+        }
+        
+    });
+    
+    
+    it("User #2 character closed!", async () => {
+        
+        const [characterAccount, bump] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("character"), nft2.mintAddress.toBuffer()], program.programId);
+        const nftTokenAccount = await spl.getAssociatedTokenAddress(nft2.mintAddress, userKeypair2.publicKey);
+        
+        // The referrer Character earned referral rewards and was respecced, so it cannot be closed
+        const [referrerCharacterAccount, bump2] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("character"), nft1.mintAddress.toBuffer()], program.programId);
+        const referrerCloseTx = program.transaction.closeCharacter(false, {
+            accounts: {
+                mine: mineAccount,
+                character: referrerCharacterAccount,
+                nftAccount: await spl.getAssociatedTokenAddress(nft1.mintAddress, userKeypair1.publicKey),
+                nftMint: nft1.mintAddress,
+                user: userKeypair1.publicKey,
+                systemProgram: anchor.web3.SystemProgram.programId,
+            },
+        });
+        
+        await assert.rejects(anchor.web3.sendAndConfirmTransaction(provider.connection, referrerCloseTx, [userKeypair1]));
+        assert.ok((await program.account.character.fetch(referrerCharacterAccount)).referralEarned > 0);
+        
+        const getCloseTx = () => program.transaction.closeCharacter(true, {
+            accounts: {
+                mine: mineAccount,
+                character: characterAccount,
+                nftAccount: nftTokenAccount,
+                nftMint: nft2.mintAddress,
+                user: userKeypair2.publicKey,
+                systemProgram: anchor.web3.SystemProgram.programId,
+            },
+        });
+        
+        // The Character was referred by Character #1, a new one would start over with a new referrer
+        await assert.rejects(anchor.web3.sendAndConfirmTransaction(provider.connection, getCloseTx(), [userKeypair2]));
+        assert.equal((await program.account.character.fetch(characterAccount)).referrer.toString(), nft1.mintAddress.toString());
+        
+        const getReferralTx = (referralWindow: number) => program.transaction.setMineReferral(500, new anchor.BN(referralWindow), new anchor.BN(1e9), {
+            accounts: {
+                mine: mineAccount,
+                manager: managerKeyepair.publicKey,
+            },
+        });
+        
+        const referralSignature = await anchor.web3.sendAndConfirmTransaction(provider.connection, getReferralTx(0), [managerKeyepair], {skipPreflight: true});
+        console.log(`\tClose mine referral window transaction: ${referralSignature}`);
+        
+        const tx = getCloseTx();
+        const signature = await anchor.web3.sendAndConfirmTransaction(provider.connection, tx, [userKeypair2], {skipPreflight: true});
+        console.log(`\tUser #2 close character transaction: ${signature}`);
+        
+        const characterAccountInfo = await provider.connection.getAccountInfo(characterAccount);
+        assert.equal(characterAccountInfo, null);
+        
+        const restoreSignature = await anchor.web3.sendAndConfirmTransaction(provider.connection, getReferralTx(30 * 24 * 3600), [managerKeyepair], {skipPreflight: true});
+        console.log(`\tRestore mine referral window transaction: ${restoreSignature}`);
+    
+    });
+    
+    
+    it("Burned NFT character closed by the admin!", async () => {
+    
+        const [characterAccount, bump] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("character"), nft3.mintAddress.toBuffer()], program.programId);
+        const nftTokenAccount = await spl.getAssociatedTokenAddress(nft3.mintAddress, userKeypair1.publicKey);
+        
+        const getCloseTx = () => program.transaction.adminCloseCharacter(true, {
+            accounts: {
+                adminSettings: adminSettingsAccount,
+                character: characterAccount,
+                nftMint: nft3.mintAddress,
+                authority: adminKeypair.publicKey,
+                systemProgram: anchor.web3.SystemProgram.programId,
+            },
+        });
+        
+        // The NFT still exists, only its holder can close the Character
+        await assert.rejects(anchor.web3.sendAndConfirmTransaction(provider.connection, getCloseTx(), [adminKeypair]));
+        assert.notEqual(await provider.connection.getAccountInfo(characterAccount), null);
+        
+        const burnSignature = await spl.burn(provider.connection, userKeypair1, nftTokenAccount, nft3.mintAddress, userKeypair1, 1);
+        console.log(`\tUser #1 burn NFT transaction: ${burnSignature}`);
+        assert.equal((await spl.getMint(provider.connection, nft3.mintAddress)).supply.toString(), "0");
+        
+        // Only the admin can close the Character of a burned NFT
+        const falseCloseTx = program.transaction.adminCloseCharacter(true, {
+            accounts: {
+                adminSettings: adminSettingsAccount,
+                character: characterAccount,
+                nftMint: nft3.mintAddress,
+                authority: userKeypair1.publicKey,
+                systemProgram: anchor.web3.SystemProgram.programId,
+            },
+        });
+        
+        await assert.rejects(anchor.web3.sendAndConfirmTransaction(provider.connection, falseCloseTx, [userKeypair1]));
+        
+        const signature = await anchor.web3.sendAndConfirmTransaction(provider.connection, getCloseTx(), [adminKeypair], {skipPreflight: true});
+        console.log(`\tAdmin close character transaction: ${signature}`);
+        
+        const characterAccountInfo = await provider.connection.getAccountInfo(characterAccount);
+        assert.equal(characterAccountInfo, null);
+    
+    });
+    
+    
+    it("Whitelist account(s) removed!", async () => {
+        
+        const tx = new anchor.web3.Transaction();
+        
+        if (creatorWhitelist) {
+            
+            const [whitelistAccount, bump] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("whitelist"), creatorKeypair.publicKey.toBuffer()], program.programId);
+            
+            const ix = program.instruction.removeWhitelist(creatorKeypair.publicKey, {
+                accounts: {
+                    adminSettings: adminSettingsAccount,
+                    whitelist: whitelistAccount,
+                    authority: adminKeypair.publicKey,
+                    systemProgram: anchor.web3.SystemProgram.programId,
+                }
+            });
+            tx.add(ix);
+        }
+        
+        for (let nft of mintWhitelist) {
+            
+            const [whitelistAccount, bump] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("whitelist"), nft.mintAddress.toBuffer()], program.programId);
+            
+            const ix = program.instruction.removeWhitelist(nft.mintAddress, {
+                accounts: {
+                    adminSettings: adminSettingsAccount,
+                    whitelist: whitelistAccount,
+                    authority: adminKeypair.publicKey,
+                    systemProgram: anchor.web3.SystemProgram.programId,
+                }
+            });
+            tx.add(ix);
+        }
+        
+        const signature = await anchor.web3.sendAndConfirmTransaction(provider.connection, tx, [adminKeypair], {skipPreflight: true});
+        console.log(`\tRemove creator whitelist account transaction: ${signature}`);
+        
+    });
+    
+});
+
+
+async function createNFT(connection: anchor.web3.Connection, creatorKeypair: Keypair, ownerKeypair: Keypair, name: string, uri: string): Promise<PublicKey> {
+    const metaplex = new Metaplex(connection);
+    metaplex.use(keypairIdentity(creatorKeypair));
+    
+    const nftTask = metaplex.nfts().create({
+        name: name,
+        uri: uri,
+        sellerFeeBasisPoints: 500, // Represents 5.00%.
+        payer: ownerKeypair,
+        updateAuthority: creatorKeypair,
+        tokenOwner: ownerKeypair.publicKey,
+        symbol: "BSTD",
+    });
+    
+    const output = await nftTask.run();
+    
+    return output;
+}
+
+
+async function createTransferFeeMint(connection: anchor.web3.Connection, payer: Keypair, mintAuthority: PublicKey, decimals: number, feeBasisPoints: number, maxFee: bigint): Promise<PublicKey> {
+    const mintKeypair = Keypair.generate();
+    const mintLen = spl.getMintLen([spl.ExtensionType.TransferFeeConfig]);
+    const lamports = await connection.getMinimumBalanceForRentExemption(mintLen);
+    const tx = new anchor.web3.Transaction().add(
+        anchor.web3.SystemProgram.createAccount({fromPubkey: payer.publicKey, newAccountPubkey: mintKeypair.publicKey, space: mintLen, lamports: lamports, programId: spl.TOKEN_2022_PROGRAM_ID}),
+        spl.createInitializeTransferFeeConfigInstruction(mintKeypair.publicKey, payer.publicKey, payer.publicKey, feeBasisPoints, maxFee, spl.TOKEN_2022_PROGRAM_ID),
+        spl.createInitializeMintInstruction(mintKeypair.publicKey, decimals, mintAuthority, null, spl.TOKEN_2022_PROGRAM_ID),
+    );
+    await anchor.web3.sendAndConfirmTransaction(connection, tx, [payer, mintKeypair]);
+    return mintKeypair.publicKey;
+}
+
+
+async function getWhitelistAccount(creatorKeypair, program_id, creatorWhitelist, nft): Promise<PublicKey> {
+    let bump;
+    let whitelistAccount: PublicKey;
+    if (creatorWhitelist) {
+        [whitelistAccount, bump] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("whitelist"), creatorKeypair.publicKey.toBuffer()], program_id);
+    } else {
+        [whitelistAccount, bump] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("whitelist"), nft.mintAddress.toBuffer()], program_id);
+    }
+    return whitelistAccount
+}
+
+
+function getTokenRecordAccount(tokenMetadataProgram: PublicKey, mint: PublicKey, token: PublicKey): PublicKey {
+    const [tokenRecordAccount, bump] = PublicKey.findProgramAddressSync([Buffer.from("metadata"), tokenMetadataProgram.toBuffer(), mint.toBuffer(), Buffer.from("token_record"), token.toBuffer()], tokenMetadataProgram);
+    return tokenRecordAccount;
+}
+
+
+function getVestingAccount(program_id: PublicKey, mint: PublicKey, user: PublicKey): PublicKey {
+    const [vestingAccount, bump] = PublicKey.findProgramAddressSync([Buffer.from("vesting"), mint.toBuffer(), user.toBuffer()], program_id);
+    return vestingAccount;
+}
+
+
+async function getBatchAccounts(program_id: PublicKey, tokenMetadataProgram: PublicKey, nft, user: PublicKey) {
+    const [characterAccount, bump] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("character"), nft.mintAddress.toBuffer()], program_id);
+    const [denylistAccount, bump2] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("denylist"), nft.mintAddress.toBuffer()], program_id);
+    const [nftEditionAccount, bump3] = await anchor.web3.PublicKey.findProgramAddress([Buffer.from("metadata"), tokenMetadataProgram.toBuffer(), nft.mintAddress.toBuffer(), Buffer.from("edition")], tokenMetadataProgram);
+    const nftTokenAccount = await spl.getAssociatedTokenAddress(nft.mintAddress, user);
+    return [
+        {pubkey: characterAccount, isWritable: true, isSigner: false},
+        {pubkey: nftTokenAccount, isWritable: true, isSigner: false},
+        {pubkey: nft.mintAddress, isWritable: false, isSigner: false},
+        {pubkey: nftEditionAccount, isWritable: false, isSigner: false},
+        {pubkey: nft.metadataAddress, isWritable: false, isSigner: false},
+        {pubkey: denylistAccount, isWritable: false, isSigner: false},
+    ];
+}
+
+
+function getMerkleLeaf(key: Buffer, data: Buffer): Buffer {
+    return Buffer.from(keccak256.arrayBuffer(Buffer.concat([Buffer.from([0]), key, data])));
+}
+
+
+// Builds the tree the program verifies against, sibling pairs are hashed in ascending order
+function getMerkleTree(leaves: Buffer[]): {root: number[], paths: number[][][]} {
+    const paths = leaves.map(() => []);
+    let indices = leaves.map((leaf, index) => [index]);
+    let level = leaves;
+    while (level.length > 1) {
+        const nextLevel = [];
+        const nextIndices = [];
+        for (let i = 0; i < level.length; i += 2) {
+            if (i + 1 == level.length) {
+                nextLevel.push(level[i]);
+                nextIndices.push(indices[i]);
+                continue;
+            }
+            for (const index of indices[i]) paths[index].push(Array.from(level[i + 1]));
+            for (const index of indices[i + 1]) paths[index].push(Array.from(level[i]));
+            const [left, right] = Buffer.compare(level[i], level[i + 1]) <= 0 ? [level[i], level[i + 1]] : [level[i + 1], level[i]];
+            nextLevel.push(Buffer.from(keccak256.arrayBuffer(Buffer.concat([Buffer.from([1]), left, right]))));
+            nextIndices.push(indices[i].concat(indices[i + 1]));
+        }
+        level = nextLevel;
+        indices = nextIndices;
+    }
+    return {root: Array.from(level[0]), paths: paths};
+}
+
+
+function borshTraits(traits): Buffer {
+    const data = Buffer.alloc(25);
+    data.writeUInt8(traits.tier, 0);
+    data.writeBigUInt64LE(BigInt(traits.miningPoints.toString()), 1);
+    data.writeBigUInt64LE(BigInt(traits.miningCapacity.toString()), 9);
+    data.writeBigUInt64LE(BigInt(traits.lootingPoints.toString()), 17);
+    return data;
+}
+
+
+function borshString(value: string): Buffer {
+    const bytes = Buffer.from(value, 'utf8');
+    const length = Buffer.alloc(4);
+    length.writeUInt32LE(bytes.length);
+    return Buffer.concat([length, bytes]);
+}
+
+
+// The bundled Metaplex JS SDK predates programmable NFTs, so the Token Metadata CreateV1 and MintV1 instructions are built by hand
+async function createProgrammableNFT(connection: anchor.web3.Connection, tokenMetadataProgram: PublicKey, creatorKeypair: Keypair, ownerKeypair: Keypair, name: string, uri: string) {
+    const mintKeypair = Keypair.generate();
+    const mintAddress = mintKeypair.publicKey;
+    const [metadataAddress, bump1] = PublicKey.findProgramAddressSync([Buffer.from("metadata"), tokenMetadataProgram.toBuffer(), mintAddress.toBuffer()], tokenMetadataProgram);
+    const [editionAddress, bump2] = PublicKey.findProgramAddressSync([Buffer.from("metadata"), tokenMetadataProgram.toBuffer(), mintAddress.toBuffer(), Buffer.from("edition")], tokenMetadataProgram);
+    const tokenAddress = await spl.getAssociatedTokenAddress(mintAddress, ownerKeypair.publicKey);
+    const tokenRecordAddress = getTokenRecordAccount(tokenMetadataProgram, mintAddress, tokenAddress);
+    
+    const sellerFeeBasisPoints = Buffer.alloc(2);
+    sellerFeeBasisPoints.writeUInt16LE(500);
+    const creatorCount = Buffer.alloc(4);
+    creatorCount.writeUInt32LE(1);
+    
+    const createData = Buffer.concat([
+        Buffer.from([42, 0]),                                                           // Create, CreateArgs::V1
+        borshString(name),
+        borshString("BSTD"),
+        borshString(uri),
+        sellerFeeBasisPoints,
+        Buffer.from([1]), creatorCount, creatorKeypair.publicKey.toBuffer(), Buffer.from([1, 100]),
+        Buffer.from([0, 1]),                                                            // primary sale happened, is mutable
+        Buffer.from([4]),                                                               // TokenStandard::ProgrammableNonFungible
+        Buffer.from([0, 0, 0, 0]),                                                      // collection, uses, collection details, rule set
+        Buffer.from([1, 0]),                                                            // decimals
+        Buffer.from([1, 0]),                                                            // PrintSupply::Zero
+    ]);
+    
+    const createIx = new anchor.web3.TransactionInstruction({
+        programId: tokenMetadataProgram,
+        keys: [
+            {pubkey: metadataAddress, isSigner: false, isWritable: true},
+            {pubkey: editionAddress, isSigner: false, isWritable: true},
+            {pubkey: mintAddress, isSigner: true, isWritable: true},
+            {pubkey: creatorKeypair.publicKey, isSigner: true, isWritable: false},
+            {pubkey: ownerKeypair.publicKey, isSigner: true, isWritable: true},
+            {pubkey: creatorKeypair.publicKey, isSigner: true, isWritable: false},
+            {pubkey: anchor.web3.SystemProgram.programId, isSigner: false, isWritable: false},
+            {pubkey: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY, isSigner: false, isWritable: false},
+            {pubkey: spl.TOKEN_PROGRAM_ID, isSigner: false, isWritable: false},
+        ],
+        data: createData,
+    });
+    
+    const amount = new anchor.BN(1).toArrayLike(Buffer, 'le', 8);
+    
+    const mintIx = new anchor.web3.TransactionInstruction({
+        programId: tokenMetadataProgram,
+        keys: [
+            {pubkey: tokenAddress, isSigner: false, isWritable: true},
+            {pubkey: ownerKeypair.publicKey, isSigner: false, isWritable: false},
+            {pubkey: metadataAddress, isSigner: false, isWritable: false},
+            {pubkey: editionAddress, isSigner: false, isWritable: true},
+            {pubkey: tokenRecordAddress, isSigner: false, isWritable: true},
+            {pubkey: mintAddress, isSigner: false, isWritable: true},
+            {pubkey: creatorKeypair.publicKey, isSigner: true, isWritable: false},
+            {pubkey: tokenMetadataProgram, isSigner: false, isWritable: false},
+            {pubkey: ownerKeypair.publicKey, isSigner: true, isWritable: true},
+            {pubkey: anchor.web3.SystemProgram.programId, isSigner: false, isWritable: false},
+            {pubkey: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY, isSigner: false, isWritable: false},
+            {pubkey: spl.TOKEN_PROGRAM_ID, isSigner: false, isWritable: false},
+            {pubkey: spl.ASSOCIATED_TOKEN_PROGRAM_ID, isSigner: false, isWritable: false},
+            {pubkey: tokenMetadataProgram, isSigner: false, isWritable: false},
+            {pubkey: tokenMetadataProgram, isSigner: false, isWritable: false},
+        ],
+        data: Buffer.concat([Buffer.from([43, 0]), amount, Buffer.from([0])]),    // Mint, MintArgs::V1
+    });
+    
+    const tx = new anchor.web3.Transaction();
+    tx.add(anchor.web3.ComputeBudgetProgram.setComputeUnitLimit({units: 400000}));
+    tx.add(createIx, mintIx);
+    await anchor.web3.sendAndConfirmTransaction(connection, tx, [ownerKeypair, creatorKeypair, mintKeypair], {skipPreflight: true});
+    
+    return {mintAddress, metadataAddress, editionAddress, tokenAddress, tokenRecordAddress};
 }